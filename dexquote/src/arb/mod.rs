@@ -0,0 +1,239 @@
+use std::cmp::Reverse;
+
+use ethers::types::U256;
+
+use crate::{
+    db::PoolStore,
+    dex::Dex,
+    path::{get_amount_out_from_path, PoolInfo},
+};
+
+mod sink;
+pub use sink::*;
+mod watch;
+pub use watch::*;
+
+// gas of the transaction and of the bot around the swaps
+const BASE_GAS: u64 = 60_000;
+
+// rough gas of a swap on each dex
+fn hop_gas(dex: Option<Dex>) -> u64 {
+    match dex {
+        Some(Dex::UniswapV2) | Some(Dex::Solidly) => 60_000,
+        Some(Dex::UniswapV3) | Some(Dex::Algebra) => 110_000,
+        Some(Dex::BalancerV2) => 120_000,
+        Some(Dex::Curve) | Some(Dex::WooFiV2) | None => 150_000,
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ArbConfig {
+    /// largest input tried, in the base token
+    pub max_amount_in: U256,
+    /// price of a unit of gas in the base token, the gas price in wei for WETH
+    pub gas_price: U256,
+    /// steps of the search of the input, each shrinks the range by a third
+    pub iterations: usize,
+    /// only use cached ticks and tick bitmaps
+    pub offline: bool,
+}
+
+impl Default for ArbConfig {
+    fn default() -> Self {
+        Self {
+            max_amount_in: U256::exp10(19),
+            // 0.1 gwei
+            gas_price: U256::exp10(8),
+            iterations: 40,
+            offline: false,
+        }
+    }
+}
+
+/// A profitable cycle from and back to the base token at its best input
+#[derive(Clone, Debug)]
+pub struct ArbOpportunity {
+    pub cycle: Vec<PoolInfo>,
+    pub amount_in: U256,
+    pub amount_out: U256,
+    pub gas: u64,
+    pub gas_cost: U256,
+    /// amount_out - amount_in - gas_cost, in the base token
+    pub net_profit: U256,
+}
+
+/// Estimated gas of a transaction swapping through the cycle
+pub fn estimate_gas(cycle: &[PoolInfo]) -> u64 {
+    BASE_GAS + cycle.iter().map(|hop| hop_gas(hop.dex)).sum::<u64>()
+}
+
+// output of the cycle, zero if it cannot be quoted
+async fn get_amount_out<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    json_rpc_url: &str,
+    cycle: &[PoolInfo],
+    amount_in: U256,
+    offline: bool,
+) -> U256 {
+    get_amount_out_from_path(store, chain_id, json_rpc_url, amount_in, cycle, offline)
+        .await
+        .unwrap_or_default()
+}
+
+/// Search the input with the most profit of a cycle, the profit being concave in the input.
+/// None if the cycle does not profit after gas.
+pub async fn optimize_cycle<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    json_rpc_url: &str,
+    cycle: &[PoolInfo],
+    config: &ArbConfig,
+) -> Option<ArbOpportunity> {
+    let amount_out = |amount_in| {
+        get_amount_out(
+            store,
+            chain_id,
+            json_rpc_url,
+            cycle,
+            amount_in,
+            config.offline,
+        )
+    };
+    // a cycle that loses on a small input loses on any input
+    let probe = (config.max_amount_in / U256::exp10(6)).max(U256::one());
+    if amount_out(probe).await <= probe {
+        return None;
+    }
+    // ternary search, out(m1) - m1 >= out(m2) - m2 keeps the lower two thirds
+    let (mut low, mut high) = (U256::zero(), config.max_amount_in);
+    for _ in 0..config.iterations {
+        if high - low < U256::from(3) {
+            break;
+        }
+        let third = (high - low) / 3;
+        let (m1, m2) = (low + third, high - third);
+        if amount_out(m1).await + m2 >= amount_out(m2).await + m1 {
+            high = m2;
+        } else {
+            low = m1;
+        }
+    }
+    let amount_in = (low + high) / 2;
+    let amount_out = amount_out(amount_in).await;
+    let gas = estimate_gas(cycle);
+    let gas_cost = config.gas_price.saturating_mul(U256::from(gas));
+    let net_profit = amount_out
+        .checked_sub(amount_in)?
+        .checked_sub(gas_cost)
+        .filter(|net_profit| !net_profit.is_zero())?;
+    Some(ArbOpportunity {
+        cycle: cycle.to_vec(),
+        amount_in,
+        amount_out,
+        gas,
+        gas_cost,
+        net_profit,
+    })
+}
+
+/// Optimize each cycle, the profitable ones are returned with the most net profit first.
+/// Cycles come from `graph::RoutingGraph::get_cycles`.
+pub async fn find_opportunities<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    json_rpc_url: &str,
+    cycles: &[Vec<PoolInfo>],
+    config: &ArbConfig,
+) -> Vec<ArbOpportunity> {
+    let mut opportunities = Vec::new();
+    for cycle in cycles {
+        if let Some(opportunity) =
+            optimize_cycle(store, chain_id, json_rpc_url, cycle, config).await
+        {
+            opportunities.push(opportunity);
+        }
+    }
+    opportunities.sort_by_key(|opportunity| Reverse(opportunity.net_profit));
+    opportunities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{add_pool, MemoryStore},
+        graph::RoutingGraph,
+    };
+    use cfmms::pool::{Pool, UniswapV2Pool};
+    use ethers::types::Address;
+
+    const CHAIN_ID: u64 = 42161;
+
+    #[tokio::test]
+    async fn test_find_opportunities() {
+        let store = MemoryStore::new();
+        let weth = Address::from_low_u64_be(1);
+        let token = Address::from_low_u64_be(2);
+        // 2 tokens per WETH in the first pool and 1 in the second
+        for (address, reserve_1) in [(10, 2_000u128), (11, 1_000)] {
+            add_pool(
+                &store,
+                CHAIN_ID,
+                Pool::UniswapV2(UniswapV2Pool {
+                    address: Address::from_low_u64_be(address),
+                    token_a: weth,
+                    token_a_decimals: 18,
+                    token_b: token,
+                    token_b_decimals: 18,
+                    reserve_0: 1_000 * 10u128.pow(18),
+                    reserve_1: reserve_1 * 10u128.pow(18),
+                    fee: 300,
+                }),
+                None,
+                "",
+            )
+            .await
+            .unwrap();
+        }
+        let graph = RoutingGraph::from_store(&store, CHAIN_ID).unwrap();
+        let cycles = graph.get_cycles(weth, 4, 10);
+        assert_eq!(cycles.len(), 2);
+
+        let config = ArbConfig {
+            max_amount_in: U256::exp10(21),
+            offline: true,
+            ..Default::default()
+        };
+        let opportunities = find_opportunities(&store, CHAIN_ID, "", &cycles, &config).await;
+        // only selling WETH in the first pool profits
+        assert_eq!(opportunities.len(), 1);
+        let opportunity = &opportunities[0];
+        assert_eq!(opportunity.cycle[0].address, Address::from_low_u64_be(10));
+        assert_eq!(opportunity.gas, BASE_GAS + 2 * 60_000);
+        assert_eq!(
+            opportunity.net_profit,
+            opportunity.amount_out - opportunity.amount_in - opportunity.gas_cost
+        );
+        // the input is at the top of the profit curve
+        let profit = |amount_in: U256, amount_out: U256| amount_out - amount_in;
+        let best = profit(opportunity.amount_in, opportunity.amount_out);
+        for amount_in in [
+            opportunity.amount_in * 9 / 10,
+            opportunity.amount_in * 11 / 10,
+        ] {
+            let amount_out =
+                get_amount_out(&store, CHAIN_ID, "", &opportunity.cycle, amount_in, true).await;
+            assert!(profit(amount_in, amount_out) < best);
+        }
+
+        // the gas costs more than the profit
+        let config = ArbConfig {
+            gas_price: U256::exp10(24),
+            ..config
+        };
+        assert!(find_opportunities(&store, CHAIN_ID, "", &cycles, &config)
+            .await
+            .is_empty());
+    }
+}
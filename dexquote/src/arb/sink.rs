@@ -0,0 +1,159 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    sync::Mutex,
+};
+
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::ArbOpportunity;
+use crate::{error::DexQuoteError, types::DexQuoteResult};
+
+/// Destination of the opportunities found while syncing
+pub trait OpportunitySink: Send + Sync {
+    fn send(&self, block_number: u64, opportunity: &ArbOpportunity) -> DexQuoteResult<()>;
+}
+
+/// An opportunity as a JSON object, amounts are decimal strings
+pub fn opportunity_to_json(block_number: u64, opportunity: &ArbOpportunity) -> serde_json::Value {
+    serde_json::json!({
+        "block_number": block_number,
+        "cycle": opportunity.cycle,
+        "amount_in": opportunity.amount_in.to_string(),
+        "amount_out": opportunity.amount_out.to_string(),
+        "gas": opportunity.gas,
+        "gas_cost": opportunity.gas_cost.to_string(),
+        "net_profit": opportunity.net_profit.to_string(),
+    })
+}
+
+/// Sends the opportunities with their block number to a channel
+pub struct ChannelSink {
+    sender: UnboundedSender<(u64, ArbOpportunity)>,
+}
+
+impl ChannelSink {
+    pub fn new(sender: UnboundedSender<(u64, ArbOpportunity)>) -> Self {
+        Self { sender }
+    }
+}
+
+impl OpportunitySink for ChannelSink {
+    fn send(&self, block_number: u64, opportunity: &ArbOpportunity) -> DexQuoteResult<()> {
+        self.sender
+            .send((block_number, opportunity.clone()))
+            .map_err(|_| DexQuoteError::SinkError("channel closed".to_string()))
+    }
+}
+
+/// Appends the opportunities to a file, one JSON object per line
+pub struct FileSink {
+    file: Mutex<File>,
+}
+
+impl FileSink {
+    pub fn open(path: &str) -> DexQuoteResult<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| DexQuoteError::SinkError(e.to_string()))?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl OpportunitySink for FileSink {
+    fn send(&self, block_number: u64, opportunity: &ArbOpportunity) -> DexQuoteResult<()> {
+        let line = opportunity_to_json(block_number, opportunity).to_string();
+        writeln!(self.file.lock().unwrap(), "{line}")
+            .map_err(|e| DexQuoteError::SinkError(e.to_string()))
+    }
+}
+
+/// Adds the opportunities to a Redis stream as a `data` field holding the JSON object.
+/// The stream is capped at about `max_len` entries.
+pub struct RedisStreamSink {
+    client: redis::Client,
+    stream: String,
+    max_len: usize,
+}
+
+impl RedisStreamSink {
+    pub fn open(redis_url: &str, stream: &str, max_len: usize) -> DexQuoteResult<Self> {
+        Ok(Self {
+            client: redis::Client::open(redis_url)?,
+            stream: stream.to_string(),
+            max_len,
+        })
+    }
+}
+
+impl OpportunitySink for RedisStreamSink {
+    fn send(&self, block_number: u64, opportunity: &ArbOpportunity) -> DexQuoteResult<()> {
+        let mut con = self.client.get_connection()?;
+        redis::cmd("XADD")
+            .arg(&self.stream)
+            .arg("MAXLEN")
+            .arg("~")
+            .arg(self.max_len)
+            .arg("*")
+            .arg("data")
+            .arg(opportunity_to_json(block_number, opportunity).to_string())
+            .query::<String>(&mut con)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::PoolInfo;
+    use ethers::types::{Address, U256};
+    use std::io::Read;
+
+    fn opportunity() -> ArbOpportunity {
+        ArbOpportunity {
+            cycle: vec![PoolInfo {
+                address: Address::from_low_u64_be(10),
+                token_in: Address::from_low_u64_be(1),
+                token_out: Address::from_low_u64_be(2),
+                dex: None,
+                fee: None,
+            }],
+            amount_in: U256::from(1_000),
+            amount_out: U256::from(1_100),
+            gas: 120_000,
+            gas_cost: U256::from(10),
+            net_profit: U256::from(90),
+        }
+    }
+
+    #[test]
+    fn test_file_sink() {
+        let path = std::env::temp_dir().join(format!("dexquote-sink-{}.jsonl", std::process::id()));
+        let path = path.to_str().unwrap();
+        let sink = FileSink::open(path).unwrap();
+        sink.send(7, &opportunity()).unwrap();
+        sink.send(8, &opportunity()).unwrap();
+
+        let mut content = String::new();
+        File::open(path)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        std::fs::remove_file(path).unwrap();
+        let lines: Vec<serde_json::Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["block_number"], 7);
+        assert_eq!(lines[1]["net_profit"], "90");
+        assert_eq!(
+            lines[1]["cycle"][0]["token_out"],
+            format!("{:?}", Address::from_low_u64_be(2))
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::{HashMap, HashSet};
+
+use ethers::types::Address;
+use tracing::warn;
+
+use super::{optimize_cycle, ArbConfig, OpportunitySink};
+use crate::{db::PoolStore, graph::RoutingGraph, path::PoolInfo};
+
+/// Cycles of the base tokens and the cycles each pool is part of,
+/// so that only the cycles of an updated pool are simulated again
+#[derive(Clone, Debug, Default)]
+pub struct CycleIndex {
+    cycles: Vec<Vec<PoolInfo>>,
+    pool_cycles: HashMap<Address, Vec<usize>>,
+}
+
+impl CycleIndex {
+    pub fn new(cycles: Vec<Vec<PoolInfo>>) -> Self {
+        let mut pool_cycles: HashMap<Address, Vec<usize>> = HashMap::new();
+        for (i, cycle) in cycles.iter().enumerate() {
+            for hop in cycle {
+                pool_cycles.entry(hop.address).or_default().push(i);
+            }
+        }
+        Self {
+            cycles,
+            pool_cycles,
+        }
+    }
+
+    /// Index the cycles of 2 to `max_hops` pools through each base token,
+    /// at most `limit` per token. Pools added to the graph later are not indexed.
+    pub fn from_graph(
+        graph: &RoutingGraph,
+        tokens: &[Address],
+        max_hops: usize,
+        limit: usize,
+    ) -> Self {
+        Self::new(
+            tokens
+                .iter()
+                .flat_map(|token| graph.get_cycles(*token, max_hops, limit))
+                .collect(),
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.cycles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cycles.is_empty()
+    }
+
+    /// Cycles through any of the pools, each once
+    pub fn get_cycles(&self, pools: &[Address]) -> Vec<&[PoolInfo]> {
+        let mut indexes: Vec<usize> = pools
+            .iter()
+            .filter_map(|pool| self.pool_cycles.get(pool))
+            .flatten()
+            .copied()
+            .collect::<HashSet<usize>>()
+            .into_iter()
+            .collect();
+        indexes.sort_unstable();
+        indexes
+            .into_iter()
+            .map(|i| self.cycles[i].as_slice())
+            .collect()
+    }
+}
+
+/// Simulates the cycles of the pools updated by `sync` and sends the profitable ones to a sink
+pub struct ArbWatcher {
+    pub index: CycleIndex,
+    pub config: ArbConfig,
+    sink: Box<dyn OpportunitySink>,
+}
+
+impl ArbWatcher {
+    pub fn new(index: CycleIndex, config: ArbConfig, sink: Box<dyn OpportunitySink>) -> Self {
+        Self {
+            index,
+            config,
+            sink,
+        }
+    }
+
+    /// Re-evaluate the cycles of the pools updated in a block,
+    /// returns the number of opportunities sent
+    pub async fn on_pools_updated<S: PoolStore>(
+        &self,
+        store: &S,
+        chain_id: u64,
+        json_rpc_url: &str,
+        block_number: u64,
+        pools: &[Address],
+    ) -> usize {
+        let mut sent = 0;
+        for cycle in self.index.get_cycles(pools) {
+            let opportunity =
+                match optimize_cycle(store, chain_id, json_rpc_url, cycle, &self.config).await {
+                    Some(opportunity) => opportunity,
+                    None => continue,
+                };
+            match self.sink.send(block_number, &opportunity) {
+                Ok(_) => sent += 1,
+                Err(e) => warn!("failed to send opportunity: {:?}", e),
+            }
+        }
+        sent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        arb::ChannelSink,
+        db::{add_pool, univ2, MemoryStore},
+    };
+    use cfmms::pool::{Pool, UniswapV2Pool};
+    use ethers::types::U256;
+    use tokio::sync::mpsc;
+
+    const CHAIN_ID: u64 = 42161;
+
+    #[tokio::test]
+    async fn test_arb_watcher() {
+        let store = MemoryStore::new();
+        let weth = Address::from_low_u64_be(1);
+        let token = Address::from_low_u64_be(2);
+        let other = Address::from_low_u64_be(3);
+        // two balanced WETH pools of `token` and a pool of `other` outside the cycles
+        for (address, token_b) in [(10, token), (11, token), (12, other)] {
+            add_pool(
+                &store,
+                CHAIN_ID,
+                Pool::UniswapV2(UniswapV2Pool {
+                    address: Address::from_low_u64_be(address),
+                    token_a: weth,
+                    token_a_decimals: 18,
+                    token_b,
+                    token_b_decimals: 18,
+                    reserve_0: 1_000 * 10u128.pow(18),
+                    reserve_1: 1_000 * 10u128.pow(18),
+                    fee: 300,
+                }),
+                None,
+                "",
+            )
+            .await
+            .unwrap();
+        }
+        let graph = RoutingGraph::from_store(&store, CHAIN_ID).unwrap();
+        let index = CycleIndex::from_graph(&graph, &[weth], 3, 100);
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.get_cycles(&[Address::from_low_u64_be(10)]).len(), 2);
+        assert!(index.get_cycles(&[Address::from_low_u64_be(12)]).is_empty());
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let config = ArbConfig {
+            max_amount_in: U256::exp10(21),
+            offline: true,
+            ..Default::default()
+        };
+        let watcher = ArbWatcher::new(index, config, Box::new(ChannelSink::new(sender)));
+        let pools = [Address::from_low_u64_be(10)];
+        // the pools are balanced
+        assert_eq!(
+            watcher
+                .on_pools_updated(&store, CHAIN_ID, "", 1, &pools)
+                .await,
+            0
+        );
+
+        // a swap moves the first pool to 2 tokens per WETH
+        univ2::update_pool(
+            &store,
+            CHAIN_ID,
+            pools[0],
+            1_000 * 10u128.pow(18),
+            2_000 * 10u128.pow(18),
+        )
+        .unwrap();
+        assert_eq!(
+            watcher
+                .on_pools_updated(&store, CHAIN_ID, "", 2, &pools)
+                .await,
+            1
+        );
+        let (block_number, opportunity) = receiver.recv().await.unwrap();
+        assert_eq!(block_number, 2);
+        assert_eq!(opportunity.cycle[0].address, pools[0]);
+        assert!(!opportunity.net_profit.is_zero());
+    }
+}
@@ -2,14 +2,155 @@ use cfmms::{
     checkpoint::generate_checkpoint_with_throttle,
     dex::{Dex, DexVariant},
 };
+use dexquote::db::univ2::UniV2Fee;
 use ethers::{
-    providers::{Http, Provider},
+    providers::{Http, Middleware, Provider},
     types::H160,
 };
-use std::{error::Error, str::FromStr, sync::Arc, time::Instant};
-use tracing::info;
+use std::{collections::HashMap, error::Error, str::FromStr, sync::Arc, time::Instant};
+use tracing::{info, warn};
 
-use crate::config;
+use crate::config::{self, FactoryConfig};
+
+/// A dex factory whose pools are imported and tracked
+pub struct Factory {
+    pub address: H160,
+    pub dex: dexquote::dex::Dex,
+    pub creation_block: u64,
+    pub fee: Option<u64>,
+    // UniswapV2 forks with directional fees charge this on token1 -> token0 swaps
+    pub fee_one_for_zero: Option<u32>,
+}
+
+impl Factory {
+    /// The fee of the pairs deployed by a UniswapV2 factory
+    pub fn univ2_fee(&self) -> Option<UniV2Fee> {
+        match (self.dex, self.fee) {
+            (dexquote::dex::Dex::UniswapV2, Some(fee)) => {
+                Some(UniV2Fee::new(fee as u32, self.fee_one_for_zero))
+            }
+            _ => None,
+        }
+    }
+
+    // cfmms only supports UniswapV2 and UniswapV3. Solidly pools are imported with
+    // `import-solidly`, Curve, Balancer and WooFi pools with `import-curve`,
+    // `import-balancer` and `import-woofi` and Algebra pools are discovered
+    // from factory events by `sync`
+    fn to_cfmms_dex(&self) -> Option<Dex> {
+        let dex_variant = match self.dex {
+            dexquote::dex::Dex::UniswapV2 => DexVariant::UniswapV2,
+            dexquote::dex::Dex::UniswapV3 => DexVariant::UniswapV3,
+            dexquote::dex::Dex::Solidly
+            | dexquote::dex::Dex::Algebra
+            | dexquote::dex::Dex::Curve
+            | dexquote::dex::Dex::BalancerV2
+            | dexquote::dex::Dex::WooFiV2 => return None,
+        };
+        Some(Dex::new(
+            self.address,
+            dex_variant,
+            self.creation_block,
+            self.fee,
+        ))
+    }
+}
+
+pub fn get_factories(chain_id: u64) -> Vec<Factory> {
+    let mut factories = vec![
+        // Sushiswap
+        Factory {
+            address: H160::from_str("0xc35DADB65012eC5796536bD9864eD8773aBc74C4").unwrap(),
+            dex: dexquote::dex::Dex::UniswapV2,
+            creation_block: 70,
+            fee: Some(300),
+            fee_one_for_zero: None,
+        },
+        // UniswapV3
+        Factory {
+            address: H160::from_str("0x1F98431c8aD98523631AE4a59f267346ea31F984").unwrap(),
+            dex: dexquote::dex::Dex::UniswapV3,
+            creation_block: 35,
+            fee: None,
+            fee_one_for_zero: None,
+        },
+    ];
+    let solidly_factory = match chain_id {
+        // Velodrome V2
+        10 => Some("0xF1046053aa5682b4F9a81b5481394DA16BE5FF5a"),
+        // Aerodrome
+        8453 => Some("0x420DD381b31aEf6683db6B902084cB0FFECe40Da"),
+        _ => None,
+    };
+    if let Some(address) = solidly_factory {
+        factories.push(Factory {
+            address: H160::from_str(address).unwrap(),
+            dex: dexquote::dex::Dex::Solidly,
+            // pools are listed with `allPools` instead of scanning logs
+            creation_block: 0,
+            fee: None,
+            fee_one_for_zero: None,
+        });
+    }
+    let algebra_factory = match chain_id {
+        // QuickSwap V3
+        137 => Some("0x411b0fAcC3489691f28ad58c47006AF5E3Ab3A28"),
+        // Camelot V3
+        42161 => Some("0x1a3c9B1d2F0529D97f2afC5136Cc23e58f1FD35B"),
+        _ => None,
+    };
+    if let Some(address) = algebra_factory {
+        factories.push(Factory {
+            address: H160::from_str(address).unwrap(),
+            dex: dexquote::dex::Dex::Algebra,
+            // pools are added from `Pool` events, backfill from here to discover all of them
+            creation_block: 0,
+            fee: None,
+            fee_one_for_zero: None,
+        });
+    }
+    factories
+}
+
+/// The factories of a chain with the UniswapV2 forks of the config.
+/// A configured factory that is already listed only overrides its fee.
+pub fn get_configured_factories(chain_id: u64, univ2_factories: &[FactoryConfig]) -> Vec<Factory> {
+    let mut factories = get_factories(chain_id);
+    for factory_config in univ2_factories {
+        let address = match H160::from_str(&factory_config.address) {
+            Ok(address) => address,
+            Err(_) => {
+                warn!("invalid factory address: {}", factory_config.address);
+                continue;
+            }
+        };
+        match factories
+            .iter_mut()
+            .find(|factory| factory.address == address)
+        {
+            Some(factory) => {
+                factory.fee = Some(factory_config.fee as u64);
+                factory.fee_one_for_zero = factory_config.fee_one_for_zero;
+            }
+            None => factories.push(Factory {
+                address,
+                dex: dexquote::dex::Dex::UniswapV2,
+                creation_block: factory_config.creation_block,
+                fee: Some(factory_config.fee as u64),
+                fee_one_for_zero: factory_config.fee_one_for_zero,
+            }),
+        }
+    }
+    factories
+}
+
+/// Fees of the pairs of each UniswapV2 factory
+pub fn get_univ2_fees(factories: &[Factory]) -> HashMap<H160, UniV2Fee> {
+    factories
+        .iter()
+        .filter_map(|factory| Some((factory.address, factory.univ2_fee()?)))
+        .collect()
+}
 
 pub async fn create_checkpoint(
     config_name: String,
@@ -18,23 +159,12 @@ pub async fn create_checkpoint(
     let start = Instant::now();
     let conf = config::get_config(config_name);
     let provider = Arc::new(Provider::<Http>::try_from(&conf.json_rpc_url).unwrap());
+    let chain_id = provider.get_chainid().await?.as_u64();
 
-    let dexes = vec![
-        // Sushiswap
-        Dex::new(
-            H160::from_str("0xc35DADB65012eC5796536bD9864eD8773aBc74C4").unwrap(),
-            DexVariant::UniswapV2,
-            70,
-            Some(300),
-        ),
-        // UniswapV3
-        Dex::new(
-            H160::from_str("0x1F98431c8aD98523631AE4a59f267346ea31F984").unwrap(),
-            DexVariant::UniswapV3,
-            35,
-            None,
-        ),
-    ];
+    let dexes = get_configured_factories(chain_id, &conf.univ2_factories)
+        .iter()
+        .filter_map(|factory| factory.to_cfmms_dex())
+        .collect();
 
     generate_checkpoint_with_throttle(dexes, provider, 100000, 5, &checkpoint_path).await?;
 
@@ -0,0 +1,60 @@
+use dexquote::{
+    arb::{find_opportunities, ArbConfig},
+    constants::provider::get_provider,
+    db::RedisStore,
+    graph::RoutingGraph,
+    path::LogPriceGraph,
+};
+use ethers::{
+    providers::Middleware,
+    types::{Address, U256},
+};
+use std::error::Error;
+use tracing::info;
+
+use crate::config;
+
+/// Search the cycles through `token` for arbitrage, the gas price is read from the node if unset.
+/// With `screen`, only the negative cycles of the log-price graph through `token` are simulated.
+#[allow(clippy::too_many_arguments)]
+pub async fn show_opportunities(
+    token: String,
+    hop: u64,
+    cycle_limit: u64,
+    max_amount_in: String,
+    gas_price: Option<String>,
+    config_name: String,
+    offline: bool,
+    screen: bool,
+) -> Result<(), Box<dyn Error>> {
+    let token = token.parse::<Address>()?;
+    let conf = config::get_config(config_name);
+    let store = RedisStore::open(&conf.redis_url)?;
+    let chain_id = 42161;
+    let gas_price = match gas_price {
+        Some(gas_price) => U256::from_dec_str(&gas_price)?,
+        None => get_provider(&conf.json_rpc_url)?.get_gas_price().await?,
+    };
+    let graph = RoutingGraph::from_store(&store, chain_id)?;
+    let cycles = match screen {
+        true => LogPriceGraph::from_routing_graph(&graph)
+            .find_negative_cycles()
+            .iter()
+            .filter(|cycle| cycle.cycle.len() <= hop as usize)
+            .filter_map(|cycle| cycle.starting_at(token))
+            .take(cycle_limit as usize)
+            .collect(),
+        false => graph.get_cycles(token, hop as usize, cycle_limit as usize),
+    };
+    info!("{} cycles through {:?}", cycles.len(), token);
+    let config = ArbConfig {
+        max_amount_in: U256::from_dec_str(&max_amount_in)?,
+        gas_price,
+        offline,
+        ..Default::default()
+    };
+    let opportunities =
+        find_opportunities(&store, chain_id, &conf.json_rpc_url, &cycles, &config).await;
+    println!("{:#?}", opportunities);
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use dexquote::{
+    db::RedisStore,
+    price::uni_v3::{get_liquidity_depth, LiquidityDepth},
+};
+use ethers::types::Address;
+use std::{error::Error, fs};
+
+use crate::config;
+
+fn ranges_csv(depth: &LiquidityDepth) -> String {
+    let mut csv =
+        "tick_lower,tick_upper,liquidity,price_lower,price_upper,amount0,amount1\n".to_string();
+    for range in &depth.ranges {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{}\n",
+            range.tick_lower,
+            range.tick_upper,
+            range.liquidity,
+            range.price_lower,
+            range.price_upper,
+            range.amount0,
+            range.amount1
+        ));
+    }
+    csv
+}
+
+fn levels_csv(depth: &LiquidityDepth) -> String {
+    let mut csv = "percent,price,amount0,amount1\n".to_string();
+    for level in &depth.levels {
+        csv.push_str(&format!(
+            "{},{},{},{}\n",
+            level.percent, level.price, level.amount0, level.amount1
+        ));
+    }
+    csv
+}
+
+/// Export the liquidity depth of a UniswapV3 pool as JSON or CSV.
+/// Without `output` it is printed, CSV ranges first and then levels.
+/// With it, JSON goes to `<output>.json` and CSV to `<output>_ranges.csv` and `<output>_levels.csv`.
+pub async fn export_depth(
+    pool: String,
+    percents: Vec<f64>,
+    format: String,
+    output: Option<String>,
+    config_name: String,
+    offline: bool,
+) -> Result<(), Box<dyn Error>> {
+    let pool_address = pool.parse::<Address>()?;
+    let conf = config::get_config(config_name);
+    let store = RedisStore::open(&conf.redis_url)?;
+    let chain_id = 42161;
+    let depth = get_liquidity_depth(
+        &store,
+        chain_id,
+        &conf.json_rpc_url,
+        pool_address,
+        &percents,
+        offline,
+    )
+    .await?;
+    match (format.as_str(), output) {
+        ("csv", Some(output)) => {
+            fs::write(format!("{output}_ranges.csv"), ranges_csv(&depth))?;
+            fs::write(format!("{output}_levels.csv"), levels_csv(&depth))?;
+        }
+        ("csv", None) => println!("{}\n{}", ranges_csv(&depth), levels_csv(&depth)),
+        (_, Some(output)) => fs::write(
+            format!("{output}.json"),
+            serde_json::to_string_pretty(&depth)?,
+        )?,
+        (_, None) => println!("{}", serde_json::to_string_pretty(&depth)?),
+    }
+    Ok(())
+}
@@ -1 +1,4 @@
+pub mod arb;
+pub mod depth;
 pub mod path;
+pub mod token;
@@ -1,6 +1,15 @@
-use dexquote::path::{get_amount_out_from_path, get_possible_paths, PoolInfo};
+use dexquote::{
+    db::RedisStore,
+    graph::{export_routing_graph_to_neo4j, RoutingGraph},
+    path::{
+        get_amount_in_from_path, get_amount_out_from_path, get_quote_price_from_path,
+        get_split_amount_out, PoolInfo,
+    },
+    price::QuotePrice,
+};
 use ethers::types::{Address, U256};
-use neo4rs::Graph;
+use std::cmp::Reverse;
+use tracing::{info, warn};
 
 use crate::config;
 
@@ -8,9 +17,18 @@ use crate::config;
 struct Route {
     pub _info: Vec<PoolInfo>,
     pub estimated_amount_out: U256,
+    // None if the decimals of a token are unknown
+    pub _price: Option<QuotePrice>,
 }
 
-async fn get_paths(
+#[derive(Debug)]
+struct RouteAmountIn {
+    pub _info: Vec<PoolInfo>,
+    pub estimated_amount_in: U256,
+    pub _price: Option<QuotePrice>,
+}
+
+fn get_paths(
     token_in: String,
     token_out: String,
     hop: u64,
@@ -18,21 +36,17 @@ async fn get_paths(
     config_name: String,
 ) -> Vec<Vec<PoolInfo>> {
     let conf = config::get_config(config_name);
-    let graph = Graph::new(conf.neo4j_uri, "neo4j", conf.neo4j_pass)
-        .await
-        .unwrap();
+    let store = RedisStore::open(&conf.redis_url).unwrap();
+    let chain_id = 42161;
+    let graph = RoutingGraph::from_store(&store, chain_id).unwrap();
     let token_in = token_in.parse::<Address>().unwrap();
     let token_out = token_out.parse::<Address>().unwrap();
-    get_possible_paths(
-        &graph,
+    graph.get_paths(
         token_in,
         token_out,
-        hop,
-        path_result_limit,
-        conf.chain_label.as_str(),
+        hop as usize,
+        path_result_limit as usize,
     )
-    .await
-    .unwrap()
 }
 
 pub async fn show_paths(
@@ -42,7 +56,7 @@ pub async fn show_paths(
     path_result_limit: u64,
     config_name: String,
 ) {
-    let paths = get_paths(token_in, token_out, hop, path_result_limit, config_name).await;
+    let paths = get_paths(token_in, token_out, hop, path_result_limit, config_name);
     println!("{:#?}", paths);
 }
 
@@ -53,37 +67,177 @@ pub async fn show_best_prices(
     path_result_limit: u64,
     amount_in: String,
     config_name: String,
+    offline: bool,
 ) {
-    let paths = get_paths(
-        token_in,
-        token_out,
-        hop,
-        path_result_limit,
-        config_name.clone(),
-    )
-    .await;
+    let token_in = token_in.parse::<Address>().unwrap();
+    let token_out = token_out.parse::<Address>().unwrap();
     let amount_in = U256::from_dec_str(amount_in.as_str()).unwrap();
     let conf = config::get_config(config_name);
+    let store = RedisStore::open(&conf.redis_url).unwrap();
     let chain_id = 42161;
+    let graph = RoutingGraph::from_store(&store, chain_id).unwrap();
+    // the paths with the best estimates from reserves and fees are simulated
+    let paths = graph.get_ranked_paths(
+        token_in,
+        token_out,
+        amount_in,
+        hop as usize,
+        path_result_limit as usize,
+    );
     let mut routes = Vec::new();
-    for path in paths {
-        let estimated_amount_out = get_amount_out_from_path(
-            &conf.redis_url,
+    for (path, _) in paths {
+        let estimated_amount_out = match get_amount_out_from_path(
+            &store,
             chain_id,
             conf.json_rpc_url.as_str(),
             amount_in,
             path.as_slice(),
+            offline,
         )
         .await
-        .unwrap();
+        {
+            Ok(estimated_amount_out) => estimated_amount_out,
+            Err(e) => {
+                warn!("skip path {:?}: {}", path, e);
+                continue;
+            }
+        };
+        let price =
+            get_quote_price_from_path(&store, chain_id, &path, amount_in, estimated_amount_out)
+                .ok();
         let route = Route {
             _info: path,
             estimated_amount_out,
+            _price: price,
         };
         routes.push(route);
     }
 
-    // sort by estimated_amount_out
-    routes.sort_by(|a, b| b.estimated_amount_out.cmp(&a.estimated_amount_out));
+    // sort by the simulated output, after fees and taxes
+    routes.sort_by_key(|route| Reverse(route.estimated_amount_out));
     println!("{:#?}", routes);
 }
+
+pub async fn show_best_prices_for_amount_out(
+    token_in: String,
+    token_out: String,
+    hop: u64,
+    path_result_limit: u64,
+    amount_out: String,
+    config_name: String,
+    offline: bool,
+) {
+    let token_in = token_in.parse::<Address>().unwrap();
+    let token_out = token_out.parse::<Address>().unwrap();
+    let amount_out = U256::from_dec_str(amount_out.as_str()).unwrap();
+    let conf = config::get_config(config_name);
+    let store = RedisStore::open(&conf.redis_url).unwrap();
+    let chain_id = 42161;
+    let graph = RoutingGraph::from_store(&store, chain_id).unwrap();
+    let paths = graph.get_ranked_paths_for_amount_out(
+        token_in,
+        token_out,
+        amount_out,
+        hop as usize,
+        path_result_limit as usize,
+    );
+    let mut routes = Vec::new();
+    for (path, _) in paths {
+        let estimated_amount_in = match get_amount_in_from_path(
+            &store,
+            chain_id,
+            conf.json_rpc_url.as_str(),
+            amount_out,
+            path.as_slice(),
+            offline,
+        )
+        .await
+        {
+            Ok(estimated_amount_in) => estimated_amount_in,
+            Err(e) => {
+                warn!("skip path {:?}: {}", path, e);
+                continue;
+            }
+        };
+        let price =
+            get_quote_price_from_path(&store, chain_id, &path, estimated_amount_in, amount_out)
+                .ok();
+        let route = RouteAmountIn {
+            _info: path,
+            estimated_amount_in,
+            _price: price,
+        };
+        routes.push(route);
+    }
+
+    // sort by estimated_amount_in, the cheapest first
+    routes.sort_by_key(|route| route.estimated_amount_in);
+    println!("{:#?}", routes);
+}
+
+/// Split the input across the best ranked paths, pools shared by paths are simulated in turn
+#[allow(clippy::too_many_arguments)]
+pub async fn show_split_route(
+    token_in: String,
+    token_out: String,
+    hop: u64,
+    path_result_limit: u64,
+    amount_in: String,
+    slices: usize,
+    config_name: String,
+    offline: bool,
+) {
+    let token_in = token_in.parse::<Address>().unwrap();
+    let token_out = token_out.parse::<Address>().unwrap();
+    let amount_in = U256::from_dec_str(amount_in.as_str()).unwrap();
+    let conf = config::get_config(config_name);
+    let store = RedisStore::open(&conf.redis_url).unwrap();
+    let chain_id = 42161;
+    let graph = RoutingGraph::from_store(&store, chain_id).unwrap();
+    let paths: Vec<Vec<PoolInfo>> = graph
+        .get_ranked_paths(
+            token_in,
+            token_out,
+            amount_in,
+            hop as usize,
+            path_result_limit as usize,
+        )
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect();
+    match get_split_amount_out(
+        &store,
+        chain_id,
+        conf.json_rpc_url.as_str(),
+        amount_in,
+        &paths,
+        slices,
+        offline,
+    )
+    .await
+    {
+        Ok(split) => println!("{:#?}", split),
+        Err(e) => warn!("failed to split {}: {}", amount_in, e),
+    }
+}
+
+/// Write the pools of the store to Neo4j
+pub async fn export_neo4j(config_name: String) {
+    let conf = config::get_config(config_name);
+    let graph = match config::connect_neo4j(&conf).await.unwrap() {
+        Some(graph) => graph,
+        None => {
+            warn!("neo4j_uri is not configured");
+            return;
+        }
+    };
+    let store = RedisStore::open(&conf.redis_url).unwrap();
+    let chain_id = 42161;
+    let routing_graph = RoutingGraph::from_store(&store, chain_id).unwrap();
+    export_routing_graph_to_neo4j(&routing_graph, &graph, &conf.chain_label).await;
+    info!(
+        "exported {} pools of {} tokens",
+        routing_graph.pool_count(),
+        routing_graph.token_count()
+    );
+}
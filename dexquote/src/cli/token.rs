@@ -0,0 +1,70 @@
+use dexquote::{
+    constants::provider::get_provider,
+    db::{
+        multicall::get_batcher,
+        token::{add_token_from_provider, detect_token_tax, TokenTax},
+        PoolStore, RedisStore,
+    },
+};
+use ethers::{providers::Middleware, types::Address};
+use std::{error::Error, sync::Arc};
+use tracing::info;
+
+use crate::config;
+
+// the token hash is created first so that the taxes are not stored without its metadata
+async fn save_token_tax(
+    token: Address,
+    tax: TokenTax,
+    config_name: String,
+) -> Result<(), Box<dyn Error>> {
+    if !tax.is_valid() {
+        return Err(format!("taxes must be below 10000 bps: {tax:?}").into());
+    }
+    let conf = config::get_config(config_name);
+    let store = RedisStore::open(&conf.redis_url)?;
+    let batcher = get_batcher(&conf.json_rpc_url)?;
+    let chain_id = batcher.client().get_chainid().await?.as_u64();
+    if store.get_token(chain_id, token)?.is_none() {
+        // scam status unknown
+        add_token_from_provider(&store, chain_id, &batcher, token, 2).await?;
+    }
+    store.update_token_tax(chain_id, token, tax)?;
+    info!("set taxes of {:?}: {:?}", token, tax);
+    Ok(())
+}
+
+pub async fn set_token_tax(
+    token: String,
+    buy: u32,
+    sell: u32,
+    transfer: u32,
+    config_name: String,
+) -> Result<(), Box<dyn Error>> {
+    let token = token.parse::<Address>()?;
+    let tax = TokenTax {
+        buy,
+        sell,
+        transfer,
+    };
+    save_token_tax(token, tax, config_name).await
+}
+
+/// Measure the taxes of a token on a local fork, e.g. `anvil --fork-url <json_rpc_url>`
+pub async fn detect_tax(
+    token: String,
+    pool: String,
+    fork_url: String,
+    save: bool,
+    config_name: String,
+) -> Result<(), Box<dyn Error>> {
+    let token = token.parse::<Address>()?;
+    let pool = pool.parse::<Address>()?;
+    let fork = Arc::new(get_provider(&fork_url)?);
+    let tax = detect_token_tax(fork, token, pool).await?;
+    println!("{tax:#?}");
+    if save {
+        save_token_tax(token, tax, config_name).await?;
+    }
+    Ok(())
+}
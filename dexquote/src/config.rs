@@ -1,3 +1,5 @@
+use dexquote::db::multicall::{set_multicall_config, MulticallConfig};
+use neo4rs::Graph;
 use serde_derive::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -8,6 +10,43 @@ pub struct Config {
     pub neo4j_uri: String,
     pub neo4j_pass: String,
     pub chain_label: String,
+    /// UniswapV2 fork factories and the fees their pairs charge
+    #[serde(default)]
+    pub univ2_factories: Vec<FactoryConfig>,
+    /// calls per Multicall3 request and requests in flight of the reads from the node
+    #[serde(default = "default_multicall_batch_size")]
+    pub multicall_batch_size: usize,
+    #[serde(default = "default_multicall_concurrency")]
+    pub multicall_concurrency: usize,
+}
+
+fn default_multicall_batch_size() -> usize {
+    MulticallConfig::default().batch_size
+}
+
+fn default_multicall_concurrency() -> usize {
+    MulticallConfig::default().concurrency
+}
+
+impl Config {
+    pub fn multicall_config(&self) -> MulticallConfig {
+        MulticallConfig {
+            batch_size: self.multicall_batch_size,
+            concurrency: self.multicall_concurrency,
+            ..Default::default()
+        }
+    }
+}
+
+/// A UniswapV2 fork factory, fees are in 1e-5 (300 = 0.3%)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FactoryConfig {
+    pub address: String,
+    pub creation_block: u64,
+    pub fee: u32,
+    /// fee of token1 -> token0 swaps when it differs from `fee`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_one_for_zero: Option<u32>,
 }
 
 impl Default for Config {
@@ -19,6 +58,9 @@ impl Default for Config {
             neo4j_uri: "".into(),
             neo4j_pass: "".into(),
             chain_label: "".into(),
+            univ2_factories: vec![],
+            multicall_batch_size: default_multicall_batch_size(),
+            multicall_concurrency: default_multicall_concurrency(),
         }
     }
 }
@@ -27,5 +69,16 @@ const APP_NAME: &str = "dexquote";
 
 pub fn get_config(config_name: String) -> Config {
     let cfg: Config = confy::load(APP_NAME, config_name.as_str()).unwrap();
+    // the db reads from the node share a batcher per url
+    set_multicall_config(&cfg.json_rpc_url, cfg.multicall_config());
     cfg
 }
+
+/// Connect to Neo4j, which is only used to export pools. Returns None without a `neo4j_uri`.
+pub async fn connect_neo4j(conf: &Config) -> Result<Option<Graph>, neo4rs::Error> {
+    if conf.neo4j_uri.is_empty() {
+        return Ok(None);
+    }
+    let graph = Graph::new(&conf.neo4j_uri, "neo4j", &conf.neo4j_pass).await?;
+    Ok(Some(graph))
+}
@@ -0,0 +1,150 @@
+use super::{multicall::MulticallBatcher, token::erc20_address_to_decimals, PoolStore};
+use crate::{types::DexQuoteResult, utils::address_str};
+use ethers::{abi::AbiEncode, prelude::*, utils::keccak256};
+
+// Algebra pools share the `ticks(int24)` layout with UniswapV3,
+// but the tick bitmap is called `tickTable`
+// ref. https://github.com/cryptoalgebra/AlgebraV1/blob/main/src/core/contracts/interfaces/pool/IAlgebraPoolState.sol
+abigen!(
+    AlgebraPoolContract,
+    r#"[
+        function token0() external view returns (address)
+        function token1() external view returns (address)
+        function liquidity() external view returns (uint128)
+        function tickSpacing() external view returns (int24)
+        function tickTable(int16) external view returns (uint256)
+    ]"#,
+);
+
+// tick spacing of pools that predate the `TickSpacing` event
+pub const ALGEBRA_DEFAULT_TICK_SPACING: i32 = 60;
+
+/// An Algebra pool, the fee is the current dynamic fee
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AlgebraPool {
+    pub address: Address,
+    pub token_a: Address,
+    pub token_a_decimals: u8,
+    pub token_b: Address,
+    pub token_b_decimals: u8,
+    pub liquidity: u128,
+    pub sqrt_price: U256,
+    pub tick: i32,
+    pub tick_spacing: i32,
+    pub fee: u32,
+    // Camelot pools charge a separate fee for one for zero swaps
+    pub fee_one_for_zero: Option<u32>,
+}
+
+impl AlgebraPool {
+    /// Fetch a pool from the node
+    pub async fn new_from_address<M: Middleware + 'static>(
+        pool_address: Address,
+        batcher: &MulticallBatcher<M>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let contract = AlgebraPoolContract::new(pool_address, batcher.client());
+        let (token_a, token_b, liquidity, tick_spacing, global_state) = tokio::join!(
+            batcher.call(contract.token_0()),
+            batcher.call(contract.token_1()),
+            batcher.call(contract.liquidity()),
+            batcher.call(contract.tick_spacing()),
+            get_global_state(pool_address, batcher),
+        );
+        let (token_a, token_b, liquidity) = (token_a?, token_b?, liquidity?);
+        let tick_spacing = tick_spacing.unwrap_or(ALGEBRA_DEFAULT_TICK_SPACING);
+        let (sqrt_price, tick, fee, fee_one_for_zero) = global_state?;
+        let (token_a_decimals, token_b_decimals) = tokio::try_join!(
+            erc20_address_to_decimals(batcher, token_a),
+            erc20_address_to_decimals(batcher, token_b),
+        )?;
+        Ok(Self {
+            address: pool_address,
+            token_a,
+            token_a_decimals,
+            token_b,
+            token_b_decimals,
+            liquidity,
+            sqrt_price,
+            tick,
+            tick_spacing,
+            fee,
+            fee_one_for_zero,
+        })
+    }
+}
+
+// `globalState` returns (price, tick, fee, timepointIndex, ...) on Algebra V1 and
+// (price, tick, feeZto, feeOtz, timepointIndex, ...) on Camelot, which has one more word
+async fn get_global_state<M: Middleware + 'static>(
+    pool_address: Address,
+    batcher: &MulticallBatcher<M>,
+) -> Result<(U256, i32, u32, Option<u32>), Box<dyn std::error::Error>> {
+    let selector = keccak256("globalState()".as_bytes())[..4].to_vec();
+    let data = batcher.call_raw(pool_address, selector.into()).await?;
+    let words: Vec<U256> = data.chunks(32).map(U256::from_big_endian).collect();
+    if words.len() < 7 {
+        return Err(format!("unexpected globalState of {:?}", pool_address).into());
+    }
+    let sqrt_price = words[0];
+    let tick = I256::from_raw(words[1]).as_i32();
+    let fee = words[2].as_u32();
+    let fee_one_for_zero = if words.len() >= 8 {
+        Some(words[3].as_u32())
+    } else {
+        None
+    };
+    Ok((sqrt_price, tick, fee, fee_one_for_zero))
+}
+
+pub fn add_pool<S: PoolStore>(store: &S, chain_id: u64, pool: AlgebraPool) -> DexQuoteResult<()> {
+    let mut fields = vec![
+        ("fee", pool.fee.to_string()),
+        ("token0", address_str(pool.token_a)),
+        ("token0_decimals", pool.token_a_decimals.to_string()),
+        ("token1", address_str(pool.token_b)),
+        ("token1_decimals", pool.token_b_decimals.to_string()),
+        ("liquidity", pool.liquidity.to_string()),
+        ("sqrt_price", pool.sqrt_price.encode_hex()),
+        ("tick", pool.tick.to_string()),
+        ("tick_spacing", pool.tick_spacing.to_string()),
+        ("liquidity_net", "0".to_string()),
+        ("dex", "ALGEBRA".to_string()),
+    ];
+    if let Some(fee_one_for_zero) = pool.fee_one_for_zero {
+        fields.push(("fee_one_for_zero", fee_one_for_zero.to_string()));
+    }
+    store.update_pool_hashmap(chain_id, pool.address, &fields)
+}
+
+pub fn update_fee<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    fee: u32,
+    fee_one_for_zero: Option<u32>,
+) -> DexQuoteResult<()> {
+    match fee_one_for_zero {
+        Some(fee_one_for_zero) => store.update_pool_hashmap(
+            chain_id,
+            pool_address,
+            &[
+                ("fee", fee.to_string()),
+                ("fee_one_for_zero", fee_one_for_zero.to_string()),
+            ],
+        ),
+        None => store.update_pool_hashmap(chain_id, pool_address, &[("fee", fee.to_string())]),
+    }
+}
+
+pub fn update_tick_spacing<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    tick_spacing: i32,
+) -> DexQuoteResult<()> {
+    store.update_pool_hashmap(
+        chain_id,
+        pool_address,
+        &[("tick_spacing", tick_spacing.to_string())],
+    )
+}
@@ -0,0 +1,303 @@
+use super::{multicall::MulticallBatcher, token::erc20_addresses_to_decimals, PoolStore};
+use crate::{types::DexQuoteResult, utils::address_str};
+use ethers::prelude::*;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// The Vault holds the tokens of every pool and emits their swaps,
+/// it has the same address on every chain
+pub const BALANCER_V2_VAULT: &str = "0xBA12222222228d8Ba445958a75a0704d566BF2C8";
+
+// ref. https://github.com/balancer/balancer-v2-monorepo/blob/master/pkg/vault/contracts/Vault.sol
+abigen!(
+    BalancerVault,
+    r#"[
+        function getPoolTokens(bytes32 poolId) external view returns (address[] tokens, uint256[] balances, uint256 lastChangeBlock)
+    ]"#,
+);
+
+// ref. https://github.com/balancer/balancer-v2-monorepo/blob/master/pkg/pool-weighted/contracts/WeightedPool.sol
+abigen!(
+    BalancerPoolContract,
+    r#"[
+        function getPoolId() external view returns (bytes32)
+        function getSwapFeePercentage() external view returns (uint256)
+        function getScalingFactors() external view returns (uint256[])
+        function getNormalizedWeights() external view returns (uint256[])
+        function getAmplificationParameter() external view returns (uint256 value, bool isUpdating, uint256 precision)
+    ]"#,
+);
+
+pub fn vault_address() -> Address {
+    BALANCER_V2_VAULT.parse().unwrap()
+}
+
+/// The first 20 bytes of a pool id are the pool address
+pub fn pool_id_to_address(pool_id: H256) -> Address {
+    Address::from_slice(&pool_id.as_bytes()[..20])
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BalancerPoolKind {
+    #[default]
+    Weighted,
+    Stable,
+}
+
+pub struct ParseBalancerPoolKindError;
+
+impl FromStr for BalancerPoolKind {
+    type Err = ParseBalancerPoolKindError;
+
+    fn from_str(kind_str: &str) -> Result<Self, Self::Err> {
+        match kind_str {
+            "WEIGHTED" => Ok(BalancerPoolKind::Weighted),
+            "STABLE" => Ok(BalancerPoolKind::Stable),
+            _ => Err(ParseBalancerPoolKindError),
+        }
+    }
+}
+
+impl BalancerPoolKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BalancerPoolKind::Weighted => "WEIGHTED",
+            BalancerPoolKind::Stable => "STABLE",
+        }
+    }
+}
+
+/// A Balancer V2 weighted or stable pool.
+/// The BPT of composable stable pools is left out of the tokens.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BalancerPool {
+    pub address: Address,
+    pub pool_id: H256,
+    pub kind: BalancerPoolKind,
+    pub tokens: Vec<Address>,
+    pub decimals: Vec<u8>,
+    pub balances: Vec<U256>,
+    // 1e18 * 10 ** (18 - decimals), times the token rate on composable stable pools
+    pub scaling_factors: Vec<U256>,
+    // normalized weights in 1e18, weighted pools only
+    pub weights: Vec<U256>,
+    // in 1e18
+    pub swap_fee: U256,
+    // amplification times 1e3 ramping from start to end, stable pools only
+    pub amp_start: U256,
+    pub amp_end: U256,
+    pub amp_start_time: u64,
+    pub amp_end_time: u64,
+}
+
+/// Scaling factor of a token without a rate provider
+pub fn get_scaling_factor(decimals: u8) -> U256 {
+    U256::exp10(36 - decimals as usize)
+}
+
+impl BalancerPool {
+    /// Fetch a pool from the node
+    pub async fn new_from_address<M: Middleware + 'static>(
+        pool_address: Address,
+        batcher: &MulticallBatcher<M>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let contract = BalancerPoolContract::new(pool_address, batcher.client());
+        let vault = BalancerVault::new(vault_address(), batcher.client());
+        let pool_id = H256::from(batcher.call(contract.get_pool_id()).await?);
+        // weighted pools revert on `getAmplificationParameter` and stable pools on
+        // `getNormalizedWeights`, both are read to save a round trip
+        let (pool_tokens, scaling_factors, swap_fee, weights, amp) = tokio::join!(
+            batcher.call(vault.get_pool_tokens(pool_id.into())),
+            batcher.call(contract.get_scaling_factors()),
+            batcher.call(contract.get_swap_fee_percentage()),
+            batcher.call(contract.get_normalized_weights()),
+            batcher.call(contract.get_amplification_parameter()),
+        );
+        let (tokens, balances, _) = pool_tokens?;
+        let scaling_factors = scaling_factors.ok();
+        let mut pool = Self {
+            address: pool_address,
+            pool_id,
+            swap_fee: swap_fee?,
+            ..Default::default()
+        };
+        match weights {
+            Ok(weights) => pool.weights = weights,
+            Err(_) => {
+                let (amp, _, _) = amp?;
+                pool.kind = BalancerPoolKind::Stable;
+                pool.amp_start = amp;
+                pool.amp_end = amp;
+            }
+        }
+        // the BPT of composable stable pools
+        let pool_tokens: Vec<(usize, Address, U256)> = tokens
+            .into_iter()
+            .zip(balances)
+            .enumerate()
+            .filter(|(_, (token, _))| *token != pool_address)
+            .map(|(i, (token, balance))| (i, token, balance))
+            .collect();
+        let token_addresses: Vec<Address> =
+            pool_tokens.iter().map(|(_, token, _)| *token).collect();
+        let decimals = erc20_addresses_to_decimals(batcher, &token_addresses).await?;
+        for ((i, token, balance), decimals) in pool_tokens.into_iter().zip(decimals) {
+            pool.tokens.push(token);
+            pool.decimals.push(decimals);
+            pool.balances.push(balance);
+            pool.scaling_factors.push(match &scaling_factors {
+                Some(scaling_factors) => scaling_factors[i],
+                None => get_scaling_factor(decimals),
+            });
+        }
+        Ok(pool)
+    }
+
+    pub fn token_index(&self, token: Address) -> Option<usize> {
+        self.tokens
+            .iter()
+            .position(|pool_token| *pool_token == token)
+    }
+}
+
+pub fn get_pool<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+) -> DexQuoteResult<Option<BalancerPool>> {
+    let target_data = store.get_pool_hashmap(chain_id, pool_address)?;
+    if target_data.is_empty() {
+        return Ok(None);
+    }
+    Ok(hashmap_to_balancer(pool_address, target_data))
+}
+
+pub fn hashmap_to_balancer(
+    pool_address: Address,
+    target_data: HashMap<String, String>,
+) -> Option<BalancerPool> {
+    let n_tokens: usize = target_data.get("n_tokens")?.parse().unwrap();
+    let kind = BalancerPoolKind::from_str(target_data.get("pool_kind")?).ok()?;
+    let mut pool = BalancerPool {
+        address: pool_address,
+        pool_id: target_data.get("pool_id")?.parse().unwrap(),
+        kind,
+        swap_fee: U256::from_dec_str(target_data.get("swap_fee")?).unwrap(),
+        ..Default::default()
+    };
+    if kind == BalancerPoolKind::Stable {
+        pool.amp_start = U256::from_dec_str(target_data.get("amp_start")?).unwrap();
+        pool.amp_end = U256::from_dec_str(target_data.get("amp_end")?).unwrap();
+        pool.amp_start_time = target_data.get("amp_start_time")?.parse().unwrap();
+        pool.amp_end_time = target_data.get("amp_end_time")?.parse().unwrap();
+    }
+    for i in 0..n_tokens {
+        pool.tokens
+            .push(target_data.get(&format!("token{i}"))?.parse().unwrap());
+        pool.decimals.push(
+            target_data
+                .get(&format!("token{i}_decimals"))?
+                .parse()
+                .unwrap(),
+        );
+        pool.balances
+            .push(U256::from_dec_str(target_data.get(&format!("balance{i}"))?).unwrap());
+        pool.scaling_factors
+            .push(U256::from_dec_str(target_data.get(&format!("scaling_factor{i}"))?).unwrap());
+        if kind == BalancerPoolKind::Weighted {
+            pool.weights
+                .push(U256::from_dec_str(target_data.get(&format!("weight{i}"))?).unwrap());
+        }
+    }
+    Some(pool)
+}
+
+pub fn add_pool<S: PoolStore>(store: &S, chain_id: u64, pool: &BalancerPool) -> DexQuoteResult<()> {
+    let mut fields = vec![
+        ("n_tokens".to_string(), pool.tokens.len().to_string()),
+        ("pool_id".to_string(), format!("{:?}", pool.pool_id)),
+        ("pool_kind".to_string(), pool.kind.as_str().to_string()),
+        ("swap_fee".to_string(), pool.swap_fee.to_string()),
+        ("dex".to_string(), "BALANCERV2".to_string()),
+    ];
+    if pool.kind == BalancerPoolKind::Stable {
+        fields.extend([
+            ("amp_start".to_string(), pool.amp_start.to_string()),
+            ("amp_end".to_string(), pool.amp_end.to_string()),
+            (
+                "amp_start_time".to_string(),
+                pool.amp_start_time.to_string(),
+            ),
+            ("amp_end_time".to_string(), pool.amp_end_time.to_string()),
+        ]);
+    }
+    for (i, token) in pool.tokens.iter().enumerate() {
+        fields.push((format!("token{i}"), address_str(*token)));
+        fields.push((format!("token{i}_decimals"), pool.decimals[i].to_string()));
+        fields.push((format!("balance{i}"), pool.balances[i].to_string()));
+        fields.push((
+            format!("scaling_factor{i}"),
+            pool.scaling_factors[i].to_string(),
+        ));
+        if pool.kind == BalancerPoolKind::Weighted {
+            fields.push((format!("weight{i}"), pool.weights[i].to_string()));
+        }
+    }
+    let fields: Vec<(&str, String)> = fields
+        .iter()
+        .map(|(field, value)| (field.as_str(), value.clone()))
+        .collect();
+    store.update_pool_hashmap(chain_id, pool.address, &fields)
+}
+
+pub fn update_balances<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    balances: &[U256],
+) -> DexQuoteResult<()> {
+    let fields: Vec<(String, String)> = balances
+        .iter()
+        .enumerate()
+        .map(|(i, balance)| (format!("balance{i}"), balance.to_string()))
+        .collect();
+    let fields: Vec<(&str, String)> = fields
+        .iter()
+        .map(|(field, value)| (field.as_str(), value.clone()))
+        .collect();
+    store.update_pool_hashmap(chain_id, pool_address, &fields)
+}
+
+pub fn update_swap_fee<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    swap_fee: U256,
+) -> DexQuoteResult<()> {
+    store.update_pool_hashmap(
+        chain_id,
+        pool_address,
+        &[("swap_fee", swap_fee.to_string())],
+    )
+}
+
+pub fn update_amp<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    amp_start: U256,
+    amp_end: U256,
+    amp_start_time: u64,
+    amp_end_time: u64,
+) -> DexQuoteResult<()> {
+    store.update_pool_hashmap(
+        chain_id,
+        pool_address,
+        &[
+            ("amp_start", amp_start.to_string()),
+            ("amp_end", amp_end.to_string()),
+            ("amp_start_time", amp_start_time.to_string()),
+            ("amp_end_time", amp_end_time.to_string()),
+        ],
+    )
+}
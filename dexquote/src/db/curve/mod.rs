@@ -0,0 +1,300 @@
+use super::{multicall::MulticallBatcher, token::erc20_addresses_to_decimals, PoolStore};
+use crate::{types::DexQuoteResult, utils::address_str};
+use ethers::prelude::*;
+use std::collections::HashMap;
+
+// ref. https://github.com/curvefi/curve-contract/blob/master/contracts/pools/3pool/StableSwap3Pool.vy
+abigen!(
+    CurvePoolContract,
+    r#"[
+        function coins(uint256) external view returns (address)
+        function balances(uint256) external view returns (uint256)
+        function initial_A() external view returns (uint256)
+        function future_A() external view returns (uint256)
+        function initial_A_time() external view returns (uint256)
+        function future_A_time() external view returns (uint256)
+        function A() external view returns (uint256)
+        function A_precise() external view returns (uint256)
+        function fee() external view returns (uint256)
+        function admin_fee() external view returns (uint256)
+        function lp_token() external view returns (address)
+        function totalSupply() external view returns (uint256)
+        function base_pool() external view returns (address)
+    ]"#,
+);
+
+// Curve uses this address for ETH
+const ETH_ADDRESS: &str = "0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE";
+// plain pools hold at most 8 coins
+const MAX_COINS: u64 = 8;
+
+/// A Curve StableSwap plain or meta pool
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CurvePool {
+    pub address: Address,
+    pub coins: Vec<Address>,
+    pub decimals: Vec<u8>,
+    pub balances: Vec<U256>,
+    // balances are multiplied by rate / 1e18 to get 18 decimal amounts
+    pub rates: Vec<U256>,
+    pub initial_a: U256,
+    pub future_a: U256,
+    pub initial_a_time: u64,
+    pub future_a_time: u64,
+    // 100 on pools storing A * 100, 1 on older pools
+    pub a_precision: U256,
+    // in 1e10
+    pub fee: U256,
+    pub admin_fee: U256,
+    pub total_supply: U256,
+    // the pool whose LP token is the last coin of a meta pool
+    pub base_pool: Option<Address>,
+}
+
+/// Rate of a coin with `decimals` decimals, 10 ** (36 - decimals)
+pub fn get_rate(decimals: u8) -> U256 {
+    U256::exp10(36 - decimals as usize)
+}
+
+impl CurvePool {
+    /// Fetch a pool from the node
+    pub async fn new_from_address<M: Middleware + 'static>(
+        pool_address: Address,
+        batcher: &MulticallBatcher<M>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let contract = CurvePoolContract::new(pool_address, batcher.client());
+        let eth: Address = ETH_ADDRESS.parse().unwrap();
+        // `coins` reverts past the last coin
+        let coin_calls = (0..MAX_COINS).map(|i| contract.coins(i.into())).collect();
+        let coins: Vec<Address> = batcher
+            .try_call_all(coin_calls)
+            .await?
+            .into_iter()
+            .map_while(Result::ok)
+            .collect();
+        if coins.len() < 2 {
+            return Err(format!("{:?} is not a Curve pool", pool_address).into());
+        }
+        let tokens: Vec<Address> = coins.iter().copied().filter(|coin| *coin != eth).collect();
+        let mut token_decimals = erc20_addresses_to_decimals(batcher, &tokens)
+            .await?
+            .into_iter();
+        let decimals: Vec<u8> = coins
+            .iter()
+            .map(|coin| match *coin == eth {
+                true => 18,
+                false => token_decimals.next().unwrap(),
+            })
+            .collect();
+        let (balances, total_supply, a, a_precise, base_pool) = tokio::join!(
+            get_balances(pool_address, coins.len(), batcher),
+            get_total_supply(pool_address, batcher),
+            batcher.call(contract.a()),
+            batcher.call(contract.a_precise()),
+            batcher.call(contract.base_pool()),
+        );
+        let a = a?;
+        let a_precision = match a_precise {
+            Ok(a_precise) if !a.is_zero() => a_precise / a,
+            _ => U256::one(),
+        };
+        let (initial_a, future_a, initial_a_time, future_a_time, fee, admin_fee) = tokio::try_join!(
+            batcher.call(contract.initial_a()),
+            batcher.call(contract.future_a()),
+            batcher.call(contract.initial_a_time()),
+            batcher.call(contract.future_a_time()),
+            batcher.call(contract.fee()),
+            batcher.call(contract.admin_fee()),
+        )?;
+        Ok(Self {
+            address: pool_address,
+            rates: decimals
+                .iter()
+                .map(|decimals| get_rate(*decimals))
+                .collect(),
+            coins,
+            decimals,
+            balances: balances?,
+            initial_a,
+            future_a,
+            initial_a_time: initial_a_time.as_u64(),
+            future_a_time: future_a_time.as_u64(),
+            a_precision,
+            fee,
+            admin_fee,
+            total_supply: total_supply?,
+            base_pool: base_pool.ok(),
+        })
+    }
+
+    pub fn coin_index(&self, token: Address) -> Option<usize> {
+        self.coins.iter().position(|coin| *coin == token)
+    }
+}
+
+pub async fn get_balances<M: Middleware + 'static>(
+    pool_address: Address,
+    n_coins: usize,
+    batcher: &MulticallBatcher<M>,
+) -> DexQuoteResult<Vec<U256>> {
+    let contract = CurvePoolContract::new(pool_address, batcher.client());
+    let calls = (0..n_coins).map(|i| contract.balances(i.into())).collect();
+    batcher.call_all(calls).await
+}
+
+// newer pools are their own LP token, older ones have a separate `lp_token`
+pub async fn get_total_supply<M: Middleware + 'static>(
+    pool_address: Address,
+    batcher: &MulticallBatcher<M>,
+) -> DexQuoteResult<U256> {
+    let contract = CurvePoolContract::new(pool_address, batcher.client());
+    match batcher.call(contract.total_supply()).await {
+        Ok(total_supply) => Ok(total_supply),
+        Err(_) => {
+            let lp_token = batcher.call(contract.lp_token()).await?;
+            let lp_contract = CurvePoolContract::new(lp_token, batcher.client());
+            batcher.call(lp_contract.total_supply()).await
+        }
+    }
+}
+
+pub fn get_pool<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+) -> DexQuoteResult<Option<CurvePool>> {
+    let target_data = store.get_pool_hashmap(chain_id, pool_address)?;
+    if target_data.is_empty() {
+        return Ok(None);
+    }
+    Ok(hashmap_to_curve(pool_address, target_data))
+}
+
+pub fn hashmap_to_curve(
+    pool_address: Address,
+    target_data: HashMap<String, String>,
+) -> Option<CurvePool> {
+    let n_coins: usize = target_data.get("n_coins")?.parse().unwrap();
+    let mut pool = CurvePool {
+        address: pool_address,
+        initial_a: U256::from_dec_str(target_data.get("initial_a")?).unwrap(),
+        future_a: U256::from_dec_str(target_data.get("future_a")?).unwrap(),
+        initial_a_time: target_data.get("initial_a_time")?.parse().unwrap(),
+        future_a_time: target_data.get("future_a_time")?.parse().unwrap(),
+        a_precision: U256::from_dec_str(target_data.get("a_precision")?).unwrap(),
+        fee: U256::from_dec_str(target_data.get("fee")?).unwrap(),
+        admin_fee: U256::from_dec_str(target_data.get("admin_fee")?).unwrap(),
+        total_supply: U256::from_dec_str(target_data.get("total_supply")?).unwrap(),
+        base_pool: target_data
+            .get("base_pool")
+            .map(|base_pool| base_pool.parse().unwrap()),
+        ..Default::default()
+    };
+    for i in 0..n_coins {
+        pool.coins
+            .push(target_data.get(&format!("token{i}"))?.parse().unwrap());
+        pool.decimals.push(
+            target_data
+                .get(&format!("token{i}_decimals"))?
+                .parse()
+                .unwrap(),
+        );
+        pool.balances
+            .push(U256::from_dec_str(target_data.get(&format!("balance{i}"))?).unwrap());
+        pool.rates
+            .push(U256::from_dec_str(target_data.get(&format!("rate{i}"))?).unwrap());
+    }
+    Some(pool)
+}
+
+pub fn add_pool<S: PoolStore>(store: &S, chain_id: u64, pool: &CurvePool) -> DexQuoteResult<()> {
+    let mut fields = vec![
+        ("n_coins".to_string(), pool.coins.len().to_string()),
+        ("initial_a".to_string(), pool.initial_a.to_string()),
+        ("future_a".to_string(), pool.future_a.to_string()),
+        (
+            "initial_a_time".to_string(),
+            pool.initial_a_time.to_string(),
+        ),
+        ("future_a_time".to_string(), pool.future_a_time.to_string()),
+        ("a_precision".to_string(), pool.a_precision.to_string()),
+        ("fee".to_string(), pool.fee.to_string()),
+        ("admin_fee".to_string(), pool.admin_fee.to_string()),
+        ("total_supply".to_string(), pool.total_supply.to_string()),
+        ("dex".to_string(), "CURVE".to_string()),
+    ];
+    if let Some(base_pool) = pool.base_pool {
+        fields.push(("base_pool".to_string(), address_str(base_pool)));
+    }
+    for (i, coin) in pool.coins.iter().enumerate() {
+        fields.push((format!("token{i}"), address_str(*coin)));
+        fields.push((format!("token{i}_decimals"), pool.decimals[i].to_string()));
+        fields.push((format!("rate{i}"), pool.rates[i].to_string()));
+    }
+    fields.extend(balance_fields(&pool.balances));
+    update_fields(store, chain_id, pool.address, &fields)
+}
+
+fn balance_fields(balances: &[U256]) -> Vec<(String, String)> {
+    balances
+        .iter()
+        .enumerate()
+        .map(|(i, balance)| (format!("balance{i}"), balance.to_string()))
+        .collect()
+}
+
+fn update_fields<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    fields: &[(String, String)],
+) -> DexQuoteResult<()> {
+    let fields: Vec<(&str, String)> = fields
+        .iter()
+        .map(|(field, value)| (field.as_str(), value.clone()))
+        .collect();
+    store.update_pool_hashmap(chain_id, pool_address, &fields)
+}
+
+pub fn update_balances<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    balances: &[U256],
+) -> DexQuoteResult<()> {
+    update_fields(store, chain_id, pool_address, &balance_fields(balances))
+}
+
+pub fn update_total_supply<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    total_supply: U256,
+) -> DexQuoteResult<()> {
+    store.update_pool_hashmap(
+        chain_id,
+        pool_address,
+        &[("total_supply", total_supply.to_string())],
+    )
+}
+
+pub fn update_a<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    initial_a: U256,
+    future_a: U256,
+    initial_a_time: u64,
+    future_a_time: u64,
+) -> DexQuoteResult<()> {
+    store.update_pool_hashmap(
+        chain_id,
+        pool_address,
+        &[
+            ("initial_a", initial_a.to_string()),
+            ("future_a", future_a.to_string()),
+            ("initial_a_time", initial_a_time.to_string()),
+            ("future_a_time", future_a_time.to_string()),
+        ],
+    )
+}
@@ -1,150 +1,186 @@
-use std::collections::HashMap;
 pub mod token;
 use cfmms::pool::Pool;
 use ethers::types::Address;
 use neo4rs::Graph;
-use redis::RedisResult;
 
 use crate::{
-    graph::{add_pool_to_neo4j, add_token_pair_to_neo4j},
-    subgraph::SubgraphPool,
-    types::DexQuoteResult,
-    utils::address_str,
+    graph::export_pool_to_neo4j, subgraph::SubgraphPool, types::DexQuoteResult, utils::address_str,
 };
 
+pub mod algebra;
+pub mod balancer;
+pub mod curve;
+pub mod multicall;
+pub mod solidly;
+mod store;
 pub mod univ2;
 pub mod univ3;
+pub mod woofi;
+pub use store::*;
 
 /// Get pool key for redis
 pub fn get_pool_key(pool_address: Address, chain_id: u64) -> String {
     format!("{}:{}", chain_id, address_str(pool_address))
 }
 
-pub fn get_dex_pools(client: &redis::Client, chain_id: u64, dex_string: &str) -> Vec<Address> {
-    let mut con = client.get_connection().unwrap();
-    let key = format!("{}:{}", chain_id, dex_string);
-    let pools: Vec<String> = redis::cmd("SMEMBERS").arg(key).query(&mut con).unwrap();
-    pools.iter().map(|x| x.parse().unwrap()).collect()
+pub async fn add_pool<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool: Pool,
+    graph: Option<&Graph>,
+    chain_label: &str,
+) -> DexQuoteResult<()> {
+    let (token0, token1);
+    let pool_address = pool.address();
+    match pool {
+        Pool::UniswapV3(pool) => {
+            store.add_dex_pool(chain_id, "UNIV3", pool_address)?;
+            univ3::add_pool(store, chain_id, pool)?;
+            (token0, token1) = (pool.token_a, pool.token_b)
+        }
+        Pool::UniswapV2(pool) => {
+            store.add_dex_pool(chain_id, "UNIV2", pool_address)?;
+            univ2::add_pool(store, chain_id, pool)?;
+            (token0, token1) = (pool.token_a, pool.token_b)
+        }
+    }
+
+    export_pool_to_neo4j(graph, chain_label, pool_address, &[token0, token1]).await;
+    Ok(())
 }
 
-pub fn add_dex_pool(
-    client: &redis::Client,
+pub async fn add_algebra_pool<S: PoolStore>(
+    store: &S,
     chain_id: u64,
-    dex_string: &str,
-    pool_address: Address,
-) -> RedisResult<()> {
-    let mut con = client.get_connection()?;
-    let key = format!("{}:{}", chain_id, dex_string);
-    redis::cmd("SADD")
-        .arg(key)
-        .arg(address_str(pool_address))
-        .query(&mut con)
+    pool: algebra::AlgebraPool,
+    graph: Option<&Graph>,
+    chain_label: &str,
+) -> DexQuoteResult<()> {
+    store.add_dex_pool(chain_id, "ALGEBRA", pool.address)?;
+    algebra::add_pool(store, chain_id, pool)?;
+
+    export_pool_to_neo4j(
+        graph,
+        chain_label,
+        pool.address,
+        &[pool.token_a, pool.token_b],
+    )
+    .await;
+    Ok(())
 }
 
-pub fn remove_dex_pool(
-    client: &redis::Client,
+pub async fn add_solidly_pool<S: PoolStore>(
+    store: &S,
     chain_id: u64,
-    dex_string: &str,
-    pool_address: Address,
-) -> RedisResult<()> {
-    let mut con = client.get_connection()?;
-    let key = format!("{}:{}", chain_id, dex_string);
-    redis::cmd("SREM")
-        .arg(key)
-        .arg(address_str(pool_address))
-        .query(&mut con)
+    pool: solidly::SolidlyPool,
+    graph: Option<&Graph>,
+    chain_label: &str,
+) -> DexQuoteResult<()> {
+    store.add_dex_pool(chain_id, "SOLIDLY", pool.address)?;
+    solidly::add_pool(store, chain_id, pool)?;
+
+    export_pool_to_neo4j(
+        graph,
+        chain_label,
+        pool.address,
+        &[pool.token_a, pool.token_b],
+    )
+    .await;
+    Ok(())
 }
 
-pub fn delete_pool(
-    client: &redis::Client,
+/// Add a Curve pool, each pair of its coins is an edge of the graph
+pub async fn add_curve_pool<S: PoolStore>(
+    store: &S,
     chain_id: u64,
-    pool_address: Address,
-) -> RedisResult<()> {
-    let mut con = client.get_connection()?;
-    let key = get_pool_key(pool_address, chain_id);
-    redis::cmd("DEL").arg(key).query(&mut con)
+    pool: curve::CurvePool,
+    graph: Option<&Graph>,
+    chain_label: &str,
+) -> DexQuoteResult<()> {
+    store.add_dex_pool(chain_id, "CURVE", pool.address)?;
+    curve::add_pool(store, chain_id, &pool)?;
+
+    export_pool_to_neo4j(graph, chain_label, pool.address, &pool.coins).await;
+    Ok(())
 }
 
-pub fn get_pool_hashmap(
-    client: &redis::Client,
+/// Add a Balancer V2 pool, each pair of its tokens is an edge of the graph
+pub async fn add_balancer_pool<S: PoolStore>(
+    store: &S,
     chain_id: u64,
-    pool_address: Address,
-) -> RedisResult<HashMap<String, String>> {
-    let mut con = client.get_connection()?;
-    let key = get_pool_key(pool_address, chain_id);
-    let target_data: HashMap<String, String> =
-        redis::cmd("HGETALL").arg(key).query(&mut con).unwrap();
-    Ok(target_data)
+    pool: balancer::BalancerPool,
+    graph: Option<&Graph>,
+    chain_label: &str,
+) -> DexQuoteResult<()> {
+    store.add_dex_pool(chain_id, "BALANCERV2", pool.address)?;
+    balancer::add_pool(store, chain_id, &pool)?;
+
+    export_pool_to_neo4j(graph, chain_label, pool.address, &pool.tokens).await;
+    Ok(())
 }
 
-pub async fn add_pool(
-    client: &redis::Client,
+/// Add a WooFi pool, base tokens trade with the quote token and with each other
+pub async fn add_woofi_pool<S: PoolStore>(
+    store: &S,
     chain_id: u64,
-    pool: Pool,
-    graph: &Graph,
+    pool: woofi::WooFiPool,
+    graph: Option<&Graph>,
     chain_label: &str,
 ) -> DexQuoteResult<()> {
-    let (token0, token1);
-    let pool_address = pool.address();
-    match pool {
-        Pool::UniswapV3(pool) => {
-            add_dex_pool(client, chain_id, "UNIV3", pool_address)?;
-            univ3::add_pool(client, chain_id, pool)?;
-            (token0, token1) = (pool.token_a, pool.token_b)
-        }
-        Pool::UniswapV2(pool) => {
-            add_dex_pool(client, chain_id, "UNIV2", pool_address)?;
-            univ2::add_pool(client, chain_id, pool)?;
-            (token0, token1) = (pool.token_a, pool.token_b)
-        }
-    }
-
-    add_token_pair_to_neo4j(graph, chain_label, [token0, token1]).await;
+    store.add_dex_pool(chain_id, "WOOFIV2", pool.address)?;
+    woofi::add_pool(store, chain_id, &pool)?;
 
-    add_pool_to_neo4j(graph, chain_label, pool_address, token0, token1).await;
+    export_pool_to_neo4j(graph, chain_label, pool.address, &pool.all_tokens()).await;
     Ok(())
 }
 
-pub async fn add_pool_from_subgraph(
-    client: &redis::Client,
+pub async fn add_pool_from_subgraph<S: PoolStore>(
+    store: &S,
     chain_id: u64,
     pool: SubgraphPool,
-    graph: &Graph,
+    graph: Option<&Graph>,
     chain_label: &str,
 ) -> DexQuoteResult<()> {
-    add_dex_pool(client, chain_id, "UNIV3", pool.address)?;
-    univ3::add_pool_from_subgraph(client, chain_id, &pool)?;
-
-    add_token_pair_to_neo4j(graph, chain_label, [pool.token0, pool.token1]).await;
+    store.add_dex_pool(chain_id, "UNIV3", pool.address)?;
+    univ3::add_pool_from_subgraph(store, chain_id, &pool)?;
 
-    add_pool_to_neo4j(graph, chain_label, pool.address, pool.token0, pool.token1).await;
+    export_pool_to_neo4j(
+        graph,
+        chain_label,
+        pool.address,
+        &[pool.token0, pool.token1],
+    )
+    .await;
     Ok(())
 }
 
-pub fn get_pool(
-    client: &redis::Client,
+/// Whether a pool of any dex is stored
+pub fn pool_exists<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+) -> DexQuoteResult<bool> {
+    let target_data = store.get_pool_hashmap(chain_id, pool_address)?;
+    Ok(target_data.contains_key("dex"))
+}
+
+/// Get a UniswapV3 or UniswapV2 pool, Solidly pools are read with `solidly::get_pool`.
+/// Algebra pools share the UniswapV3 layout and are returned as one.
+pub fn get_pool<S: PoolStore>(
+    store: &S,
     chain_id: u64,
     pool_address: Address,
 ) -> DexQuoteResult<Option<Pool>> {
-    let mut con = client.get_connection()?;
-    let key = get_pool_key(pool_address, chain_id);
-    let target_data: HashMap<String, String> =
-        redis::cmd("HGETALL").arg(key).query(&mut con).unwrap();
+    let target_data = store.get_pool_hashmap(chain_id, pool_address)?;
     let dex = match target_data.get("dex") {
-        Some(dex) => dex,
+        Some(dex) => dex.clone(),
         None => {
             return Ok(None);
         }
     };
     match dex.as_str() {
-        "UNIV3" => {
-            let pool = univ3::get_pool(client, chain_id, pool_address)?;
-            Ok(pool)
-        }
-        "UNIV2" => {
-            let pool = univ2::get_pool(client, chain_id, pool_address)?;
-            Ok(pool)
-        }
+        "UNIV3" | "ALGEBRA" => Ok(univ3::hashmap_to_univ3(pool_address, target_data)),
+        "UNIV2" => Ok(univ2::hashmap_to_univ2(pool_address, target_data)),
         _ => Ok(None),
     }
 }
@@ -0,0 +1,292 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, Mutex},
+    time::Duration,
+};
+
+use ethers::{
+    abi::Detokenize,
+    contract::{
+        multicall_contract::{Call3, Multicall3},
+        ContractCall, MULTICALL_ADDRESS,
+    },
+    providers::{Http, Middleware, Provider},
+    types::{Address, Bytes},
+};
+use tokio::{
+    sync::{mpsc, oneshot, Semaphore},
+    time::{timeout_at, Instant},
+};
+
+use crate::{constants::provider::get_provider, error::DexQuoteError, types::DexQuoteResult};
+
+/// Calls per Multicall3 request, requests in flight and the pause after each request,
+/// to stay under the rate limits of the node.
+/// Calls made within `wait` of each other share a request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MulticallConfig {
+    pub batch_size: usize,
+    pub concurrency: usize,
+    pub delay: Duration,
+    pub wait: Duration,
+}
+
+impl Default for MulticallConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 500,
+            concurrency: 4,
+            delay: Duration::from_millis(100),
+            wait: Duration::from_millis(5),
+        }
+    }
+}
+
+struct CallRequest {
+    target: Address,
+    data: Bytes,
+    response: oneshot::Sender<Result<Bytes, String>>,
+}
+
+/// Aggregates the `eth_call`s made through it into Multicall3 requests.
+/// Each call may revert on its own without failing the others of its request.
+#[derive(Debug)]
+pub struct MulticallBatcher<M> {
+    client: Arc<M>,
+    requests: mpsc::UnboundedSender<CallRequest>,
+}
+
+impl<M> Clone for MulticallBatcher<M> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            requests: self.requests.clone(),
+        }
+    }
+}
+
+impl<M: Middleware + 'static> MulticallBatcher<M> {
+    /// Start batching the calls of `client`, must be called within a tokio runtime
+    pub fn new(client: Arc<M>, config: MulticallConfig) -> Self {
+        let (requests, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(dispatch(client.clone(), receiver, config));
+        Self { client, requests }
+    }
+
+    /// The middleware to build contracts with
+    pub fn client(&self) -> Arc<M> {
+        self.client.clone()
+    }
+
+    /// Whether the batching task has stopped, along with its runtime
+    pub fn is_closed(&self) -> bool {
+        self.requests.is_closed()
+    }
+
+    /// Return data of a call to `target`
+    pub async fn call_raw(&self, target: Address, data: Bytes) -> DexQuoteResult<Bytes> {
+        receive(self.send(target, data)?).await
+    }
+
+    /// Output of a contract call
+    pub async fn call<D: Detokenize>(&self, call: ContractCall<M, D>) -> DexQuoteResult<D> {
+        let (target, data) = call_target(&call)?;
+        let output = self.call_raw(target, data).await?;
+        decode_output(&call, &output)
+    }
+
+    /// Outputs of the calls in order, they are sent at once to share requests
+    pub async fn call_all<D: Detokenize>(
+        &self,
+        calls: Vec<ContractCall<M, D>>,
+    ) -> DexQuoteResult<Vec<D>> {
+        self.try_call_all(calls).await?.into_iter().collect()
+    }
+
+    /// Result of each call in order, a reverted call does not fail the others
+    pub async fn try_call_all<D: Detokenize>(
+        &self,
+        calls: Vec<ContractCall<M, D>>,
+    ) -> DexQuoteResult<Vec<DexQuoteResult<D>>> {
+        let mut receivers = Vec::with_capacity(calls.len());
+        for call in &calls {
+            let (target, data) = call_target(call)?;
+            receivers.push(self.send(target, data)?);
+        }
+        let mut outputs = Vec::with_capacity(calls.len());
+        for (call, receiver) in calls.iter().zip(receivers) {
+            let output = receive(receiver)
+                .await
+                .and_then(|output| decode_output(call, &output));
+            outputs.push(output);
+        }
+        Ok(outputs)
+    }
+
+    fn send(
+        &self,
+        target: Address,
+        data: Bytes,
+    ) -> DexQuoteResult<oneshot::Receiver<Result<Bytes, String>>> {
+        let (response, receiver) = oneshot::channel();
+        self.requests
+            .send(CallRequest {
+                target,
+                data,
+                response,
+            })
+            .map_err(|_| DexQuoteError::MulticallError("batcher closed".into()))?;
+        Ok(receiver)
+    }
+}
+
+async fn receive(receiver: oneshot::Receiver<Result<Bytes, String>>) -> DexQuoteResult<Bytes> {
+    receiver
+        .await
+        .map_err(|_| DexQuoteError::MulticallError("request dropped".into()))?
+        .map_err(DexQuoteError::MulticallError)
+}
+
+fn call_target<M, D>(call: &ContractCall<M, D>) -> DexQuoteResult<(Address, Bytes)> {
+    let target = call
+        .tx
+        .to_addr()
+        .ok_or_else(|| DexQuoteError::MulticallError("call without a target".into()))?;
+    Ok((*target, call.tx.data().cloned().unwrap_or_default()))
+}
+
+fn decode_output<M, D: Detokenize>(call: &ContractCall<M, D>, output: &Bytes) -> DexQuoteResult<D> {
+    let tokens = call
+        .function
+        .decode_output(output)
+        .map_err(|e| DexQuoteError::MulticallError(e.to_string()))?;
+    D::from_tokens(tokens).map_err(|e| DexQuoteError::MulticallError(e.to_string()))
+}
+
+// collect the pending calls into batches and send them with at most `concurrency` in flight
+async fn dispatch<M: Middleware + 'static>(
+    client: Arc<M>,
+    mut receiver: mpsc::UnboundedReceiver<CallRequest>,
+    config: MulticallConfig,
+) {
+    let multicall = Multicall3::new(MULTICALL_ADDRESS, client);
+    let permits = Arc::new(Semaphore::new(config.concurrency.max(1)));
+    while let Some(request) = receiver.recv().await {
+        let mut batch = vec![request];
+        let deadline = Instant::now() + config.wait;
+        while batch.len() < config.batch_size.max(1) {
+            match timeout_at(deadline, receiver.recv()).await {
+                Ok(Some(request)) => batch.push(request),
+                _ => break,
+            }
+        }
+        let permit = permits.clone().acquire_owned().await.unwrap();
+        let multicall = multicall.clone();
+        tokio::spawn(async move {
+            send_batch(&multicall, batch).await;
+            tokio::time::sleep(config.delay).await;
+            drop(permit);
+        });
+    }
+}
+
+async fn send_batch<M: Middleware + 'static>(multicall: &Multicall3<M>, batch: Vec<CallRequest>) {
+    let calls = batch
+        .iter()
+        .map(|request| Call3 {
+            target: request.target,
+            allow_failure: true,
+            call_data: request.data.clone(),
+        })
+        .collect();
+    match multicall.aggregate_3(calls).call().await {
+        Ok(results) => {
+            for (request, result) in batch.into_iter().zip(results) {
+                let output = if result.success {
+                    Ok(result.return_data)
+                } else {
+                    Err(format!("call reverted: {}", result.return_data))
+                };
+                let _ = request.response.send(output);
+            }
+        }
+        Err(e) => {
+            for request in batch {
+                let _ = request.response.send(Err(e.to_string()));
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Batchers {
+    configs: HashMap<String, MulticallConfig>,
+    batchers: HashMap<String, MulticallBatcher<Provider<Http>>>,
+}
+
+static BATCHERS: LazyLock<Mutex<Batchers>> = LazyLock::new(Default::default);
+
+/// Set the config of the batcher of a node, a running batcher is restarted if it changed
+pub fn set_multicall_config(json_rpc_url: &str, config: MulticallConfig) {
+    let mut batchers = BATCHERS.lock().unwrap();
+    if batchers.configs.insert(json_rpc_url.to_string(), config) != Some(config) {
+        batchers.batchers.remove(json_rpc_url);
+    }
+}
+
+/// The batcher shared by all reads from a node, started on first use
+pub fn get_batcher(
+    json_rpc_url: &str,
+) -> Result<MulticallBatcher<Provider<Http>>, Box<dyn std::error::Error>> {
+    let mut batchers = BATCHERS.lock().unwrap();
+    if let Some(batcher) = batchers.batchers.get(json_rpc_url) {
+        if !batcher.is_closed() {
+            return Ok(batcher.clone());
+        }
+    }
+    let config = batchers
+        .configs
+        .get(json_rpc_url)
+        .copied()
+        .unwrap_or_default();
+    let batcher = MulticallBatcher::new(Arc::new(get_provider(json_rpc_url)?), config);
+    batchers
+        .batchers
+        .insert(json_rpc_url.to_string(), batcher.clone());
+    Ok(batcher)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::token::IERC20;
+    use ethers::{
+        abi::{encode, Token},
+        types::U256,
+    };
+
+    #[tokio::test]
+    async fn test_try_call_all() {
+        let (provider, mock) = Provider::mocked();
+        let batcher = MulticallBatcher::new(Arc::new(provider), MulticallConfig::default());
+        // a single aggregate3 response for both calls, a revert of the second one
+        let results = Token::Array(vec![
+            Token::Tuple(vec![
+                Token::Bool(true),
+                Token::Bytes(encode(&[Token::Uint(U256::from(6))])),
+            ]),
+            Token::Tuple(vec![Token::Bool(false), Token::Bytes(vec![])]),
+        ]);
+        mock.push::<Bytes, Bytes>(encode(&[results]).into())
+            .unwrap();
+
+        let calls = [Address::from_low_u64_be(1), Address::from_low_u64_be(2)]
+            .into_iter()
+            .map(|token| IERC20::new(token, batcher.client()).decimals())
+            .collect();
+        let outputs = batcher.try_call_all(calls).await.unwrap();
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[0].as_ref().unwrap(), &6);
+        assert!(outputs[1].is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use super::{multicall::MulticallBatcher, PoolStore};
+use crate::{types::DexQuoteResult, utils::address_str};
+use ethers::prelude::*;
+use std::collections::HashMap;
+
+// ref. https://github.com/velodrome-finance/contracts/blob/main/contracts/Pool.sol
+abigen!(
+    SolidlyPair,
+    r#"[
+        function metadata() external view returns (uint256 dec0, uint256 dec1, uint256 r0, uint256 r1, bool st, address t0, address t1)
+        function factory() external view returns (address)
+    ]"#,
+);
+
+// ref. https://github.com/velodrome-finance/contracts/blob/main/contracts/factories/PoolFactory.sol
+abigen!(
+    SolidlyFactory,
+    r#"[
+        function allPoolsLength() external view returns (uint256)
+        function allPools(uint256) external view returns (address)
+        function getFee(address pool, bool _stable) external view returns (uint256)
+    ]"#,
+);
+
+/// A Velodrome/Aerodrome style pool, either stable (x³y+y³x) or volatile (xy)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SolidlyPool {
+    pub address: Address,
+    pub token_a: Address,
+    pub token_a_decimals: u8,
+    pub token_b: Address,
+    pub token_b_decimals: u8,
+    pub reserve_0: u128,
+    pub reserve_1: u128,
+    pub stable: bool,
+    // in basis points
+    pub fee: u32,
+}
+
+impl SolidlyPool {
+    /// Fetch a pool and its fee from the node
+    pub async fn new_from_address<M: Middleware + 'static>(
+        pool_address: Address,
+        batcher: &MulticallBatcher<M>,
+    ) -> DexQuoteResult<Self> {
+        let pair = SolidlyPair::new(pool_address, batcher.client());
+        let ((dec0, dec1, r0, r1, stable, token0, token1), factory) =
+            tokio::try_join!(batcher.call(pair.metadata()), batcher.call(pair.factory()),)?;
+        let factory = SolidlyFactory::new(factory, batcher.client());
+        let fee = batcher.call(factory.get_fee(pool_address, stable)).await?;
+        Ok(Self {
+            address: pool_address,
+            token_a: token0,
+            // metadata returns 10**decimals
+            token_a_decimals: dec0.to_string().len() as u8 - 1,
+            token_b: token1,
+            token_b_decimals: dec1.to_string().len() as u8 - 1,
+            reserve_0: r0.as_u128(),
+            reserve_1: r1.as_u128(),
+            stable,
+            fee: fee.as_u32(),
+        })
+    }
+}
+
+/// Fetch the addresses of all pools created by a factory
+pub async fn get_factory_pools<M: Middleware + 'static>(
+    factory_address: Address,
+    batcher: &MulticallBatcher<M>,
+) -> DexQuoteResult<Vec<Address>> {
+    let factory = SolidlyFactory::new(factory_address, batcher.client());
+    let pool_num = batcher.call(factory.all_pools_length()).await?.as_u64();
+    let calls = (0..pool_num).map(|i| factory.all_pools(i.into())).collect();
+    batcher.call_all(calls).await
+}
+
+pub fn get_pool<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+) -> DexQuoteResult<Option<SolidlyPool>> {
+    let target_data = store.get_pool_hashmap(chain_id, pool_address)?;
+    if target_data.is_empty() {
+        return Ok(None);
+    }
+    Ok(hashmap_to_solidly(pool_address, target_data))
+}
+
+pub fn hashmap_to_solidly(
+    pool_address: Address,
+    target_data: HashMap<String, String>,
+) -> Option<SolidlyPool> {
+    let fee = target_data.get("fee")?.parse().unwrap();
+    let token_a = target_data.get("token0")?.parse().unwrap();
+    let token_a_decimals = target_data.get("token0_decimals")?.parse().unwrap();
+    let token_b = target_data.get("token1")?.parse().unwrap();
+    let token_b_decimals = target_data.get("token1_decimals")?.parse().unwrap();
+    let reserve_0 = target_data.get("reserve0")?.parse().unwrap();
+    let reserve_1 = target_data.get("reserve1")?.parse().unwrap();
+    let stable = target_data.get("stable")?.parse().unwrap();
+    Some(SolidlyPool {
+        address: pool_address,
+        token_a,
+        token_a_decimals,
+        token_b,
+        token_b_decimals,
+        reserve_0,
+        reserve_1,
+        stable,
+        fee,
+    })
+}
+
+pub fn add_pool<S: PoolStore>(store: &S, chain_id: u64, pool: SolidlyPool) -> DexQuoteResult<()> {
+    store.update_pool_hashmap(
+        chain_id,
+        pool.address,
+        &[
+            ("fee", pool.fee.to_string()),
+            ("token0", address_str(pool.token_a)),
+            ("token0_decimals", pool.token_a_decimals.to_string()),
+            ("token1", address_str(pool.token_b)),
+            ("token1_decimals", pool.token_b_decimals.to_string()),
+            ("reserve0", pool.reserve_0.to_string()),
+            ("reserve1", pool.reserve_1.to_string()),
+            ("stable", pool.stable.to_string()),
+            ("dex", "SOLIDLY".to_string()),
+        ],
+    )
+}
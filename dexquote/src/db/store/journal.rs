@@ -0,0 +1,677 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+use ethers::types::{Address, H256, U256};
+
+use super::PoolStore;
+use crate::{
+    db::token::{TokenInfo, TokenTax},
+    types::DexQuoteResult,
+};
+
+/// The state a single store write replaced, enough to undo the write.
+#[derive(Clone, Debug)]
+pub enum UndoEntry {
+    Pool {
+        chain_id: u64,
+        pool_address: Address,
+        previous: HashMap<String, String>,
+    },
+    DexPool {
+        chain_id: u64,
+        dex_string: String,
+        pool_address: Address,
+        was_member: bool,
+    },
+    Ticks {
+        chain_id: u64,
+        pool_address: Address,
+        tick: i32,
+        previous: Option<(u128, i128)>,
+    },
+    TickBitmap {
+        chain_id: u64,
+        pool_address: Address,
+        word_pos: i16,
+        previous: Option<U256>,
+    },
+}
+
+impl UndoEntry {
+    fn undo<S: PoolStore>(self, store: &S) -> DexQuoteResult<()> {
+        match self {
+            UndoEntry::Pool {
+                chain_id,
+                pool_address,
+                previous,
+            } => {
+                store.delete_pool(chain_id, pool_address)?;
+                let fields: Vec<(&str, String)> = previous
+                    .iter()
+                    .map(|(field, value)| (field.as_str(), value.clone()))
+                    .collect();
+                store.update_pool_hashmap(chain_id, pool_address, &fields)
+            }
+            UndoEntry::DexPool {
+                chain_id,
+                dex_string,
+                pool_address,
+                was_member,
+            } => {
+                if was_member {
+                    store.add_dex_pool(chain_id, &dex_string, pool_address)
+                } else {
+                    store.remove_dex_pool(chain_id, &dex_string, pool_address)
+                }
+            }
+            UndoEntry::Ticks {
+                chain_id,
+                pool_address,
+                tick,
+                previous,
+            } => match previous {
+                Some((liquidity_gross, liquidity_net)) => {
+                    store.update_ticks(chain_id, pool_address, tick, liquidity_gross, liquidity_net)
+                }
+                None => store.delete_ticks(chain_id, pool_address, tick),
+            },
+            UndoEntry::TickBitmap {
+                chain_id,
+                pool_address,
+                word_pos,
+                previous,
+            } => match previous {
+                Some(word) => store.update_tick_bitmap(chain_id, pool_address, word_pos, word),
+                // a word that was not cached is read from the node again
+                None => store.delete_tick_bitmap(chain_id, pool_address, word_pos),
+            },
+        }
+    }
+}
+
+/// Pool store wrapper that records the previous state of everything it writes.
+///
+/// Token and last block writes are passed through without being recorded,
+/// as token metadata does not depend on the block it was read at
+/// and the sync moves the last block back itself on rollback.
+pub struct RecordingStore<'a, S: PoolStore> {
+    store: &'a S,
+    entries: Mutex<Vec<UndoEntry>>,
+}
+
+impl<'a, S: PoolStore> RecordingStore<'a, S> {
+    pub fn new(store: &'a S) -> Self {
+        Self {
+            store,
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns the recorded entries in the order the writes were made
+    pub fn into_entries(self) -> Vec<UndoEntry> {
+        self.entries.into_inner().unwrap()
+    }
+
+    fn push(&self, entry: UndoEntry) {
+        self.entries.lock().unwrap().push(entry);
+    }
+
+    fn record_pool(&self, chain_id: u64, pool_address: Address) -> DexQuoteResult<()> {
+        let previous = self.store.get_pool_hashmap(chain_id, pool_address)?;
+        self.push(UndoEntry::Pool {
+            chain_id,
+            pool_address,
+            previous,
+        });
+        Ok(())
+    }
+
+    fn record_dex_pool(
+        &self,
+        chain_id: u64,
+        dex_string: &str,
+        pool_address: Address,
+    ) -> DexQuoteResult<()> {
+        let was_member = self
+            .store
+            .get_dex_pools(chain_id, dex_string)?
+            .contains(&pool_address);
+        self.push(UndoEntry::DexPool {
+            chain_id,
+            dex_string: dex_string.to_string(),
+            pool_address,
+            was_member,
+        });
+        Ok(())
+    }
+
+    fn record_ticks(&self, chain_id: u64, pool_address: Address, tick: i32) -> DexQuoteResult<()> {
+        let previous = self.store.get_ticks(chain_id, pool_address, tick)?;
+        self.push(UndoEntry::Ticks {
+            chain_id,
+            pool_address,
+            tick,
+            previous,
+        });
+        Ok(())
+    }
+
+    fn record_tick_bitmap(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        word_pos: i16,
+    ) -> DexQuoteResult<()> {
+        let previous = self
+            .store
+            .get_tick_bitmap(chain_id, pool_address, word_pos)?;
+        self.push(UndoEntry::TickBitmap {
+            chain_id,
+            pool_address,
+            word_pos,
+            previous,
+        });
+        Ok(())
+    }
+}
+
+impl<'a, S: PoolStore> PoolStore for RecordingStore<'a, S> {
+    fn get_pool_hashmap(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+    ) -> DexQuoteResult<HashMap<String, String>> {
+        self.store.get_pool_hashmap(chain_id, pool_address)
+    }
+
+    fn update_pool_hashmap(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        fields: &[(&str, String)],
+    ) -> DexQuoteResult<()> {
+        self.record_pool(chain_id, pool_address)?;
+        self.store
+            .update_pool_hashmap(chain_id, pool_address, fields)
+    }
+
+    fn delete_pool(&self, chain_id: u64, pool_address: Address) -> DexQuoteResult<()> {
+        self.record_pool(chain_id, pool_address)?;
+        self.store.delete_pool(chain_id, pool_address)
+    }
+
+    fn get_dex_pools(&self, chain_id: u64, dex_string: &str) -> DexQuoteResult<Vec<Address>> {
+        self.store.get_dex_pools(chain_id, dex_string)
+    }
+
+    fn add_dex_pool(
+        &self,
+        chain_id: u64,
+        dex_string: &str,
+        pool_address: Address,
+    ) -> DexQuoteResult<()> {
+        self.record_dex_pool(chain_id, dex_string, pool_address)?;
+        self.store.add_dex_pool(chain_id, dex_string, pool_address)
+    }
+
+    fn remove_dex_pool(
+        &self,
+        chain_id: u64,
+        dex_string: &str,
+        pool_address: Address,
+    ) -> DexQuoteResult<()> {
+        self.record_dex_pool(chain_id, dex_string, pool_address)?;
+        self.store
+            .remove_dex_pool(chain_id, dex_string, pool_address)
+    }
+
+    fn get_ticks(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        tick: i32,
+    ) -> DexQuoteResult<Option<(u128, i128)>> {
+        self.store.get_ticks(chain_id, pool_address, tick)
+    }
+
+    fn update_ticks(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        tick: i32,
+        liquidity_gross: u128,
+        liquidity_net: i128,
+    ) -> DexQuoteResult<()> {
+        self.record_ticks(chain_id, pool_address, tick)?;
+        self.store
+            .update_ticks(chain_id, pool_address, tick, liquidity_gross, liquidity_net)
+    }
+
+    fn delete_ticks(&self, chain_id: u64, pool_address: Address, tick: i32) -> DexQuoteResult<()> {
+        self.record_ticks(chain_id, pool_address, tick)?;
+        self.store.delete_ticks(chain_id, pool_address, tick)
+    }
+
+    fn get_tick_bitmap(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        word_pos: i16,
+    ) -> DexQuoteResult<Option<U256>> {
+        self.store.get_tick_bitmap(chain_id, pool_address, word_pos)
+    }
+
+    fn update_tick_bitmap(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        word_pos: i16,
+        word: U256,
+    ) -> DexQuoteResult<()> {
+        self.record_tick_bitmap(chain_id, pool_address, word_pos)?;
+        self.store
+            .update_tick_bitmap(chain_id, pool_address, word_pos, word)
+    }
+
+    fn delete_tick_bitmap(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        word_pos: i16,
+    ) -> DexQuoteResult<()> {
+        self.record_tick_bitmap(chain_id, pool_address, word_pos)?;
+        self.store
+            .delete_tick_bitmap(chain_id, pool_address, word_pos)
+    }
+
+    fn get_token(
+        &self,
+        chain_id: u64,
+        token_address: Address,
+    ) -> DexQuoteResult<Option<TokenInfo>> {
+        self.store.get_token(chain_id, token_address)
+    }
+
+    fn add_token(&self, chain_id: u64, token_info: TokenInfo) -> DexQuoteResult<()> {
+        self.store.add_token(chain_id, token_info)
+    }
+
+    fn update_scam(&self, chain_id: u64, token_address: Address, scam: u32) -> DexQuoteResult<()> {
+        self.store.update_scam(chain_id, token_address, scam)
+    }
+
+    fn update_token_tax(
+        &self,
+        chain_id: u64,
+        token_address: Address,
+        tax: TokenTax,
+    ) -> DexQuoteResult<()> {
+        self.store.update_token_tax(chain_id, token_address, tax)
+    }
+
+    fn get_last_block(&self, chain_id: u64) -> DexQuoteResult<Option<u64>> {
+        self.store.get_last_block(chain_id)
+    }
+
+    fn set_last_block(&self, chain_id: u64, block_number: u64) -> DexQuoteResult<()> {
+        self.store.set_last_block(chain_id, block_number)
+    }
+}
+
+#[derive(Debug)]
+struct JournalRecord {
+    block_number: u64,
+    block_hash: H256,
+    entries: Vec<UndoEntry>,
+}
+
+/// Per-block journal of store changes, kept for the last `reorg_depth` blocks
+/// so that the changes of orphaned blocks can be rolled back after a reorg.
+///
+/// Records are kept in the order they were made and rolled back in reverse.
+#[derive(Debug)]
+pub struct Journal {
+    reorg_depth: u64,
+    records: VecDeque<JournalRecord>,
+}
+
+impl Journal {
+    pub fn new(reorg_depth: u64) -> Self {
+        Self {
+            reorg_depth,
+            records: VecDeque::new(),
+        }
+    }
+
+    /// Record the changes a log of the given block made,
+    /// dropping records that are older than the reorg depth.
+    pub fn record(&mut self, block_number: u64, block_hash: H256, entries: Vec<UndoEntry>) {
+        self.records.push_back(JournalRecord {
+            block_number,
+            block_hash,
+            entries,
+        });
+        let latest_block = self.latest_block().unwrap_or(block_number);
+        while let Some(record) = self.records.front() {
+            if record.block_number + self.reorg_depth > latest_block {
+                break;
+            }
+            self.records.pop_front();
+        }
+    }
+
+    pub fn contains(&self, block_hash: H256) -> bool {
+        self.records
+            .iter()
+            .any(|record| record.block_hash == block_hash)
+    }
+
+    /// Returns the hash of a journaled block
+    pub fn get_hash(&self, block_number: u64) -> Option<H256> {
+        self.records
+            .iter()
+            .rev()
+            .find(|record| record.block_number == block_number)
+            .map(|record| record.block_hash)
+    }
+
+    pub fn oldest_block(&self) -> Option<u64> {
+        self.records.iter().map(|record| record.block_number).min()
+    }
+
+    pub fn latest_block(&self) -> Option<u64> {
+        self.records.iter().map(|record| record.block_number).max()
+    }
+
+    /// Whether a block that has not been seen yet conflicts with the journal,
+    /// i.e. blocks at or above its height were journaled or its parent is not the journaled block.
+    pub fn is_reorg(&self, block_number: u64, block_hash: H256, parent_hash: H256) -> bool {
+        if self.contains(block_hash) {
+            return false;
+        }
+        if let Some(latest_block) = self.latest_block() {
+            if latest_block >= block_number {
+                return true;
+            }
+        }
+        match block_number
+            .checked_sub(1)
+            .and_then(|parent_number| self.get_hash(parent_number))
+        {
+            Some(journaled_parent_hash) => journaled_parent_hash != parent_hash,
+            None => false,
+        }
+    }
+
+    /// Undo the changes of all journaled blocks at or above `from_block`, latest first.
+    /// Returns the number of rolled back records.
+    pub fn rollback<S: PoolStore>(&mut self, store: &S, from_block: u64) -> DexQuoteResult<usize> {
+        let mut rolled_back = 0;
+        let mut kept = VecDeque::with_capacity(self.records.len());
+        while let Some(record) = self.records.pop_back() {
+            if record.block_number < from_block {
+                kept.push_front(record);
+                continue;
+            }
+            for entry in record.entries.into_iter().rev() {
+                entry.undo(store)?;
+            }
+            rolled_back += 1;
+        }
+        self.records = kept;
+        Ok(rolled_back)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{univ2, univ3, MemoryStore};
+    use crate::event::{
+        get_event_sig,
+        univ2::{update_with_sync_event, UNIV2_SYNC_EVENT_SIG},
+        univ3::{update_with_swap_event, UNIV3_SWAP_EVENT_SIG},
+    };
+    use cfmms::pool::{UniswapV2Pool, UniswapV3Pool};
+    use ethers::{
+        abi::{encode, Token},
+        types::{Bytes, Log, I256, U64},
+    };
+
+    const CHAIN_ID: u64 = 42161;
+
+    fn univ2_pool() -> Address {
+        Address::from_low_u64_be(2)
+    }
+
+    fn univ3_pool() -> Address {
+        Address::from_low_u64_be(3)
+    }
+
+    fn new_store() -> MemoryStore {
+        let store = MemoryStore::new();
+        univ2::add_pool(
+            &store,
+            CHAIN_ID,
+            UniswapV2Pool {
+                address: univ2_pool(),
+                token_a: Address::from_low_u64_be(10),
+                token_a_decimals: 18,
+                token_b: Address::from_low_u64_be(11),
+                token_b_decimals: 6,
+                reserve_0: 1000,
+                reserve_1: 2000,
+                fee: 300,
+            },
+        )
+        .unwrap();
+        univ3::add_pool(
+            &store,
+            CHAIN_ID,
+            UniswapV3Pool {
+                address: univ3_pool(),
+                token_a: Address::from_low_u64_be(10),
+                token_a_decimals: 18,
+                token_b: Address::from_low_u64_be(11),
+                token_b_decimals: 6,
+                liquidity: 100,
+                sqrt_price: U256::one() << 96,
+                fee: 500,
+                tick: 0,
+                tick_spacing: 10,
+                liquidity_net: 0,
+            },
+        )
+        .unwrap();
+        store
+    }
+
+    fn block_hash(block_number: u64, fork: u64) -> H256 {
+        H256::from_low_u64_be(block_number * 100 + fork)
+    }
+
+    fn sync_log(block_number: u64, fork: u64, reserve0: u64, reserve1: u64) -> Log {
+        Log {
+            address: univ2_pool(),
+            topics: vec![get_event_sig(UNIV2_SYNC_EVENT_SIG)],
+            data: Bytes::from(encode(&[
+                Token::Uint(reserve0.into()),
+                Token::Uint(reserve1.into()),
+            ])),
+            block_number: Some(U64::from(block_number)),
+            block_hash: Some(block_hash(block_number, fork)),
+            ..Default::default()
+        }
+    }
+
+    fn swap_log(block_number: u64, fork: u64, liquidity: u64, tick: i32) -> Log {
+        Log {
+            address: univ3_pool(),
+            topics: vec![
+                get_event_sig(UNIV3_SWAP_EVENT_SIG),
+                H256::zero(),
+                H256::zero(),
+            ],
+            data: Bytes::from(encode(&[
+                Token::Int(I256::from(1).into_raw()),
+                Token::Int(I256::from(-1).into_raw()),
+                Token::Uint(U256::one() << 96),
+                Token::Uint(liquidity.into()),
+                Token::Int(I256::from(tick).into_raw()),
+            ])),
+            block_number: Some(U64::from(block_number)),
+            block_hash: Some(block_hash(block_number, fork)),
+            ..Default::default()
+        }
+    }
+
+    fn removed(mut log: Log) -> Log {
+        log.removed = Some(true);
+        log
+    }
+
+    // replay logs the way the sync loop does, rolling back on removed logs
+    fn replay(store: &MemoryStore, journal: &mut Journal, logs: &[Log]) {
+        for log in logs {
+            let block_number = log.block_number.unwrap().as_u64();
+            if log.removed == Some(true) {
+                journal.rollback(store, block_number).unwrap();
+                continue;
+            }
+            let recording_store = RecordingStore::new(store);
+            if log.topics[0] == get_event_sig(UNIV2_SYNC_EVENT_SIG) {
+                update_with_sync_event(&recording_store, CHAIN_ID, log.address, &log.data);
+            } else {
+                update_with_swap_event(&recording_store, CHAIN_ID, log.address, &log.data);
+            }
+            journal.record(
+                block_number,
+                log.block_hash.unwrap(),
+                recording_store.into_entries(),
+            );
+        }
+    }
+
+    fn pool_states(store: &MemoryStore) -> Vec<HashMap<String, String>> {
+        vec![
+            store.get_pool_hashmap(CHAIN_ID, univ2_pool()).unwrap(),
+            store.get_pool_hashmap(CHAIN_ID, univ3_pool()).unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_rollback_with_removed_logs() {
+        let canonical = vec![
+            sync_log(1, 0, 1100, 1900),
+            swap_log(1, 0, 100, -5),
+            sync_log(2, 1, 1300, 1700),
+            swap_log(3, 1, 120, -8),
+        ];
+        let expected_store = new_store();
+        replay(&expected_store, &mut Journal::new(10), &canonical);
+
+        // blocks 2 and 3 are reorged out and replaced
+        let logs = vec![
+            sync_log(1, 0, 1100, 1900),
+            swap_log(1, 0, 100, -5),
+            sync_log(2, 0, 1200, 1800),
+            swap_log(2, 0, 90, -2),
+            sync_log(3, 0, 1250, 1750),
+            removed(sync_log(2, 0, 1200, 1800)),
+            removed(swap_log(2, 0, 90, -2)),
+            removed(sync_log(3, 0, 1250, 1750)),
+            sync_log(2, 1, 1300, 1700),
+            swap_log(3, 1, 120, -8),
+        ];
+        let store = new_store();
+        let mut journal = Journal::new(10);
+        replay(&store, &mut journal, &logs);
+
+        assert_eq!(pool_states(&store), pool_states(&expected_store));
+        assert_eq!(journal.get_hash(2), Some(block_hash(2, 1)));
+        assert_eq!(journal.get_hash(3), Some(block_hash(3, 1)));
+    }
+
+    #[test]
+    fn test_rollback_with_parent_hash_mismatch() {
+        let store = new_store();
+        let initial_states = pool_states(&store);
+        let mut journal = Journal::new(10);
+        replay(
+            &store,
+            &mut journal,
+            &[sync_log(5, 0, 1100, 1900), swap_log(6, 0, 80, -3)],
+        );
+
+        // block 6 on another fork has the journaled block 5 as parent
+        assert!(!journal.is_reorg(7, block_hash(7, 0), block_hash(6, 0)));
+        assert!(journal.is_reorg(7, block_hash(7, 1), block_hash(6, 1)));
+        assert!(journal.is_reorg(6, block_hash(6, 1), block_hash(5, 0)));
+        assert!(!journal.is_reorg(6, block_hash(6, 0), block_hash(5, 0)));
+
+        assert_eq!(journal.rollback(&store, 6).unwrap(), 1);
+        let univ3_state = store.get_pool_hashmap(CHAIN_ID, univ3_pool()).unwrap();
+        assert_eq!(univ3_state, initial_states[1]);
+        assert_eq!(journal.get_hash(5), Some(block_hash(5, 0)));
+
+        assert_eq!(journal.rollback(&store, 5).unwrap(), 1);
+        assert_eq!(pool_states(&store), initial_states);
+        assert_eq!(journal.latest_block(), None);
+    }
+
+    #[test]
+    fn test_record_prunes_blocks_older_than_reorg_depth() {
+        let store = new_store();
+        let mut journal = Journal::new(3);
+        let logs: Vec<Log> = (1..=5)
+            .map(|block_number| sync_log(block_number, 0, 1000 + block_number, 2000))
+            .collect();
+        replay(&store, &mut journal, &logs);
+        assert_eq!(journal.oldest_block(), Some(3));
+        assert_eq!(journal.latest_block(), Some(5));
+
+        // blocks older than the reorg depth can no longer be rolled back
+        journal.rollback(&store, 1).unwrap();
+        let univ2_state = store.get_pool_hashmap(CHAIN_ID, univ2_pool()).unwrap();
+        assert_eq!(univ2_state.get("reserve0").unwrap(), "1002");
+    }
+
+    #[test]
+    fn test_undo_restores_ticks_bitmaps_and_dex_pools() {
+        let store = MemoryStore::new();
+        let pool_address = univ3_pool();
+        store
+            .update_ticks(CHAIN_ID, pool_address, 10, 5, 5)
+            .unwrap();
+
+        let recording_store = RecordingStore::new(&store);
+        recording_store
+            .update_ticks(CHAIN_ID, pool_address, 10, 7, -7)
+            .unwrap();
+        recording_store
+            .update_ticks(CHAIN_ID, pool_address, 20, 1, 1)
+            .unwrap();
+        recording_store
+            .update_tick_bitmap(CHAIN_ID, pool_address, 0, U256::one())
+            .unwrap();
+        recording_store
+            .add_dex_pool(CHAIN_ID, "UNIV3", pool_address)
+            .unwrap();
+        let mut journal = Journal::new(10);
+        journal.record(1, block_hash(1, 0), recording_store.into_entries());
+
+        journal.rollback(&store, 1).unwrap();
+        assert_eq!(
+            store.get_ticks(CHAIN_ID, pool_address, 10).unwrap(),
+            Some((5, 5))
+        );
+        assert_eq!(store.get_ticks(CHAIN_ID, pool_address, 20).unwrap(), None);
+        assert_eq!(
+            store.get_tick_bitmap(CHAIN_ID, pool_address, 0).unwrap(),
+            None
+        );
+        assert!(store.get_dex_pools(CHAIN_ID, "UNIV3").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,334 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::RwLock,
+};
+
+use ethers::types::{Address, U256};
+
+use super::PoolStore;
+use crate::{
+    db::token::{TokenInfo, TokenTax},
+    types::DexQuoteResult,
+};
+
+/// (chain_id, pool_address, tick)
+type TickKey = (u64, Address, i32);
+/// (chain_id, pool_address, word_pos)
+type TickBitmapKey = (u64, Address, i16);
+
+/// Thread-safe in-memory pool store.
+/// Useful for tests and short-lived runs that should not depend on a Redis server.
+#[derive(Default)]
+pub struct MemoryStore {
+    pools: RwLock<HashMap<(u64, Address), HashMap<String, String>>>,
+    dex_pools: RwLock<HashMap<(u64, String), HashSet<Address>>>,
+    ticks: RwLock<HashMap<TickKey, (u128, i128)>>,
+    tick_bitmaps: RwLock<HashMap<TickBitmapKey, U256>>,
+    tokens: RwLock<HashMap<(u64, Address), TokenInfo>>,
+    last_blocks: RwLock<HashMap<u64, u64>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl PoolStore for MemoryStore {
+    fn get_pool_hashmap(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+    ) -> DexQuoteResult<HashMap<String, String>> {
+        let pools = self.pools.read().unwrap();
+        Ok(pools
+            .get(&(chain_id, pool_address))
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn update_pool_hashmap(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        fields: &[(&str, String)],
+    ) -> DexQuoteResult<()> {
+        if fields.is_empty() {
+            return Ok(());
+        }
+        let mut pools = self.pools.write().unwrap();
+        let pool = pools.entry((chain_id, pool_address)).or_default();
+        for (field, value) in fields {
+            pool.insert(field.to_string(), value.clone());
+        }
+        Ok(())
+    }
+
+    fn delete_pool(&self, chain_id: u64, pool_address: Address) -> DexQuoteResult<()> {
+        self.pools
+            .write()
+            .unwrap()
+            .remove(&(chain_id, pool_address));
+        Ok(())
+    }
+
+    fn get_dex_pools(&self, chain_id: u64, dex_string: &str) -> DexQuoteResult<Vec<Address>> {
+        let dex_pools = self.dex_pools.read().unwrap();
+        Ok(dex_pools
+            .get(&(chain_id, dex_string.to_string()))
+            .map(|pools| pools.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    fn add_dex_pool(
+        &self,
+        chain_id: u64,
+        dex_string: &str,
+        pool_address: Address,
+    ) -> DexQuoteResult<()> {
+        self.dex_pools
+            .write()
+            .unwrap()
+            .entry((chain_id, dex_string.to_string()))
+            .or_default()
+            .insert(pool_address);
+        Ok(())
+    }
+
+    fn remove_dex_pool(
+        &self,
+        chain_id: u64,
+        dex_string: &str,
+        pool_address: Address,
+    ) -> DexQuoteResult<()> {
+        if let Some(pools) = self
+            .dex_pools
+            .write()
+            .unwrap()
+            .get_mut(&(chain_id, dex_string.to_string()))
+        {
+            pools.remove(&pool_address);
+        }
+        Ok(())
+    }
+
+    fn get_ticks(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        tick: i32,
+    ) -> DexQuoteResult<Option<(u128, i128)>> {
+        let ticks = self.ticks.read().unwrap();
+        Ok(ticks.get(&(chain_id, pool_address, tick)).cloned())
+    }
+
+    fn update_ticks(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        tick: i32,
+        liquidity_gross: u128,
+        liquidity_net: i128,
+    ) -> DexQuoteResult<()> {
+        self.ticks.write().unwrap().insert(
+            (chain_id, pool_address, tick),
+            (liquidity_gross, liquidity_net),
+        );
+        Ok(())
+    }
+
+    fn delete_ticks(&self, chain_id: u64, pool_address: Address, tick: i32) -> DexQuoteResult<()> {
+        self.ticks
+            .write()
+            .unwrap()
+            .remove(&(chain_id, pool_address, tick));
+        Ok(())
+    }
+
+    fn get_tick_bitmap(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        word_pos: i16,
+    ) -> DexQuoteResult<Option<U256>> {
+        let tick_bitmaps = self.tick_bitmaps.read().unwrap();
+        Ok(tick_bitmaps
+            .get(&(chain_id, pool_address, word_pos))
+            .cloned())
+    }
+
+    fn update_tick_bitmap(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        word_pos: i16,
+        word: U256,
+    ) -> DexQuoteResult<()> {
+        self.tick_bitmaps
+            .write()
+            .unwrap()
+            .insert((chain_id, pool_address, word_pos), word);
+        Ok(())
+    }
+
+    fn delete_tick_bitmap(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        word_pos: i16,
+    ) -> DexQuoteResult<()> {
+        self.tick_bitmaps
+            .write()
+            .unwrap()
+            .remove(&(chain_id, pool_address, word_pos));
+        Ok(())
+    }
+
+    fn get_token(
+        &self,
+        chain_id: u64,
+        token_address: Address,
+    ) -> DexQuoteResult<Option<TokenInfo>> {
+        let tokens = self.tokens.read().unwrap();
+        Ok(tokens.get(&(chain_id, token_address)).cloned())
+    }
+
+    fn add_token(&self, chain_id: u64, token_info: TokenInfo) -> DexQuoteResult<()> {
+        self.tokens
+            .write()
+            .unwrap()
+            .insert((chain_id, token_info.address), token_info);
+        Ok(())
+    }
+
+    fn update_scam(&self, chain_id: u64, token_address: Address, scam: u32) -> DexQuoteResult<()> {
+        if let Some(token) = self
+            .tokens
+            .write()
+            .unwrap()
+            .get_mut(&(chain_id, token_address))
+        {
+            token.scam = scam;
+        }
+        Ok(())
+    }
+
+    fn update_token_tax(
+        &self,
+        chain_id: u64,
+        token_address: Address,
+        tax: TokenTax,
+    ) -> DexQuoteResult<()> {
+        if let Some(token) = self
+            .tokens
+            .write()
+            .unwrap()
+            .get_mut(&(chain_id, token_address))
+        {
+            token.tax = tax;
+        }
+        Ok(())
+    }
+
+    fn get_last_block(&self, chain_id: u64) -> DexQuoteResult<Option<u64>> {
+        Ok(self.last_blocks.read().unwrap().get(&chain_id).cloned())
+    }
+
+    fn set_last_block(&self, chain_id: u64, block_number: u64) -> DexQuoteResult<()> {
+        self.last_blocks
+            .write()
+            .unwrap()
+            .insert(chain_id, block_number);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAIN_ID: u64 = 42161;
+
+    #[test]
+    fn test_pool_hashmap() {
+        let store = MemoryStore::new();
+        let pool_address = Address::random();
+        assert!(store
+            .get_pool_hashmap(CHAIN_ID, pool_address)
+            .unwrap()
+            .is_empty());
+
+        store
+            .update_pool_hashmap(
+                CHAIN_ID,
+                pool_address,
+                &[("reserve0", "1".to_string()), ("reserve1", "2".to_string())],
+            )
+            .unwrap();
+        store
+            .update_pool_hashmap(CHAIN_ID, pool_address, &[("reserve0", "3".to_string())])
+            .unwrap();
+        let target_data = store.get_pool_hashmap(CHAIN_ID, pool_address).unwrap();
+        assert_eq!(target_data.get("reserve0").unwrap(), "3");
+        assert_eq!(target_data.get("reserve1").unwrap(), "2");
+
+        store.delete_pool(CHAIN_ID, pool_address).unwrap();
+        assert!(store
+            .get_pool_hashmap(CHAIN_ID, pool_address)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_ticks_and_tick_bitmap() {
+        let store = MemoryStore::new();
+        let pool_address = Address::random();
+        store
+            .update_ticks(CHAIN_ID, pool_address, -60, 100, -100)
+            .unwrap();
+        assert_eq!(
+            store.get_ticks(CHAIN_ID, pool_address, -60).unwrap(),
+            Some((100, -100))
+        );
+        store.delete_ticks(CHAIN_ID, pool_address, -60).unwrap();
+        assert_eq!(store.get_ticks(CHAIN_ID, pool_address, -60).unwrap(), None);
+
+        store
+            .update_tick_bitmap(CHAIN_ID, pool_address, -1, U256::one())
+            .unwrap();
+        assert_eq!(
+            store.get_tick_bitmap(CHAIN_ID, pool_address, -1).unwrap(),
+            Some(U256::one())
+        );
+        assert_eq!(
+            store.get_tick_bitmap(CHAIN_ID, pool_address, 0).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_dex_pools() {
+        let store = MemoryStore::new();
+        let pool_address = Address::random();
+        store.add_dex_pool(CHAIN_ID, "UNIV2", pool_address).unwrap();
+        assert_eq!(
+            store.get_dex_pools(CHAIN_ID, "UNIV2").unwrap(),
+            vec![pool_address]
+        );
+        assert!(store.get_dex_pools(CHAIN_ID, "UNIV3").unwrap().is_empty());
+        store
+            .remove_dex_pool(CHAIN_ID, "UNIV2", pool_address)
+            .unwrap();
+        assert!(store.get_dex_pools(CHAIN_ID, "UNIV2").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_last_block() {
+        let store = MemoryStore::new();
+        assert_eq!(store.get_last_block(CHAIN_ID).unwrap(), None);
+        store.set_last_block(CHAIN_ID, 100).unwrap();
+        store.set_last_block(1, 5).unwrap();
+        assert_eq!(store.get_last_block(CHAIN_ID).unwrap(), Some(100));
+        assert_eq!(store.get_last_block(1).unwrap(), Some(5));
+    }
+}
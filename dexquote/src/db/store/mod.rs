@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+
+use ethers::types::{Address, U256};
+
+use super::token::{TokenInfo, TokenTax};
+use crate::types::DexQuoteResult;
+
+mod journal;
+mod memory;
+mod redis_store;
+pub use journal::{Journal, RecordingStore, UndoEntry};
+pub use memory::MemoryStore;
+pub use redis_store::RedisStore;
+
+/// Storage backend for pool states, ticks, tick bitmaps and tokens.
+///
+/// Pools are stored as flat string hashes keyed by their address, the same
+/// layout the `univ2`/`univ3` modules read and write, so every backend can
+/// hold any dex without knowing its fields.
+pub trait PoolStore: Send + Sync {
+    /// Get all fields of a pool. Returns an empty map if the pool does not exist.
+    fn get_pool_hashmap(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+    ) -> DexQuoteResult<HashMap<String, String>>;
+
+    /// Set the given fields of a pool, creating the pool if necessary.
+    fn update_pool_hashmap(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        fields: &[(&str, String)],
+    ) -> DexQuoteResult<()>;
+
+    fn delete_pool(&self, chain_id: u64, pool_address: Address) -> DexQuoteResult<()>;
+
+    fn get_dex_pools(&self, chain_id: u64, dex_string: &str) -> DexQuoteResult<Vec<Address>>;
+
+    fn add_dex_pool(
+        &self,
+        chain_id: u64,
+        dex_string: &str,
+        pool_address: Address,
+    ) -> DexQuoteResult<()>;
+
+    fn remove_dex_pool(
+        &self,
+        chain_id: u64,
+        dex_string: &str,
+        pool_address: Address,
+    ) -> DexQuoteResult<()>;
+
+    /// Returns (liquidity_gross, liquidity_net) of the tick
+    fn get_ticks(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        tick: i32,
+    ) -> DexQuoteResult<Option<(u128, i128)>>;
+
+    fn update_ticks(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        tick: i32,
+        liquidity_gross: u128,
+        liquidity_net: i128,
+    ) -> DexQuoteResult<()>;
+
+    fn delete_ticks(&self, chain_id: u64, pool_address: Address, tick: i32) -> DexQuoteResult<()>;
+
+    fn get_tick_bitmap(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        word_pos: i16,
+    ) -> DexQuoteResult<Option<U256>>;
+
+    fn update_tick_bitmap(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        word_pos: i16,
+        word: U256,
+    ) -> DexQuoteResult<()>;
+
+    fn delete_tick_bitmap(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        word_pos: i16,
+    ) -> DexQuoteResult<()>;
+
+    fn get_token(&self, chain_id: u64, token_address: Address)
+        -> DexQuoteResult<Option<TokenInfo>>;
+
+    fn add_token(&self, chain_id: u64, token_info: TokenInfo) -> DexQuoteResult<()>;
+
+    fn update_scam(&self, chain_id: u64, token_address: Address, scam: u32) -> DexQuoteResult<()>;
+
+    fn update_token_tax(
+        &self,
+        chain_id: u64,
+        token_address: Address,
+        tax: TokenTax,
+    ) -> DexQuoteResult<()>;
+
+    /// Returns the last block the sync has fully processed
+    fn get_last_block(&self, chain_id: u64) -> DexQuoteResult<Option<u64>>;
+
+    fn set_last_block(&self, chain_id: u64, block_number: u64) -> DexQuoteResult<()>;
+}
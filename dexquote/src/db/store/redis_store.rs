@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+
+use ethers::{
+    abi::{AbiDecode, AbiEncode},
+    types::{Address, U256},
+};
+
+use super::PoolStore;
+use crate::{
+    db::{
+        get_pool_key,
+        token::{TokenInfo, TokenTax},
+        univ3::{get_pool_tick_bitmap_key, get_pool_ticks_key},
+    },
+    types::DexQuoteResult,
+    utils::address_str,
+};
+
+/// Pool store backed by a Redis server
+pub struct RedisStore {
+    client: redis::Client,
+}
+
+impl RedisStore {
+    pub fn new(client: redis::Client) -> Self {
+        Self { client }
+    }
+
+    pub fn open(redis_url: &str) -> DexQuoteResult<Self> {
+        let client = redis::Client::open(redis_url)?;
+        Ok(Self::new(client))
+    }
+
+    fn get_connection(&self) -> DexQuoteResult<redis::Connection> {
+        Ok(self.client.get_connection()?)
+    }
+}
+
+impl PoolStore for RedisStore {
+    fn get_pool_hashmap(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+    ) -> DexQuoteResult<HashMap<String, String>> {
+        let mut con = self.get_connection()?;
+        let key = get_pool_key(pool_address, chain_id);
+        let target_data: HashMap<String, String> =
+            redis::cmd("HGETALL").arg(key).query(&mut con)?;
+        Ok(target_data)
+    }
+
+    fn update_pool_hashmap(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        fields: &[(&str, String)],
+    ) -> DexQuoteResult<()> {
+        if fields.is_empty() {
+            return Ok(());
+        }
+        let mut con = self.get_connection()?;
+        let key = get_pool_key(pool_address, chain_id);
+        let mut cmd = redis::cmd("HSET");
+        cmd.arg(key);
+        for (field, value) in fields {
+            cmd.arg(*field).arg(value);
+        }
+        cmd.query::<()>(&mut con)?;
+        Ok(())
+    }
+
+    fn delete_pool(&self, chain_id: u64, pool_address: Address) -> DexQuoteResult<()> {
+        let mut con = self.get_connection()?;
+        let key = get_pool_key(pool_address, chain_id);
+        redis::cmd("DEL").arg(key).query::<()>(&mut con)?;
+        Ok(())
+    }
+
+    fn get_dex_pools(&self, chain_id: u64, dex_string: &str) -> DexQuoteResult<Vec<Address>> {
+        let mut con = self.get_connection()?;
+        let key = format!("{}:{}", chain_id, dex_string);
+        let pools: Vec<String> = redis::cmd("SMEMBERS").arg(key).query(&mut con)?;
+        Ok(pools.iter().map(|x| x.parse().unwrap()).collect())
+    }
+
+    fn add_dex_pool(
+        &self,
+        chain_id: u64,
+        dex_string: &str,
+        pool_address: Address,
+    ) -> DexQuoteResult<()> {
+        let mut con = self.get_connection()?;
+        let key = format!("{}:{}", chain_id, dex_string);
+        redis::cmd("SADD")
+            .arg(key)
+            .arg(address_str(pool_address))
+            .query::<()>(&mut con)?;
+        Ok(())
+    }
+
+    fn remove_dex_pool(
+        &self,
+        chain_id: u64,
+        dex_string: &str,
+        pool_address: Address,
+    ) -> DexQuoteResult<()> {
+        let mut con = self.get_connection()?;
+        let key = format!("{}:{}", chain_id, dex_string);
+        redis::cmd("SREM")
+            .arg(key)
+            .arg(address_str(pool_address))
+            .query::<()>(&mut con)?;
+        Ok(())
+    }
+
+    fn get_ticks(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        tick: i32,
+    ) -> DexQuoteResult<Option<(u128, i128)>> {
+        let mut con = self.get_connection()?;
+        let key = get_pool_ticks_key(pool_address, chain_id, tick);
+        let target_data: HashMap<String, String> =
+            redis::cmd("HGETALL").arg(key).query(&mut con)?;
+        if target_data.is_empty() {
+            return Ok(None);
+        }
+        let liquidity_net = i128::decode_hex(target_data.get("liquidity_net").unwrap()).unwrap();
+        let liquidity_gross =
+            u128::decode_hex(target_data.get("liquidity_gross").unwrap()).unwrap();
+        Ok(Some((liquidity_gross, liquidity_net)))
+    }
+
+    fn update_ticks(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        tick: i32,
+        liquidity_gross: u128,
+        liquidity_net: i128,
+    ) -> DexQuoteResult<()> {
+        let mut con = self.get_connection()?;
+        let key = get_pool_ticks_key(pool_address, chain_id, tick);
+        redis::cmd("HSET")
+            .arg(key)
+            .arg("liquidity_gross")
+            .arg(liquidity_gross.encode_hex())
+            .arg("liquidity_net")
+            .arg(liquidity_net.encode_hex())
+            .query::<()>(&mut con)?;
+        Ok(())
+    }
+
+    fn delete_ticks(&self, chain_id: u64, pool_address: Address, tick: i32) -> DexQuoteResult<()> {
+        let mut con = self.get_connection()?;
+        let key = get_pool_ticks_key(pool_address, chain_id, tick);
+        redis::cmd("DEL").arg(key).query::<()>(&mut con)?;
+        Ok(())
+    }
+
+    fn get_tick_bitmap(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        word_pos: i16,
+    ) -> DexQuoteResult<Option<U256>> {
+        let mut con = self.get_connection()?;
+        let key = get_pool_tick_bitmap_key(pool_address, chain_id, word_pos);
+        let word: Option<String> = redis::cmd("GET").arg(key).query(&mut con)?;
+        Ok(word.map(|word| U256::decode_hex(word).unwrap()))
+    }
+
+    fn update_tick_bitmap(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        word_pos: i16,
+        word: U256,
+    ) -> DexQuoteResult<()> {
+        let mut con = self.get_connection()?;
+        let key = get_pool_tick_bitmap_key(pool_address, chain_id, word_pos);
+        redis::cmd("SET")
+            .arg(key)
+            .arg(word.encode_hex())
+            .query::<()>(&mut con)?;
+        Ok(())
+    }
+
+    fn delete_tick_bitmap(
+        &self,
+        chain_id: u64,
+        pool_address: Address,
+        word_pos: i16,
+    ) -> DexQuoteResult<()> {
+        let mut con = self.get_connection()?;
+        let key = get_pool_tick_bitmap_key(pool_address, chain_id, word_pos);
+        redis::cmd("DEL").arg(key).query::<()>(&mut con)?;
+        Ok(())
+    }
+
+    fn get_token(
+        &self,
+        chain_id: u64,
+        token_address: Address,
+    ) -> DexQuoteResult<Option<TokenInfo>> {
+        let mut con = self.get_connection()?;
+        let key = get_pool_key(token_address, chain_id);
+        let target_data: HashMap<String, String> =
+            redis::cmd("HGETALL").arg(key).query(&mut con)?;
+        if target_data.is_empty() {
+            return Ok(None);
+        }
+        let symbol = target_data
+            .get("symbol")
+            .unwrap_or(&"".to_string())
+            .to_string();
+        let decimals = target_data
+            .get("decimals")
+            .unwrap_or(&"18".to_string())
+            .parse()
+            .unwrap();
+        let scam = target_data
+            .get("scam")
+            .unwrap_or(&"2".to_string())
+            .parse()
+            .unwrap();
+        let tax = |field: &str| -> u32 {
+            target_data
+                .get(field)
+                .and_then(|tax| tax.parse().ok())
+                .unwrap_or(0)
+        };
+        Ok(Some(TokenInfo {
+            address: token_address,
+            symbol,
+            decimals,
+            scam,
+            tax: TokenTax {
+                buy: tax("buy_tax"),
+                sell: tax("sell_tax"),
+                transfer: tax("transfer_tax"),
+            },
+        }))
+    }
+
+    fn add_token(&self, chain_id: u64, token_info: TokenInfo) -> DexQuoteResult<()> {
+        let mut con = self.get_connection()?;
+        let key = get_pool_key(token_info.address, chain_id);
+        redis::cmd("HSET")
+            .arg(key)
+            .arg("symbol")
+            .arg(token_info.symbol)
+            .arg("decimals")
+            .arg(token_info.decimals)
+            .arg("scam")
+            .arg(token_info.scam)
+            .arg("buy_tax")
+            .arg(token_info.tax.buy)
+            .arg("sell_tax")
+            .arg(token_info.tax.sell)
+            .arg("transfer_tax")
+            .arg(token_info.tax.transfer)
+            .query::<()>(&mut con)?;
+        Ok(())
+    }
+
+    fn update_scam(&self, chain_id: u64, token_address: Address, scam: u32) -> DexQuoteResult<()> {
+        let mut con = self.get_connection()?;
+        let key = get_pool_key(token_address, chain_id);
+        redis::cmd("HSET")
+            .arg(key)
+            .arg("scam")
+            .arg(scam)
+            .query::<()>(&mut con)?;
+        Ok(())
+    }
+
+    fn update_token_tax(
+        &self,
+        chain_id: u64,
+        token_address: Address,
+        tax: TokenTax,
+    ) -> DexQuoteResult<()> {
+        let mut con = self.get_connection()?;
+        let key = get_pool_key(token_address, chain_id);
+        redis::cmd("HSET")
+            .arg(key)
+            .arg("buy_tax")
+            .arg(tax.buy)
+            .arg("sell_tax")
+            .arg(tax.sell)
+            .arg("transfer_tax")
+            .arg(tax.transfer)
+            .query::<()>(&mut con)?;
+        Ok(())
+    }
+
+    fn get_last_block(&self, chain_id: u64) -> DexQuoteResult<Option<u64>> {
+        let mut con = self.get_connection()?;
+        let key = get_last_block_key(chain_id);
+        let block_number: Option<u64> = redis::cmd("GET").arg(key).query(&mut con)?;
+        Ok(block_number)
+    }
+
+    fn set_last_block(&self, chain_id: u64, block_number: u64) -> DexQuoteResult<()> {
+        let mut con = self.get_connection()?;
+        let key = get_last_block_key(chain_id);
+        redis::cmd("SET")
+            .arg(key)
+            .arg(block_number)
+            .query::<()>(&mut con)?;
+        Ok(())
+    }
+}
+
+/// Get the key of the last block processed by the sync
+fn get_last_block_key(chain_id: u64) -> String {
+    format!("{}:last_block", chain_id)
+}
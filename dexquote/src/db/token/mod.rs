@@ -1,126 +1,232 @@
-use super::get_pool_key;
+use super::{multicall::MulticallBatcher, PoolStore};
 use ethers::{
-    prelude::{abigen, ContractError},
+    prelude::abigen,
     providers::Middleware,
-    types::Address,
+    types::{Address, U256},
 };
-use redis::RedisResult;
-use std::{collections::HashMap, sync::Arc};
+use std::sync::Arc;
+
+use crate::types::DexQuoteResult;
 
 abigen!(
     IERC20,
     r#"[
         symbol() external view returns (string memory)
         decimals() external view returns (uint8)
+        balanceOf(address) external view returns (uint256)
+        transfer(address,uint256) external returns (bool)
     ]"#,
 );
 
+pub const TAX_DENOMINATOR: u32 = 10000;
+
+#[derive(Clone, Debug)]
 pub struct TokenInfo {
     pub address: Address,
     pub symbol: String,
     pub decimals: u32,
     pub scam: u32,
+    pub tax: TokenTax,
 }
 
-pub fn get_token(
-    client: &redis::Client,
-    chain_id: u64,
-    token_address: Address,
-) -> RedisResult<Option<TokenInfo>> {
-    let mut con = client.get_connection()?;
-    let key = get_pool_key(token_address, chain_id);
-    let target_data: HashMap<String, String> =
-        redis::cmd("HGETALL").arg(key).query(&mut con).unwrap();
-    if target_data.is_empty() {
-        return Ok(None);
+/// Taxes of a fee-on-transfer token in bps, taken from the transferred amount.
+/// As in most tax tokens, a transfer from a pool pays `buy`,
+/// a transfer to a pool pays `sell` and any other transfer pays `transfer`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TokenTax {
+    pub buy: u32,
+    pub sell: u32,
+    pub transfer: u32,
+}
+
+impl TokenTax {
+    pub fn is_zero(&self) -> bool {
+        self.buy == 0 && self.sell == 0 && self.transfer == 0
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.buy < TAX_DENOMINATOR && self.sell < TAX_DENOMINATOR && self.transfer < TAX_DENOMINATOR
     }
-    let symbol = target_data
-        .get("symbol")
-        .unwrap_or(&"".to_string())
-        .to_string();
-    let decimals = target_data
-        .get("decimals")
-        .unwrap_or(&"18".to_string())
-        .parse()
-        .unwrap();
-    let scam = target_data
-        .get("scam")
-        .unwrap_or(&"2".to_string())
-        .parse()
-        .unwrap();
-    Ok(Some(TokenInfo {
-        address: token_address,
-        symbol,
-        decimals,
-        scam,
-    }))
 }
 
-pub fn add_token(client: &redis::Client, chain_id: u64, token_info: TokenInfo) {
-    let mut con = client.get_connection().unwrap();
-    let key = get_pool_key(token_info.address, chain_id);
-    let _: () = redis::cmd("HSET")
-        .arg(key)
-        .arg("symbol")
-        .arg(token_info.symbol)
-        .arg("decimals")
-        .arg(token_info.decimals)
-        .arg("scam")
-        .arg(token_info.scam)
-        .query(&mut con)
-        .unwrap();
+/// Amount received when `amount` is sent with a `tax` bps tax
+pub fn after_tax(amount: U256, tax: u32) -> U256 {
+    amount - amount * U256::from(tax) / U256::from(TAX_DENOMINATOR)
+}
+
+/// Amount to send with a `tax` bps tax to receive at least `amount`
+pub fn before_tax(amount: U256, tax: u32) -> U256 {
+    if tax == 0 {
+        return amount;
+    }
+    let denominator = U256::from(TAX_DENOMINATOR - tax);
+    (amount * U256::from(TAX_DENOMINATOR) + denominator - 1) / denominator
 }
 
-pub fn update_scam(
-    client: &redis::Client,
+/// Taxes of a token in the store, unknown tokens are not taxed
+pub fn get_token_tax<S: PoolStore>(
+    store: &S,
     chain_id: u64,
     token_address: Address,
-    scam: u32,
-) -> RedisResult<()> {
-    let mut con = client.get_connection().unwrap();
-    let key = get_pool_key(token_address, chain_id);
-    redis::cmd("HSET")
-        .arg(key)
-        .arg("scam")
-        .arg(scam)
-        .query(&mut con)
+) -> DexQuoteResult<TokenTax> {
+    Ok(store
+        .get_token(chain_id, token_address)?
+        .map(|token| token.tax)
+        .unwrap_or_default())
 }
 
-pub async fn add_token_from_provider<M: Middleware + 'static>(
-    client: &redis::Client,
+pub async fn add_token_from_provider<M: Middleware + 'static, S: PoolStore>(
+    store: &S,
     chain_id: u64,
-    middleware: Arc<M>,
+    batcher: &MulticallBatcher<M>,
     token_address: Address,
     scam: u32,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let mut con = client.get_connection().unwrap();
-    let symbol = erc20_address_to_symbol(middleware.clone(), token_address).await?;
-    let decimals = erc20_address_to_decimals(middleware, token_address).await?;
-    let key = get_pool_key(token_address, chain_id);
-    redis::cmd("HSET")
-        .arg(key)
-        .arg("symbol")
-        .arg(symbol)
-        .arg("decimals")
-        .arg(decimals)
-        .arg("scam")
-        .arg(scam)
-        .query(&mut con)?;
+    let (symbol, decimals) = tokio::try_join!(
+        erc20_address_to_symbol(batcher, token_address),
+        erc20_address_to_decimals(batcher, token_address),
+    )?;
+    store.add_token(
+        chain_id,
+        TokenInfo {
+            address: token_address,
+            symbol,
+            decimals: decimals.into(),
+            scam,
+            tax: TokenTax::default(),
+        },
+    )?;
     Ok(())
 }
 
 pub async fn erc20_address_to_symbol<M: Middleware + 'static>(
-    middleware: Arc<M>,
+    batcher: &MulticallBatcher<M>,
     address: Address,
-) -> Result<String, ContractError<M>> {
-    let token = IERC20::new(address, middleware);
-    token.symbol().call().await
+) -> DexQuoteResult<String> {
+    let token = IERC20::new(address, batcher.client());
+    batcher.call(token.symbol()).await
 }
 
 pub async fn erc20_address_to_decimals<M: Middleware + 'static>(
-    middleware: Arc<M>,
+    batcher: &MulticallBatcher<M>,
     address: Address,
-) -> Result<u8, ContractError<M>> {
-    let token = IERC20::new(address, middleware);
-    token.decimals().call().await
+) -> DexQuoteResult<u8> {
+    let token = IERC20::new(address, batcher.client());
+    batcher.call(token.decimals()).await
+}
+
+/// Decimals of the tokens in order, read together
+pub async fn erc20_addresses_to_decimals<M: Middleware + 'static>(
+    batcher: &MulticallBatcher<M>,
+    addresses: &[Address],
+) -> DexQuoteResult<Vec<u8>> {
+    let calls = addresses
+        .iter()
+        .map(|address| IERC20::new(*address, batcher.client()).decimals())
+        .collect();
+    batcher.call_all(calls).await
+}
+
+// tax in bps of a transfer of `sent` that delivered `received`, rounded up
+fn measured_tax(sent: U256, received: U256) -> u32 {
+    if sent.is_zero() || received >= sent {
+        return 0;
+    }
+    let taxed = (sent - received) * U256::from(TAX_DENOMINATOR);
+    ((taxed + sent - 1) / sent).as_u32()
+}
+
+// send `amount` of `token` from an impersonated account, returns the amount `to` received
+async fn simulate_transfer<M: Middleware + 'static>(
+    middleware: Arc<M>,
+    token: &IERC20<M>,
+    from: Address,
+    to: Address,
+    amount: U256,
+) -> Result<U256, Box<dyn std::error::Error>> {
+    let provider = middleware.provider();
+    provider
+        .request::<_, ()>("anvil_impersonateAccount", [from])
+        .await?;
+    // gas for the impersonated account
+    provider
+        .request::<_, ()>("anvil_setBalance", (from, U256::exp10(18)))
+        .await?;
+    let balance_before = token.balance_of(to).call().await?;
+    token.transfer(to, amount).from(from).send().await?.await?;
+    let balance_after = token.balance_of(to).call().await?;
+    Ok(balance_after.saturating_sub(balance_before))
+}
+
+/// Measure the taxes of a token by transferring it on a local fork (anvil) of the chain.
+/// `pool` must hold the token, part of its balance is sent to a fresh account (buy),
+/// on to another account (transfer) and back to the pool (sell).
+/// The fork is reverted to its state before the transfers.
+pub async fn detect_token_tax<M: Middleware + 'static>(
+    middleware: Arc<M>,
+    token_address: Address,
+    pool: Address,
+) -> Result<TokenTax, Box<dyn std::error::Error>> {
+    let provider = middleware.provider();
+    let snapshot: U256 = provider.request("evm_snapshot", ()).await?;
+    let token = IERC20::new(token_address, middleware.clone());
+    let result = async {
+        let amount = token.balance_of(pool).call().await? / 1000;
+        if amount.is_zero() {
+            return Err(format!("{:?} holds no {:?}", pool, token_address).into());
+        }
+        let buyer = Address::random();
+        let received = simulate_transfer(middleware.clone(), &token, pool, buyer, amount).await?;
+        let buy = measured_tax(amount, received);
+        let holder = Address::random();
+        let transferred =
+            simulate_transfer(middleware.clone(), &token, buyer, holder, received).await?;
+        let transfer = measured_tax(received, transferred);
+        let sold = simulate_transfer(middleware.clone(), &token, holder, pool, transferred).await?;
+        let sell = measured_tax(transferred, sold);
+        Ok(TokenTax {
+            buy,
+            sell,
+            transfer,
+        })
+    }
+    .await;
+    provider
+        .request::<_, bool>("evm_revert", [snapshot])
+        .await?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tax_amounts() {
+        let amount = U256::from(1_000_000u64);
+        assert_eq!(after_tax(amount, 0), amount);
+        assert_eq!(after_tax(amount, 500), U256::from(950_000u64));
+        // the token rounds its fee down
+        assert_eq!(after_tax(U256::from(999u64), 300), U256::from(970u64));
+
+        for tax in [0, 1, 300, 500, 2500] {
+            for amount in [1u64, 999, 1_000_000, 123_456_789] {
+                let amount = U256::from(amount);
+                let sent = before_tax(amount, tax);
+                assert!(after_tax(sent, tax) >= amount);
+                let upper =
+                    amount * U256::from(TAX_DENOMINATOR) / U256::from(TAX_DENOMINATOR - tax);
+                assert!(sent <= upper + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_measured_tax() {
+        let sent = U256::from(123_456_789u64);
+        for tax in [0, 1, 300, 500, 2500] {
+            assert_eq!(measured_tax(sent, after_tax(sent, tax)), tax);
+        }
+        assert_eq!(measured_tax(sent, sent + 1), 0);
+    }
 }
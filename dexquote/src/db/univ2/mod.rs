@@ -1,16 +1,22 @@
-use super::{get_pool_hashmap, get_pool_key};
+use super::{multicall::MulticallBatcher, PoolStore};
 use crate::{types::DexQuoteResult, utils::address_str};
 use cfmms::pool::{Pool, UniswapV2Pool};
 use ethers::prelude::*;
-use redis::RedisResult;
 use std::collections::HashMap;
 
-pub fn get_pool(
-    client: &redis::Client,
+abigen!(
+    UniV2PairContract,
+    r#"[
+        function factory() external view returns (address)
+    ]"#,
+);
+
+pub fn get_pool<S: PoolStore>(
+    store: &S,
     chain_id: u64,
     pool_address: Address,
-) -> RedisResult<Option<Pool>> {
-    let target_data = get_pool_hashmap(client, chain_id, pool_address)?;
+) -> DexQuoteResult<Option<Pool>> {
+    let target_data = store.get_pool_hashmap(chain_id, pool_address)?;
     if target_data.is_empty() {
         return Ok(None);
     }
@@ -40,45 +46,99 @@ pub fn hashmap_to_univ2(
     }))
 }
 
-pub fn add_pool(client: &redis::Client, chain_id: u64, pool: UniswapV2Pool) -> DexQuoteResult<()> {
-    let mut con = client.get_connection()?;
-    let key = get_pool_key(pool.address, chain_id);
-    redis::cmd("HSET")
-        .arg(key)
-        .arg("fee")
-        .arg(pool.fee)
-        .arg("token0")
-        .arg(address_str(pool.token_a))
-        .arg("token0_decimals")
-        .arg(pool.token_a_decimals)
-        .arg("token1")
-        .arg(address_str(pool.token_b))
-        .arg("token1_decimals")
-        .arg(pool.token_b_decimals)
-        .arg("reserve0")
-        .arg(pool.reserve_0.to_string())
-        .arg("reserve1")
-        .arg(pool.reserve_1.to_string())
-        .arg("dex")
-        .arg("UNIV2")
-        .query(&mut con)?;
-    Ok(())
+/// Swap fees of a pool, in 1e-5 (300 = 0.3%) like `UniswapV2Pool::fee`.
+/// `fee` is charged when token0 is sold and `fee_one_for_zero` when token1 is sold,
+/// forks such as Camelot charge different fees per direction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UniV2Fee {
+    pub fee: u32,
+    pub fee_one_for_zero: u32,
 }
 
-pub fn update_pool(
-    client: &redis::Client,
+impl UniV2Fee {
+    pub fn new(fee: u32, fee_one_for_zero: Option<u32>) -> Self {
+        Self {
+            fee,
+            fee_one_for_zero: fee_one_for_zero.unwrap_or(fee),
+        }
+    }
+
+    /// The fee charged on the input token of a swap
+    pub fn get(&self, zero_for_one: bool) -> u32 {
+        if zero_for_one {
+            self.fee
+        } else {
+            self.fee_one_for_zero
+        }
+    }
+}
+
+// pools imported before directional fees were stored only have `fee`
+pub fn hashmap_to_fee(target_data: &HashMap<String, String>) -> Option<UniV2Fee> {
+    let fee = target_data.get("fee")?.parse().ok()?;
+    let fee_one_for_zero = match target_data.get("fee_one_for_zero") {
+        Some(fee_one_for_zero) => Some(fee_one_for_zero.parse().ok()?),
+        None => None,
+    };
+    Some(UniV2Fee::new(fee, fee_one_for_zero))
+}
+
+/// Fetch the factory that deployed a pair
+pub async fn get_factory<M: Middleware + 'static>(
+    pool_address: Address,
+    batcher: &MulticallBatcher<M>,
+) -> DexQuoteResult<Address> {
+    let contract = UniV2PairContract::new(pool_address, batcher.client());
+    batcher.call(contract.factory()).await
+}
+
+pub fn add_pool<S: PoolStore>(store: &S, chain_id: u64, pool: UniswapV2Pool) -> DexQuoteResult<()> {
+    store.update_pool_hashmap(
+        chain_id,
+        pool.address,
+        &[
+            ("fee", pool.fee.to_string()),
+            ("fee_one_for_zero", pool.fee.to_string()),
+            ("token0", address_str(pool.token_a)),
+            ("token0_decimals", pool.token_a_decimals.to_string()),
+            ("token1", address_str(pool.token_b)),
+            ("token1_decimals", pool.token_b_decimals.to_string()),
+            ("reserve0", pool.reserve_0.to_string()),
+            ("reserve1", pool.reserve_1.to_string()),
+            ("dex", "UNIV2".to_string()),
+        ],
+    )
+}
+
+pub fn update_pool<S: PoolStore>(
+    store: &S,
     chain_id: u64,
     pool_address: Address,
     reserve0: u128,
     reserve1: u128,
-) -> RedisResult<()> {
-    let mut con = client.get_connection()?;
-    let key = get_pool_key(pool_address, chain_id);
-    redis::cmd("HSET")
-        .arg(key)
-        .arg("reserve0")
-        .arg(reserve0.to_string())
-        .arg("reserve1")
-        .arg(reserve1.to_string())
-        .query(&mut con)
+) -> DexQuoteResult<()> {
+    store.update_pool_hashmap(
+        chain_id,
+        pool_address,
+        &[
+            ("reserve0", reserve0.to_string()),
+            ("reserve1", reserve1.to_string()),
+        ],
+    )
+}
+
+pub fn update_fee<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    fee: UniV2Fee,
+) -> DexQuoteResult<()> {
+    store.update_pool_hashmap(
+        chain_id,
+        pool_address,
+        &[
+            ("fee", fee.fee.to_string()),
+            ("fee_one_for_zero", fee.fee_one_for_zero.to_string()),
+        ],
+    )
 }
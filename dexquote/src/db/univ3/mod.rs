@@ -1,4 +1,4 @@
-use super::{get_pool_hashmap, get_pool_key};
+use super::PoolStore;
 use crate::{
     constants::tick_spacing::get_tick_spacing, subgraph::SubgraphPool, types::DexQuoteResult,
     utils::address_str,
@@ -8,11 +8,12 @@ use ethers::{
     abi::{AbiDecode, AbiEncode},
     prelude::*,
 };
-use redis::RedisResult;
 use std::collections::HashMap;
 use tracing::warn;
+mod prefetch;
 mod tick_bitmap;
 mod ticks;
+pub use prefetch::*;
 pub use tick_bitmap::*;
 pub use ticks::*;
 
@@ -37,12 +38,12 @@ pub fn get_pool_tick_bitmap_key(pool_address: Address, chain_id: u64, word_pos:
     )
 }
 
-pub fn get_pool(
-    client: &redis::Client,
+pub fn get_pool<S: PoolStore>(
+    store: &S,
     chain_id: u64,
     pool_address: Address,
-) -> RedisResult<Option<Pool>> {
-    let target_data = get_pool_hashmap(client, chain_id, pool_address)?;
+) -> DexQuoteResult<Option<Pool>> {
+    let target_data = store.get_pool_hashmap(chain_id, pool_address)?;
     if target_data.is_empty() {
         return Ok(None);
     }
@@ -78,9 +79,7 @@ pub fn hashmap_to_univ3(
     }))
 }
 
-pub fn add_pool(client: &redis::Client, chain_id: u64, pool: UniswapV3Pool) -> DexQuoteResult<()> {
-    let mut con = client.get_connection()?;
-    let key = get_pool_key(pool.address, chain_id);
+pub fn add_pool<S: PoolStore>(store: &S, chain_id: u64, pool: UniswapV3Pool) -> DexQuoteResult<()> {
     let mut tick_spacing = pool.tick_spacing;
     let fee = pool.fee;
     // check if tick spacing is 0
@@ -93,103 +92,109 @@ pub fn add_pool(client: &redis::Client, chain_id: u64, pool: UniswapV3Pool) -> D
             }
         };
     }
-    redis::cmd("HSET")
-        .arg(key)
-        .arg("fee")
-        .arg(fee)
-        .arg("token0")
-        .arg(address_str(pool.token_a))
-        .arg("token0_decimals")
-        .arg(pool.token_a_decimals)
-        .arg("token1")
-        .arg(address_str(pool.token_b))
-        .arg("token1_decimals")
-        .arg(pool.token_b_decimals)
-        .arg("liquidity")
-        .arg(pool.liquidity.to_string())
-        .arg("sqrt_price")
-        .arg(pool.sqrt_price.encode_hex())
-        .arg("tick")
-        .arg(pool.tick)
-        .arg("tick_spacing")
-        .arg(tick_spacing)
-        .arg("liquidity_net")
-        .arg(pool.liquidity_net.to_string())
-        .arg("dex")
-        .arg("UNIV3")
-        .query(&mut con)?;
-    Ok(())
+    store.update_pool_hashmap(
+        chain_id,
+        pool.address,
+        &[
+            ("fee", fee.to_string()),
+            ("token0", address_str(pool.token_a)),
+            ("token0_decimals", pool.token_a_decimals.to_string()),
+            ("token1", address_str(pool.token_b)),
+            ("token1_decimals", pool.token_b_decimals.to_string()),
+            ("liquidity", pool.liquidity.to_string()),
+            ("sqrt_price", pool.sqrt_price.encode_hex()),
+            ("tick", pool.tick.to_string()),
+            ("tick_spacing", tick_spacing.to_string()),
+            ("liquidity_net", pool.liquidity_net.to_string()),
+            ("dex", "UNIV3".to_string()),
+        ],
+    )
 }
 
-pub fn add_pool_from_subgraph(
-    client: &redis::Client,
+pub fn add_pool_from_subgraph<S: PoolStore>(
+    store: &S,
     chain_id: u64,
     pool: &SubgraphPool,
 ) -> DexQuoteResult<()> {
-    let mut con = client.get_connection()?;
-    let key = get_pool_key(pool.address, chain_id);
     let tick_spacing = get_tick_spacing(pool.fee)?;
     let fee = pool.fee;
-    redis::cmd("HSET")
-        .arg(key)
-        .arg("fee")
-        .arg(fee)
-        .arg("token0")
-        .arg(address_str(pool.token0))
-        .arg("token0_decimals")
-        .arg(0)
-        .arg("token1")
-        .arg(address_str(pool.token1))
-        .arg("token1_decimals")
-        .arg(0)
-        .arg("liquidity")
-        .arg(pool.liquidity.to_string())
-        .arg("sqrt_price")
-        .arg("0x0")
-        .arg("tick")
-        .arg(0)
-        .arg("tick_spacing")
-        .arg(tick_spacing)
-        .arg("liquidity_net")
-        .arg(0)
-        .arg("dex")
-        .arg("UNIV3")
-        .query(&mut con)?;
-    Ok(())
+    store.update_pool_hashmap(
+        chain_id,
+        pool.address,
+        &[
+            ("fee", fee.to_string()),
+            ("token0", address_str(pool.token0)),
+            ("token0_decimals", "0".to_string()),
+            ("token1", address_str(pool.token1)),
+            ("token1_decimals", "0".to_string()),
+            ("liquidity", pool.liquidity.to_string()),
+            ("sqrt_price", "0x0".to_string()),
+            ("tick", "0".to_string()),
+            ("tick_spacing", tick_spacing.to_string()),
+            ("liquidity_net", "0".to_string()),
+            ("dex", "UNIV3".to_string()),
+        ],
+    )
 }
 
-pub fn update_pool(
-    client: &redis::Client,
+pub fn update_pool<S: PoolStore>(
+    store: &S,
     chain_id: u64,
     pool_address: Address,
     liquidity: u128,
     sqrt_price_x96: U256,
     tick: i32,
-) -> RedisResult<()> {
-    let mut con = client.get_connection()?;
-    let key = get_pool_key(pool_address, chain_id);
-    redis::cmd("HSET")
-        .arg(key)
-        .arg("liquidity")
-        .arg(liquidity.to_string())
-        .arg("sqrt_price")
-        .arg(sqrt_price_x96.encode_hex())
-        .arg("tick")
-        .arg(tick)
-        .query(&mut con)
+) -> DexQuoteResult<()> {
+    store.update_pool_hashmap(
+        chain_id,
+        pool_address,
+        &[
+            ("liquidity", liquidity.to_string()),
+            ("sqrt_price", sqrt_price_x96.encode_hex()),
+            ("tick", tick.to_string()),
+        ],
+    )
+}
+
+pub fn update_slot0<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    sqrt_price_x96: U256,
+    tick: i32,
+) -> DexQuoteResult<()> {
+    store.update_pool_hashmap(
+        chain_id,
+        pool_address,
+        &[
+            ("sqrt_price", sqrt_price_x96.encode_hex()),
+            ("tick", tick.to_string()),
+        ],
+    )
 }
 
-pub fn update_liquidity(
-    client: &redis::Client,
+pub fn update_fee_protocol<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    fee_protocol: u8,
+) -> DexQuoteResult<()> {
+    store.update_pool_hashmap(
+        chain_id,
+        pool_address,
+        &[("fee_protocol", fee_protocol.to_string())],
+    )
+}
+
+pub fn update_liquidity<S: PoolStore>(
+    store: &S,
     chain_id: u64,
     pool_address: Address,
     liquidity: u128,
-) -> RedisResult<()> {
-    let mut con = client.get_connection()?;
-    let key = get_pool_key(pool_address, chain_id);
-    redis::cmd("HSET")
-        .arg(key)
-        .arg("liquidity")
-        .arg(liquidity.to_string())
-        .query(&mut con)
+) -> DexQuoteResult<()> {
+    store.update_pool_hashmap(
+        chain_id,
+        pool_address,
+        &[("liquidity", liquidity.to_string())],
+    )
 }
@@ -0,0 +1,183 @@
+use std::{collections::HashMap, error::Error, ops::RangeInclusive, str::FromStr};
+
+use cfmms::pool::Pool;
+use ethers::{prelude::*, types::Address};
+use uniswap_v3_math::{tick_bitmap::position, tick_math};
+
+use super::{hashmap_to_univ3, UniV3Pool};
+use crate::{
+    constants::tick_spacing::get_tick_spacing,
+    db::{algebra::AlgebraPoolContract, multicall::MulticallBatcher, PoolStore},
+    dex::Dex,
+};
+
+/// Tick bitmap words and initialized ticks loaded by `prefetch_ticks`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PrefetchStats {
+    pub pools: usize,
+    pub words: usize,
+    pub ticks: usize,
+}
+
+// tick spacing and bitmap words of a stored UniswapV3 or Algebra pool
+struct TickRange {
+    dex: Dex,
+    tick_spacing: i32,
+    words: RangeInclusive<i16>,
+}
+
+fn get_tick_range<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+) -> Result<Option<TickRange>, Box<dyn Error>> {
+    let target_data = store.get_pool_hashmap(chain_id, pool_address)?;
+    let dex = match target_data.get("dex").map(|dex| Dex::from_str(dex)) {
+        Some(Ok(dex @ (Dex::UniswapV3 | Dex::Algebra))) => dex,
+        _ => return Ok(None),
+    };
+    let pool = match hashmap_to_univ3(pool_address, target_data) {
+        Some(Pool::UniswapV3(pool)) => pool,
+        _ => return Ok(None),
+    };
+    // Algebra fees are dynamic, so the pool keeps its own tick spacing
+    let tick_spacing = match dex {
+        Dex::Algebra => pool.tick_spacing,
+        _ => get_tick_spacing(pool.fee)?,
+    };
+    let min_word = position(tick_math::MIN_TICK.div_euclid(tick_spacing)).0;
+    let max_word = position(tick_math::MAX_TICK.div_euclid(tick_spacing)).0;
+    Ok(Some(TickRange {
+        dex,
+        tick_spacing,
+        words: min_word..=max_word,
+    }))
+}
+
+/// Load every tick bitmap word, empty ones included, and every initialized tick of
+/// UniswapV3 and Algebra pools into the store, so that they can be quoted offline.
+/// The reads of all the pools are batched together through Multicall3,
+/// other pools are skipped.
+pub async fn prefetch_ticks<M: Middleware + 'static, S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pools: &[Address],
+    batcher: &MulticallBatcher<M>,
+) -> Result<PrefetchStats, Box<dyn Error>> {
+    let mut stats = PrefetchStats::default();
+    let mut words = Vec::new();
+    let mut word_calls = Vec::new();
+    let mut tick_spacings = HashMap::new();
+    for pool_address in pools {
+        let range = match get_tick_range(store, chain_id, *pool_address)? {
+            Some(range) => range,
+            None => continue,
+        };
+        stats.pools += 1;
+        tick_spacings.insert(*pool_address, range.tick_spacing);
+        let univ3_pool = UniV3Pool::new(*pool_address, batcher.client());
+        let algebra_pool = AlgebraPoolContract::new(*pool_address, batcher.client());
+        for word_pos in range.words {
+            words.push((*pool_address, word_pos));
+            word_calls.push(match range.dex {
+                Dex::Algebra => algebra_pool.tick_table(word_pos),
+                _ => univ3_pool.tick_bitmap(word_pos),
+            });
+        }
+    }
+
+    let outputs = batcher.call_all(word_calls).await?;
+    let mut ticks = Vec::new();
+    for ((pool_address, word_pos), word) in words.into_iter().zip(outputs) {
+        store.update_tick_bitmap(chain_id, pool_address, word_pos, word)?;
+        stats.words += 1;
+        let tick_spacing = tick_spacings[&pool_address];
+        for bit_pos in 0..256 {
+            if word.bit(bit_pos) {
+                let compressed = word_pos as i32 * 256 + bit_pos as i32;
+                ticks.push((pool_address, compressed * tick_spacing));
+            }
+        }
+    }
+
+    // ticks are grouped by pool, as the words they were read from
+    let mut tick_calls = Vec::with_capacity(ticks.len());
+    let mut contract: Option<UniV3Pool<M>> = None;
+    for (pool_address, tick) in &ticks {
+        if contract
+            .as_ref()
+            .is_none_or(|contract| contract.address() != *pool_address)
+        {
+            contract = Some(UniV3Pool::new(*pool_address, batcher.client()));
+        }
+        tick_calls.push(contract.as_ref().unwrap().ticks(*tick));
+    }
+    let outputs = batcher.call_all(tick_calls).await?;
+    for ((pool_address, tick), (liquidity_gross, liquidity_net, ..)) in
+        ticks.into_iter().zip(outputs)
+    {
+        store.update_ticks(chain_id, pool_address, tick, liquidity_gross, liquidity_net)?;
+        stats.ticks += 1;
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{univ2, univ3::add_pool, MemoryStore};
+    use cfmms::pool::{UniswapV2Pool, UniswapV3Pool};
+    use ethers::types::U256;
+
+    const CHAIN_ID: u64 = 42161;
+
+    #[test]
+    fn test_get_tick_range() {
+        let store = MemoryStore::new();
+        let univ3_pool = Address::from_low_u64_be(10);
+        let univ2_pool = Address::from_low_u64_be(11);
+        add_pool(
+            &store,
+            CHAIN_ID,
+            UniswapV3Pool {
+                address: univ3_pool,
+                token_a: Address::from_low_u64_be(1),
+                token_a_decimals: 18,
+                token_b: Address::from_low_u64_be(2),
+                token_b_decimals: 18,
+                liquidity: 0,
+                sqrt_price: U256::one() << 96,
+                fee: 3000,
+                tick: 0,
+                tick_spacing: 60,
+                liquidity_net: 0,
+            },
+        )
+        .unwrap();
+        univ2::add_pool(
+            &store,
+            CHAIN_ID,
+            UniswapV2Pool {
+                address: univ2_pool,
+                token_a: Address::from_low_u64_be(1),
+                token_a_decimals: 18,
+                token_b: Address::from_low_u64_be(2),
+                token_b_decimals: 18,
+                reserve_0: 0,
+                reserve_1: 0,
+                fee: 300,
+            },
+        )
+        .unwrap();
+
+        // ticks -887272 to 887272 are -14788 to 14787 once compressed by 60
+        let range = get_tick_range(&store, CHAIN_ID, univ3_pool)
+            .unwrap()
+            .unwrap();
+        assert_eq!(range.tick_spacing, 60);
+        assert_eq!(range.words, -58..=57);
+        assert!(get_tick_range(&store, CHAIN_ID, univ2_pool)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,260 @@
+use super::{
+    multicall::MulticallBatcher,
+    token::{erc20_address_to_decimals, erc20_addresses_to_decimals},
+    PoolStore,
+};
+use crate::{types::DexQuoteResult, utils::address_str};
+use ethers::prelude::*;
+use std::collections::HashMap;
+
+// ref. contracts/src/woofi/IWooPPV2.sol
+abigen!(
+    WooPPV2,
+    r#"[
+        function quoteToken() external view returns (address)
+        function wooracle() external view returns (address)
+        function tokenInfos(address) external view returns (uint192 reserve, uint16 feeRate)
+    ]"#,
+);
+
+// ref. https://github.com/woonetwork/WooPoolV2/blob/main/contracts/wooracle/WooracleV2.sol
+abigen!(
+    WooracleV2,
+    r#"[
+        function state(address base) external view returns (uint128 price, uint64 spread, uint64 coeff, bool woFeasible)
+        function decimals(address base) external view returns (uint8)
+    ]"#,
+);
+
+/// Oracle state and reserve of a base token of a WooFi pool
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WooFiToken {
+    pub address: Address,
+    pub decimals: u8,
+    pub reserve: U256,
+    // in 1e5
+    pub fee_rate: u16,
+    // quote per base in `price_decimals`
+    pub price: U256,
+    pub price_decimals: u8,
+    // in 1e18
+    pub spread: u64,
+    // slippage per quote amount in 1e18
+    pub coeff: u64,
+    pub feasible: bool,
+}
+
+/// A WooPPV2 pool, every base token trades against the quote token
+/// and against each other through it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WooFiPool {
+    pub address: Address,
+    pub oracle: Address,
+    pub quote_token: Address,
+    pub quote_decimals: u8,
+    pub quote_reserve: U256,
+    pub tokens: Vec<WooFiToken>,
+}
+
+impl WooFiPool {
+    /// Fetch a pool and the given base tokens from the node
+    pub async fn new_from_address<M: Middleware + 'static>(
+        pool_address: Address,
+        base_tokens: &[Address],
+        batcher: &MulticallBatcher<M>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let contract = WooPPV2::new(pool_address, batcher.client());
+        let (quote_token, oracle) = tokio::try_join!(
+            batcher.call(contract.quote_token()),
+            batcher.call(contract.wooracle()),
+        )?;
+        let token_info_calls = base_tokens
+            .iter()
+            .map(|base_token| contract.token_infos(*base_token))
+            .collect();
+        let ((quote_reserve, _), quote_decimals, token_infos, decimals) = tokio::try_join!(
+            batcher.call(contract.token_infos(quote_token)),
+            erc20_address_to_decimals(batcher, quote_token),
+            batcher.call_all(token_info_calls),
+            erc20_addresses_to_decimals(batcher, base_tokens),
+        )?;
+        let mut tokens: Vec<WooFiToken> = base_tokens
+            .iter()
+            .zip(token_infos)
+            .zip(decimals)
+            .map(|((base_token, (reserve, fee_rate)), decimals)| WooFiToken {
+                address: *base_token,
+                decimals,
+                reserve,
+                fee_rate,
+                ..Default::default()
+            })
+            .collect();
+        get_oracle_states(oracle, &mut tokens, batcher).await?;
+        Ok(Self {
+            address: pool_address,
+            oracle,
+            quote_token,
+            quote_decimals,
+            quote_reserve,
+            tokens,
+        })
+    }
+
+    pub fn token(&self, token: Address) -> Option<&WooFiToken> {
+        self.tokens
+            .iter()
+            .find(|base_token| base_token.address == token)
+    }
+
+    /// All tokens of the pool, quote token first
+    pub fn all_tokens(&self) -> Vec<Address> {
+        let mut tokens = vec![self.quote_token];
+        tokens.extend(self.tokens.iter().map(|token| token.address));
+        tokens
+    }
+}
+
+/// Read the oracle states of base tokens together
+pub async fn get_oracle_states<M: Middleware + 'static>(
+    oracle: Address,
+    tokens: &mut [WooFiToken],
+    batcher: &MulticallBatcher<M>,
+) -> DexQuoteResult<()> {
+    let contract = WooracleV2::new(oracle, batcher.client());
+    let state_calls = tokens
+        .iter()
+        .map(|token| contract.state(token.address))
+        .collect();
+    let decimal_calls = tokens
+        .iter()
+        .map(|token| contract.decimals(token.address))
+        .collect();
+    let (states, price_decimals) = tokio::try_join!(
+        batcher.call_all(state_calls),
+        batcher.call_all(decimal_calls),
+    )?;
+    for ((token, (price, spread, coeff, feasible)), price_decimals) in
+        tokens.iter_mut().zip(states).zip(price_decimals)
+    {
+        token.price = U256::from(price);
+        token.spread = spread;
+        token.coeff = coeff;
+        token.feasible = feasible;
+        token.price_decimals = price_decimals;
+    }
+    Ok(())
+}
+
+pub fn get_pool<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+) -> DexQuoteResult<Option<WooFiPool>> {
+    let target_data = store.get_pool_hashmap(chain_id, pool_address)?;
+    if target_data.is_empty() {
+        return Ok(None);
+    }
+    Ok(hashmap_to_woofi(pool_address, target_data))
+}
+
+pub fn hashmap_to_woofi(
+    pool_address: Address,
+    target_data: HashMap<String, String>,
+) -> Option<WooFiPool> {
+    let n_tokens: usize = target_data.get("n_tokens")?.parse().unwrap();
+    let mut pool = WooFiPool {
+        address: pool_address,
+        oracle: target_data.get("oracle")?.parse().unwrap(),
+        quote_token: target_data.get("quote_token")?.parse().unwrap(),
+        quote_decimals: target_data.get("quote_decimals")?.parse().unwrap(),
+        quote_reserve: U256::from_dec_str(target_data.get("quote_reserve")?).unwrap(),
+        tokens: vec![],
+    };
+    for i in 0..n_tokens {
+        let field = |name: &str| target_data.get(&format!("{name}{i}"));
+        pool.tokens.push(WooFiToken {
+            address: field("token")?.parse().unwrap(),
+            decimals: field("decimals")?.parse().unwrap(),
+            reserve: U256::from_dec_str(field("reserve")?).unwrap(),
+            fee_rate: field("fee_rate")?.parse().unwrap(),
+            price: U256::from_dec_str(field("price")?).unwrap(),
+            price_decimals: field("price_decimals")?.parse().unwrap(),
+            spread: field("spread")?.parse().unwrap(),
+            coeff: field("coeff")?.parse().unwrap(),
+            feasible: field("feasible")? == "true",
+        });
+    }
+    Some(pool)
+}
+
+fn token_fields(i: usize, token: &WooFiToken) -> Vec<(String, String)> {
+    vec![
+        (format!("token{i}"), address_str(token.address)),
+        (format!("decimals{i}"), token.decimals.to_string()),
+        (format!("reserve{i}"), token.reserve.to_string()),
+        (format!("fee_rate{i}"), token.fee_rate.to_string()),
+        (format!("price{i}"), token.price.to_string()),
+        (
+            format!("price_decimals{i}"),
+            token.price_decimals.to_string(),
+        ),
+        (format!("spread{i}"), token.spread.to_string()),
+        (format!("coeff{i}"), token.coeff.to_string()),
+        (format!("feasible{i}"), token.feasible.to_string()),
+    ]
+}
+
+fn update_fields<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    fields: &[(String, String)],
+) -> DexQuoteResult<()> {
+    let fields: Vec<(&str, String)> = fields
+        .iter()
+        .map(|(field, value)| (field.as_str(), value.clone()))
+        .collect();
+    store.update_pool_hashmap(chain_id, pool_address, &fields)
+}
+
+pub fn add_pool<S: PoolStore>(store: &S, chain_id: u64, pool: &WooFiPool) -> DexQuoteResult<()> {
+    let mut fields = vec![
+        ("n_tokens".to_string(), pool.tokens.len().to_string()),
+        ("oracle".to_string(), address_str(pool.oracle)),
+        ("quote_token".to_string(), address_str(pool.quote_token)),
+        (
+            "quote_decimals".to_string(),
+            pool.quote_decimals.to_string(),
+        ),
+        ("quote_reserve".to_string(), pool.quote_reserve.to_string()),
+        ("dex".to_string(), "WOOFIV2".to_string()),
+    ];
+    for (i, token) in pool.tokens.iter().enumerate() {
+        fields.extend(token_fields(i, token));
+    }
+    update_fields(store, chain_id, pool.address, &fields)
+}
+
+/// Write the reserves and oracle states of a pool back to the store
+pub fn update_pool<S: PoolStore>(store: &S, chain_id: u64, pool: &WooFiPool) -> DexQuoteResult<()> {
+    let mut fields = vec![("quote_reserve".to_string(), pool.quote_reserve.to_string())];
+    for (i, token) in pool.tokens.iter().enumerate() {
+        fields.extend(token_fields(i, token));
+    }
+    update_fields(store, chain_id, pool.address, &fields)
+}
+
+/// Oracle of each WooFi pool of the chain, oracle logs are applied to its pool
+pub fn get_oracle_pools<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+) -> DexQuoteResult<HashMap<Address, Address>> {
+    let mut oracle_pools = HashMap::new();
+    for pool_address in store.get_dex_pools(chain_id, "WOOFIV2")? {
+        if let Some(pool) = get_pool(store, chain_id, pool_address)? {
+            oracle_pools.insert(pool.oracle, pool_address);
+        }
+    }
+    Ok(oracle_pools)
+}
@@ -1,9 +1,24 @@
 use std::str::FromStr;
 
-#[derive(Debug, Copy, Clone)]
+use serde_derive::{Deserialize, Serialize};
+
+// serialized as the dex label of the store
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Dex {
+    #[serde(rename = "UNIV3")]
     UniswapV3,
+    #[serde(rename = "UNIV2")]
     UniswapV2,
+    #[serde(rename = "SOLIDLY")]
+    Solidly,
+    #[serde(rename = "ALGEBRA")]
+    Algebra,
+    #[serde(rename = "CURVE")]
+    Curve,
+    #[serde(rename = "BALANCERV2")]
+    BalancerV2,
+    #[serde(rename = "WOOFIV2")]
+    WooFiV2,
 }
 
 pub struct ParseDexError;
@@ -16,6 +31,16 @@ impl FromStr for Dex {
             Ok(Dex::UniswapV3)
         } else if dex_str == "UNIV2" {
             Ok(Dex::UniswapV2)
+        } else if dex_str == "SOLIDLY" {
+            Ok(Dex::Solidly)
+        } else if dex_str == "ALGEBRA" {
+            Ok(Dex::Algebra)
+        } else if dex_str == "CURVE" {
+            Ok(Dex::Curve)
+        } else if dex_str == "BALANCERV2" {
+            Ok(Dex::BalancerV2)
+        } else if dex_str == "WOOFIV2" {
+            Ok(Dex::WooFiV2)
         } else {
             Err(ParseDexError)
         }
@@ -23,10 +48,25 @@ impl FromStr for Dex {
 }
 
 impl Dex {
+    pub const ALL: [Dex; 7] = [
+        Dex::UniswapV3,
+        Dex::UniswapV2,
+        Dex::Solidly,
+        Dex::Algebra,
+        Dex::Curve,
+        Dex::BalancerV2,
+        Dex::WooFiV2,
+    ];
+
     pub fn as_str(&self) -> &'static str {
         match self {
             Dex::UniswapV3 => "UNIV3",
             Dex::UniswapV2 => "UNIV2",
+            Dex::Solidly => "SOLIDLY",
+            Dex::Algebra => "ALGEBRA",
+            Dex::Curve => "CURVE",
+            Dex::BalancerV2 => "BALANCERV2",
+            Dex::WooFiV2 => "WOOFIV2",
         }
     }
 }
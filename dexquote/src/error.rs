@@ -17,4 +17,16 @@ pub enum DexQuoteError {
     InvalidFee(u32),
     #[error("invalid dex: {0}")]
     InvalidDex(String),
+    #[error("insufficient liquidity in pool {0:?}")]
+    InsufficientLiquidity(Address),
+    #[error("missing tick data: pool {pool:?}, word {word_pos}")]
+    MissingTickData { pool: Address, word_pos: i16 },
+    #[error("unknown decimals of token {0:?}")]
+    UnknownDecimals(Address),
+    #[error("no path can be quoted")]
+    NoRoute,
+    #[error("multicall error: {0}")]
+    MulticallError(String),
+    #[error("sink error: {0}")]
+    SinkError(String),
 }
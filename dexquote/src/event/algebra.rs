@@ -0,0 +1,180 @@
+use crate::db::{algebra, PoolStore};
+use ethers::abi::ethabi;
+use ethers::prelude::*;
+use tracing::warn;
+
+// ref. https://github.com/cryptoalgebra/AlgebraV1/blob/main/src/core/contracts/interfaces/pool/IAlgebraPoolEvents.sol
+pub const ALGEBRA_FEE_EVENT_SIG: &str = "Fee(uint16)";
+// Camelot charges separate fees for zero for one and one for zero swaps
+pub const CAMELOT_FEE_EVENT_SIG: &str = "Fee(uint16,uint16)";
+pub const ALGEBRA_TICK_SPACING_EVENT_SIG: &str = "TickSpacing(int24)";
+
+pub struct AlgebraFeeEvent {
+    pub fee: u32,
+    pub fee_one_for_zero: Option<u32>,
+}
+
+pub struct AlgebraTickSpacingEvent {
+    pub tick_spacing: i32,
+}
+
+pub fn decode_fee_event(log_data: &Bytes) -> Result<AlgebraFeeEvent, ethabi::Error> {
+    let decoded_data = ethabi::decode(&[ethabi::ParamType::Uint(16)], log_data)?;
+    let fee = decoded_data[0].clone().into_uint().unwrap().as_u32();
+    Ok(AlgebraFeeEvent {
+        fee,
+        fee_one_for_zero: None,
+    })
+}
+
+pub fn decode_camelot_fee_event(log_data: &Bytes) -> Result<AlgebraFeeEvent, ethabi::Error> {
+    let decoded_data = ethabi::decode(
+        &[ethabi::ParamType::Uint(16), ethabi::ParamType::Uint(16)],
+        log_data,
+    )?;
+    let mut decoded = decoded_data.into_iter();
+    let fee = decoded.next().unwrap().into_uint().unwrap().as_u32();
+    let fee_one_for_zero = decoded.next().unwrap().into_uint().unwrap().as_u32();
+    Ok(AlgebraFeeEvent {
+        fee,
+        fee_one_for_zero: Some(fee_one_for_zero),
+    })
+}
+
+pub fn decode_tick_spacing_event(
+    log_data: &Bytes,
+) -> Result<AlgebraTickSpacingEvent, ethabi::Error> {
+    let decoded_data = ethabi::decode(&[ethabi::ParamType::Int(24)], log_data)?;
+    let tick_spacing = I256::from_raw(decoded_data[0].clone().into_int().unwrap()).as_i32();
+    Ok(AlgebraTickSpacingEvent { tick_spacing })
+}
+
+pub fn update_with_fee_event<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    event: &AlgebraFeeEvent,
+) {
+    match algebra::update_fee(
+        store,
+        chain_id,
+        pool_address,
+        event.fee,
+        event.fee_one_for_zero,
+    ) {
+        Ok(_) => {}
+        Err(e) => {
+            warn!("failed to update fee: {:?}", e);
+        }
+    }
+}
+
+pub fn update_with_tick_spacing_event<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    event: &AlgebraTickSpacingEvent,
+) {
+    match algebra::update_tick_spacing(store, chain_id, pool_address, event.tick_spacing) {
+        Ok(_) => {}
+        Err(e) => {
+            warn!("failed to update tick spacing: {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::db::{algebra::AlgebraPool, MemoryStore};
+    use crate::error::DexQuoteError;
+    use crate::price::uni_v3::{get_price, PoolState};
+    use uniswap_v3_math::tick_math;
+
+    #[test]
+    fn test_decode_algebra_events() {
+        let log_data =
+            Bytes::from_str("0x00000000000000000000000000000000000000000000000000000000000001f4")
+                .unwrap();
+        let event = decode_fee_event(&log_data).unwrap();
+        assert_eq!(event.fee, 500);
+        assert_eq!(event.fee_one_for_zero, None);
+
+        let log_data = Bytes::from_str("0x00000000000000000000000000000000000000000000000000000000000001f400000000000000000000000000000000000000000000000000000000000003e8").unwrap();
+        let event = decode_camelot_fee_event(&log_data).unwrap();
+        assert_eq!(event.fee, 500);
+        assert_eq!(event.fee_one_for_zero, Some(1000));
+
+        let log_data =
+            Bytes::from_str("0x000000000000000000000000000000000000000000000000000000000000000a")
+                .unwrap();
+        let event = decode_tick_spacing_event(&log_data).unwrap();
+        assert_eq!(event.tick_spacing, 10);
+    }
+
+    #[tokio::test]
+    async fn test_update_with_algebra_events() {
+        let store = MemoryStore::new();
+        let chain_id = 42161;
+        let pool_address = Address::random();
+        algebra::add_pool(
+            &store,
+            chain_id,
+            AlgebraPool {
+                address: pool_address,
+                token_a: Address::from_low_u64_be(1),
+                token_a_decimals: 18,
+                token_b: Address::from_low_u64_be(2),
+                token_b_decimals: 6,
+                liquidity: 1_000_000_000_000_000,
+                sqrt_price: tick_math::get_sqrt_ratio_at_tick(5000).unwrap(),
+                tick: 5000,
+                tick_spacing: 60,
+                fee: 100,
+                fee_one_for_zero: None,
+            },
+        )
+        .unwrap();
+
+        update_with_fee_event(
+            &store,
+            chain_id,
+            pool_address,
+            &AlgebraFeeEvent {
+                fee: 3000,
+                fee_one_for_zero: None,
+            },
+        );
+        update_with_tick_spacing_event(
+            &store,
+            chain_id,
+            pool_address,
+            &AlgebraTickSpacingEvent { tick_spacing: 10 },
+        );
+
+        // the pool state quotes with the current dynamic fee
+        let pool_state = PoolState::init(&store, chain_id, String::new(), pool_address, true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(pool_state.fee, 3000);
+
+        // and with its own tick spacing instead of the one of the fee,
+        // tick 5000 is in word 1 with a spacing of 10 and in word 0 with 60
+        let result = get_price(
+            &store,
+            chain_id,
+            String::new(),
+            pool_address,
+            true,
+            U256::from(1000),
+            true,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(DexQuoteError::MissingTickData { word_pos: 1, .. })
+        ));
+    }
+}
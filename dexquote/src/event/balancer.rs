@@ -0,0 +1,349 @@
+use crate::db::{balancer, PoolStore};
+use crate::types::DexQuoteResult;
+use ethers::abi::ethabi;
+use ethers::prelude::*;
+use tracing::warn;
+
+// ref. https://github.com/balancer/balancer-v2-monorepo/blob/master/pkg/interfaces/contracts/vault/IVault.sol
+// the Vault logs swaps and joins/exits of every pool, keyed by pool id
+pub const BALANCER_SWAP_EVENT_SIG: &str = "Swap(bytes32,address,address,uint256,uint256)";
+pub const BALANCER_POOL_BALANCE_CHANGED_EVENT_SIG: &str =
+    "PoolBalanceChanged(bytes32,address,address[],int256[],uint256[])";
+// logged by the pools themselves
+pub const BALANCER_SWAP_FEE_PERCENTAGE_CHANGED_EVENT_SIG: &str =
+    "SwapFeePercentageChanged(uint256)";
+pub const BALANCER_AMP_UPDATE_STARTED_EVENT_SIG: &str =
+    "AmpUpdateStarted(uint256,uint256,uint256,uint256)";
+pub const BALANCER_AMP_UPDATE_STOPPED_EVENT_SIG: &str = "AmpUpdateStopped(uint256)";
+
+pub struct BalancerSwapEvent {
+    pub pool_id: H256,
+    pub token_in: Address,
+    pub token_out: Address,
+    pub amount_in: U256,
+    pub amount_out: U256,
+}
+
+pub struct BalancerPoolBalanceChangedEvent {
+    pub pool_id: H256,
+    pub tokens: Vec<Address>,
+    pub deltas: Vec<I256>,
+    pub protocol_fee_amounts: Vec<U256>,
+}
+
+pub struct BalancerSwapFeeEvent {
+    pub swap_fee: U256,
+}
+
+pub struct BalancerAmpUpdateEvent {
+    pub start_value: U256,
+    pub end_value: U256,
+    pub start_time: u64,
+    pub end_time: u64,
+}
+
+/// Pool of a Vault log, from the pool id in its first topic
+pub fn get_vault_log_pool(log: &Log) -> Option<Address> {
+    log.topics
+        .get(1)
+        .map(|pool_id| balancer::pool_id_to_address(*pool_id))
+}
+
+pub fn decode_swap_event(log: &Log) -> Result<BalancerSwapEvent, ethabi::Error> {
+    if log.topics.len() < 4 {
+        return Err(ethabi::Error::InvalidData);
+    }
+    let decoded_data = ethabi::decode(
+        &[ethabi::ParamType::Uint(256), ethabi::ParamType::Uint(256)],
+        &log.data,
+    )?;
+    let mut decoded = decoded_data.into_iter();
+    Ok(BalancerSwapEvent {
+        pool_id: log.topics[1],
+        token_in: Address::from(log.topics[2]),
+        token_out: Address::from(log.topics[3]),
+        amount_in: decoded.next().unwrap().into_uint().unwrap(),
+        amount_out: decoded.next().unwrap().into_uint().unwrap(),
+    })
+}
+
+pub fn decode_pool_balance_changed_event(
+    log: &Log,
+) -> Result<BalancerPoolBalanceChangedEvent, ethabi::Error> {
+    if log.topics.len() < 2 {
+        return Err(ethabi::Error::InvalidData);
+    }
+    let decoded_data = ethabi::decode(
+        &[
+            ethabi::ParamType::Array(Box::new(ethabi::ParamType::Address)),
+            ethabi::ParamType::Array(Box::new(ethabi::ParamType::Int(256))),
+            ethabi::ParamType::Array(Box::new(ethabi::ParamType::Uint(256))),
+        ],
+        &log.data,
+    )?;
+    let mut decoded = decoded_data
+        .into_iter()
+        .map(|array| array.into_array().unwrap());
+    let tokens = decoded
+        .next()
+        .unwrap()
+        .into_iter()
+        .map(|token| token.into_address().unwrap())
+        .collect();
+    let deltas = decoded
+        .next()
+        .unwrap()
+        .into_iter()
+        .map(|delta| I256::from_raw(delta.into_int().unwrap()))
+        .collect();
+    let protocol_fee_amounts = decoded
+        .next()
+        .unwrap()
+        .into_iter()
+        .map(|amount| amount.into_uint().unwrap())
+        .collect();
+    Ok(BalancerPoolBalanceChangedEvent {
+        pool_id: log.topics[1],
+        tokens,
+        deltas,
+        protocol_fee_amounts,
+    })
+}
+
+pub fn decode_swap_fee_event(log_data: &Bytes) -> Result<BalancerSwapFeeEvent, ethabi::Error> {
+    let decoded_data = ethabi::decode(&[ethabi::ParamType::Uint(256)], log_data)?;
+    let swap_fee = decoded_data[0].clone().into_uint().unwrap();
+    Ok(BalancerSwapFeeEvent { swap_fee })
+}
+
+pub fn decode_amp_update_started_event(
+    log_data: &Bytes,
+) -> Result<BalancerAmpUpdateEvent, ethabi::Error> {
+    let decoded_data = ethabi::decode(&vec![ethabi::ParamType::Uint(256); 4], log_data)?;
+    let mut decoded = decoded_data
+        .into_iter()
+        .map(|value| value.into_uint().unwrap());
+    Ok(BalancerAmpUpdateEvent {
+        start_value: decoded.next().unwrap(),
+        end_value: decoded.next().unwrap(),
+        start_time: decoded.next().unwrap().as_u64(),
+        end_time: decoded.next().unwrap().as_u64(),
+    })
+}
+
+// the amplification stays at its current value
+pub fn decode_amp_update_stopped_event(
+    log_data: &Bytes,
+) -> Result<BalancerAmpUpdateEvent, ethabi::Error> {
+    let decoded_data = ethabi::decode(&[ethabi::ParamType::Uint(256)], log_data)?;
+    let current_value = decoded_data[0].clone().into_uint().unwrap();
+    Ok(BalancerAmpUpdateEvent {
+        start_value: current_value,
+        end_value: current_value,
+        start_time: 0,
+        end_time: 0,
+    })
+}
+
+fn apply_swap<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    event: &BalancerSwapEvent,
+) -> DexQuoteResult<()> {
+    let pool_address = balancer::pool_id_to_address(event.pool_id);
+    let mut pool = match balancer::get_pool(store, chain_id, pool_address)? {
+        Some(pool) => pool,
+        None => return Ok(()),
+    };
+    // the BPT of composable stable pools is not stored
+    if let Some(i) = pool.token_index(event.token_in) {
+        pool.balances[i] += event.amount_in;
+    }
+    if let Some(j) = pool.token_index(event.token_out) {
+        pool.balances[j] = pool.balances[j].saturating_sub(event.amount_out);
+    }
+    balancer::update_balances(store, chain_id, pool_address, &pool.balances)
+}
+
+/// Apply a Vault swap to the balances of its pool, pools not stored are skipped
+pub fn update_with_swap_event<S: PoolStore>(store: &S, chain_id: u64, event: &BalancerSwapEvent) {
+    match apply_swap(store, chain_id, event) {
+        Ok(_) => {}
+        Err(e) => {
+            warn!("failed to update balances: {:?}", e);
+        }
+    }
+}
+
+fn apply_pool_balance_changed<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    event: &BalancerPoolBalanceChangedEvent,
+) -> DexQuoteResult<()> {
+    let pool_address = balancer::pool_id_to_address(event.pool_id);
+    let mut pool = match balancer::get_pool(store, chain_id, pool_address)? {
+        Some(pool) => pool,
+        None => return Ok(()),
+    };
+    for (k, token) in event.tokens.iter().enumerate() {
+        let i = match pool.token_index(*token) {
+            Some(i) => i,
+            None => continue,
+        };
+        // protocol fees leave the pool on joins and exits
+        let delta = event.deltas[k];
+        let balance = if delta.is_negative() {
+            pool.balances[i].saturating_sub(delta.unsigned_abs())
+        } else {
+            pool.balances[i] + delta.into_raw()
+        };
+        pool.balances[i] = balance.saturating_sub(event.protocol_fee_amounts[k]);
+    }
+    balancer::update_balances(store, chain_id, pool_address, &pool.balances)
+}
+
+/// Apply a join or exit to the balances of its pool
+pub fn update_with_pool_balance_changed_event<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    event: &BalancerPoolBalanceChangedEvent,
+) {
+    match apply_pool_balance_changed(store, chain_id, event) {
+        Ok(_) => {}
+        Err(e) => {
+            warn!("failed to update balances: {:?}", e);
+        }
+    }
+}
+
+pub fn update_with_swap_fee_event<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    event: &BalancerSwapFeeEvent,
+) {
+    match balancer::update_swap_fee(store, chain_id, pool_address, event.swap_fee) {
+        Ok(_) => {}
+        Err(e) => {
+            warn!("failed to update swap fee: {:?}", e);
+        }
+    }
+}
+
+pub fn update_with_amp_update_event<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    event: &BalancerAmpUpdateEvent,
+) {
+    match balancer::update_amp(
+        store,
+        chain_id,
+        pool_address,
+        event.start_value,
+        event.end_value,
+        event.start_time,
+        event.end_time,
+    ) {
+        Ok(_) => {}
+        Err(e) => {
+            warn!("failed to update amplification: {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::balancer::{get_scaling_factor, BalancerPool};
+    use crate::db::MemoryStore;
+    use crate::event::get_event_sig;
+
+    fn pool_id(pool_address: Address) -> H256 {
+        let mut pool_id = [0u8; 32];
+        pool_id[..20].copy_from_slice(pool_address.as_bytes());
+        pool_id[31] = 1;
+        H256::from(pool_id)
+    }
+
+    #[test]
+    fn test_update_with_vault_events() {
+        let store = MemoryStore::new();
+        let chain_id = 1;
+        let pool_address = Address::random();
+        let (token0, token1) = (Address::random(), Address::random());
+        balancer::add_pool(
+            &store,
+            chain_id,
+            &BalancerPool {
+                address: pool_address,
+                pool_id: pool_id(pool_address),
+                tokens: vec![token0, token1],
+                decimals: vec![18, 18],
+                balances: vec![U256::from(1000), U256::from(2000)],
+                scaling_factors: vec![get_scaling_factor(18), get_scaling_factor(18)],
+                weights: vec![U256::exp10(17) * 5, U256::exp10(17) * 5],
+                swap_fee: U256::exp10(15),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let log = Log {
+            address: balancer::vault_address(),
+            topics: vec![
+                get_event_sig(BALANCER_SWAP_EVENT_SIG),
+                pool_id(pool_address),
+                H256::from(token0),
+                H256::from(token1),
+            ],
+            data: ethabi::encode(&[
+                ethabi::Token::Uint(U256::from(100)),
+                ethabi::Token::Uint(U256::from(150)),
+            ])
+            .into(),
+            ..Default::default()
+        };
+        assert_eq!(get_vault_log_pool(&log), Some(pool_address));
+        update_with_swap_event(&store, chain_id, &decode_swap_event(&log).unwrap());
+        let pool = balancer::get_pool(&store, chain_id, pool_address)
+            .unwrap()
+            .unwrap();
+        assert_eq!(pool.balances, vec![U256::from(1100), U256::from(1850)]);
+
+        // exit of 100 token0 with 1 token0 of protocol fees, join of 50 token1
+        let log = Log {
+            address: balancer::vault_address(),
+            topics: vec![
+                get_event_sig(BALANCER_POOL_BALANCE_CHANGED_EVENT_SIG),
+                pool_id(pool_address),
+                H256::from(Address::random()),
+            ],
+            data: ethabi::encode(&[
+                ethabi::Token::Array(vec![
+                    ethabi::Token::Address(token0),
+                    ethabi::Token::Address(token1),
+                ]),
+                ethabi::Token::Array(vec![
+                    ethabi::Token::Int(I256::from(-100).into_raw()),
+                    ethabi::Token::Int(I256::from(50).into_raw()),
+                ]),
+                ethabi::Token::Array(vec![
+                    ethabi::Token::Uint(U256::one()),
+                    ethabi::Token::Uint(U256::zero()),
+                ]),
+            ])
+            .into(),
+            ..Default::default()
+        };
+        update_with_pool_balance_changed_event(
+            &store,
+            chain_id,
+            &decode_pool_balance_changed_event(&log).unwrap(),
+        );
+        let pool = balancer::get_pool(&store, chain_id, pool_address)
+            .unwrap()
+            .unwrap();
+        assert_eq!(pool.balances, vec![U256::from(999), U256::from(1900)]);
+    }
+}
@@ -0,0 +1,427 @@
+use crate::db::{curve, multicall::MulticallBatcher, PoolStore};
+use crate::price::curve::{get_admin_fee, set_base_pool_rate};
+use crate::types::DexQuoteResult;
+use crate::utils::now;
+use ethers::abi::ethabi;
+use ethers::prelude::*;
+use tracing::warn;
+
+use super::get_event_sig;
+
+// ref. https://github.com/curvefi/curve-contract/blob/master/contracts/pools/3pool/StableSwap3Pool.vy
+pub const CURVE_TOKEN_EXCHANGE_EVENT_SIG: &str =
+    "TokenExchange(address,int128,uint256,int128,uint256)";
+// meta pools swapping against the coins of their base pool
+pub const CURVE_TOKEN_EXCHANGE_UNDERLYING_EVENT_SIG: &str =
+    "TokenExchangeUnderlying(address,int128,uint256,int128,uint256)";
+pub const CURVE_REMOVE_LIQUIDITY_ONE_EVENT_SIG: &str =
+    "RemoveLiquidityOne(address,uint256,uint256)";
+// newer pools also log the token supply
+pub const CURVE_REMOVE_LIQUIDITY_ONE_WITH_SUPPLY_EVENT_SIG: &str =
+    "RemoveLiquidityOne(address,uint256,uint256,uint256)";
+pub const CURVE_RAMP_A_EVENT_SIG: &str = "RampA(uint256,uint256,uint256,uint256)";
+pub const CURVE_STOP_RAMP_A_EVENT_SIG: &str = "StopRampA(uint256,uint256)";
+
+// the liquidity events are logged with uint256[N_COINS] arrays
+const MIN_COINS: usize = 2;
+const MAX_COINS: usize = 4;
+
+pub struct CurveTokenExchangeEvent {
+    pub sold_id: usize,
+    pub tokens_sold: U256,
+    pub bought_id: usize,
+    pub tokens_bought: U256,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CurveLiquidityKind {
+    Add,
+    Remove,
+    RemoveImbalance,
+}
+
+impl CurveLiquidityKind {
+    fn name(&self) -> &'static str {
+        match self {
+            CurveLiquidityKind::Add => "AddLiquidity",
+            CurveLiquidityKind::Remove => "RemoveLiquidity",
+            CurveLiquidityKind::RemoveImbalance => "RemoveLiquidityImbalance",
+        }
+    }
+
+    // RemoveLiquidity does not log the invariant
+    fn has_invariant(&self) -> bool {
+        *self != CurveLiquidityKind::Remove
+    }
+}
+
+pub struct CurveLiquidityEvent {
+    pub kind: CurveLiquidityKind,
+    pub token_amounts: Vec<U256>,
+    pub fees: Vec<U256>,
+    pub token_supply: U256,
+}
+
+pub struct CurveRampAEvent {
+    pub initial_a: U256,
+    pub future_a: U256,
+    pub initial_a_time: u64,
+    pub future_a_time: u64,
+}
+
+/// Signature of a liquidity event of a pool with `n_coins` coins
+pub fn liquidity_event_sig(kind: CurveLiquidityKind, n_coins: usize) -> String {
+    let amounts = format!("uint256[{n_coins}]");
+    if kind.has_invariant() {
+        format!(
+            "{}(address,{amounts},{amounts},uint256,uint256)",
+            kind.name()
+        )
+    } else {
+        format!("{}(address,{amounts},{amounts},uint256)", kind.name())
+    }
+}
+
+/// Kind and number of coins of a liquidity event signature
+pub fn find_liquidity_event(event_sig: H256) -> Option<(CurveLiquidityKind, usize)> {
+    let kinds = [
+        CurveLiquidityKind::Add,
+        CurveLiquidityKind::Remove,
+        CurveLiquidityKind::RemoveImbalance,
+    ];
+    for kind in kinds {
+        for n_coins in MIN_COINS..=MAX_COINS {
+            if get_event_sig(&liquidity_event_sig(kind, n_coins)) == event_sig {
+                return Some((kind, n_coins));
+            }
+        }
+    }
+    None
+}
+
+pub fn decode_token_exchange_event(
+    log_data: &Bytes,
+) -> Result<CurveTokenExchangeEvent, ethabi::Error> {
+    let decoded_data = ethabi::decode(
+        &[
+            ethabi::ParamType::Int(128),
+            ethabi::ParamType::Uint(256),
+            ethabi::ParamType::Int(128),
+            ethabi::ParamType::Uint(256),
+        ],
+        log_data,
+    )?;
+    let mut decoded = decoded_data.into_iter();
+    let sold_id = decoded.next().unwrap().into_int().unwrap().as_usize();
+    let tokens_sold = decoded.next().unwrap().into_uint().unwrap();
+    let bought_id = decoded.next().unwrap().into_int().unwrap().as_usize();
+    let tokens_bought = decoded.next().unwrap().into_uint().unwrap();
+    Ok(CurveTokenExchangeEvent {
+        sold_id,
+        tokens_sold,
+        bought_id,
+        tokens_bought,
+    })
+}
+
+pub fn decode_liquidity_event(
+    kind: CurveLiquidityKind,
+    n_coins: usize,
+    log_data: &Bytes,
+) -> Result<CurveLiquidityEvent, ethabi::Error> {
+    let amounts = ethabi::ParamType::FixedArray(Box::new(ethabi::ParamType::Uint(256)), n_coins);
+    let mut params = vec![amounts.clone(), amounts];
+    if kind.has_invariant() {
+        params.push(ethabi::ParamType::Uint(256));
+    }
+    params.push(ethabi::ParamType::Uint(256));
+    let decoded_data = ethabi::decode(&params, log_data)?;
+    let mut decoded = decoded_data.into_iter();
+    let mut next_amounts = || -> Vec<U256> {
+        decoded
+            .next()
+            .unwrap()
+            .into_fixed_array()
+            .unwrap()
+            .into_iter()
+            .map(|amount| amount.into_uint().unwrap())
+            .collect()
+    };
+    let token_amounts = next_amounts();
+    let fees = next_amounts();
+    let token_supply = decoded.last().unwrap().into_uint().unwrap();
+    Ok(CurveLiquidityEvent {
+        kind,
+        token_amounts,
+        fees,
+        token_supply,
+    })
+}
+
+pub fn decode_ramp_a_event(log_data: &Bytes) -> Result<CurveRampAEvent, ethabi::Error> {
+    let decoded_data = ethabi::decode(&vec![ethabi::ParamType::Uint(256); 4], log_data)?;
+    let mut decoded = decoded_data
+        .into_iter()
+        .map(|value| value.into_uint().unwrap());
+    Ok(CurveRampAEvent {
+        initial_a: decoded.next().unwrap(),
+        future_a: decoded.next().unwrap(),
+        initial_a_time: decoded.next().unwrap().as_u64(),
+        future_a_time: decoded.next().unwrap().as_u64(),
+    })
+}
+
+// A stays at its current value from now on
+pub fn decode_stop_ramp_a_event(log_data: &Bytes) -> Result<CurveRampAEvent, ethabi::Error> {
+    let decoded_data = ethabi::decode(&vec![ethabi::ParamType::Uint(256); 2], log_data)?;
+    let mut decoded = decoded_data
+        .into_iter()
+        .map(|value| value.into_uint().unwrap());
+    let a = decoded.next().unwrap();
+    let timestamp = decoded.next().unwrap().as_u64();
+    Ok(CurveRampAEvent {
+        initial_a: a,
+        future_a: a,
+        initial_a_time: timestamp,
+        future_a_time: timestamp,
+    })
+}
+
+fn apply_token_exchange<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    event: &CurveTokenExchangeEvent,
+) -> DexQuoteResult<()> {
+    let mut pool = match curve::get_pool(store, chain_id, pool_address)? {
+        Some(pool) => pool,
+        None => return Ok(()),
+    };
+    let (i, j) = (event.sold_id, event.bought_id);
+    let timestamp = now();
+    set_base_pool_rate(store, chain_id, &mut pool, timestamp)?;
+    // the admin share of the fee leaves the pool
+    let admin_fee = get_admin_fee(&pool, i, j, event.tokens_sold, timestamp)?;
+    pool.balances[i] += event.tokens_sold;
+    pool.balances[j] = pool.balances[j].saturating_sub(event.tokens_bought + admin_fee);
+    curve::update_balances(store, chain_id, pool_address, &pool.balances)
+}
+
+pub fn update_with_token_exchange_event<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    event: &CurveTokenExchangeEvent,
+) {
+    match apply_token_exchange(store, chain_id, pool_address, event) {
+        Ok(_) => {}
+        Err(e) => {
+            warn!("failed to update balances: {:?}", e);
+        }
+    }
+}
+
+fn apply_liquidity<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    event: &CurveLiquidityEvent,
+) -> DexQuoteResult<()> {
+    let mut pool = match curve::get_pool(store, chain_id, pool_address)? {
+        Some(pool) => pool,
+        None => return Ok(()),
+    };
+    let fee_denominator = U256::from(10_000_000_000u64);
+    for (i, balance) in pool.balances.iter_mut().enumerate() {
+        let amount = event.token_amounts[i];
+        let admin_fee = event.fees[i] * pool.admin_fee / fee_denominator;
+        *balance = match event.kind {
+            CurveLiquidityKind::Add => (*balance + amount).saturating_sub(admin_fee),
+            CurveLiquidityKind::Remove => balance.saturating_sub(amount),
+            CurveLiquidityKind::RemoveImbalance => balance.saturating_sub(amount + admin_fee),
+        };
+    }
+    curve::update_balances(store, chain_id, pool_address, &pool.balances)?;
+    curve::update_total_supply(store, chain_id, pool_address, event.token_supply)
+}
+
+pub fn update_with_liquidity_event<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    event: &CurveLiquidityEvent,
+) {
+    match apply_liquidity(store, chain_id, pool_address, event) {
+        Ok(_) => {}
+        Err(e) => {
+            warn!("failed to update balances: {:?}", e);
+        }
+    }
+}
+
+pub fn update_with_ramp_a_event<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    event: &CurveRampAEvent,
+) {
+    match curve::update_a(
+        store,
+        chain_id,
+        pool_address,
+        event.initial_a,
+        event.future_a,
+        event.initial_a_time,
+        event.future_a_time,
+    ) {
+        Ok(_) => {}
+        Err(e) => {
+            warn!("failed to update A: {:?}", e);
+        }
+    }
+}
+
+/// Read the balances and token supply from the node.
+/// RemoveLiquidityOne does not log the coin and underlying exchanges
+/// change the meta pool by an amount only known to the pool.
+pub async fn update_from_node<M: Middleware + 'static, S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    batcher: &MulticallBatcher<M>,
+) {
+    let pool = match curve::get_pool(store, chain_id, pool_address) {
+        Ok(Some(pool)) => pool,
+        Ok(None) => return,
+        Err(e) => {
+            warn!("failed to get pool: {:?}", e);
+            return;
+        }
+    };
+    let (balances, total_supply) = tokio::join!(
+        curve::get_balances(pool_address, pool.coins.len(), batcher),
+        curve::get_total_supply(pool_address, batcher),
+    );
+    let balances = match balances {
+        Ok(balances) => balances,
+        Err(e) => {
+            warn!("failed to get balances: {:?}", e);
+            return;
+        }
+    };
+    let total_supply = match total_supply {
+        Ok(total_supply) => total_supply,
+        Err(e) => {
+            warn!("failed to get total supply: {:?}", e);
+            return;
+        }
+    };
+    match curve::update_balances(store, chain_id, pool_address, &balances)
+        .and_then(|_| curve::update_total_supply(store, chain_id, pool_address, total_supply))
+    {
+        Ok(_) => {}
+        Err(e) => {
+            warn!("failed to update balances: {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::db::curve::{get_rate, CurvePool};
+    use crate::db::MemoryStore;
+
+    #[test]
+    fn test_find_liquidity_event() {
+        let event_sig =
+            get_event_sig("AddLiquidity(address,uint256[3],uint256[3],uint256,uint256)");
+        assert_eq!(
+            find_liquidity_event(event_sig),
+            Some((CurveLiquidityKind::Add, 3))
+        );
+        let event_sig = get_event_sig("RemoveLiquidity(address,uint256[2],uint256[2],uint256)");
+        assert_eq!(
+            find_liquidity_event(event_sig),
+            Some((CurveLiquidityKind::Remove, 2))
+        );
+        assert_eq!(
+            find_liquidity_event(get_event_sig(CURVE_RAMP_A_EVENT_SIG)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_decode_curve_events() {
+        let log_data = Bytes::from_str("0x0000000000000000000000000000000000000000000000000000000000000001000000000000000000000000000000000000000000000000000000e8d4a510000000000000000000000000000000000000000000000000000000000000000002000000000000000000000000000000000000000000000000000000e8d4728730").unwrap();
+        let event = decode_token_exchange_event(&log_data).unwrap();
+        assert_eq!((event.sold_id, event.bought_id), (1, 2));
+        assert_eq!(event.tokens_sold, U256::from(1_000_000_000_000u64));
+        assert_eq!(event.tokens_bought, U256::from(999996688176u64));
+
+        let log_data = Bytes::from_str("0x000000000000000000000000000000000000000000000000000000000000006400000000000000000000000000000000000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000002000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000000000000000000000000000000000003e8").unwrap();
+        let event = decode_liquidity_event(CurveLiquidityKind::Remove, 2, &log_data).unwrap();
+        assert_eq!(event.token_amounts, vec![U256::from(100), U256::from(1)]);
+        assert_eq!(event.fees, vec![U256::from(2), U256::from(3)]);
+        assert_eq!(event.token_supply, U256::from(1000));
+    }
+
+    #[test]
+    fn test_update_with_token_exchange_event() {
+        let store = MemoryStore::new();
+        let chain_id = 1;
+        let pool_address = Address::random();
+        let decimals = vec![18, 6, 6];
+        let balances = vec![
+            U256::from(100_000_000u64) * U256::exp10(18),
+            U256::from(90_000_000u64) * U256::exp10(6),
+            U256::from(110_000_000u64) * U256::exp10(6),
+        ];
+        curve::add_pool(
+            &store,
+            chain_id,
+            &CurvePool {
+                address: pool_address,
+                coins: vec![Address::random(), Address::random(), Address::random()],
+                balances: balances.clone(),
+                rates: decimals
+                    .iter()
+                    .map(|decimals| get_rate(*decimals))
+                    .collect(),
+                decimals,
+                initial_a: 2000.into(),
+                future_a: 2000.into(),
+                a_precision: 1.into(),
+                fee: 1_000_000.into(),
+                admin_fee: 5_000_000_000u64.into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let tokens_sold = U256::from(1_000_000_000_000u64);
+        let tokens_bought = U256::from(999996688176u64);
+        update_with_token_exchange_event(
+            &store,
+            chain_id,
+            pool_address,
+            &CurveTokenExchangeEvent {
+                sold_id: 1,
+                tokens_sold,
+                bought_id: 2,
+                tokens_bought,
+            },
+        );
+        let pool = curve::get_pool(&store, chain_id, pool_address)
+            .unwrap()
+            .unwrap();
+        assert_eq!(pool.balances[0], balances[0]);
+        assert_eq!(pool.balances[1], balances[1] + tokens_sold);
+        assert_eq!(
+            pool.balances[2],
+            balances[2] - tokens_bought - U256::from(50004834)
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use cfmms::pool::{Pool, UniswapV2Pool, UniswapV3Pool};
+use ethers::{abi::ethabi, prelude::*};
+use neo4rs::Graph;
+
+use crate::{
+    db::{
+        self, algebra::AlgebraPool, multicall::MulticallBatcher, solidly::SolidlyPool, PoolStore,
+    },
+    dex::Dex,
+};
+
+use super::get_event_sig;
+
+// ref. https://github.com/Uniswap/v3-core/blob/main/contracts/interfaces/IUniswapV3Factory.sol
+pub const UNIV3_POOL_CREATED_EVENT_SIG: &str = "PoolCreated(address,address,uint24,int24,address)";
+// ref. https://github.com/Uniswap/v2-core/blob/master/contracts/interfaces/IUniswapV2Factory.sol
+pub const UNIV2_PAIR_CREATED_EVENT_SIG: &str = "PairCreated(address,address,address,uint256)";
+// ref. https://github.com/velodrome-finance/contracts/blob/main/contracts/interfaces/factories/IPoolFactory.sol
+pub const SOLIDLY_POOL_CREATED_EVENT_SIG: &str =
+    "PoolCreated(address,address,bool,address,uint256)";
+// ref. https://github.com/cryptoalgebra/AlgebraV1/blob/main/src/core/contracts/interfaces/IAlgebraFactory.sol
+pub const ALGEBRA_POOL_CREATED_EVENT_SIG: &str = "Pool(address,address,address)";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PoolCreatedEvent {
+    pub dex: Dex,
+    pub pool_address: Address,
+    pub token0: Address,
+    pub token1: Address,
+}
+
+/// Decode a UniswapV3 PoolCreated, UniswapV2 PairCreated, Solidly PoolCreated or Algebra Pool event.
+/// Returns None for any other log.
+pub fn decode_pool_created_event(log: &Log) -> Option<PoolCreatedEvent> {
+    if log.topics.len() < 3 {
+        return None;
+    }
+    let event_sig = log.topics[0];
+    let (dex, params) = if event_sig == get_event_sig(UNIV3_POOL_CREATED_EVENT_SIG) {
+        (
+            Dex::UniswapV3,
+            vec![ethabi::ParamType::Int(24), ethabi::ParamType::Address],
+        )
+    } else if event_sig == get_event_sig(UNIV2_PAIR_CREATED_EVENT_SIG) {
+        (
+            Dex::UniswapV2,
+            vec![ethabi::ParamType::Address, ethabi::ParamType::Uint(256)],
+        )
+    } else if event_sig == get_event_sig(SOLIDLY_POOL_CREATED_EVENT_SIG) {
+        (
+            Dex::Solidly,
+            vec![ethabi::ParamType::Address, ethabi::ParamType::Uint(256)],
+        )
+    } else if event_sig == get_event_sig(ALGEBRA_POOL_CREATED_EVENT_SIG) {
+        (Dex::Algebra, vec![ethabi::ParamType::Address])
+    } else {
+        return None;
+    };
+    let decoded_data = ethabi::decode(&params, &log.data).ok()?;
+    let pool_address = match dex {
+        Dex::UniswapV3 => decoded_data[1].clone().into_address()?,
+        Dex::UniswapV2 | Dex::Solidly | Dex::Algebra => decoded_data[0].clone().into_address()?,
+        Dex::Curve | Dex::BalancerV2 | Dex::WooFiV2 => return None,
+    };
+    Some(PoolCreatedEvent {
+        dex,
+        pool_address,
+        token0: Address::from(log.topics[1]),
+        token1: Address::from(log.topics[2]),
+    })
+}
+
+/// Fetch a newly created pool from the node and add it to the store and Neo4j
+pub async fn add_pool_from_created_event<M: Middleware + 'static, S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    event: &PoolCreatedEvent,
+    batcher: &MulticallBatcher<M>,
+    graph: Option<&Graph>,
+    chain_label: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match event.dex {
+        Dex::Solidly => {
+            let pool = SolidlyPool::new_from_address(event.pool_address, batcher)
+                .await
+                .map_err(|e| e.to_string())?;
+            db::add_solidly_pool(store, chain_id, pool, graph, chain_label).await?;
+            return Ok(());
+        }
+        Dex::Algebra => {
+            let pool = AlgebraPool::new_from_address(event.pool_address, batcher).await?;
+            db::add_algebra_pool(store, chain_id, pool, graph, chain_label).await?;
+            return Ok(());
+        }
+        Dex::Curve => return Err("Curve pools are added with `import-curve`".into()),
+        Dex::BalancerV2 => return Err("Balancer pools are added with `import-balancer`".into()),
+        Dex::WooFiV2 => return Err("WooFi pools are added with `import-woofi`".into()),
+        Dex::UniswapV3 => Pool::UniswapV3(
+            UniswapV3Pool::new_from_address(event.pool_address, batcher.client())
+                .await
+                .map_err(|e| e.to_string())?,
+        ),
+        Dex::UniswapV2 => Pool::UniswapV2(
+            UniswapV2Pool::new_from_address(event.pool_address, batcher.client())
+                .await
+                .map_err(|e| e.to_string())?,
+        ),
+    };
+    db::add_pool(store, chain_id, pool, graph, chain_label).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::{encode, Token};
+
+    const USDC_STR: &str = "0xFF970A61A04b1cA14834A43f5dE4533eBDDB5CC8";
+    const WETH_STR: &str = "0x82aF49447D8a07e3bd95BD0d56f35241523fBab1";
+
+    fn topics(event_sig: &str) -> Vec<H256> {
+        let token0: Address = WETH_STR.parse().unwrap();
+        let token1: Address = USDC_STR.parse().unwrap();
+        vec![get_event_sig(event_sig), token0.into(), token1.into()]
+    }
+
+    #[test]
+    fn test_decode_pool_created_event() {
+        let pool_address = Address::random();
+        let log = Log {
+            topics: [
+                topics(UNIV3_POOL_CREATED_EVENT_SIG),
+                vec![H256::from_low_u64_be(500)],
+            ]
+            .concat(),
+            data: Bytes::from(encode(&[
+                Token::Int(U256::from(10)),
+                Token::Address(pool_address),
+            ])),
+            ..Default::default()
+        };
+        assert_eq!(
+            decode_pool_created_event(&log),
+            Some(PoolCreatedEvent {
+                dex: Dex::UniswapV3,
+                pool_address,
+                token0: WETH_STR.parse().unwrap(),
+                token1: USDC_STR.parse().unwrap(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode_pair_created_event() {
+        let pool_address = Address::random();
+        let log = Log {
+            topics: topics(UNIV2_PAIR_CREATED_EVENT_SIG),
+            data: Bytes::from(encode(&[
+                Token::Address(pool_address),
+                Token::Uint(U256::from(1)),
+            ])),
+            ..Default::default()
+        };
+        let event = decode_pool_created_event(&log).unwrap();
+        assert!(matches!(event.dex, Dex::UniswapV2));
+        assert_eq!(event.pool_address, pool_address);
+
+        let log = Log {
+            topics: topics(super::super::univ2::UNIV2_SYNC_EVENT_SIG),
+            ..Default::default()
+        };
+        assert!(decode_pool_created_event(&log).is_none());
+    }
+
+    #[test]
+    fn test_decode_solidly_pool_created_event() {
+        let pool_address = Address::random();
+        let log = Log {
+            topics: [
+                topics(SOLIDLY_POOL_CREATED_EVENT_SIG),
+                vec![H256::from_low_u64_be(1)],
+            ]
+            .concat(),
+            data: Bytes::from(encode(&[
+                Token::Address(pool_address),
+                Token::Uint(U256::from(1)),
+            ])),
+            ..Default::default()
+        };
+        let event = decode_pool_created_event(&log).unwrap();
+        assert_eq!(event.dex, Dex::Solidly);
+        assert_eq!(event.pool_address, pool_address);
+    }
+}
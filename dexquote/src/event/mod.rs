@@ -1,5 +1,12 @@
+pub mod algebra;
+pub mod balancer;
+pub mod curve;
+pub mod factory;
+mod pool_event;
 pub mod univ2;
 pub mod univ3;
 mod utils;
+pub mod woofi;
 
+pub use pool_event::{decode_pool_event, PoolEvent};
 pub use utils::get_event_sig;
@@ -0,0 +1,184 @@
+use ethers::{abi::ethabi, prelude::*};
+
+use super::algebra::{
+    self, AlgebraFeeEvent, AlgebraTickSpacingEvent, ALGEBRA_FEE_EVENT_SIG,
+    ALGEBRA_TICK_SPACING_EVENT_SIG, CAMELOT_FEE_EVENT_SIG,
+};
+use super::balancer::{
+    self, BalancerAmpUpdateEvent, BalancerPoolBalanceChangedEvent, BalancerSwapEvent,
+    BalancerSwapFeeEvent, BALANCER_AMP_UPDATE_STARTED_EVENT_SIG,
+    BALANCER_AMP_UPDATE_STOPPED_EVENT_SIG, BALANCER_POOL_BALANCE_CHANGED_EVENT_SIG,
+    BALANCER_SWAP_EVENT_SIG, BALANCER_SWAP_FEE_PERCENTAGE_CHANGED_EVENT_SIG,
+};
+use super::curve::{
+    self, CurveLiquidityEvent, CurveRampAEvent, CurveTokenExchangeEvent, CURVE_RAMP_A_EVENT_SIG,
+    CURVE_REMOVE_LIQUIDITY_ONE_EVENT_SIG, CURVE_REMOVE_LIQUIDITY_ONE_WITH_SUPPLY_EVENT_SIG,
+    CURVE_STOP_RAMP_A_EVENT_SIG, CURVE_TOKEN_EXCHANGE_EVENT_SIG,
+    CURVE_TOKEN_EXCHANGE_UNDERLYING_EVENT_SIG,
+};
+use super::get_event_sig;
+use super::univ2::{
+    self, UniV2FeeEvent, UniV2SwapEvent, UniV2SyncEvent, CAMELOT_FEE_PERCENT_UPDATED_EVENT_SIG,
+    UNIV2_SWAP_EVENT_SIG, UNIV2_SYNC_EVENT_SIG, VELODROME_SYNC_EVENT_SIG,
+};
+use super::univ3::{
+    self, UniV3CollectEvent, UniV3FlashEvent, UniV3InitializeEvent, UniV3MintEvent,
+    UniV3SetFeeProtocolEvent, UniV3SwapEvent, UNIV3_BURN_EVENT_SIG, UNIV3_COLLECT_EVENT_SIG,
+    UNIV3_FLASH_EVENT_SIG, UNIV3_INITIALIZE_EVENT_SIG, UNIV3_MINT_EVENT_SIG,
+    UNIV3_SET_FEE_PROTOCOL_EVENT_SIG, UNIV3_SWAP_EVENT_SIG,
+};
+use super::woofi::{
+    self, WooFiReserveEvent, WooFiSwapEvent, WOOFI_DEPOSIT_EVENT_SIG, WOOFI_MIGRATE_EVENT_SIG,
+    WOOFI_SWAP_EVENT_SIG, WOOFI_WITHDRAW_EVENT_SIG,
+};
+
+/// A decoded event emitted by a pool
+pub enum PoolEvent {
+    UniV3Swap(UniV3SwapEvent),
+    UniV3Mint(UniV3MintEvent),
+    UniV3Burn(UniV3MintEvent),
+    UniV3Initialize(UniV3InitializeEvent),
+    UniV3Collect(UniV3CollectEvent),
+    UniV3Flash(UniV3FlashEvent),
+    UniV3SetFeeProtocol(UniV3SetFeeProtocolEvent),
+    UniV2Sync(UniV2SyncEvent),
+    UniV2Swap(UniV2SwapEvent),
+    VelodromeSync(UniV2SyncEvent),
+    UniV2Fee(UniV2FeeEvent),
+    AlgebraFee(AlgebraFeeEvent),
+    AlgebraTickSpacing(AlgebraTickSpacingEvent),
+    CurveTokenExchange(CurveTokenExchangeEvent),
+    CurveTokenExchangeUnderlying(CurveTokenExchangeEvent),
+    CurveLiquidity(CurveLiquidityEvent),
+    CurveRemoveLiquidityOne,
+    CurveRampA(CurveRampAEvent),
+    BalancerSwap(BalancerSwapEvent),
+    BalancerPoolBalanceChanged(BalancerPoolBalanceChangedEvent),
+    BalancerSwapFee(BalancerSwapFeeEvent),
+    BalancerAmpUpdate(BalancerAmpUpdateEvent),
+    WooFiSwap(WooFiSwapEvent),
+    WooFiReserve(WooFiReserveEvent),
+    /// An event this crate does not handle, with its signature
+    Unknown(H256),
+}
+
+/// Decode a pool log by its event signature
+pub fn decode_pool_event(log: &Log) -> Result<PoolEvent, ethabi::Error> {
+    let event_sig = match log.topics.first() {
+        Some(event_sig) => *event_sig,
+        None => return Ok(PoolEvent::Unknown(H256::zero())),
+    };
+    let log_data = &log.data;
+    let event = if event_sig == get_event_sig(UNIV3_SWAP_EVENT_SIG) {
+        PoolEvent::UniV3Swap(univ3::decode_swap_event(log_data)?)
+    } else if event_sig == get_event_sig(UNIV2_SYNC_EVENT_SIG) {
+        PoolEvent::UniV2Sync(univ2::decode_sync_event(log_data)?)
+    } else if event_sig == get_event_sig(UNIV3_MINT_EVENT_SIG) {
+        PoolEvent::UniV3Mint(univ3::decode_mint_event(log_data)?)
+    } else if event_sig == get_event_sig(UNIV3_BURN_EVENT_SIG) {
+        PoolEvent::UniV3Burn(univ3::decode_burn_event(log_data)?)
+    } else if event_sig == get_event_sig(UNIV3_INITIALIZE_EVENT_SIG) {
+        PoolEvent::UniV3Initialize(univ3::decode_initialize_event(log_data)?)
+    } else if event_sig == get_event_sig(UNIV3_COLLECT_EVENT_SIG) {
+        PoolEvent::UniV3Collect(univ3::decode_collect_event(log_data)?)
+    } else if event_sig == get_event_sig(UNIV3_FLASH_EVENT_SIG) {
+        PoolEvent::UniV3Flash(univ3::decode_flash_event(log_data)?)
+    } else if event_sig == get_event_sig(UNIV3_SET_FEE_PROTOCOL_EVENT_SIG) {
+        PoolEvent::UniV3SetFeeProtocol(univ3::decode_set_fee_protocol_event(log_data)?)
+    } else if event_sig == get_event_sig(UNIV2_SWAP_EVENT_SIG) {
+        let (amount_in, amount_out, zero_for_one) = univ2::decode_swap_event(log_data)?;
+        PoolEvent::UniV2Swap(UniV2SwapEvent {
+            amount_in,
+            amount_out,
+            zero_for_one,
+        })
+    } else if event_sig == get_event_sig(VELODROME_SYNC_EVENT_SIG) {
+        PoolEvent::VelodromeSync(univ2::decode_velodrome_sync_event(log_data)?)
+    } else if event_sig == get_event_sig(CAMELOT_FEE_PERCENT_UPDATED_EVENT_SIG) {
+        PoolEvent::UniV2Fee(univ2::decode_fee_percent_updated_event(log_data)?)
+    } else if event_sig == get_event_sig(ALGEBRA_FEE_EVENT_SIG) {
+        PoolEvent::AlgebraFee(algebra::decode_fee_event(log_data)?)
+    } else if event_sig == get_event_sig(CAMELOT_FEE_EVENT_SIG) {
+        PoolEvent::AlgebraFee(algebra::decode_camelot_fee_event(log_data)?)
+    } else if event_sig == get_event_sig(ALGEBRA_TICK_SPACING_EVENT_SIG) {
+        PoolEvent::AlgebraTickSpacing(algebra::decode_tick_spacing_event(log_data)?)
+    } else if event_sig == get_event_sig(CURVE_TOKEN_EXCHANGE_EVENT_SIG) {
+        PoolEvent::CurveTokenExchange(curve::decode_token_exchange_event(log_data)?)
+    } else if event_sig == get_event_sig(CURVE_TOKEN_EXCHANGE_UNDERLYING_EVENT_SIG) {
+        PoolEvent::CurveTokenExchangeUnderlying(curve::decode_token_exchange_event(log_data)?)
+    } else if event_sig == get_event_sig(CURVE_REMOVE_LIQUIDITY_ONE_EVENT_SIG)
+        || event_sig == get_event_sig(CURVE_REMOVE_LIQUIDITY_ONE_WITH_SUPPLY_EVENT_SIG)
+    {
+        PoolEvent::CurveRemoveLiquidityOne
+    } else if event_sig == get_event_sig(CURVE_RAMP_A_EVENT_SIG) {
+        PoolEvent::CurveRampA(curve::decode_ramp_a_event(log_data)?)
+    } else if event_sig == get_event_sig(CURVE_STOP_RAMP_A_EVENT_SIG) {
+        PoolEvent::CurveRampA(curve::decode_stop_ramp_a_event(log_data)?)
+    } else if event_sig == get_event_sig(BALANCER_SWAP_EVENT_SIG) {
+        PoolEvent::BalancerSwap(balancer::decode_swap_event(log)?)
+    } else if event_sig == get_event_sig(BALANCER_POOL_BALANCE_CHANGED_EVENT_SIG) {
+        PoolEvent::BalancerPoolBalanceChanged(balancer::decode_pool_balance_changed_event(log)?)
+    } else if event_sig == get_event_sig(BALANCER_SWAP_FEE_PERCENTAGE_CHANGED_EVENT_SIG) {
+        PoolEvent::BalancerSwapFee(balancer::decode_swap_fee_event(log_data)?)
+    } else if event_sig == get_event_sig(BALANCER_AMP_UPDATE_STARTED_EVENT_SIG) {
+        PoolEvent::BalancerAmpUpdate(balancer::decode_amp_update_started_event(log_data)?)
+    } else if event_sig == get_event_sig(BALANCER_AMP_UPDATE_STOPPED_EVENT_SIG) {
+        PoolEvent::BalancerAmpUpdate(balancer::decode_amp_update_stopped_event(log_data)?)
+    } else if event_sig == get_event_sig(WOOFI_SWAP_EVENT_SIG) {
+        PoolEvent::WooFiSwap(woofi::decode_swap_event(log)?)
+    } else if event_sig == get_event_sig(WOOFI_DEPOSIT_EVENT_SIG) {
+        PoolEvent::WooFiReserve(woofi::decode_reserve_event(log, true)?)
+    } else if event_sig == get_event_sig(WOOFI_WITHDRAW_EVENT_SIG)
+        || event_sig == get_event_sig(WOOFI_MIGRATE_EVENT_SIG)
+    {
+        PoolEvent::WooFiReserve(woofi::decode_reserve_event(log, false)?)
+    } else if let Some((kind, n_coins)) = curve::find_liquidity_event(event_sig) {
+        PoolEvent::CurveLiquidity(curve::decode_liquidity_event(kind, n_coins, log_data)?)
+    } else {
+        PoolEvent::Unknown(event_sig)
+    };
+    Ok(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn log_with(event_sig: H256, log_data: &str) -> Log {
+        Log {
+            topics: vec![event_sig],
+            data: Bytes::from_str(log_data).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_decode_pool_event() {
+        let log = log_with(
+            get_event_sig(UNIV3_INITIALIZE_EVENT_SIG),
+            "0x00000000000000000000000000000000000000035fe33d63a8900a892110d312fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffc18",
+        );
+        match decode_pool_event(&log).unwrap() {
+            PoolEvent::UniV3Initialize(event) => assert_eq!(event.tick, -1000),
+            _ => panic!("expected an Initialize event"),
+        }
+
+        let log = log_with(
+            get_event_sig(UNIV2_SWAP_EVENT_SIG),
+            "0x00000000000000000000000000000000000000000000000000056aa8c74b77ee0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000017f084a07abdf3f6",
+        );
+        match decode_pool_event(&log).unwrap() {
+            PoolEvent::UniV2Swap(event) => assert!(event.zero_for_one),
+            _ => panic!("expected a UniV2 Swap event"),
+        }
+
+        let event_sig = get_event_sig("Transfer(address,address,uint256)");
+        let log = log_with(event_sig, "0x");
+        match decode_pool_event(&log).unwrap() {
+            PoolEvent::Unknown(unknown_sig) => assert_eq!(unknown_sig, event_sig),
+            _ => panic!("expected an unknown event"),
+        }
+    }
+}
@@ -1,16 +1,34 @@
-use crate::db::univ2::update_pool;
+use crate::db::{
+    univ2::{self, update_pool, UniV2Fee},
+    PoolStore,
+};
 use ethers::abi::ethabi;
 use ethers::prelude::*;
 use tracing::warn;
 
 pub const UNIV2_SYNC_EVENT_SIG: &str = "Sync(uint112,uint112)";
 pub const UNIV2_SWAP_EVENT_SIG: &str = "Swap(address,uint256,uint256,uint256,uint256,address)";
+// Solidly pools (Velodrome, Aerodrome) emit reserves as uint256
+pub const VELODROME_SYNC_EVENT_SIG: &str = "Sync(uint256,uint256)";
+// Camelot pairs charge a fee per input token, in the same unit as UniswapV2Pool::fee
+// ref. https://github.com/CamelotLabs/core/blob/master/contracts/CamelotPair.sol
+pub const CAMELOT_FEE_PERCENT_UPDATED_EVENT_SIG: &str = "FeePercentUpdated(uint16,uint16)";
 
 pub struct UniV2SyncEvent {
     pub reserve0: u128,
     pub reserve1: u128,
 }
 
+pub struct UniV2FeeEvent {
+    pub fee: UniV2Fee,
+}
+
+pub struct UniV2SwapEvent {
+    pub amount_in: U256,
+    pub amount_out: U256,
+    pub zero_for_one: bool,
+}
+
 /// Decode a swap event from a log data
 /// Returns (amount_in, amount_out, zero_for_one)
 pub fn decode_swap_event(log_data: &Bytes) -> Result<(U256, U256, bool), ethabi::Error> {
@@ -63,13 +81,25 @@ pub fn decode_velodrome_sync_event(log_data: &Bytes) -> Result<UniV2SyncEvent, e
     })
 }
 
-pub fn update_with_sync_event(
-    redis_url: &str,
+pub fn decode_fee_percent_updated_event(log_data: &Bytes) -> Result<UniV2FeeEvent, ethabi::Error> {
+    let decoded_data = ethabi::decode(
+        &[ethabi::ParamType::Uint(16), ethabi::ParamType::Uint(16)],
+        log_data,
+    )?;
+    let mut decoded = decoded_data.into_iter();
+    let token0_fee = decoded.next().unwrap().into_uint().unwrap().as_u32();
+    let token1_fee = decoded.next().unwrap().into_uint().unwrap().as_u32();
+    Ok(UniV2FeeEvent {
+        fee: UniV2Fee::new(token0_fee, Some(token1_fee)),
+    })
+}
+
+pub fn update_with_sync_event<S: PoolStore>(
+    store: &S,
     chain_id: u64,
     pool_address: Address,
     log_data: &Bytes,
 ) {
-    let redis_client = redis::Client::open(redis_url).unwrap();
     let univ2_event = match decode_sync_event(log_data) {
         Ok(event) => event,
         Err(e) => {
@@ -78,7 +108,7 @@ pub fn update_with_sync_event(
         }
     };
     match update_pool(
-        &redis_client,
+        store,
         chain_id,
         pool_address,
         univ2_event.reserve0,
@@ -91,13 +121,12 @@ pub fn update_with_sync_event(
     };
 }
 
-pub fn update_with_velodrome_sync_event(
-    redis_url: &str,
+pub fn update_with_velodrome_sync_event<S: PoolStore>(
+    store: &S,
     chain_id: u64,
     pool_address: Address,
     log_data: &Bytes,
 ) {
-    let redis_client = redis::Client::open(redis_url).unwrap();
     let univ2_event = match decode_velodrome_sync_event(log_data) {
         Ok(event) => event,
         Err(e) => {
@@ -106,7 +135,7 @@ pub fn update_with_velodrome_sync_event(
         }
     };
     match update_pool(
-        &redis_client,
+        store,
         chain_id,
         pool_address,
         univ2_event.reserve0,
@@ -119,6 +148,20 @@ pub fn update_with_velodrome_sync_event(
     };
 }
 
+pub fn update_with_fee_event<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    event: &UniV2FeeEvent,
+) {
+    match univ2::update_fee(store, chain_id, pool_address, event.fee) {
+        Ok(_) => {}
+        Err(e) => {
+            warn!("failed to update fee: {:?}", e);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -152,4 +195,12 @@ mod tests {
         assert_eq!(sync_event.reserve0, 443008627484984172148922);
         assert_eq!(sync_event.reserve1, 474835554095);
     }
+
+    #[test]
+    fn test_decode_fee_percent_updated_event() {
+        let log_data = Bytes::from_str("0x000000000000000000000000000000000000000000000000000000000000012c00000000000000000000000000000000000000000000000000000000000000fa").unwrap();
+        let event = decode_fee_percent_updated_event(&log_data).unwrap();
+        assert_eq!(event.fee.get(true), 300);
+        assert_eq!(event.fee.get(false), 250);
+    }
 }
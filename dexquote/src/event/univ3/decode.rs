@@ -1,4 +1,7 @@
-use super::{UniV3MintEvent, UniV3SwapEvent};
+use super::{
+    UniV3CollectEvent, UniV3FlashEvent, UniV3InitializeEvent, UniV3MintEvent,
+    UniV3SetFeeProtocolEvent, UniV3SwapEvent,
+};
 use ethers::{
     abi::ethabi,
     types::{Bytes, I256},
@@ -72,6 +75,88 @@ pub fn decode_burn_event(log_data: &Bytes) -> Result<UniV3MintEvent, ethabi::Err
     })
 }
 
+pub fn decode_initialize_event(log_data: &Bytes) -> Result<UniV3InitializeEvent, ethabi::Error> {
+    let decoded_data = ethabi::decode(
+        &[ethabi::ParamType::Uint(160), ethabi::ParamType::Int(24)],
+        log_data,
+    )?;
+    let mut decoded = decoded_data.into_iter();
+    let sqrt_price_x96 = decoded.next().unwrap().into_uint().unwrap();
+    let tick = I256::from_raw(decoded.next().unwrap().into_int().unwrap()).as_i32();
+    Ok(UniV3InitializeEvent {
+        sqrt_price_x96,
+        tick,
+    })
+}
+
+pub fn decode_collect_event(log_data: &Bytes) -> Result<UniV3CollectEvent, ethabi::Error> {
+    let decoded_data = ethabi::decode(
+        &[
+            ethabi::ParamType::Address,
+            ethabi::ParamType::Uint(128),
+            ethabi::ParamType::Uint(128),
+        ],
+        log_data,
+    )?;
+    let mut decoded = decoded_data.into_iter();
+    let recipient = decoded.next().unwrap().into_address().unwrap();
+    let amount0 = decoded.next().unwrap().into_uint().unwrap().as_u128();
+    let amount1 = decoded.next().unwrap().into_uint().unwrap().as_u128();
+    Ok(UniV3CollectEvent {
+        recipient,
+        amount0,
+        amount1,
+    })
+}
+
+pub fn decode_flash_event(log_data: &Bytes) -> Result<UniV3FlashEvent, ethabi::Error> {
+    let decoded_data = ethabi::decode(
+        &[
+            ethabi::ParamType::Uint(256),
+            ethabi::ParamType::Uint(256),
+            ethabi::ParamType::Uint(256),
+            ethabi::ParamType::Uint(256),
+        ],
+        log_data,
+    )?;
+    let mut decoded = decoded_data.into_iter();
+    let amount0 = decoded.next().unwrap().into_uint().unwrap();
+    let amount1 = decoded.next().unwrap().into_uint().unwrap();
+    let paid0 = decoded.next().unwrap().into_uint().unwrap();
+    let paid1 = decoded.next().unwrap().into_uint().unwrap();
+    Ok(UniV3FlashEvent {
+        amount0,
+        amount1,
+        paid0,
+        paid1,
+    })
+}
+
+pub fn decode_set_fee_protocol_event(
+    log_data: &Bytes,
+) -> Result<UniV3SetFeeProtocolEvent, ethabi::Error> {
+    let decoded_data = ethabi::decode(
+        &[
+            ethabi::ParamType::Uint(8),
+            ethabi::ParamType::Uint(8),
+            ethabi::ParamType::Uint(8),
+            ethabi::ParamType::Uint(8),
+        ],
+        log_data,
+    )?;
+    let mut decoded = decoded_data.into_iter();
+    let fee_protocol0_old = decoded.next().unwrap().into_uint().unwrap().as_u32() as u8;
+    let fee_protocol1_old = decoded.next().unwrap().into_uint().unwrap().as_u32() as u8;
+    let fee_protocol0_new = decoded.next().unwrap().into_uint().unwrap().as_u32() as u8;
+    let fee_protocol1_new = decoded.next().unwrap().into_uint().unwrap().as_u32() as u8;
+    Ok(UniV3SetFeeProtocolEvent {
+        fee_protocol0_old,
+        fee_protocol1_old,
+        fee_protocol0_new,
+        fee_protocol1_new,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -141,4 +226,38 @@ mod tests {
         );
         assert_eq!(event_data.amount1, U256::from_dec_str("0").unwrap());
     }
+
+    #[test]
+    fn test_decode_initialize_event() {
+        let log_data = Bytes::from_str("0x00000000000000000000000000000000000000035fe33d63a8900a892110d312fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffc18").unwrap();
+        let event_data = decode_initialize_event(&log_data).unwrap();
+        assert_eq!(
+            event_data.sqrt_price_x96,
+            U256::from_dec_str("267360279539602653256847774482").unwrap()
+        );
+        assert_eq!(event_data.tick, -1000);
+    }
+
+    #[test]
+    fn test_decode_collect_event() {
+        let log_data = Bytes::from_str("0x000000000000000000000000c36442b4a4522e871399cd717abdd847ab11fe8800000000000000000000000000000000000000000000000000000000000003e800000000000000000000000000000000000000000000000000000000000007d0").unwrap();
+        let event_data = decode_collect_event(&log_data).unwrap();
+        assert_eq!(
+            event_data.recipient,
+            "0xc36442b4a4522e871399cd717abdd847ab11fe88"
+                .parse::<ethers::types::Address>()
+                .unwrap()
+        );
+        assert_eq!(event_data.amount0, 1000);
+        assert_eq!(event_data.amount1, 2000);
+    }
+
+    #[test]
+    fn test_decode_set_fee_protocol_event() {
+        let log_data = Bytes::from_str("0x0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000040000000000000000000000000000000000000000000000000000000000000005").unwrap();
+        let event_data = decode_set_fee_protocol_event(&log_data).unwrap();
+        assert_eq!(event_data.fee_protocol0_old, 0);
+        assert_eq!(event_data.fee_protocol1_old, 0);
+        assert_eq!(event_data.fee_protocol(), 4 + (5 << 4));
+    }
 }
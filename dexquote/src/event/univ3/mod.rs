@@ -1,6 +1,7 @@
-use std::sync::Arc;
+use std::str::FromStr;
 
-use crate::db::{get_pool, univ3};
+use crate::db::{multicall::MulticallBatcher, univ3, PoolStore};
+use crate::dex::Dex;
 use cfmms::pool::Pool;
 use ethers::abi::AbiDecode;
 use ethers::prelude::*;
@@ -25,18 +26,53 @@ pub struct UniV3MintEvent {
     pub amount1: U256,
 }
 
+pub struct UniV3InitializeEvent {
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+}
+
+pub struct UniV3CollectEvent {
+    pub recipient: Address,
+    pub amount0: u128,
+    pub amount1: u128,
+}
+
+pub struct UniV3FlashEvent {
+    pub amount0: U256,
+    pub amount1: U256,
+    pub paid0: U256,
+    pub paid1: U256,
+}
+
+pub struct UniV3SetFeeProtocolEvent {
+    pub fee_protocol0_old: u8,
+    pub fee_protocol1_old: u8,
+    pub fee_protocol0_new: u8,
+    pub fee_protocol1_new: u8,
+}
+
+impl UniV3SetFeeProtocolEvent {
+    /// The new fee protocol packed the same way as slot0.feeProtocol
+    pub fn fee_protocol(&self) -> u8 {
+        self.fee_protocol0_new + (self.fee_protocol1_new << 4)
+    }
+}
+
 // ref. https://github.com/Uniswap/v3-core/blob/main/contracts/interfaces/pool/IUniswapV3PoolEvents.sol
 pub const UNIV3_SWAP_EVENT_SIG: &str = "Swap(address,address,int256,int256,uint160,uint128,int24)";
 pub const UNIV3_MINT_EVENT_SIG: &str = "Mint(address,address,int24,int24,uint128,uint256,uint256)";
 pub const UNIV3_BURN_EVENT_SIG: &str = "Burn(address,int24,int24,uint128,uint256,uint256)";
+pub const UNIV3_INITIALIZE_EVENT_SIG: &str = "Initialize(uint160,int24)";
+pub const UNIV3_COLLECT_EVENT_SIG: &str = "Collect(address,address,int24,int24,uint128,uint128)";
+pub const UNIV3_FLASH_EVENT_SIG: &str = "Flash(address,address,uint256,uint256,uint256,uint256)";
+pub const UNIV3_SET_FEE_PROTOCOL_EVENT_SIG: &str = "SetFeeProtocol(uint8,uint8,uint8,uint8)";
 
-pub fn update_with_swap_event(
-    redis_url: &str,
+pub fn update_with_swap_event<S: PoolStore>(
+    store: &S,
     chain_id: u64,
     pool_address: Address,
     log_data: &Bytes,
 ) {
-    let redis_client = redis::Client::open(redis_url).unwrap();
     let univ3_event = match decode_swap_event(log_data) {
         Ok(event) => event,
         Err(e) => {
@@ -46,7 +82,7 @@ pub fn update_with_swap_event(
     };
     // UniswapV3
     match univ3::update_pool(
-        &redis_client,
+        store,
         chain_id,
         pool_address,
         univ3_event.liquidity,
@@ -60,6 +96,41 @@ pub fn update_with_swap_event(
     }
 }
 
+pub fn update_with_initialize_event<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    event: &UniV3InitializeEvent,
+) {
+    // pools imported from the subgraph have no price until they are initialized
+    match univ3::update_slot0(
+        store,
+        chain_id,
+        pool_address,
+        event.sqrt_price_x96,
+        event.tick,
+    ) {
+        Ok(_) => {}
+        Err(e) => {
+            warn!("failed to update slot0: {:?}", e);
+        }
+    }
+}
+
+pub fn update_with_set_fee_protocol_event<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    event: &UniV3SetFeeProtocolEvent,
+) {
+    match univ3::update_fee_protocol(store, chain_id, pool_address, event.fee_protocol()) {
+        Ok(_) => {}
+        Err(e) => {
+            warn!("failed to update fee protocol: {:?}", e);
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 struct LiquidityUpdateParams {
     pub pool_address: Address,
@@ -68,15 +139,14 @@ struct LiquidityUpdateParams {
     pub liquidity_delta: i128,
 }
 
-pub async fn update_with_liquidity_event<M: Middleware + 'static>(
-    redis_url: &str,
+pub async fn update_with_liquidity_event<M: Middleware + 'static, S: PoolStore>(
+    store: &S,
     chain_id: u64,
     pool_address: Address,
     log: &Log,
     is_mint: bool,
-    middleware: Arc<M>,
+    batcher: &MulticallBatcher<M>,
 ) {
-    let redis_client = redis::Client::open(redis_url).unwrap();
     let tick_lower = i32::decode(log.topics[2]).unwrap();
     let tick_upper = i32::decode(log.topics[3]).unwrap();
     let event_data = match is_mint {
@@ -101,15 +171,15 @@ pub async fn update_with_liquidity_event<M: Middleware + 'static>(
         tick_upper,
         liquidity_delta,
     };
-    modify_position(&redis_client, chain_id, params, middleware).await;
+    modify_position(store, chain_id, params, batcher).await;
 }
 
 // UniswapV3
-async fn modify_position<M: Middleware + 'static>(
-    redis_client: &redis::Client,
+async fn modify_position<M: Middleware + 'static, S: PoolStore>(
+    store: &S,
     chain_id: u64,
     params: LiquidityUpdateParams,
-    middleware: Arc<M>,
+    batcher: &MulticallBatcher<M>,
 ) {
     match check_ticks(params.tick_lower, params.tick_upper) {
         Ok(_) => {}
@@ -119,32 +189,37 @@ async fn modify_position<M: Middleware + 'static>(
         }
     }
 
-    let pool_info = match get_pool(redis_client, chain_id, params.pool_address) {
-        Ok(pool_info) => match pool_info {
-            Some(pool_info) => match pool_info {
-                Pool::UniswapV3(pool) => pool,
-                _ => {
-                    warn!("pool is not UniswapV3");
-                    return;
-                }
-            },
-            None => {
-                warn!("pool not found");
-                return;
-            }
-        },
+    let target_data = match store.get_pool_hashmap(chain_id, params.pool_address) {
+        Ok(target_data) => target_data,
         Err(e) => {
             warn!("{:?}", e);
             return;
         }
     };
+    // Algebra pools read their tick bitmap from a differently named function
+    let dex = match target_data.get("dex").map(|dex| Dex::from_str(dex)) {
+        Some(Ok(dex)) => dex,
+        _ => Dex::UniswapV3,
+    };
+    let pool_info = match univ3::hashmap_to_univ3(params.pool_address, target_data) {
+        Some(Pool::UniswapV3(pool)) => pool,
+        Some(_) => {
+            warn!("pool is not UniswapV3");
+            return;
+        }
+        None => {
+            warn!("pool not found");
+            return;
+        }
+    };
 
     match update_position(
-        redis_client,
+        store,
         chain_id,
         params,
         pool_info.tick_spacing,
-        middleware,
+        dex,
+        batcher,
     )
     .await
     {
@@ -163,7 +238,7 @@ async fn modify_position<M: Middleware + 'static>(
             match liquidity_math::add_delta(pool_info.liquidity, params.liquidity_delta) {
                 Ok(new_liquiidty) => {
                     match univ3::update_liquidity(
-                        redis_client,
+                        store,
                         chain_id,
                         params.pool_address,
                         new_liquiidty,
@@ -201,12 +276,13 @@ fn check_ticks(tick_lower: i32, tick_upper: i32) -> Result<(), Box<dyn std::erro
     Ok(())
 }
 
-async fn update_position<M: Middleware + 'static>(
-    redis_client: &redis::Client,
+async fn update_position<M: Middleware + 'static, S: PoolStore>(
+    store: &S,
     chain_id: u64,
     params: LiquidityUpdateParams,
     tick_spacing: i32,
-    middleware: Arc<M>,
+    dex: Dex,
+    batcher: &MulticallBatcher<M>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // _updatePosition
     let mut flipped_lower = false;
@@ -214,48 +290,50 @@ async fn update_position<M: Middleware + 'static>(
     if params.liquidity_delta != 0 {
         // tick.lower ticks.update
         flipped_lower = ticks::update(
-            redis_client,
+            store,
             chain_id,
             params.pool_address,
             params.tick_lower,
             params.liquidity_delta,
             false,
-            middleware.clone(),
+            batcher,
         )
         .await?;
 
         // tick.upper ticks.update
         flipped_upper = ticks::update(
-            redis_client,
+            store,
             chain_id,
             params.pool_address,
             params.tick_upper,
             params.liquidity_delta,
             true,
-            middleware.clone(),
+            batcher,
         )
         .await?;
 
         // update bitmap
         if flipped_lower {
             tick_bitmap::flip_tick(
-                redis_client,
+                store,
                 chain_id,
                 params.pool_address,
                 params.tick_lower,
                 tick_spacing,
-                middleware.clone(),
+                dex,
+                batcher,
             )
             .await?;
         }
         if flipped_upper {
             tick_bitmap::flip_tick(
-                redis_client,
+                store,
                 chain_id,
                 params.pool_address,
                 params.tick_upper,
                 tick_spacing,
-                middleware.clone(),
+                dex,
+                batcher,
             )
             .await?;
         }
@@ -264,20 +342,10 @@ async fn update_position<M: Middleware + 'static>(
     // clear any tick data that is no longer needed
     if params.liquidity_delta < 0 {
         if flipped_lower {
-            ticks::clear(
-                redis_client,
-                chain_id,
-                params.pool_address,
-                params.tick_lower,
-            );
+            ticks::clear(store, chain_id, params.pool_address, params.tick_lower);
         }
         if flipped_upper {
-            ticks::clear(
-                redis_client,
-                chain_id,
-                params.pool_address,
-                params.tick_upper,
-            );
+            ticks::clear(store, chain_id, params.pool_address, params.tick_upper);
         }
     }
     Ok(())
@@ -1,34 +1,31 @@
-use std::sync::Arc;
-
 use crate::{
-    db::univ3::{delete_ticks, get_ticks, get_ticks_from_provider, update_ticks},
+    db::{multicall::MulticallBatcher, univ3::get_ticks_from_provider, PoolStore},
     event::utils::before_add_delta,
 };
 use ethers::prelude::*;
 use tracing::warn;
 use uniswap_v3_math::liquidity_math;
 
-pub async fn update<M: Middleware + 'static>(
-    redis_client: &redis::Client,
+pub async fn update<M: Middleware + 'static, S: PoolStore>(
+    store: &S,
     chain_id: u64,
     pool_address: Address,
     tick: i32,
     liquidity_delta: i128,
     upper: bool,
-    middleware: Arc<M>,
+    batcher: &MulticallBatcher<M>,
 ) -> Result<bool, Box<dyn std::error::Error>> {
     let liquidity_gross_after;
     let new_liquidity_net;
     let flipped;
 
-    let ticks_on_redis = match get_ticks(redis_client, chain_id, pool_address, tick) {
+    let ticks_on_store = match store.get_ticks(chain_id, pool_address, tick) {
         Ok(ticks) => ticks,
         Err(e) => {
             return Err(Box::new(e));
         }
     };
-    if ticks_on_redis.is_some() {
-        let (liquidity_gross_before, liquidity_net) = ticks_on_redis.unwrap();
+    if let Some((liquidity_gross_before, liquidity_net)) = ticks_on_store {
         match liquidity_math::add_delta(liquidity_gross_before, liquidity_delta) {
             Ok(liquidity_gross) => {
                 // require(liquidityGrossAfter <= maxLiquidity, 'LO');
@@ -48,7 +45,7 @@ pub async fn update<M: Middleware + 'static>(
                         pool_address,
                         tick,
                         liquidity_delta,
-                        middleware,
+                        batcher,
                     )
                     .await?;
             }
@@ -59,13 +56,12 @@ pub async fn update<M: Middleware + 'static>(
                 pool_address,
                 tick,
                 liquidity_delta,
-                middleware,
+                batcher,
             )
             .await?;
     }
 
-    update_ticks(
-        redis_client,
+    store.update_ticks(
         chain_id,
         pool_address,
         tick,
@@ -79,10 +75,10 @@ async fn get_liquidity_net_gross_flipped_from_provider<M: Middleware + 'static>(
     pool_address: Address,
     tick: i32,
     liquidity_delta: i128,
-    middleware: Arc<M>,
+    batcher: &MulticallBatcher<M>,
 ) -> Result<(u128, i128, bool), Box<dyn std::error::Error>> {
     let (liquidity_gross_after, new_liquidity_net) =
-        get_ticks_from_provider(pool_address, tick, middleware).await?;
+        get_ticks_from_provider(pool_address, tick, batcher).await?;
     let liquidity_gross_before = before_add_delta(liquidity_gross_after, liquidity_delta)?;
 
     let flipped = get_flipped(liquidity_gross_before, liquidity_gross_after);
@@ -93,8 +89,8 @@ fn get_flipped(liquidity_gross_before: u128, liquidity_gross_after: u128) -> boo
     (liquidity_gross_after == 0) != (liquidity_gross_before == 0)
 }
 
-pub fn clear(redis_client: &redis::Client, chain_id: u64, pool_address: Address, tick: i32) {
-    match delete_ticks(redis_client, chain_id, pool_address, tick) {
+pub fn clear<S: PoolStore>(store: &S, chain_id: u64, pool_address: Address, tick: i32) {
+    match store.delete_ticks(chain_id, pool_address, tick) {
         Ok(_) => {}
         Err(e) => {
             warn!("{:?}", e);
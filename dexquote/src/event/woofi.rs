@@ -0,0 +1,268 @@
+use crate::db::{multicall::MulticallBatcher, woofi, PoolStore};
+use crate::price::woofi::try_query;
+use crate::types::DexQuoteResult;
+use ethers::abi::ethabi;
+use ethers::prelude::*;
+use tracing::warn;
+
+// ref. contracts/src/woofi/IWooPPV2.sol
+pub const WOOFI_SWAP_EVENT_SIG: &str =
+    "WooSwap(address,address,uint256,uint256,address,address,address,uint256,uint256)";
+pub const WOOFI_DEPOSIT_EVENT_SIG: &str = "Deposit(address,address,uint256)";
+pub const WOOFI_WITHDRAW_EVENT_SIG: &str = "Withdraw(address,address,uint256)";
+pub const WOOFI_MIGRATE_EVENT_SIG: &str = "Migrate(address,address,uint256)";
+
+pub struct WooFiSwapEvent {
+    pub from_token: Address,
+    pub to_token: Address,
+    pub from_amount: U256,
+    pub to_amount: U256,
+    pub swap_volume: U256,
+    pub swap_fee: U256,
+}
+
+/// A deposit into or a withdrawal or migration out of the reserve of a token
+pub struct WooFiReserveEvent {
+    pub token: Address,
+    pub amount: U256,
+    pub is_deposit: bool,
+}
+
+pub fn decode_swap_event(log: &Log) -> Result<WooFiSwapEvent, ethabi::Error> {
+    if log.topics.len() < 3 {
+        return Err(ethabi::Error::InvalidData);
+    }
+    let decoded_data = ethabi::decode(
+        &[
+            ethabi::ParamType::Uint(256),
+            ethabi::ParamType::Uint(256),
+            ethabi::ParamType::Address,
+            ethabi::ParamType::Address,
+            ethabi::ParamType::Uint(256),
+            ethabi::ParamType::Uint(256),
+        ],
+        &log.data,
+    )?;
+    let mut decoded = decoded_data.into_iter();
+    let from_amount = decoded.next().unwrap().into_uint().unwrap();
+    let to_amount = decoded.next().unwrap().into_uint().unwrap();
+    // from and rebateTo
+    let mut decoded = decoded.skip(2);
+    let swap_volume = decoded.next().unwrap().into_uint().unwrap();
+    let swap_fee = decoded.next().unwrap().into_uint().unwrap();
+    Ok(WooFiSwapEvent {
+        from_token: Address::from(log.topics[1]),
+        to_token: Address::from(log.topics[2]),
+        from_amount,
+        to_amount,
+        swap_volume,
+        swap_fee,
+    })
+}
+
+pub fn decode_reserve_event(
+    log: &Log,
+    is_deposit: bool,
+) -> Result<WooFiReserveEvent, ethabi::Error> {
+    if log.topics.len() < 2 {
+        return Err(ethabi::Error::InvalidData);
+    }
+    let decoded_data = ethabi::decode(&[ethabi::ParamType::Uint(256)], &log.data)?;
+    Ok(WooFiReserveEvent {
+        token: Address::from(log.topics[1]),
+        amount: decoded_data[0].clone().into_uint().unwrap(),
+        is_deposit,
+    })
+}
+
+fn apply_swap<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    event: &WooFiSwapEvent,
+) -> DexQuoteResult<()> {
+    let mut pool = match woofi::get_pool(store, chain_id, pool_address)? {
+        Some(pool) => pool,
+        None => return Ok(()),
+    };
+    // the pool posts the prices moved by the swap to the oracle
+    let quote = try_query(&pool, event.from_token, event.to_token, event.from_amount).ok();
+    let quote_token = pool.quote_token;
+    for token in pool.tokens.iter_mut() {
+        if token.address == event.from_token {
+            token.reserve += event.from_amount;
+            if let Some(new_price) = quote.and_then(|quote| quote.new_from_price) {
+                token.price = new_price;
+            }
+        } else if token.address == event.to_token {
+            token.reserve = token.reserve.saturating_sub(event.to_amount);
+            if let Some(new_price) = quote.and_then(|quote| quote.new_to_price) {
+                token.price = new_price;
+            }
+        }
+    }
+    // the fee is kept aside from the quote reserve
+    pool.quote_reserve = if event.from_token == quote_token {
+        pool.quote_reserve + event.from_amount - event.swap_fee
+    } else if event.to_token == quote_token {
+        pool.quote_reserve
+            .saturating_sub(event.to_amount + event.swap_fee)
+    } else {
+        pool.quote_reserve.saturating_sub(event.swap_fee)
+    };
+    woofi::update_pool(store, chain_id, &pool)
+}
+
+pub fn update_with_swap_event<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    event: &WooFiSwapEvent,
+) {
+    match apply_swap(store, chain_id, pool_address, event) {
+        Ok(_) => {}
+        Err(e) => {
+            warn!("failed to update reserves: {:?}", e);
+        }
+    }
+}
+
+fn apply_reserve<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    event: &WooFiReserveEvent,
+) -> DexQuoteResult<()> {
+    let mut pool = match woofi::get_pool(store, chain_id, pool_address)? {
+        Some(pool) => pool,
+        None => return Ok(()),
+    };
+    let update = |reserve: U256| {
+        if event.is_deposit {
+            reserve + event.amount
+        } else {
+            reserve.saturating_sub(event.amount)
+        }
+    };
+    if event.token == pool.quote_token {
+        pool.quote_reserve = update(pool.quote_reserve);
+    }
+    for token in pool.tokens.iter_mut() {
+        if token.address == event.token {
+            token.reserve = update(token.reserve);
+        }
+    }
+    woofi::update_pool(store, chain_id, &pool)
+}
+
+pub fn update_with_reserve_event<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    event: &WooFiReserveEvent,
+) {
+    match apply_reserve(store, chain_id, pool_address, event) {
+        Ok(_) => {}
+        Err(e) => {
+            warn!("failed to update reserves: {:?}", e);
+        }
+    }
+}
+
+/// Read the oracle states of the tokens of a pool from the node.
+/// Oracle posts are applied this way whatever the oracle logs.
+pub async fn update_with_oracle_log<M: Middleware + 'static, S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    batcher: &MulticallBatcher<M>,
+) {
+    let mut pool = match woofi::get_pool(store, chain_id, pool_address) {
+        Ok(Some(pool)) => pool,
+        Ok(None) => return,
+        Err(e) => {
+            warn!("failed to get pool: {:?}", e);
+            return;
+        }
+    };
+    if let Err(e) = woofi::get_oracle_states(pool.oracle, &mut pool.tokens, batcher).await {
+        warn!("failed to get oracle states of {:?}: {:?}", pool_address, e);
+        return;
+    }
+    if let Err(e) = woofi::update_pool(store, chain_id, &pool) {
+        warn!("failed to update oracle states: {:?}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::woofi::{WooFiPool, WooFiToken};
+    use crate::db::MemoryStore;
+
+    #[test]
+    fn test_update_with_swap_event() {
+        let store = MemoryStore::new();
+        let chain_id = 42161;
+        let pool_address = Address::random();
+        let (usdc, weth) = (Address::random(), Address::random());
+        woofi::add_pool(
+            &store,
+            chain_id,
+            &WooFiPool {
+                address: pool_address,
+                oracle: Address::random(),
+                quote_token: usdc,
+                quote_decimals: 6,
+                quote_reserve: U256::from(1_000_000u64) * U256::exp10(6),
+                tokens: vec![WooFiToken {
+                    address: weth,
+                    decimals: 18,
+                    reserve: U256::from(500) * U256::exp10(18),
+                    fee_rate: 25,
+                    price: U256::from(2000) * U256::exp10(8),
+                    price_decimals: 8,
+                    spread: 1_000_000_000_000_000,
+                    coeff: 1_000_000_000,
+                    feasible: true,
+                }],
+            },
+        )
+        .unwrap();
+
+        let log = Log {
+            address: pool_address,
+            topics: vec![
+                crate::event::get_event_sig(WOOFI_SWAP_EVENT_SIG),
+                H256::from(weth),
+                H256::from(usdc),
+                H256::from(Address::random()),
+            ],
+            data: ethabi::encode(&[
+                ethabi::Token::Uint(U256::exp10(18)),
+                ethabi::Token::Uint(U256::from(1997496501u64)),
+                ethabi::Token::Address(Address::random()),
+                ethabi::Token::Address(Address::zero()),
+                ethabi::Token::Uint(U256::from(1997996000u64)),
+                ethabi::Token::Uint(U256::from(499499)),
+            ])
+            .into(),
+            ..Default::default()
+        };
+        update_with_swap_event(
+            &store,
+            chain_id,
+            pool_address,
+            &decode_swap_event(&log).unwrap(),
+        );
+        let pool = woofi::get_pool(&store, chain_id, pool_address)
+            .unwrap()
+            .unwrap();
+        assert_eq!(pool.tokens[0].reserve, U256::from(501) * U256::exp10(18));
+        assert_eq!(pool.tokens[0].price, U256::from(199999200000u64));
+        assert_eq!(
+            pool.quote_reserve,
+            U256::from(1_000_000u64) * U256::exp10(6) - U256::from(1997996000u64)
+        );
+    }
+}
@@ -3,6 +3,9 @@ use neo4rs::{query, Graph, Node, Relation};
 
 use crate::utils::address_str;
 
+mod routing;
+pub use routing::*;
+
 pub async fn check_if_pool_already_exists(
     graph: &Graph,
     token_label: &str,
@@ -28,9 +31,10 @@ pub async fn check_if_pool_already_exists(
 pub async fn add_token_pair_to_neo4j(graph: &Graph, token_label: &str, tokens: [Address; 2]) {
     let (token0_str, token1_str) = (address_str(tokens[0]), address_str(tokens[1]));
 
-    // Since token nodes have unique constraints, you can't create the same node twice
+    // existing token nodes are matched instead of relying on a uniqueness constraint
     let query_string = format!(
-        "CREATE (token0:{token_label} {{address: $token_address0}}), (token1:{token_label} {{address: $token_address1}})
+        "MERGE (token0:{token_label} {{address: $token_address0}})
+        MERGE (token1:{token_label} {{address: $token_address1}})
         RETURN token0, token1",
     );
     let mut result = graph
@@ -72,14 +76,11 @@ pub async fn add_pool_to_neo4j(
     token0: Address,
     token1: Address,
 ) {
-    if check_if_pool_already_exists(graph, token_label, pool_address).await {
-        return;
-    }
-
+    // multi-token pools have an edge per token pair, so an edge is matched by its tokens too
     let query_string = format!(
         "MATCH (a:{token_label}), (b:{token_label})
-		WHERE a.address = $token0_address AND b.address = $token1_address 
-		CREATE (a)-[r:Pool {{ address: $address }}]->(b) 
+		WHERE a.address = $token0_address AND b.address = $token1_address
+		MERGE (a)-[r:Pool {{ address: $address }}]->(b)
 		RETURN r",
     );
     let mut result = graph
@@ -98,3 +99,34 @@ pub async fn add_pool_to_neo4j(
         assert_eq!(address, address_str(pool_address));
     }
 }
+
+/// Write a pool to Neo4j with an edge for each pair of its tokens.
+/// Neo4j is only an export of the pools, nothing is written without a graph.
+pub async fn export_pool_to_neo4j(
+    graph: Option<&Graph>,
+    token_label: &str,
+    pool_address: Address,
+    tokens: &[Address],
+) {
+    let graph = match graph {
+        Some(graph) => graph,
+        None => return,
+    };
+    for (i, token0) in tokens.iter().enumerate() {
+        for token1 in &tokens[i + 1..] {
+            add_token_pair_to_neo4j(graph, token_label, [*token0, *token1]).await;
+            add_pool_to_neo4j(graph, token_label, pool_address, *token0, *token1).await;
+        }
+    }
+}
+
+/// Write every pool of a routing graph to Neo4j
+pub async fn export_routing_graph_to_neo4j(
+    routing_graph: &RoutingGraph,
+    graph: &Graph,
+    token_label: &str,
+) {
+    for (pool_address, tokens) in routing_graph.pools() {
+        export_pool_to_neo4j(Some(graph), token_label, *pool_address, tokens).await;
+    }
+}
@@ -0,0 +1,1017 @@
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
+    str::FromStr,
+};
+
+use cfmms::pool::Pool;
+use ethers::{
+    abi::AbiDecode,
+    types::{Address, U256},
+};
+
+use crate::{
+    db::{balancer, curve, solidly, univ2, univ3, woofi, PoolStore},
+    dex::Dex,
+    path::PoolInfo,
+    types::DexQuoteResult,
+};
+
+/// A directed edge of the routing graph, a swap from `token_in` to `token_out` in a pool
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PoolEdge {
+    pub pool: Address,
+    pub dex: Dex,
+    pub token_in: Address,
+    pub token_out: Address,
+    /// fee of the swap in hundredths of a bip (1e-6), whatever the dex unit is
+    pub fee: u32,
+    /// in-range liquidity of concentrated pools and sqrt(reserve_in * reserve_out) otherwise,
+    /// both in raw token units
+    pub liquidity: U256,
+    /// reserves of a constant product pool with the same spot price and depth,
+    /// used to estimate swaps without simulating the dex
+    pub reserve_in: U256,
+    pub reserve_out: U256,
+}
+
+const FEE_DENOMINATOR: u64 = 1_000_000;
+
+impl PoolEdge {
+    /// Constant product estimate of the output, zero if the edge has no reserves
+    pub fn estimate_amount_out(&self, amount_in: U256) -> U256 {
+        if self.reserve_in.is_zero()
+            || self.reserve_out.is_zero()
+            || self.fee as u64 >= FEE_DENOMINATOR
+        {
+            return U256::zero();
+        }
+        let amount_in_with_fee = mul_div(
+            amount_in,
+            U256::from(FEE_DENOMINATOR - self.fee as u64),
+            U256::from(FEE_DENOMINATOR),
+        );
+        mul_div(
+            self.reserve_out,
+            amount_in_with_fee,
+            self.reserve_in.saturating_add(amount_in_with_fee),
+        )
+    }
+
+    /// Constant product estimate of the input for `amount_out`,
+    /// None if the edge cannot provide it
+    pub fn estimate_amount_in(&self, amount_out: U256) -> Option<U256> {
+        if self.reserve_in.is_zero()
+            || amount_out >= self.reserve_out
+            || self.fee as u64 >= FEE_DENOMINATOR
+        {
+            return None;
+        }
+        let amount_in_with_fee =
+            mul_div(self.reserve_in, amount_out, self.reserve_out - amount_out) + 1;
+        Some(
+            mul_div(
+                amount_in_with_fee,
+                U256::from(FEE_DENOMINATOR),
+                U256::from(FEE_DENOMINATOR - self.fee as u64),
+            ) + 1,
+        )
+    }
+}
+
+// a * b / c without overflowing the product, saturating the result and zero when c is zero
+fn mul_div(a: U256, b: U256, c: U256) -> U256 {
+    if c.is_zero() {
+        return U256::zero();
+    }
+    (a.full_mul(b) / c).try_into().unwrap_or(U256::MAX)
+}
+
+/// Token graph of the pools of a store, an adjacency list of the swaps leaving each token.
+/// It is built once from the store and then kept up to date pool by pool.
+#[derive(Clone, Debug, Default)]
+pub struct RoutingGraph {
+    edges: HashMap<Address, Vec<PoolEdge>>,
+    // tokens of each pool, to find its edges
+    pools: HashMap<Address, Vec<Address>>,
+}
+
+impl RoutingGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build the graph from every pool of the dex sets of a chain
+    pub fn from_store<S: PoolStore>(store: &S, chain_id: u64) -> DexQuoteResult<Self> {
+        let mut graph = Self::new();
+        graph.sync_pools(store, chain_id)?;
+        Ok(graph)
+    }
+
+    /// Add the pools that were added to the dex sets since the last sync
+    /// and remove the ones that are no longer in them
+    pub fn sync_pools<S: PoolStore>(&mut self, store: &S, chain_id: u64) -> DexQuoteResult<()> {
+        let mut stored_pools = HashSet::new();
+        for dex in Dex::ALL {
+            stored_pools.extend(store.get_dex_pools(chain_id, dex.as_str())?);
+        }
+        let removed: Vec<Address> = self
+            .pools
+            .keys()
+            .filter(|pool| !stored_pools.contains(*pool))
+            .copied()
+            .collect();
+        for pool in removed {
+            self.remove_pool(pool);
+        }
+        for pool in stored_pools {
+            if !self.pools.contains_key(&pool) {
+                self.update_pool_from_store(store, chain_id, pool)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Re-read a pool from the store, a pool that is not stored anymore is removed
+    pub fn update_pool_from_store<S: PoolStore>(
+        &mut self,
+        store: &S,
+        chain_id: u64,
+        pool_address: Address,
+    ) -> DexQuoteResult<()> {
+        let target_data = store.get_pool_hashmap(chain_id, pool_address)?;
+        self.remove_pool(pool_address);
+        if let Some(edges) = get_pool_edges(pool_address, target_data) {
+            self.add_pool(pool_address, edges);
+        }
+        Ok(())
+    }
+
+    /// Add the edges of a pool, replacing its previous edges
+    pub fn add_pool(&mut self, pool_address: Address, edges: Vec<PoolEdge>) {
+        self.remove_pool(pool_address);
+        let mut tokens = Vec::new();
+        for edge in edges {
+            if !tokens.contains(&edge.token_in) {
+                tokens.push(edge.token_in);
+            }
+            self.edges.entry(edge.token_in).or_default().push(edge);
+        }
+        self.pools.insert(pool_address, tokens);
+    }
+
+    /// Remove the edges of a pool, returns false if the pool was not in the graph
+    pub fn remove_pool(&mut self, pool_address: Address) -> bool {
+        let tokens = match self.pools.remove(&pool_address) {
+            Some(tokens) => tokens,
+            None => return false,
+        };
+        for token in tokens {
+            if let Some(edges) = self.edges.get_mut(&token) {
+                edges.retain(|edge| edge.pool != pool_address);
+                if edges.is_empty() {
+                    self.edges.remove(&token);
+                }
+            }
+        }
+        true
+    }
+
+    /// Tokens of each pool
+    pub fn pools(&self) -> &HashMap<Address, Vec<Address>> {
+        &self.pools
+    }
+
+    pub fn pool_count(&self) -> usize {
+        self.pools.len()
+    }
+
+    pub fn token_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Tokens with at least one swap from them
+    pub fn tokens(&self) -> impl Iterator<Item = Address> + '_ {
+        self.edges.keys().copied()
+    }
+
+    /// Swaps from a token
+    pub fn edges(&self, token: Address) -> &[PoolEdge] {
+        self.edges.get(&token).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Paths of at most `max_hops` pools from `token_in` to `token_out`, shortest first.
+    /// A path uses a pool and passes a token at most once, at most `limit` paths are returned.
+    pub fn get_paths(
+        &self,
+        token_in: Address,
+        token_out: Address,
+        max_hops: usize,
+        limit: usize,
+    ) -> Vec<Vec<PoolInfo>> {
+        if token_in == token_out {
+            return Vec::new();
+        }
+        self.search_paths(token_in, token_out, 1, max_hops, limit)
+    }
+
+    /// Cycles of 2 to `max_hops` pools from `token` back to it, shortest first.
+    /// A cycle uses a pool and passes a token at most once, at most `limit` cycles are returned.
+    pub fn get_cycles(&self, token: Address, max_hops: usize, limit: usize) -> Vec<Vec<PoolInfo>> {
+        self.search_paths(token, token, 2, max_hops, limit)
+    }
+
+    fn search_paths(
+        &self,
+        token_in: Address,
+        token_out: Address,
+        min_hops: usize,
+        max_hops: usize,
+        limit: usize,
+    ) -> Vec<Vec<PoolInfo>> {
+        let mut paths = Vec::new();
+        if limit == 0 {
+            return paths;
+        }
+        // hops to token_out, tokens further than the remaining hops are not explored
+        let distances = self.get_distances(token_out, max_hops);
+        if !distances.contains_key(&token_in) {
+            return paths;
+        }
+        for hops in min_hops..=max_hops {
+            let mut search = PathSearch {
+                graph: self,
+                token_out,
+                distances: &distances,
+                limit,
+                route: Vec::with_capacity(hops),
+                visited: vec![token_in],
+                paths: &mut paths,
+            };
+            search.search(token_in, hops);
+            if paths.len() >= limit {
+                break;
+            }
+        }
+        paths
+    }
+
+    /// Paths of at most `max_hops` pools from `token_in` to `token_out`, with the most estimated
+    /// output of `amount_in` first. The estimates use the reserves and fee of each edge,
+    /// so the best `limit` paths can be simulated exactly afterwards.
+    pub fn get_ranked_paths(
+        &self,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256,
+        max_hops: usize,
+        limit: usize,
+    ) -> Vec<(Vec<PoolInfo>, U256)> {
+        self.rank_paths(token_in, token_out, amount_in, max_hops, limit, false)
+    }
+
+    /// Paths of at most `max_hops` pools from `token_in` to `token_out`, with the least estimated
+    /// input for `amount_out` first
+    pub fn get_ranked_paths_for_amount_out(
+        &self,
+        token_in: Address,
+        token_out: Address,
+        amount_out: U256,
+        max_hops: usize,
+        limit: usize,
+    ) -> Vec<(Vec<PoolInfo>, U256)> {
+        self.rank_paths(token_out, token_in, amount_out, max_hops, limit, true)
+    }
+
+    /// The edge of a pool between two tokens
+    pub fn get_edge(
+        &self,
+        pool: Address,
+        token_in: Address,
+        token_out: Address,
+    ) -> Option<&PoolEdge> {
+        self.edges(token_in)
+            .iter()
+            .find(|edge| edge.pool == pool && edge.token_out == token_out)
+    }
+
+    // Beam search from `start` to `end`, hop by hop. Only the best `limit` partial routes to
+    // each token are extended, so the search stays bounded on dense graphs.
+    // An exact output search starts from token_out and walks the edges backwards.
+    fn rank_paths(
+        &self,
+        start: Address,
+        end: Address,
+        amount: U256,
+        max_hops: usize,
+        limit: usize,
+        exact_output: bool,
+    ) -> Vec<(Vec<PoolInfo>, U256)> {
+        let mut ranked: Vec<RankedRoute> = Vec::new();
+        if start == end || limit == 0 || amount.is_zero() {
+            return Vec::new();
+        }
+        let distances = self.get_distances(end, max_hops);
+        if !distances.contains_key(&start) {
+            return Vec::new();
+        }
+        // better estimates first, shorter routes on ties
+        let order = |a: &RankedRoute, b: &RankedRoute| {
+            let by_amount = if exact_output {
+                a.amount.cmp(&b.amount)
+            } else {
+                b.amount.cmp(&a.amount)
+            };
+            by_amount.then(a.route.len().cmp(&b.route.len()))
+        };
+        let mut frontier = HashMap::from([(
+            start,
+            vec![RankedRoute {
+                route: Vec::new(),
+                amount,
+            }],
+        )]);
+        for hops in 1..=max_hops {
+            let mut next: HashMap<Address, Vec<RankedRoute>> = HashMap::new();
+            for (token, routes) in &frontier {
+                for route in routes {
+                    for edge in self.edges(*token) {
+                        let edge = match exact_output {
+                            false => edge,
+                            // the swap into `token`
+                            true => match self.get_edge(edge.pool, edge.token_out, *token) {
+                                Some(edge) => edge,
+                                None => continue,
+                            },
+                        };
+                        let next_token = if exact_output {
+                            edge.token_in
+                        } else {
+                            edge.token_out
+                        };
+                        if next_token == start
+                            || route.route.iter().any(|used| {
+                                used.pool == edge.pool
+                                    || used.token_in == next_token
+                                    || used.token_out == next_token
+                            })
+                        {
+                            continue;
+                        }
+                        match distances.get(&next_token) {
+                            Some(distance) if *distance <= max_hops - hops => {}
+                            _ => continue,
+                        }
+                        let amount = if exact_output {
+                            match edge.estimate_amount_in(route.amount) {
+                                Some(amount) => amount,
+                                None => continue,
+                            }
+                        } else {
+                            let amount = edge.estimate_amount_out(route.amount);
+                            if amount.is_zero() {
+                                continue;
+                            }
+                            amount
+                        };
+                        let mut extended = route.route.clone();
+                        extended.push(*edge);
+                        let extended = RankedRoute {
+                            route: extended,
+                            amount,
+                        };
+                        if next_token == end {
+                            ranked.push(extended);
+                        } else {
+                            next.entry(next_token).or_default().push(extended);
+                        }
+                    }
+                }
+            }
+            for routes in next.values_mut() {
+                routes.sort_by(order);
+                routes.truncate(limit);
+            }
+            frontier = next;
+        }
+        ranked.sort_by(order);
+        ranked.truncate(limit);
+        ranked
+            .into_iter()
+            .map(|mut ranked| {
+                if exact_output {
+                    ranked.route.reverse();
+                }
+                (
+                    ranked.route.iter().map(PoolInfo::from).collect(),
+                    ranked.amount,
+                )
+            })
+            .collect()
+    }
+
+    // hops from each token to `token`, pools trade both ways so the edges are followed backwards
+    fn get_distances(&self, token: Address, max_hops: usize) -> HashMap<Address, usize> {
+        let mut distances = HashMap::from([(token, 0)]);
+        let mut queue = VecDeque::from([token]);
+        while let Some(current) = queue.pop_front() {
+            let distance = distances[&current];
+            if distance == max_hops {
+                continue;
+            }
+            for edge in self.edges(current) {
+                if let Entry::Vacant(entry) = distances.entry(edge.token_out) {
+                    entry.insert(distance + 1);
+                    queue.push_back(edge.token_out);
+                }
+            }
+        }
+        distances
+    }
+}
+
+// a partial route of the ranked search and its estimated amount at its last token
+struct RankedRoute {
+    route: Vec<PoolEdge>,
+    amount: U256,
+}
+
+// depth-first search of the paths with an exact number of hops
+struct PathSearch<'a> {
+    graph: &'a RoutingGraph,
+    token_out: Address,
+    distances: &'a HashMap<Address, usize>,
+    limit: usize,
+    route: Vec<PoolEdge>,
+    visited: Vec<Address>,
+    paths: &'a mut Vec<Vec<PoolInfo>>,
+}
+
+impl<'a> PathSearch<'a> {
+    fn search(&mut self, token: Address, hops_left: usize) {
+        for edge in self.graph.edges(token) {
+            if self.paths.len() >= self.limit {
+                return;
+            }
+            let next = edge.token_out;
+            if self.route.iter().any(|used| used.pool == edge.pool) {
+                continue;
+            }
+            // checked before the visited tokens, a cycle ends at its first token
+            if next == self.token_out {
+                if hops_left == 1 {
+                    self.route.push(*edge);
+                    self.paths
+                        .push(self.route.iter().map(PoolInfo::from).collect());
+                    self.route.pop();
+                }
+                continue;
+            }
+            if self.visited.contains(&next) {
+                continue;
+            }
+            match self.distances.get(&next) {
+                Some(distance) if *distance < hops_left => {}
+                _ => continue,
+            }
+            self.route.push(*edge);
+            self.visited.push(next);
+            self.search(next, hops_left - 1);
+            self.visited.pop();
+            self.route.pop();
+        }
+    }
+}
+
+impl From<&PoolEdge> for PoolInfo {
+    fn from(edge: &PoolEdge) -> Self {
+        PoolInfo {
+            address: edge.pool,
+            token_in: edge.token_in,
+            token_out: edge.token_out,
+            dex: Some(edge.dex),
+            fee: Some(edge.fee),
+        }
+    }
+}
+
+fn reserve_liquidity(reserve_in: U256, reserve_out: U256) -> U256 {
+    reserve_in
+        .full_mul(reserve_out)
+        .integer_sqrt()
+        .try_into()
+        .unwrap_or(U256::MAX)
+}
+
+// virtual reserves of a concentrated pool in its current range, L / sqrt(P) and L * sqrt(P)
+fn concentrated_reserves(liquidity: U256, sqrt_price_x96: U256) -> [U256; 2] {
+    let q96 = U256::one() << 96;
+    [
+        mul_div(liquidity, q96, sqrt_price_x96),
+        mul_div(liquidity, sqrt_price_x96, q96),
+    ]
+}
+
+// Stable pools are estimated as constant product pools holding their whole value
+// in each token, which underestimates them on large amounts.
+// `scales` turn a balance into a common 18 decimal amount, in 1e18.
+fn stable_reserves(balances: &[U256], scales: &[U256]) -> Vec<U256> {
+    let scale = U256::exp10(18);
+    let value = balances
+        .iter()
+        .zip(scales)
+        .fold(U256::zero(), |value, (balance, rate)| {
+            value.saturating_add(mul_div(*balance, *rate, scale))
+        });
+    scales
+        .iter()
+        .map(|rate| mul_div(value, scale, *rate))
+        .collect()
+}
+
+// both directions of a two token pool
+fn pair_edges(
+    pool: Address,
+    dex: Dex,
+    tokens: [Address; 2],
+    fees: [u32; 2],
+    liquidity: U256,
+    reserves: [U256; 2],
+) -> Vec<PoolEdge> {
+    vec![
+        PoolEdge {
+            pool,
+            dex,
+            token_in: tokens[0],
+            token_out: tokens[1],
+            fee: fees[0],
+            liquidity,
+            reserve_in: reserves[0],
+            reserve_out: reserves[1],
+        },
+        PoolEdge {
+            pool,
+            dex,
+            token_in: tokens[1],
+            token_out: tokens[0],
+            fee: fees[1],
+            liquidity,
+            reserve_in: reserves[1],
+            reserve_out: reserves[0],
+        },
+    ]
+}
+
+// every ordered pair of tokens of a multi-token pool
+fn multi_token_edges(
+    pool: Address,
+    dex: Dex,
+    tokens: &[Address],
+    balances: &[U256],
+    fee: impl Fn(usize, usize) -> u32,
+    reserves: impl Fn(usize, usize) -> [U256; 2],
+) -> Vec<PoolEdge> {
+    let mut edges = Vec::new();
+    for i in 0..tokens.len() {
+        for j in 0..tokens.len() {
+            if i == j {
+                continue;
+            }
+            let [reserve_in, reserve_out] = reserves(i, j);
+            edges.push(PoolEdge {
+                pool,
+                dex,
+                token_in: tokens[i],
+                token_out: tokens[j],
+                fee: fee(i, j),
+                liquidity: reserve_liquidity(balances[i], balances[j]),
+                reserve_in,
+                reserve_out,
+            });
+        }
+    }
+    edges
+}
+
+/// Edges of a pool from its stored hashmap, None if the dex is unknown or a field is missing
+pub fn get_pool_edges(
+    pool_address: Address,
+    target_data: HashMap<String, String>,
+) -> Option<Vec<PoolEdge>> {
+    let dex = Dex::from_str(target_data.get("dex")?).ok()?;
+    let edges = match dex {
+        Dex::UniswapV3 => match univ3::hashmap_to_univ3(pool_address, target_data)? {
+            Pool::UniswapV3(pool) => pair_edges(
+                pool_address,
+                dex,
+                [pool.token_a, pool.token_b],
+                [pool.fee, pool.fee],
+                U256::from(pool.liquidity),
+                concentrated_reserves(U256::from(pool.liquidity), pool.sqrt_price),
+            ),
+            Pool::UniswapV2(_) => return None,
+        },
+        Dex::UniswapV2 => {
+            let fee = univ2::hashmap_to_fee(&target_data)?;
+            match univ2::hashmap_to_univ2(pool_address, target_data)? {
+                Pool::UniswapV2(pool) => {
+                    let reserves = [pool.reserve_0.into(), pool.reserve_1.into()];
+                    pair_edges(
+                        pool_address,
+                        dex,
+                        [pool.token_a, pool.token_b],
+                        // 1e-5 to 1e-6
+                        [fee.get(true) * 10, fee.get(false) * 10],
+                        reserve_liquidity(reserves[0], reserves[1]),
+                        reserves,
+                    )
+                }
+                Pool::UniswapV3(_) => return None,
+            }
+        }
+        Dex::Solidly => {
+            let pool = solidly::hashmap_to_solidly(pool_address, target_data)?;
+            let reserves = [pool.reserve_0.into(), pool.reserve_1.into()];
+            // bps to 1e-6
+            pair_edges(
+                pool_address,
+                dex,
+                [pool.token_a, pool.token_b],
+                [pool.fee * 100, pool.fee * 100],
+                reserve_liquidity(reserves[0], reserves[1]),
+                reserves,
+            )
+        }
+        Dex::Algebra => {
+            let fee: u32 = target_data.get("fee")?.parse().ok()?;
+            let fee_one_for_zero = match target_data.get("fee_one_for_zero") {
+                Some(fee_one_for_zero) => fee_one_for_zero.parse().ok()?,
+                None => fee,
+            };
+            let liquidity = U256::from_dec_str(target_data.get("liquidity")?).ok()?;
+            let sqrt_price = U256::decode_hex(target_data.get("sqrt_price")?).unwrap_or_default();
+            pair_edges(
+                pool_address,
+                dex,
+                [
+                    target_data.get("token0")?.parse().ok()?,
+                    target_data.get("token1")?.parse().ok()?,
+                ],
+                [fee, fee_one_for_zero],
+                liquidity,
+                concentrated_reserves(liquidity, sqrt_price),
+            )
+        }
+        Dex::Curve => {
+            let pool = curve::hashmap_to_curve(pool_address, target_data)?;
+            // 1e10 to 1e-6
+            let fee = (pool.fee / U256::exp10(4)).as_u32();
+            let reserves = stable_reserves(&pool.balances, &pool.rates);
+            multi_token_edges(
+                pool_address,
+                dex,
+                &pool.coins,
+                &pool.balances,
+                |_, _| fee,
+                |i, j| [reserves[i], reserves[j]],
+            )
+        }
+        Dex::BalancerV2 => {
+            let pool = balancer::hashmap_to_balancer(pool_address, target_data)?;
+            // 1e18 to 1e-6
+            let fee = (pool.swap_fee / U256::exp10(12)).as_u32();
+            let reserves = match pool.kind {
+                // balance / weight keeps the spot price of the weights,
+                // scaled so that an equal weight pool keeps its balances
+                balancer::BalancerPoolKind::Weighted => pool
+                    .balances
+                    .iter()
+                    .zip(&pool.weights)
+                    .map(|(balance, weight)| {
+                        mul_div(
+                            *balance,
+                            U256::exp10(18),
+                            weight.saturating_mul(U256::from(pool.tokens.len())),
+                        )
+                    })
+                    .collect(),
+                balancer::BalancerPoolKind::Stable => {
+                    stable_reserves(&pool.balances, &pool.scaling_factors)
+                }
+            };
+            multi_token_edges(
+                pool_address,
+                dex,
+                &pool.tokens,
+                &pool.balances,
+                |_, _| fee,
+                |i, j| [reserves[i], reserves[j]],
+            )
+        }
+        Dex::WooFiV2 => {
+            let pool = woofi::hashmap_to_woofi(pool_address, target_data)?;
+            let tokens = pool.all_tokens();
+            let mut balances = vec![pool.quote_reserve];
+            balances.extend(pool.tokens.iter().map(|token| token.reserve));
+            // the quote token has no fee, a base to base swap pays the higher fee
+            let fee_rate = |i: usize| match i {
+                0 => 0,
+                _ => pool.tokens[i - 1].fee_rate as u32,
+            };
+            // oracle value of an amount of a base token in quote token units, and back
+            let quote_per_base = |i: usize| {
+                let token = &pool.tokens[i - 1];
+                (
+                    token
+                        .price
+                        .saturating_mul(U256::exp10(pool.quote_decimals as usize)),
+                    U256::exp10(token.price_decimals as usize + token.decimals as usize),
+                )
+            };
+            let to_quote = |i: usize, amount: U256| match i {
+                0 => amount,
+                _ => {
+                    let (numerator, denominator) = quote_per_base(i);
+                    mul_div(amount, numerator, denominator)
+                }
+            };
+            let from_quote = |i: usize, amount: U256| match i {
+                0 => amount,
+                _ => {
+                    let (numerator, denominator) = quote_per_base(i);
+                    mul_div(amount, denominator, numerator)
+                }
+            };
+            // the output is bounded by the reserve of token_out, priced by the oracle
+            let reserves =
+                |i: usize, j: usize| [from_quote(i, to_quote(j, balances[j])), balances[j]];
+            // 1e5 to 1e-6
+            multi_token_edges(
+                pool_address,
+                dex,
+                &tokens,
+                &balances,
+                |i, j| fee_rate(i).max(fee_rate(j)) * 10,
+                reserves,
+            )
+        }
+    };
+    Some(edges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{
+        add_pool, add_solidly_pool, curve::CurvePool, solidly::SolidlyPool, MemoryStore,
+    };
+    use cfmms::pool::UniswapV2Pool;
+
+    const CHAIN_ID: u64 = 42161;
+
+    fn token(n: u64) -> Address {
+        Address::from_low_u64_be(n)
+    }
+
+    fn univ2_pool(address: Address, token_a: Address, token_b: Address) -> Pool {
+        Pool::UniswapV2(UniswapV2Pool {
+            address,
+            token_a,
+            token_a_decimals: 18,
+            token_b,
+            token_b_decimals: 18,
+            reserve_0: 4_000,
+            reserve_1: 9_000,
+            fee: 300,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_routing_graph_from_store() {
+        let store = MemoryStore::new();
+        // 1 - 2 - 3 - 4, 1 - 3, and a curve pool with 2, 3 and 5
+        for (pool, token_a, token_b) in [(100, 1, 2), (101, 2, 3), (102, 3, 4), (103, 1, 3)] {
+            add_pool(
+                &store,
+                CHAIN_ID,
+                univ2_pool(token(pool), token(token_a), token(token_b)),
+                None,
+                "",
+            )
+            .await
+            .unwrap();
+        }
+        add_solidly_pool(
+            &store,
+            CHAIN_ID,
+            SolidlyPool {
+                address: token(104),
+                token_a: token(4),
+                token_a_decimals: 18,
+                token_b: token(1),
+                token_b_decimals: 18,
+                reserve_0: 1,
+                reserve_1: 1,
+                stable: false,
+                fee: 30,
+                ..Default::default()
+            },
+            None,
+            "",
+        )
+        .await
+        .unwrap();
+        crate::db::add_curve_pool(
+            &store,
+            CHAIN_ID,
+            CurvePool {
+                address: token(105),
+                coins: vec![token(2), token(3), token(5)],
+                decimals: vec![18; 3],
+                balances: vec![U256::from(100); 3],
+                rates: vec![U256::exp10(18); 3],
+                fee: U256::from(4_000_000),
+                ..Default::default()
+            },
+            None,
+            "",
+        )
+        .await
+        .unwrap();
+
+        let mut graph = RoutingGraph::from_store(&store, CHAIN_ID).unwrap();
+        assert_eq!(graph.pool_count(), 6);
+        assert_eq!(graph.token_count(), 5);
+        let edge = graph
+            .edges(token(1))
+            .iter()
+            .find(|edge| edge.pool == token(100))
+            .unwrap();
+        assert_eq!(edge.dex, Dex::UniswapV2);
+        assert_eq!(edge.fee, 3000);
+        assert_eq!(edge.liquidity, U256::from(6_000));
+        // 3 pairs in both directions
+        assert_eq!(
+            graph
+                .edges(token(5))
+                .iter()
+                .filter(|edge| edge.pool == token(105))
+                .count(),
+            2
+        );
+        assert_eq!(graph.edges(token(5))[0].fee, 400);
+        assert_eq!(
+            graph
+                .edges(token(4))
+                .iter()
+                .find(|edge| edge.pool == token(104))
+                .unwrap()
+                .fee,
+            3000
+        );
+
+        let paths = graph.get_paths(token(1), token(4), 1, 10);
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0][0].address, token(104));
+
+        let paths = graph.get_paths(token(1), token(3), 3, 100);
+        let pools: Vec<Vec<Address>> = paths
+            .iter()
+            .map(|path| path.iter().map(|pool| pool.address).collect())
+            .collect();
+        // shortest first, no token is passed twice
+        assert_eq!(pools[0], vec![token(103)]);
+        assert!(pools.iter().all(|path| path.len() <= 3));
+        assert!(pools
+            .iter()
+            .any(|path| path == &vec![token(100), token(101)]));
+        assert!(pools
+            .iter()
+            .any(|path| path == &vec![token(100), token(105)]));
+        assert!(pools
+            .iter()
+            .any(|path| path == &vec![token(104), token(102)]));
+        assert!(!pools
+            .iter()
+            .any(|path| path.contains(&token(103)) && path.len() > 1));
+        assert_eq!(pools.len(), 4);
+        for path in &paths {
+            assert_eq!(path[0].token_in, token(1));
+            assert_eq!(path[path.len() - 1].token_out, token(3));
+            for hop in path.windows(2) {
+                assert_eq!(hop[0].token_out, hop[1].token_in);
+            }
+        }
+        assert_eq!(graph.get_paths(token(1), token(3), 3, 2).len(), 2);
+
+        // two pools of 2 and 3 make a cycle in each direction
+        assert_eq!(graph.get_cycles(token(2), 2, 10).len(), 2);
+        let cycles = graph.get_cycles(token(1), 3, 100);
+        assert_eq!(cycles.len(), 6);
+        for cycle in &cycles {
+            assert_eq!(cycle.len(), 3);
+            assert_eq!(cycle[0].token_in, token(1));
+            assert_eq!(cycle[2].token_out, token(1));
+        }
+        assert!(graph.get_paths(token(1), token(1), 3, 100).is_empty());
+
+        // pools removed from the store are removed from the graph
+        store.delete_pool(CHAIN_ID, token(103)).unwrap();
+        store
+            .remove_dex_pool(CHAIN_ID, "UNIV2", token(103))
+            .unwrap();
+        graph.sync_pools(&store, CHAIN_ID).unwrap();
+        assert_eq!(graph.pool_count(), 5);
+        assert_eq!(graph.get_paths(token(1), token(3), 1, 10).len(), 0);
+
+        add_pool(
+            &store,
+            CHAIN_ID,
+            univ2_pool(token(106), token(5), token(6)),
+            None,
+            "",
+        )
+        .await
+        .unwrap();
+        graph.sync_pools(&store, CHAIN_ID).unwrap();
+        assert_eq!(graph.get_paths(token(2), token(6), 2, 10).len(), 1);
+        assert!(graph.remove_pool(token(106)));
+        assert!(!graph.remove_pool(token(106)));
+        assert_eq!(graph.token_count(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_get_ranked_paths() {
+        let store = MemoryStore::new();
+        let reserves = [
+            (200, 1, 2, 1_000),
+            (201, 1, 2, 1_000_000_000_000_000_000_000_000),
+            (202, 1, 3, 1_000_000_000_000_000_000_000_000),
+            (203, 3, 2, 1_000_000_000_000_000_000_000_000),
+        ];
+        for (pool, token_a, token_b, reserve) in reserves {
+            add_pool(
+                &store,
+                CHAIN_ID,
+                Pool::UniswapV2(UniswapV2Pool {
+                    address: token(pool),
+                    token_a: token(token_a),
+                    token_a_decimals: 18,
+                    token_b: token(token_b),
+                    token_b_decimals: 18,
+                    reserve_0: reserve,
+                    reserve_1: reserve,
+                    fee: 300,
+                }),
+                None,
+                "",
+            )
+            .await
+            .unwrap();
+        }
+        let graph = RoutingGraph::from_store(&store, CHAIN_ID).unwrap();
+        let amount = U256::exp10(18);
+        let reserve = U256::exp10(24);
+
+        // the deep pools first, the shallow direct pool does not make the top 2
+        let ranked = graph.get_ranked_paths(token(1), token(2), amount, 2, 2);
+        let pools: Vec<Vec<Address>> = ranked
+            .iter()
+            .map(|(path, _)| path.iter().map(|pool| pool.address).collect())
+            .collect();
+        assert_eq!(pools, vec![vec![token(201)], vec![token(202), token(203)]]);
+        assert_eq!(ranked[0].0[0].dex, Some(Dex::UniswapV2));
+        assert_eq!(ranked[0].0[0].fee, Some(3000));
+        // the estimate of a UniswapV2 pool is its exact output
+        let expected = reserve * amount * 997 / (reserve * 1000 + amount * 997);
+        assert_eq!(ranked[0].1, expected);
+        assert!(ranked[1].1 < ranked[0].1);
+        assert_eq!(
+            graph
+                .get_ranked_paths(token(1), token(2), amount, 2, 10)
+                .len(),
+            3
+        );
+        assert_eq!(
+            graph
+                .get_ranked_paths(token(1), token(2), amount, 1, 10)
+                .len(),
+            2
+        );
+
+        // the least input first, the shallow pool cannot provide the output
+        let ranked = graph.get_ranked_paths_for_amount_out(token(1), token(2), amount, 2, 10);
+        assert_eq!(ranked.len(), 2);
+        assert_eq!(ranked[0].0[0].address, token(201));
+        assert_eq!(ranked[1].0[0].token_in, token(1));
+        assert_eq!(ranked[1].0[1].token_out, token(2));
+        let edge = graph.get_edge(token(201), token(1), token(2)).unwrap();
+        assert!(edge.estimate_amount_out(ranked[0].1) >= amount);
+        assert!(ranked[0].1 < ranked[1].1);
+
+        // a concentrated pool at price 1 holds its liquidity in both tokens
+        assert_eq!(
+            concentrated_reserves(U256::from(1_000), U256::one() << 96),
+            [U256::from(1_000), U256::from(1_000)]
+        );
+    }
+}
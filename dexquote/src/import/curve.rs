@@ -0,0 +1,80 @@
+use dexquote::db::{add_curve_pool, curve, multicall::get_batcher, pool_exists, RedisStore};
+use ethers::{providers::Middleware, types::Address};
+use std::{error::Error, time::Instant};
+use tracing::{info, warn};
+
+use crate::config;
+
+/// Import Curve pools, there is no factory listing plain and meta pools alike
+pub async fn import_pool(pools: Vec<String>, config_name: String) -> Result<(), Box<dyn Error>> {
+    let start = Instant::now();
+    let conf = config::get_config(config_name);
+    let graph = config::connect_neo4j(&conf).await?;
+
+    let store = RedisStore::open(&conf.redis_url)?;
+
+    let batcher = get_batcher(&conf.json_rpc_url)?;
+    let chain_id = batcher.client().get_chainid().await?.as_u64();
+
+    let total_pool_num = pools.len();
+    let mut err_count = 0;
+    let mut already_imported = 0;
+    for pool in pools {
+        let pool_address: Address = match pool.parse() {
+            Ok(pool_address) => pool_address,
+            Err(e) => {
+                err_count += 1;
+                warn!("Invalid pool address {}: {:?}", pool, e);
+                continue;
+            }
+        };
+        match pool_exists(&store, chain_id, pool_address) {
+            Ok(true) => {
+                already_imported += 1;
+                continue;
+            }
+            Ok(false) => {}
+            Err(_) => {
+                err_count += 1;
+                continue;
+            }
+        }
+
+        let pool = match curve::CurvePool::new_from_address(pool_address, &batcher).await {
+            Ok(pool) => pool,
+            Err(e) => {
+                err_count += 1;
+                warn!("Error fetching pool {:?}: {:?}", pool_address, e);
+                continue;
+            }
+        };
+        // meta pools are priced with the virtual price of their base pool
+        if let Some(base_pool) = pool.base_pool {
+            if !pool_exists(&store, chain_id, base_pool)? {
+                warn!(
+                    "Base pool {:?} of {:?} is not imported",
+                    base_pool, pool_address
+                );
+            }
+        }
+        match add_curve_pool(&store, chain_id, pool, graph.as_ref(), &conf.chain_label).await {
+            Ok(_) => {}
+            Err(e) => {
+                err_count += 1;
+                warn!("Error adding pool: {:?}", e);
+            }
+        };
+    }
+
+    let elapsed = start.elapsed();
+    info!(
+        total_pool_num,
+        already_imported,
+        err_count,
+        "Imported {} pools in {} seconds",
+        total_pool_num - err_count - already_imported,
+        elapsed.as_secs(),
+    );
+
+    Ok(())
+}
@@ -1,27 +1,41 @@
-use cfmms::checkpoint;
-use dexquote::db::{add_pool, get_pool};
-use ethers::providers::{Http, Middleware, Provider};
+use cfmms::{checkpoint, pool::Pool};
+use dexquote::db::{
+    add_pool, get_pool,
+    multicall::get_batcher,
+    univ2::{get_factory, update_fee},
+    RedisStore,
+};
+use ethers::providers::Middleware;
 use indicatif::{ProgressBar, ProgressStyle};
-use neo4rs::Graph;
-use std::{error::Error, sync::Arc, time::Instant};
+use std::{error::Error, time::Instant};
 use tracing::{info, warn};
+pub mod balancer;
+pub mod curve;
+pub mod solidly;
+pub mod ticks;
 pub mod univ3;
+pub mod woofi;
 
+use crate::checkpoint::{get_configured_factories, get_univ2_fees};
 use crate::config;
 
 pub async fn import_pool(
     config_name: String,
     checkpoint_path: String,
     sync: bool,
+    prefetch_ticks: bool,
 ) -> Result<(), Box<dyn Error>> {
     let start = Instant::now();
     let conf = config::get_config(config_name);
-    let graph = Graph::new(conf.neo4j_uri, "neo4j", conf.neo4j_pass).await?;
+    let graph = config::connect_neo4j(&conf).await?;
 
-    let redis_client = redis::Client::open(conf.redis_url).unwrap();
+    let store = RedisStore::open(&conf.redis_url)?;
 
-    let provider = Arc::new(Provider::<Http>::try_from(&conf.json_rpc_url).unwrap());
+    let batcher = get_batcher(&conf.json_rpc_url)?;
+    let provider = batcher.client();
     let chain_id = provider.get_chainid().await?.as_u64();
+    // checkpoints only carry one fee per pool, configured fork fees are looked up by factory
+    let univ2_fees = get_univ2_fees(&get_configured_factories(chain_id, &conf.univ2_factories));
 
     let pools;
     if sync {
@@ -29,7 +43,7 @@ pub async fn import_pool(
             &checkpoint_path,
             100000,
             5,
-            provider,
+            provider.clone(),
         )
         .await?;
     } else {
@@ -39,6 +53,7 @@ pub async fn import_pool(
     let total_pool_num = pools.len();
     let mut err_count = 0;
     let mut already_imported = 0;
+    let mut univ3_pools = Vec::new();
     let pb = ProgressBar::new(total_pool_num as u64);
     pb.set_style(
         ProgressStyle::default_bar()
@@ -49,7 +64,7 @@ pub async fn import_pool(
     pb.set_prefix("Importing");
     for pool in pools {
         let pool_address = pool.address();
-        let pool_on_redis = get_pool(&redis_client, chain_id, pool_address);
+        let pool_on_redis = get_pool(&store, chain_id, pool_address);
 
         if pool_on_redis.is_err() {
             err_count += 1;
@@ -63,7 +78,19 @@ pub async fn import_pool(
             continue;
         };
 
-        match add_pool(&redis_client, chain_id, pool, &graph, &conf.chain_label).await {
+        let is_univ2 = matches!(pool, Pool::UniswapV2(_));
+        match add_pool(&store, chain_id, pool, graph.as_ref(), &conf.chain_label).await {
+            Ok(_) if is_univ2 && !conf.univ2_factories.is_empty() => {
+                match get_factory(pool_address, &batcher).await {
+                    Ok(factory) => {
+                        if let Some(fee) = univ2_fees.get(&factory) {
+                            update_fee(&store, chain_id, pool_address, *fee)?;
+                        }
+                    }
+                    Err(e) => warn!("Error getting factory of {:?}: {:?}", pool_address, e),
+                }
+            }
+            Ok(_) if !is_univ2 => univ3_pools.push(pool_address),
             Ok(_) => {}
             Err(e) => {
                 err_count += 1;
@@ -84,5 +111,8 @@ pub async fn import_pool(
         elapsed.as_secs(),
     );
 
+    if prefetch_ticks {
+        ticks::prefetch_pools(&store, chain_id, &batcher, &univ3_pools).await?;
+    }
     Ok(())
 }
@@ -0,0 +1,88 @@
+use dexquote::{
+    db::{add_solidly_pool, multicall::get_batcher, pool_exists, solidly, RedisStore},
+    dex::Dex,
+};
+use ethers::providers::Middleware;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::{error::Error, time::Instant};
+use tracing::{info, warn};
+
+use crate::{checkpoint::get_factories, config};
+
+/// Import all pools of the Solidly factories of the chain
+pub async fn import_pool(config_name: String) -> Result<(), Box<dyn Error>> {
+    let start = Instant::now();
+    let conf = config::get_config(config_name);
+    let graph = config::connect_neo4j(&conf).await?;
+
+    let store = RedisStore::open(&conf.redis_url)?;
+
+    let batcher = get_batcher(&conf.json_rpc_url)?;
+    let chain_id = batcher.client().get_chainid().await?.as_u64();
+
+    let mut pools = vec![];
+    for factory in get_factories(chain_id) {
+        if factory.dex != Dex::Solidly {
+            continue;
+        }
+        pools.extend(solidly::get_factory_pools(factory.address, &batcher).await?);
+    }
+
+    let total_pool_num = pools.len();
+    let mut err_count = 0;
+    let mut already_imported = 0;
+    let pb = ProgressBar::new(total_pool_num as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}")
+            .expect("Error when setting progress bar style")
+            .progress_chars("=> "),
+    );
+    pb.set_prefix("Importing");
+    for pool_address in pools {
+        match pool_exists(&store, chain_id, pool_address) {
+            Ok(true) => {
+                already_imported += 1;
+                pb.inc(1);
+                continue;
+            }
+            Ok(false) => {}
+            Err(_) => {
+                err_count += 1;
+                pb.inc(1);
+                continue;
+            }
+        }
+
+        let pool = match solidly::SolidlyPool::new_from_address(pool_address, &batcher).await {
+            Ok(pool) => pool,
+            Err(e) => {
+                err_count += 1;
+                warn!("Error fetching pool {:?}: {:?}", pool_address, e);
+                pb.inc(1);
+                continue;
+            }
+        };
+        match add_solidly_pool(&store, chain_id, pool, graph.as_ref(), &conf.chain_label).await {
+            Ok(_) => {}
+            Err(e) => {
+                err_count += 1;
+                warn!("Error adding pool: {:?}", e);
+            }
+        };
+        pb.inc(1);
+    }
+
+    pb.finish_and_clear();
+    let elapsed = start.elapsed();
+    info!(
+        total_pool_num,
+        already_imported,
+        err_count,
+        "Imported {} pools in {} seconds",
+        total_pool_num - err_count - already_imported,
+        elapsed.as_secs(),
+    );
+
+    Ok(())
+}
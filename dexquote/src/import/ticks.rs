@@ -0,0 +1,85 @@
+use dexquote::db::{
+    multicall::{MulticallBatcher, MulticallConfig},
+    univ3::{prefetch_ticks, PrefetchStats},
+    PoolStore, RedisStore,
+};
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    types::Address,
+};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::{error::Error, sync::Arc, time::Duration, time::Instant};
+use tracing::info;
+
+use crate::config;
+
+// pools whose reads are batched together, a progress bar step
+const POOLS_PER_STEP: usize = 20;
+
+/// Load the tick bitmaps and initialized ticks of the pools, so that they can be quoted offline
+pub async fn prefetch_pools(
+    store: &RedisStore,
+    chain_id: u64,
+    batcher: &MulticallBatcher<Provider<Http>>,
+    pools: &[Address],
+) -> Result<(), Box<dyn Error>> {
+    let start = Instant::now();
+    let pb = ProgressBar::new(pools.len() as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}")
+            .expect("Error when setting progress bar style")
+            .progress_chars("=> "),
+    );
+    pb.set_prefix("Prefetching ticks");
+    let mut stats = PrefetchStats::default();
+    for step in pools.chunks(POOLS_PER_STEP) {
+        let step_stats = prefetch_ticks(store, chain_id, step, batcher).await?;
+        stats.pools += step_stats.pools;
+        stats.words += step_stats.words;
+        stats.ticks += step_stats.ticks;
+        pb.inc(step.len() as u64);
+    }
+    pb.finish_and_clear();
+    info!(
+        stats.words,
+        stats.ticks,
+        "Prefetched the ticks of {} pools in {} seconds",
+        stats.pools,
+        start.elapsed().as_secs(),
+    );
+    Ok(())
+}
+
+/// Prefetch the ticks of the pools, or of every stored UniswapV3 and Algebra pool if none is given
+pub async fn prefetch(
+    pools: Vec<String>,
+    batch_size: usize,
+    concurrency: usize,
+    delay_ms: u64,
+    config_name: String,
+) -> Result<(), Box<dyn Error>> {
+    let conf = config::get_config(config_name);
+    let store = RedisStore::open(&conf.redis_url)?;
+    let provider = Arc::new(Provider::<Http>::try_from(&conf.json_rpc_url)?);
+    let chain_id = provider.get_chainid().await?.as_u64();
+    let pools = match pools.is_empty() {
+        true => {
+            let mut pools = store.get_dex_pools(chain_id, "UNIV3")?;
+            pools.extend(store.get_dex_pools(chain_id, "ALGEBRA")?);
+            pools
+        }
+        false => pools
+            .iter()
+            .map(|pool| pool.parse::<Address>())
+            .collect::<Result<_, _>>()?,
+    };
+    let multicall_config = MulticallConfig {
+        batch_size,
+        concurrency,
+        delay: Duration::from_millis(delay_ms),
+        ..Default::default()
+    };
+    let batcher = MulticallBatcher::new(provider, multicall_config);
+    prefetch_pools(&store, chain_id, &batcher, &pools).await
+}
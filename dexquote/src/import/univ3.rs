@@ -1,30 +1,31 @@
 use dexquote::{
-    db::{add_pool_from_subgraph, get_pool},
+    db::{add_pool_from_subgraph, get_pool, multicall::get_batcher, RedisStore},
     subgraph,
 };
-use ethers::providers::{Http, Middleware, Provider};
+use ethers::providers::Middleware;
 use indicatif::{ProgressBar, ProgressStyle};
-use neo4rs::Graph;
-use std::{error::Error, sync::Arc, time::Instant};
+use std::{error::Error, time::Instant};
 use tracing::{info, warn};
 
+use super::ticks;
 use crate::config;
 
-pub async fn import_pool(config_name: String) -> Result<(), Box<dyn Error>> {
+pub async fn import_pool(config_name: String, prefetch_ticks: bool) -> Result<(), Box<dyn Error>> {
     let start = Instant::now();
     let conf = config::get_config(config_name);
-    let graph = Graph::new(conf.neo4j_uri, "neo4j", conf.neo4j_pass).await?;
+    let graph = config::connect_neo4j(&conf).await?;
 
-    let redis_client = redis::Client::open(conf.redis_url).unwrap();
+    let store = RedisStore::open(&conf.redis_url)?;
 
-    let provider = Arc::new(Provider::<Http>::try_from(&conf.json_rpc_url).unwrap());
-    let chain_id = provider.get_chainid().await?.as_u64();
+    let batcher = get_batcher(&conf.json_rpc_url)?;
+    let chain_id = batcher.client().get_chainid().await?.as_u64();
 
     let pools = subgraph::get_subgraph_poools(chain_id).await;
 
     let total_pool_num = pools.len();
     let mut err_count = 0;
     let mut already_imported = 0;
+    let mut imported_pools = Vec::new();
     let pb = ProgressBar::new(total_pool_num as u64);
     pb.set_style(
         ProgressStyle::default_bar()
@@ -34,7 +35,7 @@ pub async fn import_pool(config_name: String) -> Result<(), Box<dyn Error>> {
     );
     pb.set_prefix("Importing");
     for pool in pools {
-        let pool_on_redis = get_pool(&redis_client, chain_id, pool.address);
+        let pool_on_redis = get_pool(&store, chain_id, pool.address);
 
         if pool_on_redis.is_err() {
             err_count += 1;
@@ -48,9 +49,11 @@ pub async fn import_pool(config_name: String) -> Result<(), Box<dyn Error>> {
             continue;
         };
 
-        match add_pool_from_subgraph(&redis_client, chain_id, pool, &graph, &conf.chain_label).await
+        let pool_address = pool.address;
+        match add_pool_from_subgraph(&store, chain_id, pool, graph.as_ref(), &conf.chain_label)
+            .await
         {
-            Ok(_) => {}
+            Ok(_) => imported_pools.push(pool_address),
             Err(e) => {
                 err_count += 1;
                 warn!("Error adding pool: {:?}", e);
@@ -70,5 +73,8 @@ pub async fn import_pool(config_name: String) -> Result<(), Box<dyn Error>> {
         elapsed.as_secs(),
     );
 
+    if prefetch_ticks {
+        ticks::prefetch_pools(&store, chain_id, &batcher, &imported_pools).await?;
+    }
     Ok(())
 }
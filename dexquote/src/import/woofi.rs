@@ -0,0 +1,40 @@
+use dexquote::db::{add_woofi_pool, multicall::get_batcher, pool_exists, woofi, RedisStore};
+use ethers::{providers::Middleware, types::Address};
+use std::error::Error;
+use tracing::info;
+
+use crate::config;
+
+/// Import a WooFi pool with the given base tokens, the pool does not list its tokens
+pub async fn import_pool(
+    pool: String,
+    tokens: Vec<String>,
+    config_name: String,
+) -> Result<(), Box<dyn Error>> {
+    let conf = config::get_config(config_name);
+    let graph = config::connect_neo4j(&conf).await?;
+
+    let store = RedisStore::open(&conf.redis_url)?;
+
+    let batcher = get_batcher(&conf.json_rpc_url)?;
+    let chain_id = batcher.client().get_chainid().await?.as_u64();
+
+    let pool_address: Address = pool.parse()?;
+    if pool_exists(&store, chain_id, pool_address)? {
+        info!("Pool {:?} is already imported", pool_address);
+        return Ok(());
+    }
+    let base_tokens = tokens
+        .iter()
+        .map(|token| token.parse())
+        .collect::<Result<Vec<Address>, _>>()?;
+    let pool = woofi::WooFiPool::new_from_address(pool_address, &base_tokens, &batcher).await?;
+    add_woofi_pool(&store, chain_id, pool, graph.as_ref(), &conf.chain_label).await?;
+    info!(
+        "Imported pool {:?} with {} base tokens",
+        pool_address,
+        base_tokens.len()
+    );
+
+    Ok(())
+}
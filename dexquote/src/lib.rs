@@ -1,3 +1,4 @@
+pub mod arb;
 pub mod constants;
 pub mod db;
 pub mod dex;
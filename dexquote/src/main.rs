@@ -41,19 +41,141 @@ enum Commands {
         /// Sync pools before import
         #[clap(short, long, default_value = "false")]
         sync: bool,
+        /// Load all the tick bitmaps and ticks of the imported UniswapV3 pools
+        #[clap(long, default_value = "false")]
+        prefetch_ticks: bool,
     },
     /// Import univ3 pools from subgraph pools
     ImportUniv3 {
         /// Set configuration name
         #[clap(short, long, default_value = "default")]
         name: String,
+        /// Load all the tick bitmaps and ticks of the imported pools
+        #[clap(long, default_value = "false")]
+        prefetch_ticks: bool,
+    },
+    /// Load all the tick bitmaps and ticks of UniswapV3 and Algebra pools with multicall,
+    /// so that they can be quoted offline
+    PrefetchTicks {
+        /// Pool addresses separated by commas, every stored pool if unset
+        #[clap(short, long, value_delimiter = ',')]
+        pools: Vec<String>,
+        /// Calls per multicall request
+        #[clap(long, default_value = "500")]
+        batch_size: usize,
+        /// Multicall requests in flight
+        #[clap(long, default_value = "4")]
+        concurrency: usize,
+        /// Pause after each request, in milliseconds
+        #[clap(long, default_value = "100")]
+        delay_ms: u64,
+        /// Set configuration name
+        #[clap(short, long, default_value = "default")]
+        name: String,
+    },
+    /// Import pools from the Solidly factories of the chain
+    ImportSolidly {
+        /// Set configuration name
+        #[clap(short, long, default_value = "default")]
+        name: String,
+    },
+    /// Import Curve pools by address
+    ImportCurve {
+        /// Pool addresses, separated by commas
+        #[clap(short, long, value_delimiter = ',')]
+        pools: Vec<String>,
+        /// Set configuration name
+        #[clap(short, long, default_value = "default")]
+        name: String,
+    },
+    /// Import Balancer V2 weighted and stable pools by address
+    ImportBalancer {
+        /// Pool addresses, separated by commas
+        #[clap(short, long, value_delimiter = ',')]
+        pools: Vec<String>,
+        /// Set configuration name
+        #[clap(short, long, default_value = "default")]
+        name: String,
+    },
+    /// Import a WooFi pool and its base tokens
+    ImportWoofi {
+        /// Pool address
+        #[clap(short, long)]
+        pool: String,
+        /// Base token addresses, separated by commas
+        #[clap(short, long, value_delimiter = ',')]
+        tokens: Vec<String>,
+        /// Set configuration name
+        #[clap(short, long, default_value = "default")]
+        name: String,
+    },
+    /// Set the fee-on-transfer taxes of a token, in bps
+    SetTokenTax {
+        #[clap(long)]
+        token: String,
+        /// Tax on transfers from a pool
+        #[clap(long, default_value = "0")]
+        buy: u32,
+        /// Tax on transfers to a pool
+        #[clap(long, default_value = "0")]
+        sell: u32,
+        /// Tax on other transfers
+        #[clap(long, default_value = "0")]
+        transfer: u32,
+        /// Set configuration name
+        #[clap(short, long, default_value = "default")]
+        name: String,
+    },
+    /// Detect the taxes of a token by simulating transfers on a local fork (anvil)
+    DetectTokenTax {
+        #[clap(long)]
+        token: String,
+        /// A pool holding the token
+        #[clap(long)]
+        pool: String,
+        /// JSON-RPC url of the fork
+        #[clap(long, default_value = "http://127.0.0.1:8545")]
+        fork_url: String,
+        /// Store the detected taxes
+        #[clap(long, default_value = "false")]
+        save: bool,
+        /// Set configuration name
+        #[clap(short, long, default_value = "default")]
+        name: String,
     },
     /// Update pool states
     Sync {
-        /// Number of threads
+        /// Number of workers, logs are sharded over them by pool address
         /// Default: 4
         #[clap(short, long, default_value = "4")]
         threads: usize,
+        /// Number of recent blocks whose changes are kept to roll back reorgs
+        #[clap(long, default_value = "64")]
+        reorg_depth: u64,
+        /// Backfill logs from this block instead of the block after the last processed one
+        #[clap(long)]
+        from_block: Option<u64>,
+        /// Set configuration name
+        #[clap(short, long, default_value = "default")]
+        name: String,
+        /// Base tokens of the cycles searched for arbitrage after each update, separated by commas
+        #[clap(long, value_delimiter = ',')]
+        arb_tokens: Vec<String>,
+        /// Most pools of an arbitrage cycle
+        #[clap(long, default_value = "3", value_parser = clap::value_parser!(u64).range(2..=4))]
+        arb_hop: u64,
+        /// Most cycles indexed per base token
+        #[clap(long, default_value = "1000")]
+        arb_cycle_limit: u64,
+        /// Where opportunities go: `channel` logs them, `file:<path>` or `redis:<stream>`
+        #[clap(long, default_value = "channel")]
+        arb_sink: String,
+        /// Gas price of the arbitrage transactions in wei
+        #[clap(long, default_value = "100000000")]
+        arb_gas_price: String,
+    },
+    /// Export the pools of the store to Neo4j
+    ExportNeo4j {
         /// Set configuration name
         #[clap(short, long, default_value = "default")]
         name: String,
@@ -72,14 +194,63 @@ enum Commands {
         #[clap(short, long, default_value = "default")]
         name: String,
     },
+    /// Search cycles from and back to a token for arbitrage, net of gas
+    Arb {
+        /// Base token of the cycles, WETH by default
+        #[clap(long, default_value = "0x82aF49447D8a07e3bd95BD0d56f35241523fBab1")]
+        token: String,
+        /// Most pools of a cycle, from 2 to 4
+        #[clap(long, default_value = "3", value_parser = clap::value_parser!(u64).range(2..=4))]
+        hop: u64,
+        #[clap(long, default_value = "100")]
+        cycle_limit: u64,
+        /// Largest input tried, in the base token
+        #[clap(long, default_value = "10000000000000000000")]
+        max_amount_in: String,
+        /// Gas price in wei, read from the node if unset
+        #[clap(long)]
+        gas_price: Option<String>,
+        /// Set configuration name
+        #[clap(short, long, default_value = "default")]
+        name: String,
+        /// Only use cached ticks and tick bitmaps
+        #[clap(long, default_value = "false")]
+        offline: bool,
+        /// Only simulate the negative cycles of the log-price graph
+        #[clap(long, default_value = "false")]
+        screen: bool,
+    },
+    /// Export the liquidity per tick range of a UniswapV3 pool
+    /// and the amounts that move its price by each percentage
+    Depth {
+        #[clap(long)]
+        pool: String,
+        /// Price changes in percent, up and down, separated by commas
+        #[clap(long, value_delimiter = ',', default_value = "0.5,1,2,5,10")]
+        percents: Vec<f64>,
+        #[clap(long, default_value = "json", value_parser = ["json", "csv"])]
+        format: String,
+        /// Output path without extension, printed if unset
+        #[clap(short, long)]
+        output: Option<String>,
+        /// Set configuration name
+        #[clap(short, long, default_value = "default")]
+        name: String,
+        /// Only use cached ticks and tick bitmaps
+        #[clap(long, default_value = "false")]
+        offline: bool,
+    },
     /// Quote prices
     Quote {
         #[clap(long)]
         token_in: String,
         #[clap(long)]
         token_out: String,
-        #[clap(short, long)]
-        amount_in: String,
+        #[clap(short, long, required_unless_present = "amount_out")]
+        amount_in: Option<String>,
+        /// Quote the required input for an exact output instead
+        #[clap(long, conflicts_with = "amount_in")]
+        amount_out: Option<String>,
         #[clap(long, default_value = "1")]
         hop: u64,
         #[clap(short, long, default_value = "5")]
@@ -87,6 +258,12 @@ enum Commands {
         /// Set configuration name
         #[clap(short, long, default_value = "default")]
         name: String,
+        /// Only use cached ticks and tick bitmaps, skipping paths with missing data
+        #[clap(long, default_value = "false")]
+        offline: bool,
+        /// Split the input across the paths in this many equal parts
+        #[clap(long, conflicts_with = "amount_out")]
+        split: Option<usize>,
     },
 }
 
@@ -103,15 +280,83 @@ async fn main() -> Result<(), Box<dyn Error>> {
         Commands::Checkpoint { name, path } => {
             checkpoint::create_checkpoint(name, path).await?;
         }
-        Commands::Import { name, path, sync } => {
-            import::import_pool(name, path, sync).await?;
+        Commands::Import {
+            name,
+            path,
+            sync,
+            prefetch_ticks,
+        } => {
+            import::import_pool(name, path, sync, prefetch_ticks).await?;
+        }
+        Commands::ImportUniv3 {
+            name,
+            prefetch_ticks,
+        } => {
+            import::univ3::import_pool(name, prefetch_ticks).await?;
+        }
+        Commands::PrefetchTicks {
+            pools,
+            batch_size,
+            concurrency,
+            delay_ms,
+            name,
+        } => {
+            import::ticks::prefetch(pools, batch_size, concurrency, delay_ms, name).await?;
+        }
+        Commands::ImportSolidly { name } => {
+            import::solidly::import_pool(name).await?;
+        }
+        Commands::ImportCurve { pools, name } => {
+            import::curve::import_pool(pools, name).await?;
+        }
+        Commands::ImportBalancer { pools, name } => {
+            import::balancer::import_pool(pools, name).await?;
+        }
+        Commands::ImportWoofi { pool, tokens, name } => {
+            import::woofi::import_pool(pool, tokens, name).await?;
+        }
+        Commands::SetTokenTax {
+            token,
+            buy,
+            sell,
+            transfer,
+            name,
+        } => {
+            cli::token::set_token_tax(token, buy, sell, transfer, name).await?;
         }
-        Commands::ImportUniv3 { name } => {
-            import::univ3::import_pool(name).await?;
+        Commands::DetectTokenTax {
+            token,
+            pool,
+            fork_url,
+            save,
+            name,
+        } => {
+            cli::token::detect_tax(token, pool, fork_url, save, name).await?;
         }
-        Commands::Sync { threads, name } => {
-            sync::update_pool_states(threads, name).await?;
+        Commands::Sync {
+            threads,
+            reorg_depth,
+            from_block,
+            name,
+            arb_tokens,
+            arb_hop,
+            arb_cycle_limit,
+            arb_sink,
+            arb_gas_price,
+        } => {
+            let arb = match arb_tokens.is_empty() {
+                true => None,
+                false => Some(sync::ArbOptions {
+                    tokens: arb_tokens,
+                    max_hops: arb_hop,
+                    cycle_limit: arb_cycle_limit,
+                    sink: arb_sink,
+                    gas_price: arb_gas_price,
+                }),
+            };
+            sync::update_pool_states(threads, reorg_depth, from_block, name, arb).await?;
         }
+        Commands::ExportNeo4j { name } => cli::path::export_neo4j(name).await,
         Commands::Path {
             token_in,
             token_out,
@@ -119,24 +364,87 @@ async fn main() -> Result<(), Box<dyn Error>> {
             path_result_limit,
             name,
         } => cli::path::show_paths(token_in, token_out, hop, path_result_limit, name).await,
-        Commands::Quote {
-            token_in,
-            token_out,
-            amount_in,
+        Commands::Arb {
+            token,
             hop,
-            path_result_limit,
+            cycle_limit,
+            max_amount_in,
+            gas_price,
             name,
+            offline,
+            screen,
         } => {
-            cli::path::show_best_prices(
-                token_in,
-                token_out,
+            cli::arb::show_opportunities(
+                token,
                 hop,
-                path_result_limit,
-                amount_in,
+                cycle_limit,
+                max_amount_in,
+                gas_price,
                 name,
+                offline,
+                screen,
             )
-            .await
+            .await?;
         }
+        Commands::Depth {
+            pool,
+            percents,
+            format,
+            output,
+            name,
+            offline,
+        } => {
+            cli::depth::export_depth(pool, percents, format, output, name, offline).await?;
+        }
+        Commands::Quote {
+            token_in,
+            token_out,
+            amount_in,
+            amount_out,
+            hop,
+            path_result_limit,
+            name,
+            offline,
+            split,
+        } => match (amount_out, split) {
+            (Some(amount_out), _) => {
+                cli::path::show_best_prices_for_amount_out(
+                    token_in,
+                    token_out,
+                    hop,
+                    path_result_limit,
+                    amount_out,
+                    name,
+                    offline,
+                )
+                .await
+            }
+            (None, Some(slices)) => {
+                cli::path::show_split_route(
+                    token_in,
+                    token_out,
+                    hop,
+                    path_result_limit,
+                    amount_in.unwrap(),
+                    slices,
+                    name,
+                    offline,
+                )
+                .await
+            }
+            (None, None) => {
+                cli::path::show_best_prices(
+                    token_in,
+                    token_out,
+                    hop,
+                    path_result_limit,
+                    amount_in.unwrap(),
+                    name,
+                    offline,
+                )
+                .await
+            }
+        },
     }
 
     Ok(())
@@ -0,0 +1,261 @@
+use std::collections::{HashMap, HashSet};
+
+use ethers::types::Address;
+
+use super::PoolInfo;
+use crate::{
+    db::PoolStore,
+    graph::{PoolEdge, RoutingGraph},
+    types::DexQuoteResult,
+    utils::u256_to_f64,
+};
+
+// Relaxations smaller than this are rounding noise of the logarithms
+const EPSILON: f64 = 1e-12;
+
+/// -ln(marginal price × (1 - fee)) of a swap, from the spot reserves of the edge.
+/// The weights of a cycle sum below zero when it returns more than it takes at the margin.
+/// None if the edge has no reserves.
+pub fn log_price_weight(edge: &PoolEdge) -> Option<f64> {
+    if edge.reserve_in.is_zero() || edge.reserve_out.is_zero() || edge.fee >= 1_000_000 {
+        return None;
+    }
+    let fee = (1.0 - edge.fee as f64 / 1e6).ln();
+    Some(u256_to_f64(edge.reserve_in).ln() - u256_to_f64(edge.reserve_out).ln() - fee)
+}
+
+/// A cycle of the log-price graph whose weights sum below zero
+#[derive(Clone, Debug)]
+pub struct NegativeCycle {
+    pub cycle: Vec<PoolInfo>,
+    pub weight: f64,
+}
+
+impl NegativeCycle {
+    /// Output per input of the cycle for an infinitesimal amount, above 1
+    pub fn rate(&self) -> f64 {
+        (-self.weight).exp()
+    }
+
+    /// The cycle from and back to `token`, None if it does not pass the token
+    pub fn starting_at(&self, token: Address) -> Option<Vec<PoolInfo>> {
+        let start = self.cycle.iter().position(|hop| hop.token_in == token)?;
+        let mut cycle = self.cycle[start..].to_vec();
+        cycle.extend_from_slice(&self.cycle[..start]);
+        Some(cycle)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct WeightedEdge {
+    edge: PoolEdge,
+    from: usize,
+    to: usize,
+    weight: f64,
+}
+
+/// Edges of the routing graph weighted by `log_price_weight`, a cheap screen for arbitrage.
+/// Only the negative cycles it finds need the exact simulation of their swaps.
+#[derive(Clone, Debug, Default)]
+pub struct LogPriceGraph {
+    tokens: Vec<Address>,
+    edges: Vec<WeightedEdge>,
+}
+
+impl LogPriceGraph {
+    pub fn from_routing_graph(graph: &RoutingGraph) -> Self {
+        let mut token_indexes: HashMap<Address, usize> = HashMap::new();
+        let mut tokens = Vec::new();
+        let mut index = |token: Address| {
+            *token_indexes.entry(token).or_insert_with(|| {
+                tokens.push(token);
+                tokens.len() - 1
+            })
+        };
+        let mut edges = Vec::new();
+        for token in graph.tokens() {
+            for edge in graph.edges(token) {
+                if let Some(weight) = log_price_weight(edge) {
+                    edges.push(WeightedEdge {
+                        edge: *edge,
+                        from: index(edge.token_in),
+                        to: index(edge.token_out),
+                        weight,
+                    });
+                }
+            }
+        }
+        Self { tokens, edges }
+    }
+
+    /// Build the graph from every pool of the dex sets of a chain
+    pub fn from_store<S: PoolStore>(store: &S, chain_id: u64) -> DexQuoteResult<Self> {
+        Ok(Self::from_routing_graph(&RoutingGraph::from_store(
+            store, chain_id,
+        )?))
+    }
+
+    pub fn token_count(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Negative cycles found by Bellman-Ford from every token at once.
+    /// The rounds stop as soon as the predecessors of the tokens form cycles, so the result
+    /// is a set of candidates rather than every negative cycle of the graph.
+    /// A cycle passes a token and uses a pool at most once.
+    pub fn find_negative_cycles(&self) -> Vec<NegativeCycle> {
+        let token_count = self.tokens.len();
+        // as if a virtual source had a free edge to every token
+        let mut distances = vec![0.0; token_count];
+        // last edge that relaxed each token
+        let mut predecessors: Vec<Option<usize>> = vec![None; token_count];
+        for _ in 0..token_count {
+            let mut relaxed = false;
+            for (i, edge) in self.edges.iter().enumerate() {
+                let distance = distances[edge.from] + edge.weight;
+                if distance < distances[edge.to] - EPSILON {
+                    distances[edge.to] = distance;
+                    predecessors[edge.to] = Some(i);
+                    relaxed = true;
+                }
+            }
+            if !relaxed {
+                break;
+            }
+            let cycles = self.predecessor_cycles(&predecessors);
+            if !cycles.is_empty() {
+                return cycles;
+            }
+        }
+        Vec::new()
+    }
+
+    // Cycles of the predecessor graph, each token has at most one predecessor
+    // so every walk back from a token ends at a token without one or enters a cycle
+    fn predecessor_cycles(&self, predecessors: &[Option<usize>]) -> Vec<NegativeCycle> {
+        // first token of the walk that visited each token
+        let mut visited: Vec<Option<usize>> = vec![None; self.tokens.len()];
+        let mut cycles = Vec::new();
+        for start in 0..self.tokens.len() {
+            let mut token = start;
+            loop {
+                if let Some(walk) = visited[token] {
+                    if walk == start {
+                        cycles.extend(self.get_cycle(token, predecessors));
+                    }
+                    break;
+                }
+                visited[token] = Some(start);
+                match predecessors[token] {
+                    Some(edge) => token = self.edges[edge].from,
+                    None => break,
+                }
+            }
+        }
+        cycles
+    }
+
+    // the predecessor cycle through a token, in swap order
+    fn get_cycle(&self, token: usize, predecessors: &[Option<usize>]) -> Option<NegativeCycle> {
+        let mut edges = Vec::new();
+        let mut current = token;
+        loop {
+            let edge = &self.edges[predecessors[current]?];
+            edges.push(edge);
+            current = edge.from;
+            if current == token {
+                break;
+            }
+        }
+        edges.reverse();
+        let pools: HashSet<Address> = edges.iter().map(|edge| edge.edge.pool).collect();
+        let weight: f64 = edges.iter().map(|edge| edge.weight).sum();
+        if pools.len() < edges.len() || weight >= -EPSILON {
+            return None;
+        }
+        Some(NegativeCycle {
+            cycle: edges
+                .iter()
+                .map(|edge| PoolInfo::from(&edge.edge))
+                .collect(),
+            weight,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{univ2::add_pool, MemoryStore};
+    use cfmms::pool::UniswapV2Pool;
+
+    const CHAIN_ID: u64 = 42161;
+
+    fn token(n: u64) -> Address {
+        Address::from_low_u64_be(n)
+    }
+
+    fn add_univ2_pool(store: &MemoryStore, address: u64, tokens: [u64; 2], reserves: [u128; 2]) {
+        add_pool(
+            store,
+            CHAIN_ID,
+            UniswapV2Pool {
+                address: token(address),
+                token_a: token(tokens[0]),
+                token_a_decimals: 18,
+                token_b: token(tokens[1]),
+                token_b_decimals: 18,
+                reserve_0: reserves[0],
+                reserve_1: reserves[1],
+                fee: 300,
+            },
+        )
+        .unwrap();
+        store
+            .add_dex_pool(CHAIN_ID, "UNIV2", token(address))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_find_negative_cycles() {
+        let store = MemoryStore::new();
+        let reserve = 1_000_000_000_000_000_000_000_000;
+        add_univ2_pool(&store, 100, [1, 2], [reserve, reserve]);
+        add_univ2_pool(&store, 101, [2, 3], [reserve, reserve]);
+        add_univ2_pool(&store, 102, [1, 3], [reserve, reserve]);
+        add_univ2_pool(&store, 103, [3, 4], [reserve, reserve * 2]);
+
+        // consistent prices, the fees make every cycle lose
+        let graph = LogPriceGraph::from_store(&store, CHAIN_ID).unwrap();
+        assert_eq!(graph.token_count(), 4);
+        assert_eq!(graph.edge_count(), 8);
+        assert!(graph.find_negative_cycles().is_empty());
+
+        // token 1 is 10% cheaper in the third pool
+        add_univ2_pool(&store, 102, [1, 3], [reserve * 11 / 10, reserve]);
+        let graph = LogPriceGraph::from_store(&store, CHAIN_ID).unwrap();
+        let cycles = graph.find_negative_cycles();
+        assert_eq!(cycles.len(), 1);
+        let expected = 1.1 * 0.997_f64.powi(3);
+        assert!((cycles[0].rate() - expected).abs() < 1e-9);
+
+        let cycle = cycles[0].starting_at(token(1)).unwrap();
+        let hops: Vec<(Address, Address, Address)> = cycle
+            .iter()
+            .map(|hop| (hop.address, hop.token_in, hop.token_out))
+            .collect();
+        assert_eq!(
+            hops,
+            vec![
+                (token(100), token(1), token(2)),
+                (token(101), token(2), token(3)),
+                (token(102), token(3), token(1)),
+            ]
+        );
+        assert!(cycles[0].starting_at(token(4)).is_none());
+    }
+}
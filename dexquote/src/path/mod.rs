@@ -1,18 +1,34 @@
 use ethers::types::Address;
+mod cycle;
+pub use cycle::{log_price_weight, LogPriceGraph, NegativeCycle};
 mod price;
 use neo4rs::{query, Graph, Path};
-pub use price::get_amount_out_from_path;
+pub use price::{
+    get_amount_in_from_path, get_amount_out_from_path, get_quote_price_from_path,
+    get_spot_price_from_path,
+};
+mod split;
 use serde_derive::{Deserialize, Serialize};
+pub use split::{get_split_amount_out, SplitQuote, SplitRoute};
 
-use crate::{subgraph::SubgraphPool, utils::address_str};
+use crate::{dex::Dex, subgraph::SubgraphPool, utils::address_str};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PoolInfo {
     pub address: Address,
     pub token_in: Address,
     pub token_out: Address,
+    // unknown on paths from the Neo4j export
+    #[serde(default)]
+    pub dex: Option<Dex>,
+    /// fee of the swap in 1e-6, as `graph::PoolEdge::fee`
+    #[serde(default)]
+    pub fee: Option<u32>,
 }
 
+/// Paths from the Neo4j export, in no particular order.
+/// `graph::RoutingGraph::get_paths` finds them without Neo4j
+/// and `graph::RoutingGraph::get_ranked_paths` ranks them by liquidity and fee.
 pub async fn get_possible_paths(
     graph: &Graph,
     token_in: Address,
@@ -49,6 +65,8 @@ pub async fn get_possible_paths(
                 address: pool_address,
                 token_in: token_in.parse().unwrap(),
                 token_out: token_out.parse().unwrap(),
+                dex: None,
+                fee: None,
             };
             route.push(pool);
         }
@@ -1,26 +1,281 @@
 use super::PoolInfo;
-use crate::price;
+use crate::{
+    db::{
+        token::{after_tax, before_tax, get_token_tax},
+        PoolStore,
+    },
+    error::DexQuoteError,
+    price::{self, QuotePrice},
+    types::DexQuoteResult,
+};
 use ethers::types::U256;
 
-pub async fn get_amount_out_from_path(
-    redis_url: &str,
+// Fee-on-transfer taxes are applied to every transfer of the route: the input sent to the
+// first pool pays the sell tax of token_in, and each pool output, whether it goes to the
+// next pool or to the recipient, pays the buy tax of its token.
+// The transfer tax is only charged between other accounts, so no transfer of the route pays it.
+// Pools are assumed to swap what they received, as with the
+// `...SupportingFeeOnTransferTokens` router functions.
+
+pub async fn get_amount_out_from_path<S: PoolStore>(
+    store: &S,
     chain_id: u64,
     json_rpc_url: &str,
     amount_in: U256,
     path: &[PoolInfo],
+    offline: bool,
 ) -> Result<U256, Box<dyn std::error::Error>> {
     let mut estimated_amount_out = amount_in;
+    if let Some(first) = path.first() {
+        let tax = get_token_tax(store, chain_id, first.token_in)?;
+        estimated_amount_out = after_tax(estimated_amount_out, tax.sell);
+    }
     for route in path {
         estimated_amount_out = price::get_price(
-            redis_url,
+            store,
             chain_id,
             json_rpc_url.to_string(),
             route.address,
             route.token_in,
             route.token_out,
             estimated_amount_out,
+            offline,
         )
         .await?;
+        let tax = get_token_tax(store, chain_id, route.token_out)?;
+        estimated_amount_out = after_tax(estimated_amount_out, tax.buy);
     }
     Ok(estimated_amount_out)
 }
+
+/// Required input for the path to return exactly `amount_out`.
+/// The path is evaluated backwards, from the last hop to the first.
+pub async fn get_amount_in_from_path<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    json_rpc_url: &str,
+    amount_out: U256,
+    path: &[PoolInfo],
+    offline: bool,
+) -> Result<U256, Box<dyn std::error::Error>> {
+    let mut estimated_amount_in = amount_out;
+    for route in path.iter().rev() {
+        let tax = get_token_tax(store, chain_id, route.token_out)?;
+        estimated_amount_in = before_tax(estimated_amount_in, tax.buy);
+        estimated_amount_in = price::get_amount_in(
+            store,
+            chain_id,
+            json_rpc_url.to_string(),
+            route.address,
+            route.token_in,
+            route.token_out,
+            estimated_amount_in,
+            offline,
+        )
+        .await?;
+    }
+    if let Some(first) = path.first() {
+        let tax = get_token_tax(store, chain_id, first.token_in)?;
+        estimated_amount_in = before_tax(estimated_amount_in, tax.sell);
+    }
+    Ok(estimated_amount_in)
+}
+
+/// Spot price of a path in whole `token_out` per whole `token_in`, before fees and taxes.
+/// It is the product of the spot prices of its pools.
+pub fn get_spot_price_from_path<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    path: &[PoolInfo],
+) -> DexQuoteResult<f64> {
+    path.iter().try_fold(1.0, |price, route| {
+        Ok(price
+            * price::get_spot_price(
+                store,
+                chain_id,
+                route.address,
+                route.token_in,
+                route.token_out,
+            )?)
+    })
+}
+
+/// Spot price, execution price and price impact of a quote of the path
+pub fn get_quote_price_from_path<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    path: &[PoolInfo],
+    amount_in: U256,
+    amount_out: U256,
+) -> DexQuoteResult<QuotePrice> {
+    let (first, last) = match (path.first(), path.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Err(DexQuoteError::NoRoute),
+    };
+    let first_data = store.get_pool_hashmap(chain_id, first.address)?;
+    let last_data = store.get_pool_hashmap(chain_id, last.address)?;
+    let decimals_in = price::get_token_decimals(store, chain_id, &first_data, first.token_in)?;
+    let decimals_out = price::get_token_decimals(store, chain_id, &last_data, last.token_out)?;
+    Ok(QuotePrice::new(
+        get_spot_price_from_path(store, chain_id, path)?,
+        price::get_execution_price(amount_in, amount_out, decimals_in, decimals_out),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{
+        token::{TokenInfo, TokenTax},
+        univ2::add_pool,
+        MemoryStore,
+    };
+    use cfmms::pool::UniswapV2Pool;
+    use ethers::types::Address;
+
+    const CHAIN_ID: u64 = 42161;
+
+    #[tokio::test]
+    async fn test_get_amount_from_path_with_tax() {
+        let store = MemoryStore::new();
+        let token_a = Address::from_low_u64_be(1);
+        let token_b = Address::from_low_u64_be(2);
+        let token_c = Address::from_low_u64_be(3);
+        let pool_ab = Address::from_low_u64_be(10);
+        let pool_bc = Address::from_low_u64_be(11);
+        for (address, token_a, token_b) in
+            [(pool_ab, token_a, token_b), (pool_bc, token_b, token_c)]
+        {
+            add_pool(
+                &store,
+                CHAIN_ID,
+                UniswapV2Pool {
+                    address,
+                    token_a,
+                    token_a_decimals: 18,
+                    token_b,
+                    token_b_decimals: 18,
+                    reserve_0: 1_000_000_000_000_000_000_000,
+                    reserve_1: 1_000_000_000_000_000_000_000,
+                    fee: 300,
+                },
+            )
+            .unwrap();
+        }
+        let path = vec![
+            PoolInfo {
+                address: pool_ab,
+                token_in: token_a,
+                token_out: token_b,
+                dex: None,
+                fee: None,
+            },
+            PoolInfo {
+                address: pool_bc,
+                token_in: token_b,
+                token_out: token_c,
+                dex: None,
+                fee: None,
+            },
+        ];
+        let amount_in = U256::exp10(18);
+        let untaxed = get_amount_out_from_path(&store, CHAIN_ID, "", amount_in, &path, true)
+            .await
+            .unwrap();
+
+        store
+            .add_token(
+                CHAIN_ID,
+                TokenInfo {
+                    address: token_b,
+                    symbol: "TAX".to_string(),
+                    decimals: 18,
+                    scam: 0,
+                    tax: TokenTax::default(),
+                },
+            )
+            .unwrap();
+        let tax = TokenTax {
+            buy: 500,
+            sell: 300,
+            transfer: 100,
+        };
+        store.update_token_tax(CHAIN_ID, token_b, tax).unwrap();
+
+        // the 5% buy tax of token_b is paid when the first pool sends it to the second
+        let amount_out = get_amount_out_from_path(&store, CHAIN_ID, "", amount_in, &path, true)
+            .await
+            .unwrap();
+        let first_hop = price::get_price(
+            &store,
+            CHAIN_ID,
+            "".to_string(),
+            pool_ab,
+            token_a,
+            token_b,
+            amount_in,
+            true,
+        )
+        .await
+        .unwrap();
+        let expected = price::get_price(
+            &store,
+            CHAIN_ID,
+            "".to_string(),
+            pool_bc,
+            token_b,
+            token_c,
+            after_tax(first_hop, tax.buy),
+            true,
+        )
+        .await
+        .unwrap();
+        assert_eq!(amount_out, expected);
+        assert!(amount_out < untaxed);
+
+        // the required input buys at least the requested output through the tax
+        let amount_in_back = get_amount_in_from_path(&store, CHAIN_ID, "", amount_out, &path, true)
+            .await
+            .unwrap();
+        let amount_out_check =
+            get_amount_out_from_path(&store, CHAIN_ID, "", amount_in_back, &path, true)
+                .await
+                .unwrap();
+        assert!(amount_out_check >= amount_out);
+        assert!(amount_in_back <= amount_in + 2);
+
+        // the taxes are part of the price impact, not of the spot price
+        let quote_price =
+            get_quote_price_from_path(&store, CHAIN_ID, &path, amount_in, amount_out).unwrap();
+        assert!((quote_price.mid_price - 1.0).abs() < 1e-12);
+        assert!(quote_price.price_impact > 0.05);
+        let untaxed_price =
+            get_quote_price_from_path(&store, CHAIN_ID, &path, amount_in, untaxed).unwrap();
+        assert!(untaxed_price.price_impact < quote_price.price_impact);
+
+        // selling token_b into the route pays its sell tax
+        let reverse = vec![PoolInfo {
+            address: pool_bc,
+            token_in: token_b,
+            token_out: token_c,
+            dex: None,
+            fee: None,
+        }];
+        let amount_out = get_amount_out_from_path(&store, CHAIN_ID, "", amount_in, &reverse, true)
+            .await
+            .unwrap();
+        let expected = price::get_price(
+            &store,
+            CHAIN_ID,
+            "".to_string(),
+            pool_bc,
+            token_b,
+            token_c,
+            after_tax(amount_in, tax.sell),
+            true,
+        )
+        .await
+        .unwrap();
+        assert_eq!(amount_out, expected);
+    }
+}
@@ -0,0 +1,521 @@
+use std::{cmp::Reverse, collections::HashMap, str::FromStr};
+
+use super::PoolInfo;
+use crate::{
+    db::{
+        balancer, curve, solidly,
+        token::{after_tax, get_token_tax},
+        univ2, woofi, MemoryStore, PoolStore,
+    },
+    dex::Dex,
+    error::DexQuoteError,
+    event::{balancer::BalancerSwapEvent, curve::CurveTokenExchangeEvent, woofi::WooFiSwapEvent},
+    price::{
+        self,
+        uni_v3::{to_dex_quote_error, PoolState},
+        woofi::try_query,
+    },
+    types::DexQuoteResult,
+};
+use cfmms::pool::Pool;
+use ethers::types::{Address, I256, U256};
+
+/// A path of a split route and the part of the input sent through it
+#[derive(Clone, Debug)]
+pub struct SplitRoute {
+    pub path: Vec<PoolInfo>,
+    pub amount_in: U256,
+    pub amount_out: U256,
+}
+
+#[derive(Clone, Debug)]
+pub struct SplitQuote {
+    pub amount_in: U256,
+    pub amount_out: U256,
+    /// paths that received a part of the input, the largest part first
+    pub routes: Vec<SplitRoute>,
+}
+
+/// Split `amount_in` across `paths` to maximise the total output, as the greedy split of
+/// Uniswap's alpha router. The input is sent in `slices` equal parts, each through the path
+/// that returns the most for it after the parts already sent, so pools shared by paths are
+/// quoted from the state the previous parts left.
+pub async fn get_split_amount_out<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    json_rpc_url: &str,
+    amount_in: U256,
+    paths: &[Vec<PoolInfo>],
+    slices: usize,
+    offline: bool,
+) -> DexQuoteResult<SplitQuote> {
+    let mut simulation = SplitSimulation::new(store, chain_id, json_rpc_url, offline);
+    let slices = slices.max(1);
+    let slice = amount_in / slices;
+    let mut amounts = vec![(U256::zero(), U256::zero()); paths.len()];
+    for k in 0..slices {
+        // the last part gets the remainder of the division
+        let amount = if k == slices - 1 {
+            amount_in - slice * (slices - 1)
+        } else {
+            slice
+        };
+        if amount.is_zero() {
+            continue;
+        }
+        let mut best: Option<(usize, U256)> = None;
+        for (i, path) in paths.iter().enumerate() {
+            if let Ok(amount_out) = simulation.quote(path, amount).await {
+                if best.is_none_or(|(_, best_amount_out)| amount_out > best_amount_out) {
+                    best = Some((i, amount_out));
+                }
+            }
+        }
+        let (i, _) = best.ok_or(DexQuoteError::NoRoute)?;
+        let amount_out = simulation.swap(&paths[i], amount).await?;
+        amounts[i].0 += amount;
+        amounts[i].1 += amount_out;
+    }
+
+    let mut routes: Vec<SplitRoute> = paths
+        .iter()
+        .zip(amounts)
+        .filter(|(_, (amount_in, _))| !amount_in.is_zero())
+        .map(|(path, (amount_in, amount_out))| SplitRoute {
+            path: path.clone(),
+            amount_in,
+            amount_out,
+        })
+        .collect();
+    routes.sort_by_key(|route| Reverse(route.amount_in));
+    Ok(SplitQuote {
+        amount_in,
+        amount_out: routes
+            .iter()
+            .fold(U256::zero(), |total, route| total + route.amount_out),
+        routes,
+    })
+}
+
+// Pool states after the swaps of the parts already routed. Concentrated pools are moved with
+// `PoolState::update_state`, the other pools are copied to a scratch store and moved by
+// replaying the swaps as the pool events `sync` applies.
+struct SplitSimulation<'a, S: PoolStore> {
+    store: &'a S,
+    chain_id: u64,
+    json_rpc_url: &'a str,
+    offline: bool,
+    loaded: HashMap<Address, Dex>,
+    concentrated: HashMap<Address, PoolState<'a, S>>,
+    scratch: MemoryStore,
+}
+
+impl<'a, S: PoolStore> SplitSimulation<'a, S> {
+    fn new(store: &'a S, chain_id: u64, json_rpc_url: &'a str, offline: bool) -> Self {
+        Self {
+            store,
+            chain_id,
+            json_rpc_url,
+            offline,
+            loaded: HashMap::new(),
+            concentrated: HashMap::new(),
+            scratch: MemoryStore::new(),
+        }
+    }
+
+    // output of the path for `amount_in`, taxes included as `get_amount_out_from_path`
+    async fn quote(&mut self, path: &[PoolInfo], amount_in: U256) -> DexQuoteResult<U256> {
+        self.simulate(path, amount_in, false).await
+    }
+
+    // as `quote`, and moves the pools of the path
+    async fn swap(&mut self, path: &[PoolInfo], amount_in: U256) -> DexQuoteResult<U256> {
+        self.simulate(path, amount_in, true).await
+    }
+
+    async fn simulate(
+        &mut self,
+        path: &[PoolInfo],
+        amount_in: U256,
+        update: bool,
+    ) -> DexQuoteResult<U256> {
+        let mut amount = amount_in;
+        if let Some(first) = path.first() {
+            let tax = get_token_tax(self.store, self.chain_id, first.token_in)?;
+            amount = after_tax(amount, tax.sell);
+        }
+        for hop in path {
+            amount = self.simulate_hop(hop, amount, update).await?;
+            let tax = get_token_tax(self.store, self.chain_id, hop.token_out)?;
+            amount = after_tax(amount, tax.buy);
+        }
+        Ok(amount)
+    }
+
+    async fn simulate_hop(
+        &mut self,
+        hop: &PoolInfo,
+        amount_in: U256,
+        update: bool,
+    ) -> DexQuoteResult<U256> {
+        let dex = self.load(hop.address)?;
+        let zero_for_one = hop.token_in < hop.token_out;
+        if let Some(pool_state) = self.concentrated.get_mut(&hop.address) {
+            let amount_specified = I256::from_raw(amount_in);
+            return if update {
+                pool_state
+                    .update_state(amount_specified, zero_for_one)
+                    .await
+                    .map_err(to_dex_quote_error)
+            } else {
+                let (amount_out, _, _, _) = pool_state
+                    .get_price(amount_specified, zero_for_one)
+                    .await
+                    .map_err(to_dex_quote_error)?;
+                Ok(amount_out)
+            };
+        }
+        let amount_out = price::get_price(
+            &self.scratch,
+            self.chain_id,
+            self.json_rpc_url.to_string(),
+            hop.address,
+            hop.token_in,
+            hop.token_out,
+            amount_in,
+            self.offline,
+        )
+        .await?;
+        if update {
+            self.apply_swap(dex, hop, amount_in, amount_out)?;
+        }
+        Ok(amount_out)
+    }
+
+    // Read a pool on its first use, concentrated pools into a `PoolState`
+    // and the others into the scratch store, with the base pool of a Curve meta pool
+    fn load(&mut self, pool_address: Address) -> DexQuoteResult<Dex> {
+        if let Some(dex) = self.loaded.get(&pool_address) {
+            return Ok(*dex);
+        }
+        let target_data = self.store.get_pool_hashmap(self.chain_id, pool_address)?;
+        let dex_string = target_data
+            .get("dex")
+            .ok_or(DexQuoteError::PoolNotFound(pool_address))?;
+        let dex = Dex::from_str(dex_string)
+            .map_err(|_| DexQuoteError::InvalidDex(dex_string.to_string()))?;
+        match dex {
+            Dex::UniswapV3 | Dex::Algebra => {
+                let pool_state = PoolState::init_with_hashmap(
+                    self.store,
+                    self.chain_id,
+                    self.json_rpc_url.to_string(),
+                    pool_address,
+                    target_data,
+                    self.offline,
+                )?
+                .ok_or(DexQuoteError::PoolNotFound(pool_address))?;
+                self.concentrated.insert(pool_address, pool_state);
+            }
+            _ => {
+                if let Some(base_pool) = target_data.get("base_pool") {
+                    let base_pool = base_pool
+                        .parse()
+                        .map_err(|_| DexQuoteError::PoolNotFound(pool_address))?;
+                    self.copy_pool(base_pool)?;
+                }
+                self.copy_pool(pool_address)?;
+            }
+        }
+        self.loaded.insert(pool_address, dex);
+        Ok(dex)
+    }
+
+    // a pool already copied keeps the swaps applied to it
+    fn copy_pool(&self, pool_address: Address) -> DexQuoteResult<()> {
+        if !self
+            .scratch
+            .get_pool_hashmap(self.chain_id, pool_address)?
+            .is_empty()
+        {
+            return Ok(());
+        }
+        let target_data = self.store.get_pool_hashmap(self.chain_id, pool_address)?;
+        let fields: Vec<(&str, String)> = target_data
+            .iter()
+            .map(|(field, value)| (field.as_str(), value.clone()))
+            .collect();
+        self.scratch
+            .update_pool_hashmap(self.chain_id, pool_address, &fields)
+    }
+
+    fn apply_swap(
+        &self,
+        dex: Dex,
+        hop: &PoolInfo,
+        amount_in: U256,
+        amount_out: U256,
+    ) -> DexQuoteResult<()> {
+        let (scratch, chain_id, pool_address) = (&self.scratch, self.chain_id, hop.address);
+        match dex {
+            Dex::UniswapV2 | Dex::Solidly => {
+                let (reserve_0, reserve_1) = match dex {
+                    Dex::Solidly => {
+                        let pool = solidly::get_pool(scratch, chain_id, pool_address)?
+                            .ok_or(DexQuoteError::PoolNotFound(pool_address))?;
+                        (pool.reserve_0, pool.reserve_1)
+                    }
+                    _ => match univ2::get_pool(scratch, chain_id, pool_address)? {
+                        Some(Pool::UniswapV2(pool)) => (pool.reserve_0, pool.reserve_1),
+                        _ => return Err(DexQuoteError::PoolNotFound(pool_address)),
+                    },
+                };
+                let to_u128 = |amount: U256| {
+                    u128::try_from(amount)
+                        .map_err(|_| DexQuoteError::MathError("reserve overflow".to_string()))
+                };
+                let (amount_in, amount_out) = (to_u128(amount_in)?, to_u128(amount_out)?);
+                let add = |reserve: u128| {
+                    reserve
+                        .checked_add(amount_in)
+                        .ok_or_else(|| DexQuoteError::MathError("reserve overflow".to_string()))
+                };
+                let sub = |reserve: u128| {
+                    reserve
+                        .checked_sub(amount_out)
+                        .ok_or(DexQuoteError::InsufficientLiquidity(pool_address))
+                };
+                let (reserve_0, reserve_1) = if hop.token_in < hop.token_out {
+                    (add(reserve_0)?, sub(reserve_1)?)
+                } else {
+                    (sub(reserve_0)?, add(reserve_1)?)
+                };
+                univ2::update_pool(scratch, chain_id, pool_address, reserve_0, reserve_1)?;
+            }
+            Dex::Curve => {
+                let pool = curve::get_pool(scratch, chain_id, pool_address)?
+                    .ok_or(DexQuoteError::PoolNotFound(pool_address))?;
+                let index = |token| pool.coins.iter().position(|coin| *coin == token);
+                if let (Some(sold_id), Some(bought_id)) =
+                    (index(hop.token_in), index(hop.token_out))
+                {
+                    crate::event::curve::update_with_token_exchange_event(
+                        scratch,
+                        chain_id,
+                        pool_address,
+                        &CurveTokenExchangeEvent {
+                            sold_id,
+                            tokens_sold: amount_in,
+                            bought_id,
+                            tokens_bought: amount_out,
+                        },
+                    );
+                }
+            }
+            Dex::BalancerV2 => {
+                let pool = balancer::get_pool(scratch, chain_id, pool_address)?
+                    .ok_or(DexQuoteError::PoolNotFound(pool_address))?;
+                crate::event::balancer::update_with_swap_event(
+                    scratch,
+                    chain_id,
+                    &BalancerSwapEvent {
+                        pool_id: pool.pool_id,
+                        token_in: hop.token_in,
+                        token_out: hop.token_out,
+                        amount_in,
+                        amount_out,
+                    },
+                );
+            }
+            Dex::WooFiV2 => {
+                let pool = woofi::get_pool(scratch, chain_id, pool_address)?
+                    .ok_or(DexQuoteError::PoolNotFound(pool_address))?;
+                let quote = try_query(&pool, hop.token_in, hop.token_out, amount_in)?;
+                crate::event::woofi::update_with_swap_event(
+                    scratch,
+                    chain_id,
+                    pool_address,
+                    &WooFiSwapEvent {
+                        from_token: hop.token_in,
+                        to_token: hop.token_out,
+                        from_amount: amount_in,
+                        to_amount: amount_out,
+                        swap_volume: quote.swap_volume,
+                        swap_fee: quote.swap_fee,
+                    },
+                );
+            }
+            // moved by their `PoolState`
+            Dex::UniswapV3 | Dex::Algebra => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{univ2::add_pool, univ3},
+        path::{get_amount_out_from_path, PoolInfo},
+    };
+    use cfmms::pool::{UniswapV2Pool, UniswapV3Pool};
+
+    const CHAIN_ID: u64 = 42161;
+
+    fn hop(address: u64, token_in: u64, token_out: u64) -> PoolInfo {
+        PoolInfo {
+            address: Address::from_low_u64_be(address),
+            token_in: Address::from_low_u64_be(token_in),
+            token_out: Address::from_low_u64_be(token_out),
+            dex: Some(Dex::UniswapV2),
+            fee: Some(3000),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_split_amount_out_shared_pool() {
+        let store = MemoryStore::new();
+        // 1 -> 3 is shared by both paths, 3 -> 2 has two pools
+        for (address, token_a, token_b) in [(10, 1, 3), (11, 2, 3), (12, 2, 3)] {
+            add_pool(
+                &store,
+                CHAIN_ID,
+                UniswapV2Pool {
+                    address: Address::from_low_u64_be(address),
+                    token_a: Address::from_low_u64_be(token_a),
+                    token_a_decimals: 18,
+                    token_b: Address::from_low_u64_be(token_b),
+                    token_b_decimals: 18,
+                    reserve_0: 1_000_000_000_000_000_000_000_000,
+                    reserve_1: 1_000_000_000_000_000_000_000_000,
+                    fee: 300,
+                },
+            )
+            .unwrap();
+        }
+        let paths = vec![
+            vec![hop(10, 1, 3), hop(11, 3, 2)],
+            vec![hop(10, 1, 3), hop(12, 3, 2)],
+        ];
+        let amount_in = U256::exp10(23);
+        let split = get_split_amount_out(&store, CHAIN_ID, "", amount_in, &paths, 10, true)
+            .await
+            .unwrap();
+        let single = get_amount_out_from_path(&store, CHAIN_ID, "", amount_in, &paths[0], true)
+            .await
+            .unwrap();
+
+        assert_eq!(split.routes.len(), 2);
+        assert_eq!(split.routes[0].amount_in, amount_in / 2);
+        assert_eq!(split.routes[1].amount_in, amount_in / 2);
+        assert_eq!(
+            split.amount_out,
+            split.routes[0].amount_out + split.routes[1].amount_out
+        );
+        assert!(split.amount_out > single);
+        // quoting half of the input on each path as if the shared pool was not moved
+        // counts its liquidity twice
+        let mut independent = U256::zero();
+        for path in &paths {
+            independent +=
+                get_amount_out_from_path(&store, CHAIN_ID, "", amount_in / 2, path, true)
+                    .await
+                    .unwrap();
+        }
+        assert!(split.amount_out < independent);
+
+        // a single slice takes the best path
+        let split = get_split_amount_out(&store, CHAIN_ID, "", amount_in, &paths, 1, true)
+            .await
+            .unwrap();
+        assert_eq!(split.routes.len(), 1);
+        assert_eq!(split.amount_out, single);
+
+        // the store is left untouched
+        let single_again =
+            get_amount_out_from_path(&store, CHAIN_ID, "", amount_in, &paths[0], true)
+                .await
+                .unwrap();
+        assert_eq!(single_again, single);
+    }
+
+    #[tokio::test]
+    async fn test_get_split_amount_out_shared_univ3_pool() {
+        let store = MemoryStore::new();
+        let liquidity: u128 = 1_000_000_000_000_000_000_000_000;
+        // 1 -> 3 is a UniswapV3 pool shared by both paths, 3 -> 2 has two pools
+        univ3::add_pool(
+            &store,
+            CHAIN_ID,
+            UniswapV3Pool {
+                address: Address::from_low_u64_be(10),
+                token_a: Address::from_low_u64_be(1),
+                token_a_decimals: 18,
+                token_b: Address::from_low_u64_be(3),
+                token_b_decimals: 18,
+                liquidity,
+                sqrt_price: U256::one() << 96,
+                fee: 3000,
+                tick: 0,
+                tick_spacing: 60,
+                liquidity_net: 0,
+            },
+        )
+        .unwrap();
+        // a single position from tick -600 to 600
+        let pool_address = Address::from_low_u64_be(10);
+        store
+            .update_tick_bitmap(CHAIN_ID, pool_address, -1, U256::one() << 246)
+            .unwrap();
+        store
+            .update_tick_bitmap(CHAIN_ID, pool_address, 0, U256::one() << 10)
+            .unwrap();
+        store
+            .update_ticks(CHAIN_ID, pool_address, -600, liquidity, liquidity as i128)
+            .unwrap();
+        store
+            .update_ticks(CHAIN_ID, pool_address, 600, liquidity, -(liquidity as i128))
+            .unwrap();
+        for address in [11, 12] {
+            add_pool(
+                &store,
+                CHAIN_ID,
+                UniswapV2Pool {
+                    address: Address::from_low_u64_be(address),
+                    token_a: Address::from_low_u64_be(2),
+                    token_a_decimals: 18,
+                    token_b: Address::from_low_u64_be(3),
+                    token_b_decimals: 18,
+                    reserve_0: 1_000_000_000_000_000_000_000_000_000,
+                    reserve_1: 1_000_000_000_000_000_000_000_000_000,
+                    fee: 300,
+                },
+            )
+            .unwrap();
+        }
+        let univ3_hop = PoolInfo {
+            dex: Some(Dex::UniswapV3),
+            ..hop(10, 1, 3)
+        };
+        let paths = vec![
+            vec![univ3_hop.clone(), hop(11, 3, 2)],
+            vec![univ3_hop, hop(12, 3, 2)],
+        ];
+        // the slices move the price within a tick
+        let amount_in = U256::exp10(19);
+        let split = get_split_amount_out(&store, CHAIN_ID, "", amount_in, &paths, 10, true)
+            .await
+            .unwrap();
+        assert_eq!(split.routes.len(), 2);
+
+        let mut independent = U256::zero();
+        for path in &paths {
+            independent +=
+                get_amount_out_from_path(&store, CHAIN_ID, "", amount_in / 2, path, true)
+                    .await
+                    .unwrap();
+        }
+        assert!(split.amount_out < independent);
+    }
+}
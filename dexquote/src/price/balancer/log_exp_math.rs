@@ -0,0 +1,226 @@
+use crate::error::DexQuoteError;
+use crate::types::DexQuoteResult;
+use ethers::types::{I256, U256};
+
+// ref. https://github.com/balancer/balancer-v2-monorepo/blob/master/pkg/solidity-utils/contracts/math/LogExpMath.sol
+// Solidity int256 division truncates towards zero, as I256 does.
+
+fn int(value: &str) -> I256 {
+    I256::from_dec_str(value).unwrap()
+}
+
+fn one_18() -> I256 {
+    I256::exp10(18)
+}
+
+fn one_20() -> I256 {
+    I256::exp10(20)
+}
+
+fn one_36() -> I256 {
+    I256::exp10(36)
+}
+
+fn max_natural_exponent() -> I256 {
+    I256::from(130) * one_18()
+}
+
+fn min_natural_exponent() -> I256 {
+    I256::from(-41) * one_18()
+}
+
+// 18 decimal constants, a0 and a1 have no decimals
+const X0: &str = "128000000000000000000";
+const A0: &str = "38877084059945950922200000000000000000000000000000000000";
+const X1: &str = "64000000000000000000";
+const A1: &str = "6235149080811616882910000000";
+
+// 20 decimal constants, x2 to x11 are 2^5 to 2^-4 and a_n is e^x_n
+const X_N: [&str; 10] = [
+    "3200000000000000000000",
+    "1600000000000000000000",
+    "800000000000000000000",
+    "400000000000000000000",
+    "200000000000000000000",
+    "100000000000000000000",
+    "50000000000000000000",
+    "25000000000000000000",
+    "12500000000000000000",
+    "6250000000000000000",
+];
+const A_N: [&str; 10] = [
+    "7896296018268069516100000000000000",
+    "888611052050787263676000000",
+    "298095798704172827474000",
+    "5459815003314423907810",
+    "738905609893065022723",
+    "271828182845904523536",
+    "164872127070012814685",
+    "128402541668774148407",
+    "113314845306682631683",
+    "106449445891785942956",
+];
+
+fn out_of_bounds(name: &str) -> DexQuoteError {
+    DexQuoteError::MathError(format!("{name} out of bounds"))
+}
+
+/// x^y, both in 18 decimals
+pub fn pow(x: U256, y: U256) -> DexQuoteResult<U256> {
+    if y.is_zero() {
+        return Ok(U256::exp10(18));
+    }
+    if x.is_zero() {
+        return Ok(U256::zero());
+    }
+    if x.bit(255) {
+        return Err(out_of_bounds("x"));
+    }
+    let x = I256::from_raw(x);
+    // 2^254 / 1e20
+    let mild_exponent_bound = (U256::one() << 254) / U256::exp10(20);
+    if y >= mild_exponent_bound {
+        return Err(out_of_bounds("y"));
+    }
+    let y = I256::from_raw(y);
+
+    let ln_36_lower_bound = one_18() - I256::exp10(17);
+    let ln_36_upper_bound = one_18() + I256::exp10(17);
+    let mut logx_times_y = if ln_36_lower_bound < x && x < ln_36_upper_bound {
+        let ln_36_x = ln_36(x);
+        (ln_36_x / one_18()) * y + ((ln_36_x % one_18()) * y) / one_18()
+    } else {
+        ln(x) * y
+    };
+    logx_times_y /= one_18();
+    if logx_times_y < min_natural_exponent() || logx_times_y > max_natural_exponent() {
+        return Err(out_of_bounds("product"));
+    }
+    Ok(exp(logx_times_y)?.into_raw())
+}
+
+/// e^x, in 18 decimals
+pub fn exp(mut x: I256) -> DexQuoteResult<I256> {
+    if x < min_natural_exponent() || x > max_natural_exponent() {
+        return Err(out_of_bounds("exponent"));
+    }
+    if x < I256::zero() {
+        return Ok((one_18() * one_18()) / exp(-x)?);
+    }
+    let first_an;
+    if x >= int(X0) {
+        x -= int(X0);
+        first_an = int(A0);
+    } else if x >= int(X1) {
+        x -= int(X1);
+        first_an = int(A1);
+    } else {
+        first_an = I256::one();
+    }
+    x *= I256::from(100);
+
+    let mut product = one_20();
+    // e^x2 to e^x9
+    for (x_n, a_n) in X_N.iter().zip(A_N.iter()).take(8) {
+        if x >= int(x_n) {
+            x -= int(x_n);
+            product = (product * int(a_n)) / one_20();
+        }
+    }
+
+    // Taylor series of the remainder up to the 12th term
+    let mut series_sum = one_20();
+    let mut term = x;
+    series_sum += term;
+    for n in 2..=12 {
+        term = ((term * x) / one_20()) / I256::from(n);
+        series_sum += term;
+    }
+    Ok((((product * series_sum) / one_20()) * first_an) / I256::from(100))
+}
+
+// natural logarithm in 18 decimals
+fn ln(mut a: I256) -> I256 {
+    if a < one_18() {
+        return -ln((one_18() * one_18()) / a);
+    }
+    let mut sum = I256::zero();
+    if a >= int(A0) * one_18() {
+        a /= int(A0);
+        sum += int(X0);
+    }
+    if a >= int(A1) * one_18() {
+        a /= int(A1);
+        sum += int(X1);
+    }
+    sum *= I256::from(100);
+    a *= I256::from(100);
+    for (x_n, a_n) in X_N.iter().zip(A_N.iter()) {
+        if a >= int(a_n) {
+            a = (a * one_20()) / int(a_n);
+            sum += int(x_n);
+        }
+    }
+
+    // ln(a) = 2 * artanh((a - 1) / (a + 1))
+    let z = ((a - one_20()) * one_20()) / (a + one_20());
+    let z_squared = (z * z) / one_20();
+    let mut num = z;
+    let mut series_sum = num;
+    for n in [3, 5, 7, 9, 11] {
+        num = (num * z_squared) / one_20();
+        series_sum += num / I256::from(n);
+    }
+    series_sum *= I256::from(2);
+    (sum + series_sum) / I256::from(100)
+}
+
+// natural logarithm in 36 decimals, for x close to one
+fn ln_36(mut x: I256) -> I256 {
+    x *= one_18();
+    let z = ((x - one_36()) * one_36()) / (x + one_36());
+    let z_squared = (z * z) / one_36();
+    let mut num = z;
+    let mut series_sum = num;
+    for n in [3, 5, 7, 9, 11, 13, 15] {
+        num = (num * z_squared) / one_36();
+        series_sum += num / I256::from(n);
+    }
+    series_sum * I256::from(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pow() {
+        let cases = [
+            (
+                "500000000000000000",
+                "250000000000000000",
+                "840896415253714543",
+            ),
+            (
+                "123456789000000000000",
+                "1500000000000000000",
+                "1371742093758573429237",
+            ),
+            (
+                "1001000000000000000",
+                "666666666666666666",
+                "1000666555604909484",
+            ),
+            (
+                "700000000000000000000",
+                "300000000000000000",
+                "7137225393759445866",
+            ),
+        ];
+        for (x, y, expected) in cases {
+            let x = U256::from_dec_str(x).unwrap();
+            let y = U256::from_dec_str(y).unwrap();
+            assert_eq!(pow(x, y).unwrap(), U256::from_dec_str(expected).unwrap());
+        }
+    }
+}
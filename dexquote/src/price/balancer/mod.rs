@@ -0,0 +1,533 @@
+use std::collections::HashMap;
+
+use crate::db::balancer::{get_pool, hashmap_to_balancer, BalancerPool, BalancerPoolKind};
+use crate::db::PoolStore;
+use crate::error::DexQuoteError;
+use crate::types::DexQuoteResult;
+use crate::utils::now;
+use ethers::types::{Address, U256};
+
+pub mod log_exp_math;
+
+// ref. https://github.com/balancer/balancer-v2-monorepo/blob/master/pkg/pool-weighted/contracts/WeightedMath.sol
+// swaps may not move more than 30% of a balance
+const MAX_IN_RATIO: u64 = 300_000_000_000_000_000;
+const MAX_OUT_RATIO: u64 = 300_000_000_000_000_000;
+// relative error allowed for pow, 1e-14
+const MAX_POW_RELATIVE_ERROR: u64 = 10000;
+const AMP_PRECISION: u64 = 1000;
+const MAX_ITERATIONS: usize = 255;
+
+pub fn get_price<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    token_in: Address,
+    token_out: Address,
+    amount_in: U256,
+) -> DexQuoteResult<U256> {
+    match get_pool(store, chain_id, pool_address)? {
+        Some(pool) => get_amount_out(&pool, token_in, token_out, amount_in, now()),
+        None => Err(DexQuoteError::PoolNotFound(pool_address)),
+    }
+}
+
+pub fn get_price_with_hashmap(
+    pool_address: Address,
+    token_in: Address,
+    token_out: Address,
+    amount_in: U256,
+    target_data: HashMap<String, String>,
+) -> DexQuoteResult<U256> {
+    match hashmap_to_balancer(pool_address, target_data) {
+        Some(pool) => get_amount_out(&pool, token_in, token_out, amount_in, now()),
+        None => Err(DexQuoteError::PoolNotFound(pool_address)),
+    }
+}
+
+pub fn get_amount_in_with_hashmap(
+    pool_address: Address,
+    token_in: Address,
+    token_out: Address,
+    amount_out: U256,
+    target_data: HashMap<String, String>,
+) -> DexQuoteResult<U256> {
+    match hashmap_to_balancer(pool_address, target_data) {
+        Some(pool) => get_amount_in(&pool, token_in, token_out, amount_out, now()),
+        None => Err(DexQuoteError::PoolNotFound(pool_address)),
+    }
+}
+
+fn one() -> U256 {
+    U256::exp10(18)
+}
+
+fn overflow() -> DexQuoteError {
+    DexQuoteError::MathError("balancer math overflow".to_string())
+}
+
+fn mul(a: U256, b: U256) -> DexQuoteResult<U256> {
+    a.checked_mul(b).ok_or_else(overflow)
+}
+
+fn sub(a: U256, b: U256) -> DexQuoteResult<U256> {
+    a.checked_sub(b).ok_or_else(overflow)
+}
+
+fn div(a: U256, b: U256) -> DexQuoteResult<U256> {
+    a.checked_div(b).ok_or_else(overflow)
+}
+
+fn div_up(a: U256, b: U256) -> DexQuoteResult<U256> {
+    if a.is_zero() {
+        return Ok(U256::zero());
+    }
+    Ok(div(a - 1, b)? + 1)
+}
+
+// FixedPoint, 18 decimals
+fn mul_down(a: U256, b: U256) -> DexQuoteResult<U256> {
+    Ok(mul(a, b)? / one())
+}
+
+fn mul_up(a: U256, b: U256) -> DexQuoteResult<U256> {
+    let product = mul(a, b)?;
+    if product.is_zero() {
+        return Ok(U256::zero());
+    }
+    Ok((product - 1) / one() + 1)
+}
+
+fn fixed_div_down(a: U256, b: U256) -> DexQuoteResult<U256> {
+    div(mul(a, one())?, b)
+}
+
+fn fixed_div_up(a: U256, b: U256) -> DexQuoteResult<U256> {
+    if a.is_zero() {
+        return Ok(U256::zero());
+    }
+    Ok(div(mul(a, one())? - 1, b)? + 1)
+}
+
+fn complement(x: U256) -> U256 {
+    if x < one() {
+        one() - x
+    } else {
+        U256::zero()
+    }
+}
+
+fn pow_up(x: U256, y: U256) -> DexQuoteResult<U256> {
+    if y == one() {
+        return Ok(x);
+    }
+    if y == one() * 2 {
+        return mul_up(x, x);
+    }
+    if y == one() * 4 {
+        let square = mul_up(x, x)?;
+        return mul_up(square, square);
+    }
+    let raw = log_exp_math::pow(x, y)?;
+    let max_error = mul_up(raw, MAX_POW_RELATIVE_ERROR.into())? + 1;
+    Ok(raw + max_error)
+}
+
+/// WeightedMath.calcOutGivenIn on upscaled amounts
+pub fn calc_out_given_in(
+    balance_in: U256,
+    weight_in: U256,
+    balance_out: U256,
+    weight_out: U256,
+    amount_in: U256,
+) -> DexQuoteResult<U256> {
+    if amount_in > mul_down(balance_in, MAX_IN_RATIO.into())? {
+        return Err(DexQuoteError::MathError("max in ratio".to_string()));
+    }
+    let denominator = balance_in + amount_in;
+    let base = fixed_div_up(balance_in, denominator)?;
+    let exponent = fixed_div_down(weight_in, weight_out)?;
+    let power = pow_up(base, exponent)?;
+    mul_down(balance_out, complement(power))
+}
+
+/// WeightedMath.calcInGivenOut on upscaled amounts
+pub fn calc_in_given_out(
+    balance_in: U256,
+    weight_in: U256,
+    balance_out: U256,
+    weight_out: U256,
+    amount_out: U256,
+) -> DexQuoteResult<U256> {
+    if amount_out > mul_down(balance_out, MAX_OUT_RATIO.into())? {
+        return Err(DexQuoteError::MathError("max out ratio".to_string()));
+    }
+    let base = fixed_div_up(balance_out, balance_out - amount_out)?;
+    let exponent = fixed_div_up(weight_out, weight_in)?;
+    let power = pow_up(base, exponent)?;
+    mul_up(balance_in, sub(power, one())?)
+}
+
+/// StableMath invariant of upscaled balances, `amp` includes the 1e3 precision
+pub fn calculate_invariant(amp: U256, balances: &[U256]) -> DexQuoteResult<U256> {
+    let n = U256::from(balances.len());
+    let sum = balances
+        .iter()
+        .fold(U256::zero(), |sum, balance| sum + balance);
+    if sum.is_zero() {
+        return Ok(U256::zero());
+    }
+    let amp_precision = U256::from(AMP_PRECISION);
+    let amp_times_total = mul(amp, n)?;
+    let mut invariant = sum;
+    for _ in 0..MAX_ITERATIONS {
+        let mut d_p = invariant;
+        for balance in balances {
+            d_p = div(mul(d_p, invariant)?, mul(*balance, n)?)?;
+        }
+        let prev_invariant = invariant;
+        invariant = div(
+            mul(
+                div(mul(amp_times_total, sum)?, amp_precision)? + mul(d_p, n)?,
+                invariant,
+            )?,
+            div(
+                mul(sub(amp_times_total, amp_precision)?, invariant)?,
+                amp_precision,
+            )? + mul(n + 1, d_p)?,
+        )?;
+        if invariant.abs_diff(prev_invariant) <= U256::one() {
+            return Ok(invariant);
+        }
+    }
+    Err(DexQuoteError::MathError(
+        "stable invariant does not converge".to_string(),
+    ))
+}
+
+// balance of `token_index` keeping the invariant with the other balances
+fn get_token_balance(
+    amp: U256,
+    balances: &[U256],
+    invariant: U256,
+    token_index: usize,
+) -> DexQuoteResult<U256> {
+    let n = U256::from(balances.len());
+    let amp_precision = U256::from(AMP_PRECISION);
+    let amp_times_total = mul(amp, n)?;
+    let mut sum = balances[0];
+    let mut p_d = mul(balances[0], n)?;
+    for balance in &balances[1..] {
+        p_d = div(mul(mul(p_d, *balance)?, n)?, invariant)?;
+        sum += *balance;
+    }
+    sum -= balances[token_index];
+    let inv2 = mul(invariant, invariant)?;
+    let c = mul(
+        mul(div_up(inv2, mul(amp_times_total, p_d)?)?, amp_precision)?,
+        balances[token_index],
+    )?;
+    let b = sum + mul(div(invariant, amp_times_total)?, amp_precision)?;
+    let mut token_balance = div_up(inv2 + c, invariant + b)?;
+    for _ in 0..MAX_ITERATIONS {
+        let prev_token_balance = token_balance;
+        token_balance = div_up(
+            mul(token_balance, token_balance)? + c,
+            sub(mul(token_balance, 2.into())? + b, invariant)?,
+        )?;
+        if token_balance.abs_diff(prev_token_balance) <= U256::one() {
+            return Ok(token_balance);
+        }
+    }
+    Err(DexQuoteError::MathError(
+        "stable balance does not converge".to_string(),
+    ))
+}
+
+/// Amplification at `timestamp`, interpolated linearly while it is updating
+pub fn get_amp(pool: &BalancerPool, timestamp: u64) -> U256 {
+    let (start, end, start_time, end_time) = (
+        pool.amp_start,
+        pool.amp_end,
+        pool.amp_start_time,
+        pool.amp_end_time,
+    );
+    if timestamp >= end_time || end_time <= start_time {
+        return end;
+    }
+    let elapsed = U256::from(timestamp.saturating_sub(start_time));
+    let duration = U256::from(end_time - start_time);
+    if end > start {
+        start + (end - start) * elapsed / duration
+    } else {
+        start - (start - end) * elapsed / duration
+    }
+}
+
+fn token_indexes(
+    pool: &BalancerPool,
+    token_in: Address,
+    token_out: Address,
+) -> DexQuoteResult<(usize, usize)> {
+    match (pool.token_index(token_in), pool.token_index(token_out)) {
+        (Some(i), Some(j)) if i != j => Ok((i, j)),
+        _ => Err(DexQuoteError::GetPriceError(format!(
+            "{:?} does not trade {:?} for {:?}",
+            pool.address, token_in, token_out
+        ))),
+    }
+}
+
+fn upscaled_balances(pool: &BalancerPool) -> DexQuoteResult<Vec<U256>> {
+    pool.balances
+        .iter()
+        .zip(pool.scaling_factors.iter())
+        .map(|(balance, scaling_factor)| mul_down(*balance, *scaling_factor))
+        .collect()
+}
+
+/// Amount of `token_out` received for `amount_in` of `token_in`, as the pool `onSwap`
+pub fn get_amount_out(
+    pool: &BalancerPool,
+    token_in: Address,
+    token_out: Address,
+    amount_in: U256,
+    timestamp: u64,
+) -> DexQuoteResult<U256> {
+    let (i, j) = token_indexes(pool, token_in, token_out)?;
+    let amount_in = amount_in - mul_up(amount_in, pool.swap_fee)?;
+    let amount_in = mul_down(amount_in, pool.scaling_factors[i])?;
+    let balances = upscaled_balances(pool)?;
+    let amount_out = match pool.kind {
+        BalancerPoolKind::Weighted => calc_out_given_in(
+            balances[i],
+            pool.weights[i],
+            balances[j],
+            pool.weights[j],
+            amount_in,
+        )?,
+        BalancerPoolKind::Stable => {
+            let amp = get_amp(pool, timestamp);
+            let invariant = calculate_invariant(amp, &balances)?;
+            let mut balances = balances;
+            balances[i] += amount_in;
+            let final_balance_out = get_token_balance(amp, &balances, invariant, j)?;
+            balances[j]
+                .checked_sub(final_balance_out + 1)
+                .ok_or(DexQuoteError::InsufficientLiquidity(pool.address))?
+        }
+    };
+    fixed_div_down(amount_out, pool.scaling_factors[j])
+}
+
+/// Amount of `token_in` needed to receive `amount_out` of `token_out`
+pub fn get_amount_in(
+    pool: &BalancerPool,
+    token_in: Address,
+    token_out: Address,
+    amount_out: U256,
+    timestamp: u64,
+) -> DexQuoteResult<U256> {
+    let (i, j) = token_indexes(pool, token_in, token_out)?;
+    let amount_out = mul_down(amount_out, pool.scaling_factors[j])?;
+    let balances = upscaled_balances(pool)?;
+    if amount_out >= balances[j] {
+        return Err(DexQuoteError::InsufficientLiquidity(pool.address));
+    }
+    let amount_in = match pool.kind {
+        BalancerPoolKind::Weighted => calc_in_given_out(
+            balances[i],
+            pool.weights[i],
+            balances[j],
+            pool.weights[j],
+            amount_out,
+        )?,
+        BalancerPoolKind::Stable => {
+            let amp = get_amp(pool, timestamp);
+            let invariant = calculate_invariant(amp, &balances)?;
+            let mut balances = balances;
+            balances[j] -= amount_out;
+            let final_balance_in = get_token_balance(amp, &balances, invariant, i)?;
+            sub(final_balance_in, balances[i])? + 1
+        }
+    };
+    let amount_in = fixed_div_up(amount_in, pool.scaling_factors[i])?;
+    fixed_div_up(amount_in, complement(pool.swap_fee))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::balancer::get_scaling_factor;
+
+    fn weighted_pool(balances: Vec<U256>, decimals: Vec<u8>, weights: Vec<u64>) -> BalancerPool {
+        BalancerPool {
+            tokens: vec![Address::from_low_u64_be(1), Address::from_low_u64_be(2)],
+            balances,
+            scaling_factors: decimals.iter().map(|d| get_scaling_factor(*d)).collect(),
+            decimals,
+            weights: weights
+                .into_iter()
+                .map(|weight| U256::from(weight) * U256::exp10(16))
+                .collect(),
+            swap_fee: U256::from(3) * U256::exp10(15),
+            ..Default::default()
+        }
+    }
+
+    fn stable_pool() -> BalancerPool {
+        let decimals = vec![18, 6, 6];
+        BalancerPool {
+            kind: BalancerPoolKind::Stable,
+            tokens: (1..=3).map(Address::from_low_u64_be).collect(),
+            balances: vec![
+                U256::from(10_000_000u64) * U256::exp10(18),
+                U256::from(9_000_000u64) * U256::exp10(6),
+                U256::from(11_000_000u64) * U256::exp10(6),
+            ],
+            scaling_factors: decimals.iter().map(|d| get_scaling_factor(*d)).collect(),
+            decimals,
+            swap_fee: U256::exp10(14),
+            amp_start: U256::from(200 * AMP_PRECISION),
+            amp_end: U256::from(200 * AMP_PRECISION),
+            ..Default::default()
+        }
+    }
+
+    fn token(n: u64) -> Address {
+        Address::from_low_u64_be(n)
+    }
+
+    #[test]
+    fn test_weighted_get_amount_out() {
+        // 80/20, the exponent 4 is computed with squares
+        let pool = weighted_pool(
+            vec![
+                U256::from(1_000_000u64) * U256::exp10(18),
+                U256::from(2500) * U256::exp10(18),
+            ],
+            vec![18, 18],
+            vec![80, 20],
+        );
+        let amount_out = get_amount_out(
+            &pool,
+            token(1),
+            token(2),
+            U256::from(1000) * U256::exp10(18),
+            0,
+        )
+        .unwrap();
+        assert_eq!(amount_out, U256::from(9945199240031635000u64));
+        let amount_out = get_amount_out(&pool, token(2), token(1), U256::exp10(18), 0).unwrap();
+        assert_eq!(
+            amount_out,
+            U256::from_dec_str("99675157195293000000").unwrap()
+        );
+
+        // 60/40 goes through LogExpMath
+        let pool = weighted_pool(
+            vec![
+                U256::from(100) * U256::exp10(8),
+                U256::from(1000) * U256::exp10(18),
+            ],
+            vec![8, 18],
+            vec![60, 40],
+        );
+        let amount_out = get_amount_out(&pool, token(1), token(2), U256::exp10(8), 0).unwrap();
+        assert_eq!(amount_out, U256::from(14770767132421920000u64));
+    }
+
+    #[test]
+    fn test_weighted_get_amount_in() {
+        let pool = weighted_pool(
+            vec![
+                U256::from(5_000_000u64) * U256::exp10(6),
+                U256::from(2000) * U256::exp10(18),
+            ],
+            vec![6, 18],
+            vec![50, 50],
+        );
+        let amount_out = get_amount_out(
+            &pool,
+            token(1),
+            token(2),
+            U256::from(10000) * U256::exp10(6),
+            0,
+        )
+        .unwrap();
+        assert_eq!(amount_out, U256::from(3980063752876762000u64));
+        let amount_in = get_amount_in(&pool, token(1), token(2), U256::exp10(18), 0).unwrap();
+        assert_eq!(amount_in, U256::from(2508776957u64));
+
+        let pool = weighted_pool(
+            vec![
+                U256::from(100) * U256::exp10(8),
+                U256::from(1000) * U256::exp10(18),
+            ],
+            vec![8, 18],
+            vec![60, 40],
+        );
+        let amount_in = get_amount_in(
+            &pool,
+            token(1),
+            token(2),
+            U256::from(10) * U256::exp10(18),
+            0,
+        )
+        .unwrap();
+        assert_eq!(amount_in, U256::from(67429496));
+
+        // more than 30% of the balance
+        assert!(get_amount_in(
+            &pool,
+            token(1),
+            token(2),
+            U256::from(400) * U256::exp10(18),
+            0
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_stable() {
+        let pool = stable_pool();
+        let balances = upscaled_balances(&pool).unwrap();
+        assert_eq!(
+            calculate_invariant(pool.amp_end, &balances).unwrap(),
+            U256::from_dec_str("29999497495769750027066016").unwrap()
+        );
+        let amount_out = get_amount_out(
+            &pool,
+            token(1),
+            token(2),
+            U256::from(100_000u64) * U256::exp10(18),
+            0,
+        )
+        .unwrap();
+        assert_eq!(amount_out, U256::from(99928556035u64));
+        let amount_out = get_amount_out(
+            &pool,
+            token(3),
+            token(1),
+            U256::from(1_000_000u64) * U256::exp10(6),
+            0,
+        )
+        .unwrap();
+        assert_eq!(
+            amount_out,
+            U256::from_dec_str("998970239975007851686982").unwrap()
+        );
+        let amount_in = get_amount_in(
+            &pool,
+            token(1),
+            token(2),
+            U256::from(100_000u64) * U256::exp10(6),
+            0,
+        )
+        .unwrap();
+        assert_eq!(
+            amount_in,
+            U256::from_dec_str("100071499098047404036490").unwrap()
+        );
+    }
+}
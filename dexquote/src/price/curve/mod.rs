@@ -0,0 +1,396 @@
+use std::collections::HashMap;
+
+use crate::db::curve::{get_pool, hashmap_to_curve, CurvePool};
+use crate::db::PoolStore;
+use crate::error::DexQuoteError;
+use crate::types::DexQuoteResult;
+use crate::utils::now;
+use ethers::types::{Address, U256};
+
+// ref. https://github.com/curvefi/curve-contract/blob/master/contracts/pools/3pool/StableSwap3Pool.vy
+const FEE_DENOMINATOR: u64 = 10_000_000_000;
+const MAX_ITERATIONS: usize = 255;
+
+fn precision() -> U256 {
+    U256::exp10(18)
+}
+
+fn overflow() -> DexQuoteError {
+    DexQuoteError::MathError("curve math overflow".to_string())
+}
+
+fn mul(a: U256, b: U256) -> DexQuoteResult<U256> {
+    a.checked_mul(b).ok_or_else(overflow)
+}
+
+fn sub(a: U256, b: U256) -> DexQuoteResult<U256> {
+    a.checked_sub(b).ok_or_else(overflow)
+}
+
+fn div(a: U256, b: U256) -> DexQuoteResult<U256> {
+    a.checked_div(b).ok_or_else(overflow)
+}
+
+pub fn get_price<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    token_in: Address,
+    token_out: Address,
+    amount_in: U256,
+) -> DexQuoteResult<U256> {
+    match get_pool(store, chain_id, pool_address)? {
+        Some(pool) => quote(
+            store, chain_id, pool, token_in, token_out, amount_in, get_dy,
+        ),
+        None => Err(DexQuoteError::PoolNotFound(pool_address)),
+    }
+}
+
+pub fn get_price_with_hashmap<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    token_in: Address,
+    token_out: Address,
+    amount_in: U256,
+    target_data: HashMap<String, String>,
+) -> DexQuoteResult<U256> {
+    match hashmap_to_curve(pool_address, target_data) {
+        Some(pool) => quote(
+            store, chain_id, pool, token_in, token_out, amount_in, get_dy,
+        ),
+        None => Err(DexQuoteError::PoolNotFound(pool_address)),
+    }
+}
+
+pub fn get_amount_in_with_hashmap<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    token_in: Address,
+    token_out: Address,
+    amount_out: U256,
+    target_data: HashMap<String, String>,
+) -> DexQuoteResult<U256> {
+    match hashmap_to_curve(pool_address, target_data) {
+        Some(pool) => quote(
+            store, chain_id, pool, token_in, token_out, amount_out, get_dx,
+        ),
+        None => Err(DexQuoteError::PoolNotFound(pool_address)),
+    }
+}
+
+type Quote = fn(&CurvePool, usize, usize, U256, u64) -> DexQuoteResult<U256>;
+
+fn quote<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    mut pool: CurvePool,
+    token_in: Address,
+    token_out: Address,
+    amount: U256,
+    quote: Quote,
+) -> DexQuoteResult<U256> {
+    let (i, j) = match (pool.coin_index(token_in), pool.coin_index(token_out)) {
+        (Some(i), Some(j)) if i != j => (i, j),
+        _ => {
+            return Err(DexQuoteError::GetPriceError(format!(
+                "{:?} does not trade {:?} for {:?}",
+                pool.address, token_in, token_out
+            )))
+        }
+    };
+    let now = now();
+    set_base_pool_rate(store, chain_id, &mut pool, now)?;
+    quote(&pool, i, j, amount, now)
+}
+
+/// Value the LP token of a meta pool at the base pool virtual price.
+/// Plain pools are left as they are.
+pub fn set_base_pool_rate<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool: &mut CurvePool,
+    timestamp: u64,
+) -> DexQuoteResult<()> {
+    if let Some(base_pool_address) = pool.base_pool {
+        let base_pool = get_pool(store, chain_id, base_pool_address)?
+            .ok_or(DexQuoteError::PoolNotFound(base_pool_address))?;
+        let last = pool.rates.len() - 1;
+        pool.rates[last] = get_virtual_price(&base_pool, timestamp)?;
+    }
+    Ok(())
+}
+
+/// A at `timestamp`, interpolated linearly while it is ramping
+pub fn get_a(pool: &CurvePool, timestamp: u64) -> U256 {
+    let (a0, a1, t0, t1) = (
+        pool.initial_a,
+        pool.future_a,
+        pool.initial_a_time,
+        pool.future_a_time,
+    );
+    if timestamp >= t1 || t1 <= t0 {
+        return a1;
+    }
+    let elapsed = U256::from(timestamp.saturating_sub(t0));
+    let duration = U256::from(t1 - t0);
+    if a1 > a0 {
+        a0 + (a1 - a0) * elapsed / duration
+    } else {
+        a0 - (a0 - a1) * elapsed / duration
+    }
+}
+
+fn xp(pool: &CurvePool) -> DexQuoteResult<Vec<U256>> {
+    pool.rates
+        .iter()
+        .zip(pool.balances.iter())
+        .map(|(rate, balance)| Ok(mul(*rate, *balance)? / precision()))
+        .collect()
+}
+
+/// StableSwap invariant D
+pub fn get_d(xp: &[U256], amp: U256, a_precision: U256) -> DexQuoteResult<U256> {
+    let n = U256::from(xp.len());
+    let s = xp.iter().fold(U256::zero(), |s, x| s + x);
+    if s.is_zero() {
+        return Ok(U256::zero());
+    }
+    let mut d = s;
+    let ann = mul(amp, n)?;
+    for _ in 0..MAX_ITERATIONS {
+        let mut d_p = d;
+        for x in xp {
+            d_p = div(mul(d_p, d)?, mul(*x, n)?)?;
+        }
+        let d_prev = d;
+        let numerator = mul(mul(ann, s)? / a_precision + mul(d_p, n)?, d)?;
+        let denominator = mul(sub(ann, a_precision)?, d)? / a_precision + mul(n + 1, d_p)?;
+        d = div(numerator, denominator)?;
+        if d.abs_diff(d_prev) <= U256::one() {
+            return Ok(d);
+        }
+    }
+    Err(DexQuoteError::MathError("D does not converge".to_string()))
+}
+
+/// Balance of coin `j` in `xp` units once coin `i` is set to `x`
+pub fn get_y(
+    i: usize,
+    j: usize,
+    x: U256,
+    xp: &[U256],
+    amp: U256,
+    a_precision: U256,
+) -> DexQuoteResult<U256> {
+    let n = U256::from(xp.len());
+    let d = get_d(xp, amp, a_precision)?;
+    let ann = mul(amp, n)?;
+    let mut c = d;
+    let mut s = U256::zero();
+    for (k, xp_k) in xp.iter().enumerate() {
+        let x_k = if k == i {
+            x
+        } else if k != j {
+            *xp_k
+        } else {
+            continue;
+        };
+        s += x_k;
+        c = div(mul(c, d)?, mul(x_k, n)?)?;
+    }
+    c = div(mul(mul(c, d)?, a_precision)?, mul(ann, n)?)?;
+    let b = s + div(mul(d, a_precision)?, ann)?;
+    let mut y = d;
+    for _ in 0..MAX_ITERATIONS {
+        let y_prev = y;
+        y = div(mul(y, y)? + c, sub(mul(y, 2.into())? + b, d)?)?;
+        if y.abs_diff(y_prev) <= U256::one() {
+            return Ok(y);
+        }
+    }
+    Err(DexQuoteError::MathError("y does not converge".to_string()))
+}
+
+/// Amount of coin `j` received for `dx` of coin `i`, as the pool `get_dy`
+pub fn get_dy(
+    pool: &CurvePool,
+    i: usize,
+    j: usize,
+    dx: U256,
+    timestamp: u64,
+) -> DexQuoteResult<U256> {
+    let xp = xp(pool)?;
+    let amp = get_a(pool, timestamp);
+    let x = xp[i] + mul(dx, pool.rates[i])? / precision();
+    let y = get_y(i, j, x, &xp, amp, pool.a_precision)?;
+    let dy = xp[j]
+        .checked_sub(y + 1)
+        .ok_or(DexQuoteError::InsufficientLiquidity(pool.address))?;
+    let fee = mul(pool.fee, dy)? / FEE_DENOMINATOR;
+    Ok(mul(dy - fee, precision())? / pool.rates[j])
+}
+
+/// Fee kept by the pool admin on a `dx` swap, in coin `j`.
+/// It leaves the pool balances on `exchange`.
+pub fn get_admin_fee(
+    pool: &CurvePool,
+    i: usize,
+    j: usize,
+    dx: U256,
+    timestamp: u64,
+) -> DexQuoteResult<U256> {
+    let xp = xp(pool)?;
+    let amp = get_a(pool, timestamp);
+    let x = xp[i] + mul(dx, pool.rates[i])? / precision();
+    let y = get_y(i, j, x, &xp, amp, pool.a_precision)?;
+    let dy = xp[j]
+        .checked_sub(y + 1)
+        .ok_or(DexQuoteError::InsufficientLiquidity(pool.address))?;
+    let dy_fee = mul(dy, pool.fee)? / FEE_DENOMINATOR;
+    let dy_admin_fee = mul(dy_fee, pool.admin_fee)? / FEE_DENOMINATOR;
+    Ok(mul(dy_admin_fee, precision())? / pool.rates[j])
+}
+
+/// Amount of coin `i` needed to receive `dy` of coin `j`, as the pool `get_dx`
+pub fn get_dx(
+    pool: &CurvePool,
+    i: usize,
+    j: usize,
+    dy: U256,
+    timestamp: u64,
+) -> DexQuoteResult<U256> {
+    let xp = xp(pool)?;
+    let amp = get_a(pool, timestamp);
+    let fee_denominator = U256::from(FEE_DENOMINATOR);
+    let dy_with_fee = div(
+        mul(mul(dy, pool.rates[j])? / precision() + 1, fee_denominator)?,
+        sub(fee_denominator, pool.fee)?,
+    )?;
+    let y = xp[j]
+        .checked_sub(dy_with_fee)
+        .ok_or(DexQuoteError::InsufficientLiquidity(pool.address))?;
+    let x = get_y(j, i, y, &xp, amp, pool.a_precision)?;
+    Ok(mul(sub(x, xp[i])?, precision())? / pool.rates[i] + 1)
+}
+
+/// Value of one LP token in 18 decimals
+pub fn get_virtual_price(pool: &CurvePool, timestamp: u64) -> DexQuoteResult<U256> {
+    if pool.total_supply.is_zero() {
+        return Err(DexQuoteError::InsufficientLiquidity(pool.address));
+    }
+    let d = get_d(&xp(pool)?, get_a(pool, timestamp), pool.a_precision)?;
+    Ok(mul(d, precision())? / pool.total_supply)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::curve::get_rate;
+
+    fn three_pool() -> CurvePool {
+        let decimals = vec![18, 6, 6];
+        CurvePool {
+            coins: vec![Address::random(), Address::random(), Address::random()],
+            balances: vec![
+                U256::from(100_000_000u64) * U256::exp10(18),
+                U256::from(90_000_000u64) * U256::exp10(6),
+                U256::from(110_000_000u64) * U256::exp10(6),
+            ],
+            rates: decimals
+                .iter()
+                .map(|decimals| get_rate(*decimals))
+                .collect(),
+            decimals,
+            initial_a: 2000.into(),
+            future_a: 2000.into(),
+            a_precision: 1.into(),
+            fee: 1_000_000.into(),
+            admin_fee: 5_000_000_000u64.into(),
+            ..Default::default()
+        }
+    }
+
+    // A ramping from 100 to 200 between t=1000 and t=2000
+    fn ramping_pool() -> CurvePool {
+        CurvePool {
+            coins: vec![Address::random(), Address::random()],
+            decimals: vec![6, 6],
+            balances: vec![
+                U256::from(5_000_000u64) * U256::exp10(6),
+                U256::from(7_000_000u64) * U256::exp10(6),
+            ],
+            rates: vec![get_rate(6), get_rate(6)],
+            initial_a: 10000.into(),
+            future_a: 20000.into(),
+            initial_a_time: 1000,
+            future_a_time: 2000,
+            a_precision: 100.into(),
+            fee: 4_000_000.into(),
+            admin_fee: 5_000_000_000u64.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_get_d() {
+        let pool = three_pool();
+        let d = get_d(&xp(&pool).unwrap(), 2000.into(), 1.into()).unwrap();
+        assert_eq!(
+            d,
+            U256::from_dec_str("299999495205290737126766893").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_get_dy() {
+        let pool = three_pool();
+        let dy = get_dy(&pool, 0, 1, U256::from(1_000_000u64) * U256::exp10(18), 0).unwrap();
+        assert_eq!(dy, U256::from(999838237754u64));
+        let dy = get_dy(&pool, 2, 0, U256::from(5_000_000u64) * U256::exp10(6), 0).unwrap();
+        assert_eq!(dy, U256::from_dec_str("4999153505881229804510207").unwrap());
+        let dy = get_dy(&pool, 1, 2, U256::exp10(6), 0).unwrap();
+        assert_eq!(dy, U256::from(1000001));
+    }
+
+    #[test]
+    fn test_get_admin_fee() {
+        let pool = three_pool();
+        let dx = U256::from(1_000_000u64) * U256::exp10(6);
+        assert_eq!(
+            get_dy(&pool, 1, 2, dx, 0).unwrap(),
+            U256::from(999996688176u64)
+        );
+        assert_eq!(
+            get_admin_fee(&pool, 1, 2, dx, 0).unwrap(),
+            U256::from(50004834)
+        );
+    }
+
+    #[test]
+    fn test_ramping_a() {
+        let pool = ramping_pool();
+        assert_eq!(get_a(&pool, 1500), U256::from(15000));
+        assert_eq!(get_a(&pool, 3000), U256::from(20000));
+        let dx = U256::from(1_000_000u64) * U256::exp10(6);
+        assert_eq!(
+            get_dy(&pool, 0, 1, dx, 1500).unwrap(),
+            U256::from(1000734513178u64)
+        );
+        assert_eq!(
+            get_dy(&pool, 0, 1, dx, 3000).unwrap(),
+            U256::from(1000452355888u64)
+        );
+    }
+
+    #[test]
+    fn test_get_dx() {
+        let pool = three_pool();
+        let dy = U256::from(1_000_000u64) * U256::exp10(6);
+        let dx = get_dx(&pool, 0, 1, dy, 0).unwrap();
+        assert!(get_dy(&pool, 0, 1, dx, 0).unwrap() >= dy);
+        assert!(get_dy(&pool, 0, 1, dx - U256::exp10(13), 0).unwrap() < dy);
+    }
+}
@@ -2,26 +2,38 @@ use std::str::FromStr;
 
 use ethers::types::{Address, U256};
 
-use crate::{db::get_pool_hashmap, dex::Dex, error::DexQuoteError, types::DexQuoteResult};
+use crate::{db::PoolStore, dex::Dex, error::DexQuoteError, types::DexQuoteResult};
 
+pub mod balancer;
+pub mod curve;
+pub mod solidly;
+mod spot;
+pub use spot::*;
 pub mod uni_v2;
 pub mod uni_v3;
+pub mod woofi;
 
-pub async fn get_price(
-    redis_url: &str,
+/// Quote `amount_in` of `token_in` through a pool.
+/// If `offline` is set, UniswapV3 ticks and tick bitmaps are only read from the store
+/// and a missing one is returned as `DexQuoteError::MissingTickData`.
+#[allow(clippy::too_many_arguments)]
+pub async fn get_price<S: PoolStore>(
+    store: &S,
     chain_id: u64,
     json_rpc_url: String,
     pool_address: Address,
     token_in: Address,
     token_out: Address,
     amount_in: U256,
+    offline: bool,
 ) -> DexQuoteResult<U256> {
-    let redis_client = redis::Client::open(redis_url)?;
-    let target_data = get_pool_hashmap(&redis_client, chain_id, pool_address)?;
+    let target_data = store.get_pool_hashmap(chain_id, pool_address)?;
     if target_data.is_empty() {
         return Err(DexQuoteError::PoolNotFound(pool_address));
     }
-    let dex_string = target_data.get("dex").unwrap();
+    let dex_string = target_data
+        .get("dex")
+        .ok_or(DexQuoteError::PoolNotFound(pool_address))?;
     let dex = match Dex::from_str(dex_string) {
         Ok(dex) => dex,
         Err(_) => {
@@ -30,20 +42,143 @@ pub async fn get_price(
     };
     let zero_for_one = token_in < token_out;
     match dex {
-        Dex::UniswapV3 => {
+        Dex::UniswapV3 | Dex::Algebra => {
             uni_v3::get_price_with_hashmap(
-                redis_url,
+                store,
                 chain_id,
                 json_rpc_url,
                 pool_address,
                 zero_for_one,
                 amount_in,
                 target_data,
+                offline,
             )
             .await
         }
         Dex::UniswapV2 => {
             uni_v2::get_price_with_hashmap(pool_address, token_in, amount_in, target_data)
         }
+        Dex::Solidly => {
+            solidly::get_price_with_hashmap(pool_address, token_in, amount_in, target_data)
+        }
+        Dex::Curve => curve::get_price_with_hashmap(
+            store,
+            chain_id,
+            pool_address,
+            token_in,
+            token_out,
+            amount_in,
+            target_data,
+        ),
+        Dex::BalancerV2 => balancer::get_price_with_hashmap(
+            pool_address,
+            token_in,
+            token_out,
+            amount_in,
+            target_data,
+        ),
+        Dex::WooFiV2 => {
+            woofi::get_price_with_hashmap(pool_address, token_in, token_out, amount_in, target_data)
+        }
+    }
+}
+
+/// Required input of `token_in` to receive exactly `amount_out` of `token_out` from a pool.
+#[allow(clippy::too_many_arguments)]
+pub async fn get_amount_in<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    json_rpc_url: String,
+    pool_address: Address,
+    token_in: Address,
+    token_out: Address,
+    amount_out: U256,
+    offline: bool,
+) -> DexQuoteResult<U256> {
+    let target_data = store.get_pool_hashmap(chain_id, pool_address)?;
+    if target_data.is_empty() {
+        return Err(DexQuoteError::PoolNotFound(pool_address));
+    }
+    let dex_string = target_data
+        .get("dex")
+        .ok_or(DexQuoteError::PoolNotFound(pool_address))?;
+    let dex = match Dex::from_str(dex_string) {
+        Ok(dex) => dex,
+        Err(_) => {
+            return Err(DexQuoteError::InvalidDex(dex_string.to_string()));
+        }
+    };
+    let zero_for_one = token_in < token_out;
+    match dex {
+        Dex::UniswapV3 | Dex::Algebra => {
+            uni_v3::get_amount_in_with_hashmap(
+                store,
+                chain_id,
+                json_rpc_url,
+                pool_address,
+                zero_for_one,
+                amount_out,
+                target_data,
+                offline,
+            )
+            .await
+        }
+        Dex::UniswapV2 => {
+            uni_v2::get_amount_in_with_hashmap(pool_address, token_in, amount_out, target_data)
+        }
+        Dex::Solidly => {
+            solidly::get_amount_in_with_hashmap(pool_address, token_in, amount_out, target_data)
+        }
+        Dex::Curve => curve::get_amount_in_with_hashmap(
+            store,
+            chain_id,
+            pool_address,
+            token_in,
+            token_out,
+            amount_out,
+            target_data,
+        ),
+        Dex::BalancerV2 => balancer::get_amount_in_with_hashmap(
+            pool_address,
+            token_in,
+            token_out,
+            amount_out,
+            target_data,
+        ),
+        Dex::WooFiV2 => woofi::get_amount_in_with_hashmap(
+            pool_address,
+            token_in,
+            token_out,
+            amount_out,
+            target_data,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::MemoryStore;
+
+    #[tokio::test]
+    async fn test_get_amount_in_without_dex() {
+        let store = MemoryStore::new();
+        let pool_address = Address::from_low_u64_be(10);
+        let (token_in, token_out) = (Address::from_low_u64_be(1), Address::from_low_u64_be(2));
+        store
+            .update_pool_hashmap(42161, pool_address, &[("reserve0", "1".to_string())])
+            .unwrap();
+        let result = get_amount_in(
+            &store,
+            42161,
+            "".to_string(),
+            pool_address,
+            token_in,
+            token_out,
+            U256::one(),
+            true,
+        )
+        .await;
+        assert!(matches!(result, Err(DexQuoteError::PoolNotFound(_))));
     }
 }
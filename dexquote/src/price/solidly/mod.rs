@@ -0,0 +1,312 @@
+use std::collections::HashMap;
+
+use crate::db::solidly::{get_pool, hashmap_to_solidly, SolidlyPool};
+use crate::db::PoolStore;
+use crate::error::DexQuoteError;
+use crate::types::DexQuoteResult;
+use ethers::types::{Address, U256};
+
+// Solidly pool fees are in basis points
+const FEE_DENOMINATOR: u32 = 10000;
+
+pub fn get_price<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    token_in: Address,
+    amount_in: U256,
+) -> DexQuoteResult<U256> {
+    match get_pool(store, chain_id, pool_address)? {
+        Some(pool) => get_amount_out(&pool, token_in, amount_in),
+        None => Err(DexQuoteError::PoolNotFound(pool_address)),
+    }
+}
+
+pub fn get_price_with_hashmap(
+    pool_address: Address,
+    token_in: Address,
+    amount_in: U256,
+    target_data: HashMap<String, String>,
+) -> DexQuoteResult<U256> {
+    match hashmap_to_solidly(pool_address, target_data) {
+        Some(pool) => get_amount_out(&pool, token_in, amount_in),
+        None => Err(DexQuoteError::PoolNotFound(pool_address)),
+    }
+}
+
+pub fn get_amount_in_with_hashmap(
+    pool_address: Address,
+    token_in: Address,
+    amount_out: U256,
+    target_data: HashMap<String, String>,
+) -> DexQuoteResult<U256> {
+    match hashmap_to_solidly(pool_address, target_data) {
+        Some(pool) => get_amount_in(&pool, token_in, amount_out),
+        None => Err(DexQuoteError::PoolNotFound(pool_address)),
+    }
+}
+
+fn e18() -> U256 {
+    U256::exp10(18)
+}
+
+fn overflow() -> DexQuoteError {
+    DexQuoteError::MathError("solidly math overflow".to_string())
+}
+
+// a * b / 1e18
+fn mul_e18(a: U256, b: U256) -> DexQuoteResult<U256> {
+    Ok(a.checked_mul(b).ok_or_else(overflow)? / e18())
+}
+
+fn add(a: U256, b: U256) -> DexQuoteResult<U256> {
+    a.checked_add(b).ok_or_else(overflow)
+}
+
+// reserves and amounts scaled to 18 decimals, as the stable curve works on them
+struct StableCurve {
+    decimals0: U256,
+    decimals1: U256,
+}
+
+impl StableCurve {
+    fn new(pool: &SolidlyPool) -> Self {
+        Self {
+            decimals0: U256::exp10(pool.token_a_decimals as usize),
+            decimals1: U256::exp10(pool.token_b_decimals as usize),
+        }
+    }
+
+    // x³y+y³x of the unscaled reserves
+    fn k(&self, x: U256, y: U256) -> DexQuoteResult<U256> {
+        let x = x.checked_mul(e18()).ok_or_else(overflow)? / self.decimals0;
+        let y = y.checked_mul(e18()).ok_or_else(overflow)? / self.decimals1;
+        f(x, y)
+    }
+
+    // solve f(x0, y) = xy for y, starting from y
+    // ref. https://github.com/velodrome-finance/contracts/blob/main/contracts/Pool.sol
+    fn get_y(&self, x0: U256, xy: U256, mut y: U256) -> DexQuoteResult<U256> {
+        for _ in 0..255 {
+            let k = f(x0, y)?;
+            if k < xy {
+                let mut dy = (xy - k) * e18() / d(x0, y)?;
+                if dy.is_zero() {
+                    if k == xy {
+                        return Ok(y);
+                    }
+                    // the pool contract compares with the scaled invariant here
+                    if self.k(x0, y + 1)? > xy {
+                        return Ok(y + 1);
+                    }
+                    dy = U256::one();
+                }
+                y = add(y, dy)?;
+            } else {
+                let mut dy = (k - xy) * e18() / d(x0, y)?;
+                if dy.is_zero() {
+                    if k == xy || f(x0, y.checked_sub(U256::one()).ok_or_else(overflow)?)? < xy {
+                        return Ok(y);
+                    }
+                    dy = U256::one();
+                }
+                y = y.checked_sub(dy).ok_or_else(overflow)?;
+            }
+        }
+        Err(DexQuoteError::MathError("!y".to_string()))
+    }
+}
+
+fn f(x0: U256, y: U256) -> DexQuoteResult<U256> {
+    let a = mul_e18(x0, y)?;
+    let b = add(mul_e18(x0, x0)?, mul_e18(y, y)?)?;
+    mul_e18(a, b)
+}
+
+fn d(x0: U256, y: U256) -> DexQuoteResult<U256> {
+    let three_x0 = x0.checked_mul(U256::from(3)).ok_or_else(overflow)?;
+    add(
+        mul_e18(three_x0, mul_e18(y, y)?)?,
+        mul_e18(mul_e18(x0, x0)?, x0)?,
+    )
+}
+
+// (reserve_in, reserve_out, decimals_in, decimals_out)
+fn directional(pool: &SolidlyPool, token_in: Address) -> (U256, U256, U256, U256) {
+    let curve = StableCurve::new(pool);
+    if token_in == pool.token_a {
+        (
+            pool.reserve_0.into(),
+            pool.reserve_1.into(),
+            curve.decimals0,
+            curve.decimals1,
+        )
+    } else {
+        (
+            pool.reserve_1.into(),
+            pool.reserve_0.into(),
+            curve.decimals1,
+            curve.decimals0,
+        )
+    }
+}
+
+/// Output amount of a swap, same as `Pool.getAmountOut`
+pub fn get_amount_out(
+    pool: &SolidlyPool,
+    token_in: Address,
+    amount_in: U256,
+) -> DexQuoteResult<U256> {
+    let amount_in = amount_in - amount_in * U256::from(pool.fee) / U256::from(FEE_DENOMINATOR);
+    let (reserve_in, reserve_out, decimals_in, decimals_out) = directional(pool, token_in);
+    if !pool.stable {
+        let denominator = add(reserve_in, amount_in)?;
+        if denominator.is_zero() {
+            return Err(DexQuoteError::InsufficientLiquidity(pool.address));
+        }
+        return Ok(amount_in.checked_mul(reserve_out).ok_or_else(overflow)? / denominator);
+    }
+    let curve = StableCurve::new(pool);
+    let xy = curve.k(pool.reserve_0.into(), pool.reserve_1.into())?;
+    let reserve_in = reserve_in * e18() / decimals_in;
+    let reserve_out = reserve_out * e18() / decimals_out;
+    let amount_in = amount_in.checked_mul(e18()).ok_or_else(overflow)? / decimals_in;
+    let y = curve.get_y(add(amount_in, reserve_in)?, xy, reserve_out)?;
+    let amount_out = reserve_out
+        .checked_sub(y)
+        .ok_or(DexQuoteError::InsufficientLiquidity(pool.address))?;
+    Ok(amount_out * decimals_out / e18())
+}
+
+/// Input amount needed to receive at least `amount_out`
+pub fn get_amount_in(
+    pool: &SolidlyPool,
+    token_in: Address,
+    amount_out: U256,
+) -> DexQuoteResult<U256> {
+    let (reserve_in, reserve_out, decimals_in, decimals_out) = directional(pool, token_in);
+    if reserve_in.is_zero() || amount_out >= reserve_out {
+        return Err(DexQuoteError::InsufficientLiquidity(pool.address));
+    }
+    let amount_in_after_fee = if pool.stable {
+        let curve = StableCurve::new(pool);
+        let xy = curve.k(pool.reserve_0.into(), pool.reserve_1.into())?;
+        let reserve_in = reserve_in * e18() / decimals_in;
+        let reserve_out = reserve_out * e18() / decimals_out;
+        // round the output up so that the input buys at least `amount_out`
+        let amount_out = (amount_out * e18() + decimals_out - 1) / decimals_out;
+        let new_reserve_in = curve.get_y(reserve_out - amount_out, xy, reserve_in)?;
+        let amount_in = new_reserve_in
+            .checked_sub(reserve_in)
+            .ok_or(DexQuoteError::InsufficientLiquidity(pool.address))?;
+        amount_in * decimals_in / e18() + U256::one()
+    } else {
+        reserve_in * amount_out / (reserve_out - amount_out) + U256::one()
+    };
+    // invert `amount_in - amount_in * fee / 10000`, rounding up
+    let fee_denominator = U256::from(FEE_DENOMINATOR);
+    let fee_multiplier = U256::from(FEE_DENOMINATOR - pool.fee);
+    Ok((amount_in_after_fee * fee_denominator + fee_multiplier - 1) / fee_multiplier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{solidly::add_pool, MemoryStore};
+    use ethers::utils::parse_units;
+
+    const CHAIN_ID: u64 = 10;
+
+    fn stable_pool() -> SolidlyPool {
+        // USDC/DAI
+        SolidlyPool {
+            address: Address::random(),
+            token_a: Address::from_low_u64_be(1),
+            token_a_decimals: 6,
+            token_b: Address::from_low_u64_be(2),
+            token_b_decimals: 18,
+            reserve_0: 1_000_000_000_000,
+            reserve_1: 1_200_000_000_000_000_000_000_000,
+            stable: true,
+            fee: 5,
+        }
+    }
+
+    fn volatile_pool() -> SolidlyPool {
+        // WETH/USDC
+        SolidlyPool {
+            address: Address::random(),
+            token_a: Address::from_low_u64_be(1),
+            token_a_decimals: 18,
+            token_b: Address::from_low_u64_be(2),
+            token_b_decimals: 6,
+            reserve_0: 1_000_000_000_000_000_000_000,
+            reserve_1: 2_000_000_000_000,
+            stable: false,
+            fee: 30,
+        }
+    }
+
+    // expected values are from a line by line port of Pool.getAmountOut
+    #[test]
+    fn test_get_amount_out_stable() {
+        let pool = stable_pool();
+        let amount_out =
+            get_amount_out(&pool, pool.token_a, parse_units("10000", 6).unwrap().into()).unwrap();
+        assert_eq!(
+            amount_out,
+            U256::from_dec_str("10007920571407402645346").unwrap()
+        );
+        let amount_out = get_amount_out(
+            &pool,
+            pool.token_b,
+            parse_units("10000", 18).unwrap().into(),
+        )
+        .unwrap();
+        assert_eq!(amount_out, U256::from(9977593348u64));
+    }
+
+    #[test]
+    fn test_get_amount_out_volatile() {
+        let store = MemoryStore::new();
+        let pool = volatile_pool();
+        add_pool(&store, CHAIN_ID, pool).unwrap();
+        let amount_out = get_price(
+            &store,
+            CHAIN_ID,
+            pool.address,
+            pool.token_a,
+            parse_units("1", 18).unwrap().into(),
+        )
+        .unwrap();
+        assert_eq!(amount_out, U256::from(1992013962u64));
+    }
+
+    #[test]
+    fn test_get_amount_in() {
+        for pool in [stable_pool(), volatile_pool()] {
+            for (token_in, amount_out) in [
+                (
+                    pool.token_a,
+                    U256::exp10(pool.token_b_decimals as usize + 3),
+                ),
+                (
+                    pool.token_b,
+                    U256::exp10(pool.token_a_decimals as usize - 1),
+                ),
+            ] {
+                let amount_in = get_amount_in(&pool, token_in, amount_out).unwrap();
+                // the required input always buys at least the requested output
+                assert!(get_amount_out(&pool, token_in, amount_in).unwrap() >= amount_out);
+                let less = get_amount_out(&pool, token_in, amount_in * 999 / 1000).unwrap();
+                assert!(less < amount_out);
+            }
+        }
+        let pool = volatile_pool();
+        let result = get_amount_in(&pool, pool.token_a, U256::from(pool.reserve_1));
+        assert!(matches!(
+            result,
+            Err(DexQuoteError::InsufficientLiquidity(_))
+        ));
+    }
+}
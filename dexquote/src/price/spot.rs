@@ -0,0 +1,323 @@
+use std::{collections::HashMap, str::FromStr};
+
+use cfmms::pool::Pool;
+use ethers::types::{Address, U256};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    db::{balancer::hashmap_to_balancer, curve::hashmap_to_curve, solidly::hashmap_to_solidly},
+    db::{univ3, PoolStore},
+    dex::Dex,
+    error::DexQuoteError,
+    graph::get_pool_edges,
+    price::{balancer, curve, solidly},
+    types::DexQuoteResult,
+    utils::{now, u256_to_f64},
+};
+
+/// Prices of a quote in whole `token_out` per whole `token_in`
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QuotePrice {
+    /// spot price before fees
+    pub mid_price: f64,
+    /// amount_out / amount_in
+    pub execution_price: f64,
+    /// 1 - execution_price / mid_price, fees and taxes included
+    pub price_impact: f64,
+}
+
+impl QuotePrice {
+    pub fn new(mid_price: f64, execution_price: f64) -> Self {
+        Self {
+            mid_price,
+            execution_price,
+            price_impact: 1.0 - execution_price / mid_price,
+        }
+    }
+}
+
+/// Decimals of a token from the token store, or else from the fields of a pool holding it.
+/// The zero decimals of pools imported from the subgraph are unknown decimals.
+pub fn get_token_decimals<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    target_data: &HashMap<String, String>,
+    token: Address,
+) -> DexQuoteResult<u32> {
+    if let Some(info) = store.get_token(chain_id, token)? {
+        return Ok(info.decimals);
+    }
+    let is_token = |field: &str| {
+        target_data
+            .get(field)
+            .and_then(|address| address.parse::<Address>().ok())
+            == Some(token)
+    };
+    let decimals = if is_token("quote_token") {
+        target_data.get("quote_decimals")
+    } else {
+        (0..)
+            .map_while(|i| target_data.get(&format!("token{i}")).map(|_| i))
+            .find(|i| is_token(&format!("token{i}")))
+            .and_then(|i| {
+                target_data
+                    .get(&format!("token{i}_decimals"))
+                    .or_else(|| target_data.get(&format!("decimals{i}")))
+            })
+    };
+    match decimals.and_then(|decimals| decimals.parse::<u32>().ok()) {
+        Some(decimals) if decimals > 0 => Ok(decimals),
+        _ => Err(DexQuoteError::UnknownDecimals(token)),
+    }
+}
+
+fn no_swap(pool_address: Address, token_in: Address, token_out: Address) -> DexQuoteError {
+    DexQuoteError::GetPriceError(format!(
+        "no swap from {token_in:?} to {token_out:?} in pool {pool_address:?}"
+    ))
+}
+
+// a millionth of the balance of token_in, too small to move the price
+fn marginal_amount(balance_in: U256) -> U256 {
+    (balance_in / U256::exp10(6)).max(U256::one())
+}
+
+/// Spot price of a pool in raw units of `token_out` per raw unit of `token_in`, before fees.
+/// It is read from `sqrt_price` for concentrated pools.
+/// Curve, Balancer and Solidly pools price by their invariant, so a marginal amount
+/// is quoted through their math without the fee.
+/// Other pools use the reserves of the equivalent constant product pool of `graph::PoolEdge`.
+pub fn get_raw_spot_price<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    token_in: Address,
+    token_out: Address,
+    target_data: HashMap<String, String>,
+) -> DexQuoteResult<f64> {
+    let dex_string = target_data
+        .get("dex")
+        .ok_or(DexQuoteError::PoolNotFound(pool_address))?;
+    let dex =
+        Dex::from_str(dex_string).map_err(|_| DexQuoteError::InvalidDex(dex_string.to_string()))?;
+    let price = match dex {
+        Dex::UniswapV3 | Dex::Algebra => match univ3::hashmap_to_univ3(pool_address, target_data) {
+            Some(Pool::UniswapV3(pool)) => {
+                let sqrt_price = u256_to_f64(pool.sqrt_price) / 2f64.powi(96);
+                let price = sqrt_price * sqrt_price;
+                match token_in < token_out {
+                    true => price,
+                    false => 1.0 / price,
+                }
+            }
+            _ => return Err(DexQuoteError::PoolNotFound(pool_address)),
+        },
+        Dex::Curve => {
+            let mut pool = hashmap_to_curve(pool_address, target_data)
+                .ok_or(DexQuoteError::PoolNotFound(pool_address))?;
+            let (i, j) = match (pool.coin_index(token_in), pool.coin_index(token_out)) {
+                (Some(i), Some(j)) if i != j => (i, j),
+                _ => return Err(no_swap(pool_address, token_in, token_out)),
+            };
+            let timestamp = now();
+            curve::set_base_pool_rate(store, chain_id, &mut pool, timestamp)?;
+            pool.fee = U256::zero();
+            let amount_in = marginal_amount(pool.balances[i]);
+            let amount_out = curve::get_dy(&pool, i, j, amount_in, timestamp)?;
+            u256_to_f64(amount_out) / u256_to_f64(amount_in)
+        }
+        Dex::BalancerV2 => {
+            let mut pool = hashmap_to_balancer(pool_address, target_data)
+                .ok_or(DexQuoteError::PoolNotFound(pool_address))?;
+            let i = pool
+                .tokens
+                .iter()
+                .position(|token| *token == token_in)
+                .ok_or_else(|| no_swap(pool_address, token_in, token_out))?;
+            pool.swap_fee = U256::zero();
+            let amount_in = marginal_amount(pool.balances[i]);
+            let amount_out =
+                balancer::get_amount_out(&pool, token_in, token_out, amount_in, now())?;
+            u256_to_f64(amount_out) / u256_to_f64(amount_in)
+        }
+        Dex::Solidly => {
+            let mut pool = hashmap_to_solidly(pool_address, target_data)
+                .ok_or(DexQuoteError::PoolNotFound(pool_address))?;
+            let reserve_in = if (token_in, token_out) == (pool.token_a, pool.token_b) {
+                pool.reserve_0
+            } else if (token_in, token_out) == (pool.token_b, pool.token_a) {
+                pool.reserve_1
+            } else {
+                return Err(no_swap(pool_address, token_in, token_out));
+            };
+            pool.fee = 0;
+            let amount_in = marginal_amount(reserve_in.into());
+            let amount_out = solidly::get_amount_out(&pool, token_in, amount_in)?;
+            u256_to_f64(amount_out) / u256_to_f64(amount_in)
+        }
+        Dex::UniswapV2 | Dex::WooFiV2 => {
+            let edge = get_pool_edges(pool_address, target_data)
+                .and_then(|edges| {
+                    edges
+                        .into_iter()
+                        .find(|edge| edge.token_in == token_in && edge.token_out == token_out)
+                })
+                .ok_or_else(|| no_swap(pool_address, token_in, token_out))?;
+            u256_to_f64(edge.reserve_out) / u256_to_f64(edge.reserve_in)
+        }
+    };
+    if !price.is_finite() || price <= 0.0 {
+        return Err(DexQuoteError::InsufficientLiquidity(pool_address));
+    }
+    Ok(price)
+}
+
+/// Spot price of a pool in whole `token_out` per whole `token_in`, before fees
+pub fn get_spot_price<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    token_in: Address,
+    token_out: Address,
+) -> DexQuoteResult<f64> {
+    let target_data = store.get_pool_hashmap(chain_id, pool_address)?;
+    if target_data.is_empty() {
+        return Err(DexQuoteError::PoolNotFound(pool_address));
+    }
+    let decimals_in = get_token_decimals(store, chain_id, &target_data, token_in)?;
+    let decimals_out = get_token_decimals(store, chain_id, &target_data, token_out)?;
+    let raw_price = get_raw_spot_price(
+        store,
+        chain_id,
+        pool_address,
+        token_in,
+        token_out,
+        target_data,
+    )?;
+    Ok(raw_price * 10f64.powi(decimals_in as i32 - decimals_out as i32))
+}
+
+/// Price paid by a quote in whole `token_out` per whole `token_in`
+pub fn get_execution_price(
+    amount_in: U256,
+    amount_out: U256,
+    decimals_in: u32,
+    decimals_out: u32,
+) -> f64 {
+    u256_to_f64(amount_out) / u256_to_f64(amount_in)
+        * 10f64.powi(decimals_in as i32 - decimals_out as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{
+        curve::{self as curve_db, get_rate, CurvePool},
+        univ2, MemoryStore,
+    };
+    use cfmms::pool::{UniswapV2Pool, UniswapV3Pool};
+
+    const CHAIN_ID: u64 = 42161;
+
+    #[test]
+    fn test_get_spot_price() {
+        let store = MemoryStore::new();
+        let weth = Address::from_low_u64_be(1);
+        let usdc = Address::from_low_u64_be(2);
+        let univ2_pool = Address::from_low_u64_be(10);
+        let univ3_pool = Address::from_low_u64_be(11);
+        // 1000 WETH and 2,000,000 USDC
+        univ2::add_pool(
+            &store,
+            CHAIN_ID,
+            UniswapV2Pool {
+                address: univ2_pool,
+                token_a: weth,
+                token_a_decimals: 18,
+                token_b: usdc,
+                token_b_decimals: 6,
+                reserve_0: 1_000_000_000_000_000_000_000,
+                reserve_1: 2_000_000_000_000,
+                fee: 300,
+            },
+        )
+        .unwrap();
+        let price = get_spot_price(&store, CHAIN_ID, univ2_pool, weth, usdc).unwrap();
+        assert!((price - 2000.0).abs() < 1e-9);
+        let price = get_spot_price(&store, CHAIN_ID, univ2_pool, usdc, weth).unwrap();
+        assert!((price - 0.0005).abs() < 1e-15);
+
+        // sqrt(2000e6 / 1e18) * 2^96
+        let sqrt_price = U256::from_dec_str("3543191142285914205922034").unwrap();
+        univ3::add_pool(
+            &store,
+            CHAIN_ID,
+            UniswapV3Pool {
+                address: univ3_pool,
+                token_a: weth,
+                token_a_decimals: 18,
+                token_b: usdc,
+                token_b_decimals: 6,
+                liquidity: 0,
+                sqrt_price,
+                fee: 500,
+                tick: 0,
+                tick_spacing: 10,
+                liquidity_net: 0,
+            },
+        )
+        .unwrap();
+        // out of range pools still have a price
+        let price = get_spot_price(&store, CHAIN_ID, univ3_pool, weth, usdc).unwrap();
+        assert!((price - 2000.0).abs() < 1e-6);
+
+        let execution_price =
+            get_execution_price(U256::exp10(18), U256::from(1_990_000_000u64), 18, 6);
+        assert!((execution_price - 1990.0).abs() < 1e-9);
+        let quote_price = QuotePrice::new(2000.0, execution_price);
+        assert!((quote_price.price_impact - 0.005).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_get_spot_price_imbalanced_curve_pool() {
+        let store = MemoryStore::new();
+        let usdc = Address::from_low_u64_be(1);
+        let usdt = Address::from_low_u64_be(2);
+        let pool_address = Address::from_low_u64_be(10);
+        // 1,000,000 USDC and 9,000,000 USDT
+        curve_db::add_pool(
+            &store,
+            CHAIN_ID,
+            &CurvePool {
+                address: pool_address,
+                coins: vec![usdc, usdt],
+                decimals: vec![6, 6],
+                balances: vec![
+                    U256::from(1_000_000u64) * U256::exp10(6),
+                    U256::from(9_000_000u64) * U256::exp10(6),
+                ],
+                rates: vec![get_rate(6), get_rate(6)],
+                initial_a: 50.into(),
+                future_a: 50.into(),
+                a_precision: 1.into(),
+                fee: 4_000_000.into(),
+                admin_fee: 5_000_000_000u64.into(),
+                total_supply: U256::from(10_000_000u64) * U256::exp10(18),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        // the scarce USDC is worth more than a USDT
+        let price = get_spot_price(&store, CHAIN_ID, pool_address, usdc, usdt).unwrap();
+        assert!(price > 1.01);
+        let inverse_price = get_spot_price(&store, CHAIN_ID, pool_address, usdt, usdc).unwrap();
+        assert!((price * inverse_price - 1.0).abs() < 1e-5);
+
+        // a small swap only pays the 0.04% fee on top of the mid price
+        let amount_in = U256::from(10_000_000u64);
+        let amount_out =
+            curve::get_price(&store, CHAIN_ID, pool_address, usdc, usdt, amount_in).unwrap();
+        let quote_price = QuotePrice::new(price, get_execution_price(amount_in, amount_out, 6, 6));
+        assert!((quote_price.price_impact - 0.0004).abs() < 1e-5);
+    }
+}
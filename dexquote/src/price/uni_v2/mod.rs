@@ -1,38 +1,28 @@
 use std::collections::HashMap;
 
-use crate::db::univ2::{get_pool, hashmap_to_univ2};
+use crate::db::univ2::{hashmap_to_fee, hashmap_to_univ2};
+use crate::db::PoolStore;
 use crate::error::DexQuoteError;
 use crate::types::DexQuoteResult;
 use cfmms::pool::Pool;
 use ethers::prelude::*;
 use ethers::types::U256;
 
-pub const UNIV2_BASIC_FEE: u32 = 9970;
-pub const FEE_DENOMINATOR: u32 = 10000;
+// pool fees are in 1e-5, e.g. 300 = 0.3%, 250 = 0.25% on PancakeSwap
+pub const UNIV2_FEE_DENOMINATOR: u32 = 100000;
 
-pub fn get_price(
-    redis_url: &str,
+pub fn get_price<S: PoolStore>(
+    store: &S,
     chain_id: u64,
     pool_address: Address,
     token_in: Address,
     amount_in: U256,
 ) -> DexQuoteResult<U256> {
-    let redis_client = redis::Client::open(redis_url).unwrap();
-    let pool_state = match get_pool(&redis_client, chain_id, pool_address) {
-        Ok(pool_state) => match pool_state {
-            Some(pool_state) => pool_state,
-            None => {
-                return Err(DexQuoteError::PoolNotFound(pool_address));
-            }
-        },
-        Err(e) => {
-            return Err(DexQuoteError::RedisError(e));
-        }
-    };
-    if let Pool::UniswapV2(pool) = pool_state {
-        return Ok(pool.simulate_swap(token_in, amount_in));
+    let target_data = store.get_pool_hashmap(chain_id, pool_address)?;
+    if target_data.is_empty() {
+        return Err(DexQuoteError::PoolNotFound(pool_address));
     }
-    Err(DexQuoteError::PoolNotFound(pool_address))
+    get_price_with_hashmap(pool_address, token_in, amount_in, target_data)
 }
 
 pub fn get_price_with_hashmap(
@@ -41,18 +31,127 @@ pub fn get_price_with_hashmap(
     amount_in: U256,
     target_data: HashMap<String, String>,
 ) -> DexQuoteResult<U256> {
+    let fee = hashmap_to_fee(&target_data).ok_or(DexQuoteError::PoolNotFound(pool_address))?;
     let pool_state = hashmap_to_univ2(pool_address, target_data);
     if let Some(Pool::UniswapV2(pool)) = pool_state {
-        return Ok(pool.simulate_swap(token_in, amount_in));
+        let zero_for_one = pool.token_a == token_in;
+        let (reserve_in, reserve_out) = if zero_for_one {
+            (pool.reserve_0, pool.reserve_1)
+        } else {
+            (pool.reserve_1, pool.reserve_0)
+        };
+        return simulate_swap(reserve_in, reserve_out, fee.get(zero_for_one), amount_in);
     }
     Err(DexQuoteError::PoolNotFound(pool_address))
 }
 
+pub fn get_amount_in<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    token_in: Address,
+    amount_out: U256,
+) -> DexQuoteResult<U256> {
+    let target_data = store.get_pool_hashmap(chain_id, pool_address)?;
+    if target_data.is_empty() {
+        return Err(DexQuoteError::PoolNotFound(pool_address));
+    }
+    get_amount_in_with_hashmap(pool_address, token_in, amount_out, target_data)
+}
+
+pub fn get_amount_in_with_hashmap(
+    pool_address: Address,
+    token_in: Address,
+    amount_out: U256,
+    target_data: HashMap<String, String>,
+) -> DexQuoteResult<U256> {
+    let fee = hashmap_to_fee(&target_data).ok_or(DexQuoteError::PoolNotFound(pool_address))?;
+    let pool_state = hashmap_to_univ2(pool_address, target_data);
+    if let Some(Pool::UniswapV2(pool)) = pool_state {
+        let zero_for_one = pool.token_a == token_in;
+        let (reserve_in, reserve_out) = if zero_for_one {
+            (pool.reserve_0, pool.reserve_1)
+        } else {
+            (pool.reserve_1, pool.reserve_0)
+        };
+        return simulate_swap_exact_output(
+            pool_address,
+            reserve_in,
+            reserve_out,
+            fee.get(zero_for_one),
+            amount_out,
+        );
+    }
+    Err(DexQuoteError::PoolNotFound(pool_address))
+}
+
+fn overflow() -> DexQuoteError {
+    DexQuoteError::MathError("univ2 math overflow".to_string())
+}
+
+// constant product swap with the fee taken from the input,
+// same as `UniswapV2Pool::simulate_swap` for fees that are a multiple of 10
+// ref. https://github.com/Uniswap/v2-periphery/blob/0335e8f7e1bd1e8d8329fd300aea2ef2f36dd19f/contracts/libraries/UniswapV2Library.sol#L43-L50
+fn simulate_swap(
+    reserve_in: u128,
+    reserve_out: u128,
+    fee: u32,
+    amount_in: U256,
+) -> DexQuoteResult<U256> {
+    if fee >= UNIV2_FEE_DENOMINATOR {
+        return Err(DexQuoteError::InvalidFee(fee));
+    }
+    let reserve_in = U256::from(reserve_in);
+    let reserve_out = U256::from(reserve_out);
+    let amount_in_with_fee = amount_in
+        .checked_mul(U256::from(UNIV2_FEE_DENOMINATOR - fee))
+        .ok_or_else(overflow)?;
+    // the product of the input and the reserve may not fit in 256 bits
+    let numerator = amount_in_with_fee.full_mul(reserve_out);
+    let denominator =
+        reserve_in.full_mul(U256::from(UNIV2_FEE_DENOMINATOR)) + U512::from(amount_in_with_fee);
+    if denominator.is_zero() {
+        return Ok(U256::zero());
+    }
+    (numerator / denominator).try_into().map_err(|_| overflow())
+}
+
+// constant product inverse of `simulate_swap`
+// ref. https://github.com/Uniswap/v2-periphery/blob/0335e8f7e1bd1e8d8329fd300aea2ef2f36dd19f/contracts/libraries/UniswapV2Library.sol#L53-L60
+fn simulate_swap_exact_output(
+    pool_address: Address,
+    reserve_in: u128,
+    reserve_out: u128,
+    fee: u32,
+    amount_out: U256,
+) -> DexQuoteResult<U256> {
+    if fee >= UNIV2_FEE_DENOMINATOR {
+        return Err(DexQuoteError::InvalidFee(fee));
+    }
+    let reserve_in = U256::from(reserve_in);
+    let reserve_out = U256::from(reserve_out);
+    if reserve_in.is_zero() || amount_out >= reserve_out {
+        return Err(DexQuoteError::InsufficientLiquidity(pool_address));
+    }
+    let numerator = reserve_in.full_mul(amount_out) * U512::from(UNIV2_FEE_DENOMINATOR);
+    let denominator = (reserve_out - amount_out).full_mul(U256::from(UNIV2_FEE_DENOMINATOR - fee));
+    let amount_in: U256 = (numerator / denominator)
+        .try_into()
+        .map_err(|_| overflow())?;
+    amount_in.checked_add(U256::one()).ok_or_else(overflow)
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
 
-    use crate::{constants::provider::get_provider, db::univ2::add_pool};
+    use crate::{
+        constants::provider::get_provider,
+        db::{
+            univ2::{add_pool, update_fee, UniV2Fee},
+            MemoryStore, RedisStore,
+        },
+    };
 
     use super::*;
     use cfmms::pool::UniswapV2Pool;
@@ -114,14 +213,161 @@ mod tests {
         let pool_info = UniswapV2Pool::new_from_address(pool_address, middleware.clone())
             .await
             .unwrap();
-        let redis_client = redis::Client::open(REDIS_URL).unwrap();
-        add_pool(&redis_client, CHAIN_ID, pool_info).unwrap();
+        let store = RedisStore::open(REDIS_URL).unwrap();
+        add_pool(&store, CHAIN_ID, pool_info).unwrap();
         let amount_out =
-            super::get_price(REDIS_URL, CHAIN_ID, pool_address, token_in, amount_in).unwrap();
+            super::get_price(&store, CHAIN_ID, pool_address, token_in, amount_in).unwrap();
         assert_eq!((amount_out > parse_units("0", 18).unwrap().into()), true);
         let amount_out_from_router = get_price_from_router(token_in, token_out, amount_in)
             .await
             .unwrap();
         assert_eq!(amount_out, amount_out_from_router);
     }
+
+    #[test]
+    fn test_get_price_memory_store() {
+        let store = MemoryStore::new();
+        let token_in = USDC_STR.parse::<Address>().unwrap();
+        let token_out = WETH_STR.parse::<Address>().unwrap();
+        let pool_address = Address::random();
+        add_pool(
+            &store,
+            CHAIN_ID,
+            UniswapV2Pool {
+                address: pool_address,
+                token_a: token_out,
+                token_a_decimals: 18,
+                token_b: token_in,
+                token_b_decimals: 6,
+                reserve_0: 1_000_000_000_000_000_000_000,
+                reserve_1: 2_000_000_000_000,
+                fee: 300,
+            },
+        )
+        .unwrap();
+
+        let amount_in: U256 = parse_units("10000", 6).unwrap().into();
+        let amount_out =
+            super::get_price(&store, CHAIN_ID, pool_address, token_in, amount_in).unwrap();
+        // 10000e6 * 997 * 1000e18 / (2_000_000e6 * 1000 + 10000e6 * 997)
+        assert_eq!(
+            amount_out,
+            U256::from_dec_str("4960273038901078125").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_get_amount_in_memory_store() {
+        let store = MemoryStore::new();
+        let token_in = USDC_STR.parse::<Address>().unwrap();
+        let token_out = WETH_STR.parse::<Address>().unwrap();
+        let pool_address = Address::random();
+        add_pool(
+            &store,
+            CHAIN_ID,
+            UniswapV2Pool {
+                address: pool_address,
+                token_a: token_out,
+                token_a_decimals: 18,
+                token_b: token_in,
+                token_b_decimals: 6,
+                reserve_0: 1_000_000_000_000_000_000_000,
+                reserve_1: 2_000_000_000_000,
+                fee: 300,
+            },
+        )
+        .unwrap();
+
+        let amount_out = U256::from_dec_str("4960273038901078125").unwrap();
+        let amount_in =
+            super::get_amount_in(&store, CHAIN_ID, pool_address, token_in, amount_out).unwrap();
+        assert_eq!(amount_in, parse_units("10000", 6).unwrap().into());
+        // the required input always buys at least the requested output
+        let amount_out_check =
+            super::get_price(&store, CHAIN_ID, pool_address, token_in, amount_in).unwrap();
+        assert!(amount_out_check >= amount_out);
+
+        let result = super::get_amount_in(
+            &store,
+            CHAIN_ID,
+            pool_address,
+            token_in,
+            parse_units("1000", 18).unwrap().into(),
+        );
+        assert!(matches!(
+            result,
+            Err(DexQuoteError::InsufficientLiquidity(_))
+        ));
+    }
+
+    #[test]
+    fn test_simulate_swap_large_amount() {
+        let (reserve_in, reserve_out) = (1_000_000_000_000_000_000_000, 2_000_000_000_000);
+        // the output approaches the reserve instead of overflowing
+        let amount_out =
+            simulate_swap(reserve_in, reserve_out, 300, U256::MAX / U256::exp10(6)).unwrap();
+        assert!(amount_out < U256::from(reserve_out));
+        assert!(amount_out > U256::from(reserve_out - 1_000_000));
+        assert!(matches!(
+            simulate_swap(reserve_in, reserve_out, 300, U256::MAX),
+            Err(DexQuoteError::MathError(_))
+        ));
+        let amount_in =
+            simulate_swap_exact_output(Address::zero(), u128::MAX, u128::MAX, 300, U256::one())
+                .unwrap();
+        assert_eq!(amount_in, U256::from(2));
+    }
+
+    #[test]
+    fn test_get_price_pool_fee() {
+        let store = MemoryStore::new();
+        let token_in = USDC_STR.parse::<Address>().unwrap();
+        let token_out = WETH_STR.parse::<Address>().unwrap();
+        let pool_address = Address::random();
+        add_pool(
+            &store,
+            CHAIN_ID,
+            UniswapV2Pool {
+                address: pool_address,
+                token_a: token_out,
+                token_a_decimals: 18,
+                token_b: token_in,
+                token_b_decimals: 6,
+                reserve_0: 1_000_000_000_000_000_000_000,
+                reserve_1: 2_000_000_000_000,
+                fee: 250,
+            },
+        )
+        .unwrap();
+
+        let amount_in: U256 = parse_units("10000", 6).unwrap().into();
+        let amount_out =
+            super::get_price(&store, CHAIN_ID, pool_address, token_in, amount_in).unwrap();
+        // 10000e6 * 9975 * 1000e18 / (2_000_000e6 * 10000 + 10000e6 * 9975)
+        assert_eq!(
+            amount_out,
+            U256::from_dec_str("4962748292889215039").unwrap()
+        );
+        let amount_in_back =
+            super::get_amount_in(&store, CHAIN_ID, pool_address, token_in, amount_out).unwrap();
+        assert!(amount_in_back <= amount_in);
+        let amount_out_check =
+            super::get_price(&store, CHAIN_ID, pool_address, token_in, amount_in_back).unwrap();
+        assert!(amount_out_check >= amount_out);
+
+        // token1 -> token0 swaps pay `fee_one_for_zero`, token0 -> token1 swaps keep `fee`
+        update_fee(&store, CHAIN_ID, pool_address, UniV2Fee::new(250, Some(0))).unwrap();
+        let amount_out =
+            super::get_price(&store, CHAIN_ID, pool_address, token_in, amount_in).unwrap();
+        // 10000e6 * 1000e18 / (2_000_000e6 + 10000e6)
+        assert_eq!(
+            amount_out,
+            U256::from_dec_str("4975124378109452736").unwrap()
+        );
+        let amount_in = parse_units("1", 18).unwrap().into();
+        let amount_out =
+            super::get_price(&store, CHAIN_ID, pool_address, token_out, amount_in).unwrap();
+        // 1e18 * 9975 * 2_000_000e6 / (1000e18 * 10000 + 1e18 * 9975)
+        assert_eq!(amount_out, U256::from(1993011970u64));
+    }
 }
@@ -1,9 +1,13 @@
 use std::collections::HashMap;
+use std::str::FromStr;
 
 use super::tick_bitmap;
 use crate::constants::{sqrt_p::get_sqrt_price_limit_x96, tick_spacing::get_tick_spacing};
 use crate::db::univ3::get_ticks_and_update_if_necessary;
-use crate::db::univ3::{get_pool, hashmap_to_univ3};
+use crate::db::univ3::hashmap_to_univ3;
+use crate::db::PoolStore;
+use crate::dex::Dex;
+use crate::error::DexQuoteError;
 use crate::types::DexQuoteResult;
 use cfmms::pool::Pool;
 use ethers::core::types::{Address, I256, U256};
@@ -18,17 +22,22 @@ struct Slot0 {
     fee_protocol: u8,
 }
 
-pub struct PoolState {
-    redis_client: redis::Client,
+pub struct PoolState<'a, S: PoolStore> {
+    store: &'a S,
     chain_id: u64,
     json_rpc_url: String,
     pub token0: Address,
     pub token1: Address,
     pool_address: Address,
+    dex: Dex,
     slot0: Slot0,
     liquidity: u128,
     tick_spacing: i32,
     pub fee: u32,
+    // Camelot pools charge a separate fee for one for zero swaps
+    fee_one_for_zero: Option<u32>,
+    // only read ticks and tick bitmaps from the store, never from the node
+    offline: bool,
 }
 
 struct SwapCache {
@@ -84,83 +93,130 @@ struct StepComputations {
     fee_amount: U256,
 }
 
-impl PoolState {
+// get liquidity_net of an initialized tick from the store, falling back to the node unless offline
+pub(super) async fn get_liquidity_net<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    json_rpc_url: &str,
+    pool_address: Address,
+    tick: i32,
+    tick_spacing: i32,
+    offline: bool,
+) -> Result<i128, Box<dyn std::error::Error>> {
+    if !offline {
+        return get_ticks_and_update_if_necessary(
+            store,
+            chain_id,
+            pool_address,
+            tick,
+            json_rpc_url,
+        )
+        .await;
+    }
+    match store.get_ticks(chain_id, pool_address, tick)? {
+        Some((_, liquidity_net)) => Ok(liquidity_net),
+        None => Err(Box::new(DexQuoteError::MissingTickData {
+            pool: pool_address,
+            word_pos: tick_bitmap::position(tick / tick_spacing).0,
+        })),
+    }
+}
+
+impl<'a, S: PoolStore> PoolState<'a, S> {
     pub fn init(
-        redis_url: &str,
+        store: &'a S,
         chain_id: u64,
         json_rpc_url: String,
         pool_address: Address,
+        offline: bool,
     ) -> DexQuoteResult<Option<Self>> {
-        let redis_client = redis::Client::open(redis_url)?;
-        let pool_state = get_pool(&redis_client, chain_id, pool_address)?;
-        Self::from_pool_option(
-            pool_state,
-            redis_client,
+        let target_data = store.get_pool_hashmap(chain_id, pool_address)?;
+        if target_data.is_empty() {
+            return Ok(None);
+        }
+        Self::init_with_hashmap(
+            store,
             chain_id,
             json_rpc_url,
             pool_address,
+            target_data,
+            offline,
         )
     }
 
     pub fn init_with_hashmap(
-        redis_url: &str,
+        store: &'a S,
         chain_id: u64,
         json_rpc_url: String,
         pool_address: Address,
         target_data: HashMap<String, String>,
+        offline: bool,
     ) -> DexQuoteResult<Option<Self>> {
-        let redis_client = redis::Client::open(redis_url)?;
-        let pool_state = hashmap_to_univ3(pool_address, target_data);
-        Self::from_pool_option(
-            pool_state,
-            redis_client,
+        let dex = match target_data.get("dex").map(|dex| Dex::from_str(dex)) {
+            Some(Ok(dex)) => dex,
+            _ => Dex::UniswapV3,
+        };
+        // pools imported before the fee protocol was tracked have no field
+        let fee_protocol = target_data
+            .get("fee_protocol")
+            .and_then(|fee_protocol| fee_protocol.parse().ok())
+            .unwrap_or(0);
+        let fee_one_for_zero = target_data
+            .get("fee_one_for_zero")
+            .and_then(|fee| fee.parse().ok());
+        let pool_state = match hashmap_to_univ3(pool_address, target_data) {
+            Some(Pool::UniswapV3(pool_state)) => pool_state,
+            _ => return Ok(None),
+        };
+        let fee = pool_state.fee;
+        // Algebra fees are dynamic, so the pool keeps its own tick spacing
+        let tick_spacing = match dex {
+            Dex::Algebra => pool_state.tick_spacing,
+            _ => get_tick_spacing(fee)?,
+        };
+        let slot0 = Slot0 {
+            sqrt_price_x96: pool_state.sqrt_price,
+            tick: pool_state.tick,
+            fee_protocol,
+        };
+        Ok(Some(Self {
+            store,
             chain_id,
             json_rpc_url,
+            token0: pool_state.token_a,
+            token1: pool_state.token_b,
             pool_address,
-        )
+            dex,
+            liquidity: pool_state.liquidity,
+            slot0,
+            tick_spacing,
+            fee,
+            fee_one_for_zero,
+            offline,
+        }))
     }
 
-    fn from_pool_option(
-        pool_state: Option<Pool>,
-        redis_client: redis::Client,
-        chain_id: u64,
-        json_rpc_url: String,
-        pool_address: Address,
-    ) -> DexQuoteResult<Option<Self>> {
-        match pool_state {
-            Some(Pool::UniswapV3(pool_state)) => {
-                let fee = pool_state.fee;
-                let tick_spacing = get_tick_spacing(fee)?;
-                let slot0 = Slot0 {
-                    sqrt_price_x96: pool_state.sqrt_price,
-                    tick: pool_state.tick,
-                    fee_protocol: 0,
-                };
-                let token0 = pool_state.token_a;
-                let token1 = pool_state.token_b;
-                Ok(Some(Self {
-                    redis_client,
-                    chain_id,
-                    json_rpc_url,
-                    token0,
-                    token1,
-                    pool_address,
-                    liquidity: pool_state.liquidity,
-                    slot0,
-                    tick_spacing,
-                    fee,
-                }))
-            }
-            _ => Ok(None),
+    // returns the output amount for an exact input swap and the input amount for an exact output swap
+    fn delta_to_amount(&self, amount0: I256, amount1: I256, exact_input: bool) -> U256 {
+        match (amount0 > I256::zero(), exact_input) {
+            (true, true) => U256::try_from(-amount1).unwrap(),
+            (false, true) => U256::try_from(-amount0).unwrap(),
+            (true, false) => U256::try_from(amount0).unwrap(),
+            (false, false) => U256::try_from(amount1).unwrap(),
         }
     }
 
-    fn delta_to_amount(&self, amount0: I256, amount1: I256) -> U256 {
-        if amount0 > I256::zero() {
-            U256::try_from(-amount1).unwrap()
-        } else {
-            U256::try_from(-amount0).unwrap()
-        }
+    async fn get_liquidity_net(&self, tick: i32) -> Result<i128, Box<dyn std::error::Error>> {
+        get_liquidity_net(
+            self.store,
+            self.chain_id,
+            &self.json_rpc_url,
+            self.pool_address,
+            tick,
+            self.tick_spacing,
+            self.offline,
+        )
+        .await
     }
 
     pub async fn update_state(
@@ -168,25 +224,48 @@ impl PoolState {
         amount_specified: I256,
         zero_for_one: bool,
     ) -> Result<U256, Box<dyn std::error::Error>> {
-        let (amount_out, state, is_tick_changed, is_liquidity_changed) =
+        let (amount_out, state, _, is_liquidity_changed) =
             self.get_price(amount_specified, zero_for_one).await?;
-        // update pool state
+        // update pool state, the price moves even when the tick does not
         // ref. https://github.com/Uniswap/v3-core/blob/05c10bf6d547d6121622ac51c457f93775e1df09/contracts/UniswapV3Pool.sol#L734-L756
-        if is_tick_changed {
-            self.slot0.sqrt_price_x96 = state.sqrt_price_x96;
-            self.slot0.tick = state.tick;
-        }
+        self.slot0.sqrt_price_x96 = state.sqrt_price_x96;
+        self.slot0.tick = state.tick;
         if is_liquidity_changed {
             self.liquidity = state.liquidity;
         }
         Ok(amount_out)
     }
 
+    /// Required input to receive exactly `amount_out`.
+    /// Fails with `DexQuoteError::InsufficientLiquidity` if the pool cannot fill the whole output.
+    pub async fn get_amount_in(
+        &self,
+        amount_out: U256,
+        zero_for_one: bool,
+    ) -> Result<U256, Box<dyn std::error::Error>> {
+        let amount_specified = -I256::try_from(amount_out)?;
+        let (amount_in, state, _, _) = self.get_price(amount_specified, zero_for_one).await?;
+        if state.amount_specified_remaining != I256::zero() {
+            return Err(Box::new(DexQuoteError::InsufficientLiquidity(
+                self.pool_address,
+            )));
+        }
+        Ok(amount_in)
+    }
+
+    /// Simulate a swap. A positive `amount_specified` is an exact input swap and the
+    /// returned amount is the output, a negative one is an exact output swap and the
+    /// returned amount is the required input.
     pub async fn get_price(
         &self,
         amount_specified: I256,
         zero_for_one: bool,
     ) -> Result<(U256, SwapState, bool, bool), Box<dyn std::error::Error>> {
+        let exact_input = amount_specified > I256::zero();
+        let fee = match (zero_for_one, self.fee_one_for_zero) {
+            (false, Some(fee_one_for_zero)) => fee_one_for_zero,
+            _ => self.fee,
+        };
         let sqrt_price_limit_x96 = get_sqrt_price_limit_x96(zero_for_one);
         let slot0_start = self.slot0;
 
@@ -223,13 +302,15 @@ impl PoolState {
 
             (step.tick_next, step.initialized) =
                 tick_bitmap::next_initialized_tick_within_one_word(
-                    &self.redis_client,
+                    self.store,
                     self.chain_id,
                     &self.json_rpc_url,
                     self.pool_address,
+                    self.dex,
                     state.tick,
                     self.tick_spacing,
                     zero_for_one,
+                    self.offline,
                 )
                 .await?;
 
@@ -264,11 +345,17 @@ impl PoolState {
                 sqrt_ratio_target_x96,
                 state.liquidity,
                 state.amount_specified_remaining,
-                self.fee,
+                fee,
             )?;
 
-            state.amount_specified_remaining -= I256::try_from(step.amount_in + step.fee_amount)?;
-            state.amount_calculated -= I256::try_from(step.amount_out)?;
+            if exact_input {
+                state.amount_specified_remaining -=
+                    I256::try_from(step.amount_in + step.fee_amount)?;
+                state.amount_calculated -= I256::try_from(step.amount_out)?;
+            } else {
+                state.amount_specified_remaining += I256::try_from(step.amount_out)?;
+                state.amount_calculated += I256::try_from(step.amount_in + step.fee_amount)?;
+            }
             if state.amount_specified_remaining == I256::zero() {
                 break;
             };
@@ -284,14 +371,7 @@ impl PoolState {
             if state.sqrt_price_x96 == step.sqrt_price_next_x96 {
                 // if the tick is initialized, run the tick transition
                 if step.initialized {
-                    let mut liquidity_net = get_ticks_and_update_if_necessary(
-                        &self.redis_client,
-                        self.chain_id,
-                        self.pool_address,
-                        step.tick_next,
-                        &self.json_rpc_url,
-                    )
-                    .await?;
+                    let mut liquidity_net = self.get_liquidity_net(step.tick_next).await?;
                     // if we're moving leftward, we interpret liquidity_net as the opposite sign
                     // safe because liquidity_net cannot be type(int128).min
                     if zero_for_one {
@@ -314,7 +394,7 @@ impl PoolState {
         let is_tick_changed = state.tick != slot0_start.tick;
         let is_liquidity_changed = cache.liquidity_start != state.liquidity;
 
-        let (amount0, amount1) = if zero_for_one {
+        let (amount0, amount1) = if zero_for_one == exact_input {
             (
                 amount_specified - state.amount_specified_remaining,
                 state.amount_calculated,
@@ -325,7 +405,7 @@ impl PoolState {
                 amount_specified - state.amount_specified_remaining,
             )
         };
-        let amount_out = self.delta_to_amount(amount0, amount1);
-        Ok((amount_out, state, is_tick_changed, is_liquidity_changed))
+        let amount = self.delta_to_amount(amount0, amount1, exact_input);
+        Ok((amount, state, is_tick_changed, is_liquidity_changed))
     }
 }
@@ -0,0 +1,362 @@
+use std::str::FromStr;
+
+use cfmms::pool::Pool;
+use ethers::types::Address;
+use serde_derive::{Deserialize, Serialize};
+use uniswap_v3_math::{liquidity_math, tick_math};
+
+use super::{
+    custom::get_liquidity_net, tick_bitmap::next_initialized_tick_within_one_word,
+    to_dex_quote_error,
+};
+use crate::{
+    constants::tick_spacing::get_tick_spacing,
+    db::{univ3::hashmap_to_univ3, PoolStore},
+    dex::Dex,
+    error::DexQuoteError,
+    price::get_token_decimals,
+    types::DexQuoteResult,
+    utils::u256_to_f64,
+};
+
+const Q96: f64 = 79228162514264337593543950336.0;
+
+/// Liquidity in range between two ticks, prices in whole token1 per whole token0
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LiquidityRange {
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    pub price_lower: f64,
+    pub price_upper: f64,
+    /// whole tokens swapped to cross the range
+    pub amount0: f64,
+    pub amount1: f64,
+}
+
+/// Whole tokens swapped to move the price by `percent` from the current price.
+/// Moving it down sells `amount0` for `amount1`, moving it up sells `amount1` for `amount0`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DepthLevel {
+    /// negative below the current price
+    pub percent: f64,
+    pub price: f64,
+    pub amount0: f64,
+    pub amount1: f64,
+}
+
+/// Liquidity around the current price of a UniswapV3 or Algebra pool
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LiquidityDepth {
+    pub pool: Address,
+    pub token0: Address,
+    pub token1: Address,
+    pub tick: i32,
+    pub price: f64,
+    /// from the lowest tick, split at the current tick
+    pub ranges: Vec<LiquidityRange>,
+    /// from the lowest price
+    pub levels: Vec<DepthLevel>,
+}
+
+// amounts of token0 and token1 swapped between two sqrt prices
+fn get_amounts(liquidity: u128, sqrt_price_a: f64, sqrt_price_b: f64) -> (f64, f64) {
+    let (lower, upper) = match sqrt_price_a < sqrt_price_b {
+        true => (sqrt_price_a, sqrt_price_b),
+        false => (sqrt_price_b, sqrt_price_a),
+    };
+    let liquidity = liquidity as f64;
+    (
+        liquidity * (1.0 / lower - 1.0 / upper),
+        liquidity * (upper - lower),
+    )
+}
+
+fn get_sqrt_price_at_tick(tick: i32) -> DexQuoteResult<f64> {
+    let sqrt_price_x96 = tick_math::get_sqrt_ratio_at_tick(tick)
+        .map_err(|e| DexQuoteError::MathError(e.to_string()))?;
+    Ok(u256_to_f64(sqrt_price_x96) / Q96)
+}
+
+struct DepthWalk<'a, S: PoolStore> {
+    store: &'a S,
+    chain_id: u64,
+    json_rpc_url: &'a str,
+    pool_address: Address,
+    dex: Dex,
+    tick_spacing: i32,
+    offline: bool,
+    tick: i32,
+    sqrt_price: f64,
+    liquidity: u128,
+    // raw units per whole token
+    scale0: f64,
+    scale1: f64,
+}
+
+impl<S: PoolStore> DepthWalk<'_, S> {
+    fn price(&self, sqrt_price: f64) -> f64 {
+        sqrt_price * sqrt_price * self.scale0 / self.scale1
+    }
+
+    // Walk the initialized ticks from the current price, down or up, until the price moved
+    // by the largest of `percents`. Ranges and levels are in walking order.
+    async fn walk(
+        &self,
+        down: bool,
+        percents: &[f64],
+    ) -> DexQuoteResult<(Vec<LiquidityRange>, Vec<DepthLevel>)> {
+        let mut percents: Vec<f64> = percents
+            .iter()
+            .filter(|percent| **percent > 0.0 && (!down || **percent < 100.0))
+            .copied()
+            .collect();
+        percents.sort_by(f64::total_cmp);
+        let mut percents = percents.into_iter().peekable();
+
+        let (mut ranges, mut levels): (Vec<LiquidityRange>, _) = (Vec::new(), Vec::new());
+        let (mut tick, mut range_tick) = (self.tick, self.tick);
+        let mut sqrt_price = self.sqrt_price;
+        let mut liquidity = self.liquidity;
+        // raw amounts swapped so far
+        let (mut amount0, mut amount1) = (0.0, 0.0);
+        while percents.peek().is_some() {
+            let (tick_next, initialized) = next_initialized_tick_within_one_word(
+                self.store,
+                self.chain_id,
+                self.json_rpc_url,
+                self.pool_address,
+                self.dex,
+                tick,
+                self.tick_spacing,
+                down,
+                self.offline,
+            )
+            .await
+            .map_err(to_dex_quote_error)?;
+            let tick_next = tick_next.clamp(tick_math::MIN_TICK, tick_math::MAX_TICK);
+            let sqrt_price_next = get_sqrt_price_at_tick(tick_next)?;
+
+            while let Some(percent) = percents.peek().copied() {
+                let ratio = match down {
+                    true => 1.0 - percent / 100.0,
+                    false => 1.0 + percent / 100.0,
+                };
+                let sqrt_price_target = self.sqrt_price * ratio.sqrt();
+                if (down && sqrt_price_target < sqrt_price_next)
+                    || (!down && sqrt_price_target > sqrt_price_next)
+                {
+                    break;
+                }
+                let (step0, step1) = get_amounts(liquidity, sqrt_price, sqrt_price_target);
+                levels.push(DepthLevel {
+                    percent: if down { -percent } else { percent },
+                    price: self.price(sqrt_price_target),
+                    amount0: (amount0 + step0) / self.scale0,
+                    amount1: (amount1 + step1) / self.scale1,
+                });
+                percents.next();
+            }
+
+            if tick_next != range_tick {
+                let (step0, step1) = get_amounts(liquidity, sqrt_price, sqrt_price_next);
+                amount0 += step0;
+                amount1 += step1;
+                let (tick_lower, tick_upper) = match down {
+                    true => (tick_next, range_tick),
+                    false => (range_tick, tick_next),
+                };
+                match ranges.last_mut() {
+                    // word boundaries do not change the liquidity
+                    Some(last) if last.liquidity == liquidity => {
+                        last.tick_lower = last.tick_lower.min(tick_lower);
+                        last.tick_upper = last.tick_upper.max(tick_upper);
+                        last.amount0 += step0 / self.scale0;
+                        last.amount1 += step1 / self.scale1;
+                    }
+                    _ => ranges.push(LiquidityRange {
+                        tick_lower,
+                        tick_upper,
+                        liquidity,
+                        price_lower: 0.0,
+                        price_upper: 0.0,
+                        amount0: step0 / self.scale0,
+                        amount1: step1 / self.scale1,
+                    }),
+                }
+            }
+            if (down && tick_next == tick_math::MIN_TICK)
+                || (!down && tick_next == tick_math::MAX_TICK)
+            {
+                break;
+            }
+            if initialized {
+                let liquidity_net = get_liquidity_net(
+                    self.store,
+                    self.chain_id,
+                    self.json_rpc_url,
+                    self.pool_address,
+                    tick_next,
+                    self.tick_spacing,
+                    self.offline,
+                )
+                .await
+                .map_err(to_dex_quote_error)?;
+                let liquidity_delta = if down { -liquidity_net } else { liquidity_net };
+                liquidity = liquidity_math::add_delta(liquidity, liquidity_delta)
+                    .map_err(|e| DexQuoteError::MathError(e.to_string()))?;
+            }
+            sqrt_price = sqrt_price_next;
+            range_tick = tick_next;
+            tick = if down { tick_next - 1 } else { tick_next };
+        }
+
+        for range in ranges.iter_mut() {
+            range.price_lower = self.price(get_sqrt_price_at_tick(range.tick_lower)?);
+            range.price_upper = self.price(get_sqrt_price_at_tick(range.tick_upper)?);
+        }
+        Ok((ranges, levels))
+    }
+}
+
+/// Liquidity per tick range and the amounts to move the price of a UniswapV3 or Algebra pool
+/// by each of `percents`, up and down, walking its tick bitmap from the current tick.
+/// If `offline` is set, a tick or bitmap word missing from the store is an error.
+pub async fn get_liquidity_depth<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    json_rpc_url: &str,
+    pool_address: Address,
+    percents: &[f64],
+    offline: bool,
+) -> DexQuoteResult<LiquidityDepth> {
+    let target_data = store.get_pool_hashmap(chain_id, pool_address)?;
+    let dex_string = target_data
+        .get("dex")
+        .ok_or(DexQuoteError::PoolNotFound(pool_address))?;
+    let dex =
+        Dex::from_str(dex_string).map_err(|_| DexQuoteError::InvalidDex(dex_string.to_string()))?;
+    if !matches!(dex, Dex::UniswapV3 | Dex::Algebra) {
+        return Err(DexQuoteError::InvalidDex(dex_string.to_string()));
+    }
+    let pool = match hashmap_to_univ3(pool_address, target_data.clone()) {
+        Some(Pool::UniswapV3(pool)) => pool,
+        _ => return Err(DexQuoteError::PoolNotFound(pool_address)),
+    };
+    let decimals0 = get_token_decimals(store, chain_id, &target_data, pool.token_a)?;
+    let decimals1 = get_token_decimals(store, chain_id, &target_data, pool.token_b)?;
+    // Algebra fees are dynamic, so the pool keeps its own tick spacing
+    let tick_spacing = match dex {
+        Dex::Algebra => pool.tick_spacing,
+        _ => get_tick_spacing(pool.fee)?,
+    };
+    let walk = DepthWalk {
+        store,
+        chain_id,
+        json_rpc_url,
+        pool_address,
+        dex,
+        tick_spacing,
+        offline,
+        tick: pool.tick,
+        sqrt_price: u256_to_f64(pool.sqrt_price) / Q96,
+        liquidity: pool.liquidity,
+        scale0: 10f64.powi(decimals0 as i32),
+        scale1: 10f64.powi(decimals1 as i32),
+    };
+    let (mut ranges, mut levels) = walk.walk(true, percents).await?;
+    ranges.reverse();
+    levels.reverse();
+    let (ranges_up, levels_up) = walk.walk(false, percents).await?;
+    ranges.extend(ranges_up);
+    levels.extend(levels_up);
+    Ok(LiquidityDepth {
+        pool: pool_address,
+        token0: pool.token_a,
+        token1: pool.token_b,
+        tick: pool.tick,
+        price: walk.price(walk.sqrt_price),
+        ranges,
+        levels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{univ3::add_pool, MemoryStore};
+    use cfmms::pool::UniswapV3Pool;
+    use ethers::types::U256;
+
+    const CHAIN_ID: u64 = 42161;
+
+    #[tokio::test]
+    async fn test_get_liquidity_depth() {
+        let store = MemoryStore::new();
+        let pool_address = Address::from_low_u64_be(10);
+        let liquidity: u128 = 1_000_000_000_000_000_000;
+        add_pool(
+            &store,
+            CHAIN_ID,
+            UniswapV3Pool {
+                address: pool_address,
+                token_a: Address::from_low_u64_be(1),
+                token_a_decimals: 18,
+                token_b: Address::from_low_u64_be(2),
+                token_b_decimals: 18,
+                liquidity,
+                sqrt_price: U256::one() << 96,
+                fee: 3000,
+                tick: 0,
+                tick_spacing: 60,
+                liquidity_net: 0,
+            },
+        )
+        .unwrap();
+        // a single position from tick -600 to 600, ticks -10 and 10 once compressed
+        store
+            .update_tick_bitmap(CHAIN_ID, pool_address, -1, U256::one() << 246)
+            .unwrap();
+        store
+            .update_tick_bitmap(CHAIN_ID, pool_address, 0, U256::one() << 10)
+            .unwrap();
+        store
+            .update_ticks(CHAIN_ID, pool_address, -600, liquidity, liquidity as i128)
+            .unwrap();
+        store
+            .update_ticks(CHAIN_ID, pool_address, 600, liquidity, -(liquidity as i128))
+            .unwrap();
+
+        let depth = get_liquidity_depth(&store, CHAIN_ID, "", pool_address, &[1.0, 5.0], true)
+            .await
+            .unwrap();
+        assert_eq!(depth.price, 1.0);
+        let ticks: Vec<(i32, i32)> = depth
+            .ranges
+            .iter()
+            .map(|range| (range.tick_lower, range.tick_upper))
+            .collect();
+        assert_eq!(ticks, vec![(-600, 0), (0, 600)]);
+        assert!(depth
+            .ranges
+            .iter()
+            .all(|range| range.liquidity == liquidity));
+
+        let percents: Vec<f64> = depth.levels.iter().map(|level| level.percent).collect();
+        assert_eq!(percents, vec![-5.0, -1.0, 1.0, 5.0]);
+        // within one position the amounts are those of a constant product pool with L = 1
+        let level = &depth.levels[0];
+        assert!((level.price - 0.95).abs() < 1e-12);
+        assert!((level.amount0 - (1.0 / 0.95f64.sqrt() - 1.0)).abs() < 1e-12);
+        assert!((level.amount1 - (1.0 - 0.95f64.sqrt())).abs() < 1e-12);
+        let level = &depth.levels[3];
+        assert!((level.amount1 - (1.05f64.sqrt() - 1.0)).abs() < 1e-12);
+
+        // a missing bitmap word past the position fails offline
+        assert!(
+            get_liquidity_depth(&store, CHAIN_ID, "", pool_address, &[99.0], true)
+                .await
+                .is_err()
+        );
+    }
+}
@@ -3,55 +3,61 @@ use std::collections::HashMap;
 use ethers::core::types::{Address, I256, U256};
 mod custom;
 pub use custom::*;
+mod depth;
+pub use depth::*;
 mod tick_bitmap;
 
-use crate::{error::DexQuoteError, types::DexQuoteResult};
+use crate::{db::PoolStore, error::DexQuoteError, types::DexQuoteResult};
 
-pub async fn get_price(
-    redis_url: &str,
+pub async fn get_price<S: PoolStore>(
+    store: &S,
     chain_id: u64,
     json_rpc_url: String,
     pool_address: Address,
     zero_for_one: bool,
     amount_in: U256,
+    offline: bool,
 ) -> DexQuoteResult<U256> {
-    let pool_state = match custom::PoolState::init(redis_url, chain_id, json_rpc_url, pool_address)
-    {
-        Ok(pool_state) => match pool_state {
-            Some(pool_state) => pool_state,
-            None => {
-                return Err(DexQuoteError::PoolNotFound(pool_address));
+    let pool_state =
+        match custom::PoolState::init(store, chain_id, json_rpc_url, pool_address, offline) {
+            Ok(pool_state) => match pool_state {
+                Some(pool_state) => pool_state,
+                None => {
+                    return Err(DexQuoteError::PoolNotFound(pool_address));
+                }
+            },
+            Err(e) => {
+                return Err(e);
             }
-        },
-        Err(e) => {
-            return Err(e);
-        }
-    };
+        };
     let amoount_specified = I256::from_raw(amount_in);
     let (amount_out, _, _, _) = match pool_state.get_price(amoount_specified, zero_for_one).await {
         Ok(result) => result,
         Err(e) => {
-            return Err(DexQuoteError::GetPriceError(e.to_string()));
+            return Err(to_dex_quote_error(e));
         }
     };
     Ok(amount_out)
 }
 
-pub async fn get_price_with_hashmap(
-    redis_url: &str,
+#[allow(clippy::too_many_arguments)]
+pub async fn get_price_with_hashmap<S: PoolStore>(
+    store: &S,
     chain_id: u64,
     json_rpc_url: String,
     pool_address: Address,
     zero_for_one: bool,
     amount_in: U256,
     target_data: HashMap<String, String>,
+    offline: bool,
 ) -> DexQuoteResult<U256> {
     let pool_state = match custom::PoolState::init_with_hashmap(
-        redis_url,
+        store,
         chain_id,
         json_rpc_url,
         pool_address,
         target_data,
+        offline,
     ) {
         Ok(pool_state) => match pool_state {
             Some(pool_state) => pool_state,
@@ -67,17 +73,78 @@ pub async fn get_price_with_hashmap(
     let (amount_out, _, _, _) = match pool_state.get_price(amoount_specified, zero_for_one).await {
         Ok(result) => result,
         Err(e) => {
-            return Err(DexQuoteError::GetPriceError(e.to_string()));
+            return Err(to_dex_quote_error(e));
         }
     };
     Ok(amount_out)
 }
 
+pub async fn get_amount_in<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    json_rpc_url: String,
+    pool_address: Address,
+    zero_for_one: bool,
+    amount_out: U256,
+    offline: bool,
+) -> DexQuoteResult<U256> {
+    let pool_state =
+        match custom::PoolState::init(store, chain_id, json_rpc_url, pool_address, offline)? {
+            Some(pool_state) => pool_state,
+            None => {
+                return Err(DexQuoteError::PoolNotFound(pool_address));
+            }
+        };
+    pool_state
+        .get_amount_in(amount_out, zero_for_one)
+        .await
+        .map_err(to_dex_quote_error)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn get_amount_in_with_hashmap<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    json_rpc_url: String,
+    pool_address: Address,
+    zero_for_one: bool,
+    amount_out: U256,
+    target_data: HashMap<String, String>,
+    offline: bool,
+) -> DexQuoteResult<U256> {
+    let pool_state = match custom::PoolState::init_with_hashmap(
+        store,
+        chain_id,
+        json_rpc_url,
+        pool_address,
+        target_data,
+        offline,
+    )? {
+        Some(pool_state) => pool_state,
+        None => {
+            return Err(DexQuoteError::PoolNotFound(pool_address));
+        }
+    };
+    pool_state
+        .get_amount_in(amount_out, zero_for_one)
+        .await
+        .map_err(to_dex_quote_error)
+}
+
+// keep typed errors such as `MissingTickData` and wrap everything else
+pub(crate) fn to_dex_quote_error(e: Box<dyn std::error::Error>) -> DexQuoteError {
+    match e.downcast::<DexQuoteError>() {
+        Ok(e) => *e,
+        Err(e) => DexQuoteError::GetPriceError(e.to_string()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::constants::provider::get_provider;
     use crate::db::univ3::{add_pool, add_tick_bitmap_from_tick};
+    use crate::db::{MemoryStore, RedisStore};
     use cfmms::pool::UniswapV3Pool;
     use compute_univ3_address::uni_v3::get_pool_address;
     use ethers::{prelude::abigen, utils::parse_units};
@@ -141,27 +208,21 @@ mod tests {
         let pool_info = UniswapV3Pool::new_from_address(pool_address, middleware.clone())
             .await
             .unwrap();
-        let redis_client = redis::Client::open(REDIS_URL).unwrap();
+        let store = RedisStore::open(REDIS_URL).unwrap();
         let tick = pool_info.tick;
-        add_pool(&redis_client, CHAIN_ID, pool_info).unwrap();
-        add_tick_bitmap_from_tick(
-            &redis_client,
-            CHAIN_ID,
-            pool_address,
-            tick,
-            fee,
-            &json_rpc_url,
-        )
-        .await
-        .unwrap();
+        add_pool(&store, CHAIN_ID, pool_info).unwrap();
+        add_tick_bitmap_from_tick(&store, CHAIN_ID, pool_address, tick, fee, &json_rpc_url)
+            .await
+            .unwrap();
 
         let amount_out = super::get_price(
-            REDIS_URL,
+            &store,
             CHAIN_ID,
             json_rpc_url,
             pool_address,
             zero_for_one,
             amount_in,
+            false,
         )
         .await
         .unwrap();
@@ -192,27 +253,21 @@ mod tests {
         let pool_info = UniswapV3Pool::new_from_address(pool_address, middleware.clone())
             .await
             .unwrap();
-        let redis_client = redis::Client::open(REDIS_URL).unwrap();
+        let store = RedisStore::open(REDIS_URL).unwrap();
         let tick = pool_info.tick;
-        add_pool(&redis_client, CHAIN_ID, pool_info).unwrap();
-        add_tick_bitmap_from_tick(
-            &redis_client,
-            CHAIN_ID,
-            pool_address,
-            tick,
-            fee,
-            &json_rpc_url,
-        )
-        .await
-        .unwrap();
+        add_pool(&store, CHAIN_ID, pool_info).unwrap();
+        add_tick_bitmap_from_tick(&store, CHAIN_ID, pool_address, tick, fee, &json_rpc_url)
+            .await
+            .unwrap();
 
         let amount_out = super::get_price(
-            REDIS_URL,
+            &store,
             CHAIN_ID,
             json_rpc_url,
             pool_address,
             zero_for_one,
             amount_in,
+            false,
         )
         .await
         .unwrap();
@@ -222,4 +277,127 @@ mod tests {
             .unwrap();
         assert_eq!(amount_out, amount_out_quoter);
     }
+
+    #[tokio::test]
+    async fn test_get_price_offline_missing_tick_data() {
+        let store = MemoryStore::new();
+        let pool_address = Address::random();
+        let pool = UniswapV3Pool {
+            address: pool_address,
+            token_a: USDC_STR.parse().unwrap(),
+            token_a_decimals: 6,
+            token_b: WETH_STR.parse().unwrap(),
+            token_b_decimals: 18,
+            liquidity: 1_000_000_000_000_000_000,
+            sqrt_price: U256::from(1) << 96,
+            fee: 500,
+            tick: 0,
+            tick_spacing: 10,
+            liquidity_net: 0,
+        };
+        add_pool(&store, CHAIN_ID, pool).unwrap();
+
+        // the rpc url is never used in offline mode
+        let result = super::get_price(
+            &store,
+            CHAIN_ID,
+            "".to_string(),
+            pool_address,
+            true,
+            U256::from(1_000_000),
+            true,
+        )
+        .await;
+        match result {
+            Err(DexQuoteError::MissingTickData { pool, word_pos }) => {
+                assert_eq!(pool, pool_address);
+                assert_eq!(word_pos, 0);
+            }
+            _ => panic!("expected MissingTickData, got {:?}", result),
+        }
+
+        // an empty word lets the swap move on to the next word on the left
+        store
+            .update_tick_bitmap(CHAIN_ID, pool_address, 0, U256::zero())
+            .unwrap();
+        let result = super::get_price(
+            &store,
+            CHAIN_ID,
+            "".to_string(),
+            pool_address,
+            true,
+            U256::from(1_000_000),
+            true,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(DexQuoteError::MissingTickData { word_pos: -1, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_amount_in_offline() {
+        let store = MemoryStore::new();
+        let pool_address = Address::random();
+        let pool = UniswapV3Pool {
+            address: pool_address,
+            token_a: USDC_STR.parse().unwrap(),
+            token_a_decimals: 6,
+            token_b: WETH_STR.parse().unwrap(),
+            token_b_decimals: 18,
+            liquidity: 1_000_000_000_000_000_000,
+            sqrt_price: U256::from(1) << 96,
+            fee: 500,
+            tick: 0,
+            tick_spacing: 10,
+            liquidity_net: 0,
+        };
+        add_pool(&store, CHAIN_ID, pool).unwrap();
+        // no initialized ticks within the words around the current tick
+        for word_pos in [-1, 0] {
+            store
+                .update_tick_bitmap(CHAIN_ID, pool_address, word_pos, U256::zero())
+                .unwrap();
+        }
+
+        let amount_out = U256::from(1_000_000);
+        let amount_in = super::get_amount_in(
+            &store,
+            CHAIN_ID,
+            "".to_string(),
+            pool_address,
+            true,
+            amount_out,
+            true,
+        )
+        .await
+        .unwrap();
+        assert!(amount_in > amount_out);
+        // the required input buys at least the requested output
+        let amount_out_check = super::get_price(
+            &store,
+            CHAIN_ID,
+            "".to_string(),
+            pool_address,
+            true,
+            amount_in,
+            true,
+        )
+        .await
+        .unwrap();
+        assert!(amount_out_check >= amount_out);
+        let amount_out_less = super::get_price(
+            &store,
+            CHAIN_ID,
+            "".to_string(),
+            pool_address,
+            true,
+            amount_in - 1,
+            true,
+        )
+        .await
+        .unwrap();
+        assert!(amount_out_less < amount_out);
+    }
 }
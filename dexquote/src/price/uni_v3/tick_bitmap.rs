@@ -1,21 +1,57 @@
 use crate::db::univ3::get_tick_bitmap_and_update_if_necessary;
+use crate::db::PoolStore;
+use crate::dex::Dex;
+use crate::error::DexQuoteError;
 
 use ethers::prelude::*;
 use uniswap_v3_math::bit_math;
-use uniswap_v3_math::error::UniswapV3MathError;
 
-//Returns next and initialized. This function calls the node to get the word at the word_pos.
+// get the word from the store if it exists, otherwise get it from the node unless offline
+async fn get_word<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    json_rpc_url: &str,
+    pool_address: H160,
+    dex: Dex,
+    word_pos: i16,
+    offline: bool,
+) -> Result<U256, Box<dyn std::error::Error>> {
+    if offline {
+        return match store.get_tick_bitmap(chain_id, pool_address, word_pos)? {
+            Some(word) => Ok(word),
+            None => Err(Box::new(DexQuoteError::MissingTickData {
+                pool: pool_address,
+                word_pos,
+            })),
+        };
+    }
+    get_tick_bitmap_and_update_if_necessary(
+        store,
+        chain_id,
+        pool_address,
+        word_pos,
+        dex,
+        json_rpc_url,
+    )
+    .await
+}
+
+//Returns next and initialized. This function calls the node to get the word at the word_pos
+//unless `offline` is set, in which case a missing word is a `DexQuoteError::MissingTickData`.
 //current_word is the current word in the TickBitmap of the pool based on `tick`. TickBitmap[word_pos] = current_word
 //Where word_pos is the 256 bit offset of the ticks word_pos.. word_pos := tick >> 8
-pub async fn next_initialized_tick_within_one_word(
-    redis_client: &redis::Client,
+#[allow(clippy::too_many_arguments)]
+pub async fn next_initialized_tick_within_one_word<S: PoolStore>(
+    store: &S,
     chain_id: u64,
     json_rpc_url: &str,
     pool_address: H160,
+    dex: Dex,
     tick: i32,
     tick_spacing: i32,
     lte: bool,
-) -> Result<(i32, bool), UniswapV3MathError> {
+    offline: bool,
+) -> Result<(i32, bool), Box<dyn std::error::Error>> {
     let compressed = if tick < 0 && tick % tick_spacing != 0 {
         (tick / tick_spacing) - 1
     } else {
@@ -26,19 +62,16 @@ pub async fn next_initialized_tick_within_one_word(
         let (word_pos, bit_pos) = position(compressed);
         let mask = (U256::one() << bit_pos) - 1 + (U256::one() << bit_pos);
 
-        // get the word from redis if it exists, otherwise get it from the node
-        let word = match get_tick_bitmap_and_update_if_necessary(
-            redis_client,
+        let word = get_word(
+            store,
             chain_id,
+            json_rpc_url,
             pool_address,
+            dex,
             word_pos,
-            json_rpc_url,
+            offline,
         )
-        .await
-        {
-            Ok(word) => word,
-            Err(e) => return Err(UniswapV3MathError::MiddlewareError(e.to_string())),
-        };
+        .await?;
 
         let masked = word & mask;
 
@@ -59,19 +92,16 @@ pub async fn next_initialized_tick_within_one_word(
         let (word_pos, bit_pos) = position(compressed + 1);
         let mask = !((U256::one() << bit_pos) - U256::one());
 
-        // get the word from redis if it exists, otherwise get it from the node
-        let word = match get_tick_bitmap_and_update_if_necessary(
-            redis_client,
+        let word = get_word(
+            store,
             chain_id,
+            json_rpc_url,
             pool_address,
+            dex,
             word_pos,
-            json_rpc_url,
+            offline,
         )
-        .await
-        {
-            Ok(word) => word,
-            Err(e) => return Err(UniswapV3MathError::MiddlewareError(e.to_string())),
-        };
+        .await?;
 
         let masked = word & mask;
         let initialized = !masked.is_zero();
@@ -0,0 +1,404 @@
+use std::collections::HashMap;
+
+use crate::db::woofi::{get_pool, hashmap_to_woofi, WooFiPool, WooFiToken};
+use crate::db::PoolStore;
+use crate::error::DexQuoteError;
+use crate::types::DexQuoteResult;
+use ethers::types::{Address, U256};
+
+// ref. https://github.com/woonetwork/WooPoolV2/blob/main/contracts/WooPPV2.sol
+const FEE_RATE_DENOMINATOR: u64 = 100_000;
+const MAX_SEARCH_ITERATIONS: usize = 256;
+
+pub fn get_price<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    pool_address: Address,
+    token_in: Address,
+    token_out: Address,
+    amount_in: U256,
+) -> DexQuoteResult<U256> {
+    match get_pool(store, chain_id, pool_address)? {
+        Some(pool) => Ok(query(&pool, token_in, token_out, amount_in)?.to_amount),
+        None => Err(DexQuoteError::PoolNotFound(pool_address)),
+    }
+}
+
+pub fn get_price_with_hashmap(
+    pool_address: Address,
+    token_in: Address,
+    token_out: Address,
+    amount_in: U256,
+    target_data: HashMap<String, String>,
+) -> DexQuoteResult<U256> {
+    match hashmap_to_woofi(pool_address, target_data) {
+        Some(pool) => Ok(query(&pool, token_in, token_out, amount_in)?.to_amount),
+        None => Err(DexQuoteError::PoolNotFound(pool_address)),
+    }
+}
+
+pub fn get_amount_in_with_hashmap(
+    pool_address: Address,
+    token_in: Address,
+    token_out: Address,
+    amount_out: U256,
+    target_data: HashMap<String, String>,
+) -> DexQuoteResult<U256> {
+    match hashmap_to_woofi(pool_address, target_data) {
+        Some(pool) => get_amount_in(&pool, token_in, token_out, amount_out),
+        None => Err(DexQuoteError::PoolNotFound(pool_address)),
+    }
+}
+
+fn e18() -> U256 {
+    U256::exp10(18)
+}
+
+fn overflow() -> DexQuoteError {
+    DexQuoteError::MathError("woofi math overflow".to_string())
+}
+
+fn mul(a: U256, b: U256) -> DexQuoteResult<U256> {
+    a.checked_mul(b).ok_or_else(overflow)
+}
+
+fn sub(a: U256, b: U256) -> DexQuoteResult<U256> {
+    a.checked_sub(b).ok_or_else(overflow)
+}
+
+fn div(a: U256, b: U256) -> DexQuoteResult<U256> {
+    a.checked_div(b).ok_or_else(overflow)
+}
+
+/// Result of a swap and the prices the pool posts to the oracle
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WooFiQuote {
+    pub to_amount: U256,
+    // in the quote token
+    pub swap_fee: U256,
+    // quote volume of the swap, fee included
+    pub swap_volume: U256,
+    pub new_from_price: Option<U256>,
+    pub new_to_price: Option<U256>,
+}
+
+struct Decimals {
+    price_dec: U256,
+    quote_dec: U256,
+    base_dec: U256,
+}
+
+impl Decimals {
+    fn new(token: &WooFiToken, quote_decimals: u8) -> Self {
+        Self {
+            price_dec: U256::exp10(token.price_decimals as usize),
+            quote_dec: U256::exp10(quote_decimals as usize),
+            base_dec: U256::exp10(token.decimals as usize),
+        }
+    }
+}
+
+fn check_feasible(token: &WooFiToken) -> DexQuoteResult<()> {
+    if !token.feasible || token.price.is_zero() {
+        return Err(DexQuoteError::GetPriceError(format!(
+            "oracle of {:?} is not feasible",
+            token.address
+        )));
+    }
+    Ok(())
+}
+
+/// Quote amount for `base_amount` and the new base price,
+/// quote = base * price * (1 - k * base * price - spread)
+fn calc_quote_amount_sell_base(
+    token: &WooFiToken,
+    spread: u64,
+    quote_decimals: u8,
+    base_amount: U256,
+) -> DexQuoteResult<(U256, U256)> {
+    check_feasible(token)?;
+    let decs = Decimals::new(token, quote_decimals);
+    let (price, coeff) = (token.price, U256::from(token.coeff));
+    let slippage = mul(mul(coeff, base_amount)?, price)? / decs.base_dec / decs.price_dec;
+    let coef = sub(sub(e18(), slippage)?, spread.into())?;
+    let quote_amount = mul(
+        mul(mul(base_amount, decs.quote_dec)?, price)? / decs.price_dec,
+        coef,
+    )? / e18()
+        / decs.base_dec;
+    // new price = price * (1 - 2 * k * price * base)
+    let double_slippage =
+        mul(mul(mul(coeff, 2.into())?, price)?, base_amount)? / decs.price_dec / decs.base_dec;
+    let new_price = mul(sub(e18(), double_slippage)?, price)? / e18();
+    Ok((quote_amount, new_price))
+}
+
+/// Base amount for `quote_amount` and the new base price,
+/// base = quote / price * (1 - k * quote - spread)
+fn calc_base_amount_sell_quote(
+    token: &WooFiToken,
+    spread: u64,
+    quote_decimals: u8,
+    quote_amount: U256,
+) -> DexQuoteResult<(U256, U256)> {
+    check_feasible(token)?;
+    let decs = Decimals::new(token, quote_decimals);
+    let (price, coeff) = (token.price, U256::from(token.coeff));
+    let slippage = mul(quote_amount, coeff)? / decs.quote_dec;
+    let coef = sub(sub(e18(), slippage)?, spread.into())?;
+    let base_amount = mul(
+        div(
+            mul(mul(quote_amount, decs.base_dec)?, decs.price_dec)?,
+            price,
+        )?,
+        coef,
+    )? / e18()
+        / decs.quote_dec;
+    // new price = price / (1 - 2 * k * quote)
+    let double_slippage = mul(mul(coeff, 2.into())?, quote_amount)? / decs.quote_dec;
+    let new_price = div(mul(e18(), price)?, sub(e18(), double_slippage)?)?;
+    Ok((base_amount, new_price))
+}
+
+fn base_token(pool: &WooFiPool, token: Address) -> DexQuoteResult<&WooFiToken> {
+    pool.token(token).ok_or_else(|| {
+        DexQuoteError::GetPriceError(format!("{:?} does not trade {:?}", pool.address, token))
+    })
+}
+
+fn fee(amount: U256, fee_rate: u16) -> DexQuoteResult<U256> {
+    Ok(mul(amount, fee_rate.into())? / FEE_RATE_DENOMINATOR)
+}
+
+/// `tryQuery` of the pool, the reserve of `to_token` is not checked
+pub fn try_query(
+    pool: &WooFiPool,
+    from_token: Address,
+    to_token: Address,
+    from_amount: U256,
+) -> DexQuoteResult<WooFiQuote> {
+    if from_token == to_token {
+        return Err(DexQuoteError::GetPriceError(format!(
+            "cannot swap {:?} for itself",
+            from_token
+        )));
+    }
+    if from_token == pool.quote_token {
+        // quote to base, the fee is taken from the quote amount in
+        let token = base_token(pool, to_token)?;
+        let swap_fee = fee(from_amount, token.fee_rate)?;
+        let (to_amount, new_price) = calc_base_amount_sell_quote(
+            token,
+            token.spread,
+            pool.quote_decimals,
+            from_amount - swap_fee,
+        )?;
+        Ok(WooFiQuote {
+            to_amount,
+            swap_fee,
+            swap_volume: from_amount,
+            new_from_price: None,
+            new_to_price: Some(new_price),
+        })
+    } else if to_token == pool.quote_token {
+        // base to quote, the fee is taken from the quote amount out
+        let token = base_token(pool, from_token)?;
+        let (quote_amount, new_price) =
+            calc_quote_amount_sell_base(token, token.spread, pool.quote_decimals, from_amount)?;
+        let swap_fee = fee(quote_amount, token.fee_rate)?;
+        Ok(WooFiQuote {
+            to_amount: quote_amount - swap_fee,
+            swap_fee,
+            swap_volume: quote_amount,
+            new_from_price: Some(new_price),
+            new_to_price: None,
+        })
+    } else {
+        // base to base through the quote token, with half of the larger spread on each leg
+        let from = base_token(pool, from_token)?;
+        let to = base_token(pool, to_token)?;
+        let spread = from.spread.max(to.spread) / 2;
+        let fee_rate = from.fee_rate.max(to.fee_rate);
+        let (quote_amount, new_from_price) =
+            calc_quote_amount_sell_base(from, spread, pool.quote_decimals, from_amount)?;
+        let swap_fee = fee(quote_amount, fee_rate)?;
+        let (to_amount, new_to_price) =
+            calc_base_amount_sell_quote(to, spread, pool.quote_decimals, quote_amount - swap_fee)?;
+        Ok(WooFiQuote {
+            to_amount,
+            swap_fee,
+            swap_volume: quote_amount,
+            new_from_price: Some(new_from_price),
+            new_to_price: Some(new_to_price),
+        })
+    }
+}
+
+/// `query` of the pool, fails if the pool cannot pay out `to_token`
+pub fn query(
+    pool: &WooFiPool,
+    from_token: Address,
+    to_token: Address,
+    from_amount: U256,
+) -> DexQuoteResult<WooFiQuote> {
+    let quote = try_query(pool, from_token, to_token, from_amount)?;
+    let reserve = if to_token == pool.quote_token {
+        pool.quote_reserve
+    } else {
+        base_token(pool, to_token)?.reserve
+    };
+    if quote.to_amount > reserve {
+        return Err(DexQuoteError::InsufficientLiquidity(pool.address));
+    }
+    // base to base pays the fee out of the quote reserve
+    if from_token != pool.quote_token
+        && to_token != pool.quote_token
+        && quote.swap_fee > pool.quote_reserve
+    {
+        return Err(DexQuoteError::InsufficientLiquidity(pool.address));
+    }
+    Ok(quote)
+}
+
+/// Smallest amount of `token_in` to receive at least `amount_out` of `token_out`.
+/// The pool has no inverse query, so it is searched for.
+pub fn get_amount_in(
+    pool: &WooFiPool,
+    token_in: Address,
+    token_out: Address,
+    amount_out: U256,
+) -> DexQuoteResult<U256> {
+    let receives = |amount_in: U256| -> DexQuoteResult<bool> {
+        Ok(query(pool, token_in, token_out, amount_in)?.to_amount >= amount_out)
+    };
+    // double until enough, then bisect
+    let mut low = U256::zero();
+    let mut high = U256::one();
+    let mut found = false;
+    for _ in 0..MAX_SEARCH_ITERATIONS {
+        if receives(high)? {
+            found = true;
+            break;
+        }
+        low = high;
+        high = mul(high, 2.into())?;
+    }
+    if !found {
+        return Err(DexQuoteError::InsufficientLiquidity(pool.address));
+    }
+    while high - low > U256::one() {
+        let mid = low + (high - low) / 2;
+        // too large amounts fail on the oracle coefficient
+        match receives(mid) {
+            Ok(true) => high = mid,
+            Ok(false) => low = mid,
+            Err(_) => high = mid,
+        }
+    }
+    Ok(high)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(n: u64) -> Address {
+        Address::from_low_u64_be(n)
+    }
+
+    // USDC quote, WETH and WBTC bases
+    fn pool() -> WooFiPool {
+        WooFiPool {
+            address: token(100),
+            oracle: token(101),
+            quote_token: token(1),
+            quote_decimals: 6,
+            quote_reserve: U256::from(1_000_000u64) * U256::exp10(6),
+            tokens: vec![
+                WooFiToken {
+                    address: token(2),
+                    decimals: 18,
+                    reserve: U256::from(500) * U256::exp10(18),
+                    fee_rate: 25,
+                    price: U256::from(2000) * U256::exp10(8),
+                    price_decimals: 8,
+                    spread: 1_000_000_000_000_000,
+                    coeff: 1_000_000_000,
+                    feasible: true,
+                },
+                WooFiToken {
+                    address: token(3),
+                    decimals: 8,
+                    reserve: U256::from(10) * U256::exp10(8),
+                    fee_rate: 30,
+                    price: U256::from(30000) * U256::exp10(8),
+                    price_decimals: 8,
+                    spread: 500_000_000_000_000,
+                    coeff: 2_000_000_000,
+                    feasible: true,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_try_query() {
+        let pool = pool();
+        // base to quote
+        let quote = try_query(&pool, token(2), token(1), U256::exp10(18)).unwrap();
+        assert_eq!(quote.to_amount, U256::from(1997496501u64));
+        assert_eq!(quote.swap_fee, U256::from(499499));
+        assert_eq!(quote.new_from_price, Some(U256::from(199999200000u64)));
+
+        // quote to base
+        let quote = try_query(
+            &pool,
+            token(1),
+            token(2),
+            U256::from(10_000u64) * U256::exp10(6),
+        )
+        .unwrap();
+        assert_eq!(quote.to_amount, U256::from(4993701274996875000u64));
+        assert_eq!(quote.new_to_price, Some(U256::from(200003999079u64)));
+
+        // base to base
+        let quote = try_query(&pool, token(3), token(2), U256::exp10(8)).unwrap();
+        assert_eq!(quote.to_amount, U256::from(14979159742256110019u64));
+        assert_eq!(quote.swap_fee, U256::from(8994960));
+    }
+
+    #[test]
+    fn test_query() {
+        let mut pool = pool();
+        assert!(query(&pool, token(3), token(2), U256::exp10(8)).is_ok());
+        pool.tokens[0].reserve = U256::exp10(18);
+        assert!(matches!(
+            query(&pool, token(3), token(2), U256::exp10(8)),
+            Err(DexQuoteError::InsufficientLiquidity(_))
+        ));
+        pool.tokens[1].feasible = false;
+        assert!(matches!(
+            try_query(&pool, token(3), token(1), U256::exp10(8)),
+            Err(DexQuoteError::GetPriceError(_))
+        ));
+    }
+
+    #[test]
+    fn test_get_amount_in() {
+        let pool = pool();
+        let amount_out = U256::exp10(18);
+        let amount_in = get_amount_in(&pool, token(1), token(2), amount_out).unwrap();
+        assert!(
+            query(&pool, token(1), token(2), amount_in)
+                .unwrap()
+                .to_amount
+                >= amount_out
+        );
+        assert!(
+            query(&pool, token(1), token(2), amount_in - 1)
+                .unwrap()
+                .to_amount
+                < amount_out
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use dexquote::arb::{
+    ArbConfig, ArbOpportunity, ArbWatcher, ChannelSink, CycleIndex, FileSink, OpportunitySink,
+    RedisStreamSink,
+};
+use dexquote::db::RedisStore;
+use dexquote::graph::RoutingGraph;
+use ethers::types::{Address, U256};
+use std::error::Error;
+use std::sync::Arc;
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tracing::info;
+
+/// Entries kept in a Redis stream sink
+const REDIS_STREAM_MAX_LEN: usize = 10_000;
+
+/// Arbitrage search on the cycles of the pools updated by the workers
+pub struct ArbOptions {
+    /// base tokens of the cycles
+    pub tokens: Vec<String>,
+    pub max_hops: u64,
+    pub cycle_limit: u64,
+    /// `channel` logs the opportunities, `file:<path>` appends them as JSON lines
+    /// and `redis:<stream>` adds them to a Redis stream
+    pub sink: String,
+    /// in wei
+    pub gas_price: String,
+}
+
+fn get_sink(sink: &str, redis_url: &str) -> Result<Box<dyn OpportunitySink>, Box<dyn Error>> {
+    if let Some(path) = sink.strip_prefix("file:") {
+        return Ok(Box::new(FileSink::open(path)?));
+    }
+    if let Some(stream) = sink.strip_prefix("redis:") {
+        return Ok(Box::new(RedisStreamSink::open(
+            redis_url,
+            stream,
+            REDIS_STREAM_MAX_LEN,
+        )?));
+    }
+    if sink == "channel" {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<(u64, ArbOpportunity)>();
+        tokio::spawn(async move {
+            while let Some((block_number, opportunity)) = receiver.recv().await {
+                info!("opportunity at block {}: {:?}", block_number, opportunity);
+            }
+        });
+        return Ok(Box::new(ChannelSink::new(sender)));
+    }
+    Err(format!("unknown arbitrage sink: {sink}").into())
+}
+
+/// Index the cycles of the base tokens and simulate the cycles of each pool sent to the
+/// returned channel. The workers keep syncing while cycles are simulated.
+pub fn spawn_watcher(
+    options: ArbOptions,
+    store: Arc<RedisStore>,
+    chain_id: u64,
+    json_rpc_url: String,
+    redis_url: &str,
+) -> Result<UnboundedSender<(u64, Address)>, Box<dyn Error>> {
+    let tokens = options
+        .tokens
+        .iter()
+        .map(|token| token.parse::<Address>())
+        .collect::<Result<Vec<_>, _>>()?;
+    let graph = RoutingGraph::from_store(store.as_ref(), chain_id)?;
+    let index = CycleIndex::from_graph(
+        &graph,
+        &tokens,
+        options.max_hops as usize,
+        options.cycle_limit as usize,
+    );
+    info!("watching {} cycles for arbitrage", index.len());
+    let config = ArbConfig {
+        gas_price: U256::from_dec_str(&options.gas_price)?,
+        ..Default::default()
+    };
+    let watcher = ArbWatcher::new(index, config, get_sink(&options.sink, redis_url)?);
+
+    let (sender, mut receiver) = mpsc::unbounded_channel::<(u64, Address)>();
+    tokio::spawn(async move {
+        while let Some((mut block_number, pool_address)) = receiver.recv().await {
+            // pools updated while the previous cycles were simulated are evaluated together,
+            // at the latest of their blocks
+            let mut pools = vec![pool_address];
+            while let Ok((next_block_number, pool_address)) = receiver.try_recv() {
+                block_number = block_number.max(next_block_number);
+                if !pools.contains(&pool_address) {
+                    pools.push(pool_address);
+                }
+            }
+            watcher
+                .on_pools_updated(
+                    store.as_ref(),
+                    chain_id,
+                    &json_rpc_url,
+                    block_number,
+                    &pools,
+                )
+                .await;
+        }
+    });
+    Ok(sender)
+}
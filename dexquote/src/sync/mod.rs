@@ -1,82 +1,675 @@
-use dexquote::constants::provider::get_provider;
-use dexquote::db::get_dex_pools;
-use dexquote::event::get_event_sig;
-use dexquote::event::univ2::{update_with_sync_event, UNIV2_SYNC_EVENT_SIG};
+use dexquote::db::balancer::vault_address;
+use dexquote::db::multicall::{get_batcher, MulticallBatcher};
+use dexquote::db::univ2::{update_fee, UniV2Fee};
+use dexquote::db::woofi::get_oracle_pools;
+use dexquote::db::{pool_exists, Journal, PoolStore, RecordingStore, RedisStore};
+use dexquote::event::algebra::{update_with_fee_event, update_with_tick_spacing_event};
+use dexquote::event::balancer::{
+    self, get_vault_log_pool, update_with_amp_update_event, update_with_pool_balance_changed_event,
+    update_with_swap_fee_event,
+};
+use dexquote::event::curve::{
+    self, update_from_node, update_with_ramp_a_event, update_with_token_exchange_event,
+};
+use dexquote::event::factory::{add_pool_from_created_event, decode_pool_created_event};
+use dexquote::event::univ2::{self, update_with_sync_event, update_with_velodrome_sync_event};
 use dexquote::event::univ3::{
-    update_with_liquidity_event, update_with_swap_event, UNIV3_BURN_EVENT_SIG,
-    UNIV3_MINT_EVENT_SIG, UNIV3_SWAP_EVENT_SIG,
+    update_with_initialize_event, update_with_liquidity_event, update_with_set_fee_protocol_event,
+    update_with_swap_event,
 };
+use dexquote::event::woofi::{self, update_with_oracle_log, update_with_reserve_event};
+use dexquote::event::{decode_pool_event, PoolEvent};
 use ethers::prelude::*;
-use std::sync::Arc;
-use tracing::info;
+use neo4rs::Graph;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
 
-use crossbeam_channel::unbounded;
+use tokio::sync::mpsc::{self, Sender};
 
-use crate::config::{self, Config};
+mod arb;
+pub use arb::ArbOptions;
 
-fn get_filter(config_name: String, chain_id: u64) -> Filter {
-    let conf = config::get_config(config_name);
-    let redis_client = redis::Client::open(conf.redis_url).unwrap();
+use crate::checkpoint::{get_configured_factories, get_univ2_fees};
+use crate::config;
 
-    let univ3_pools = get_dex_pools(&redis_client, chain_id, "UNIV3");
-    let univ2_pools = get_dex_pools(&redis_client, chain_id, "UNIV2");
-    let all_pools = [univ3_pools, univ2_pools];
-    let all_pools: Vec<Address> = all_pools.iter().flatten().cloned().collect();
-    Filter::new().address(all_pools)
+/// Maximum number of blocks per `eth_getLogs` request during backfill
+const BACKFILL_CHUNK_SIZE: u64 = 2000;
+/// Number of logs a worker can have queued before the feed waits for it
+const WORKER_CHANNEL_CAPACITY: usize = 1024;
+
+// pools to sync and factories to discover new pools from
+fn get_filter_addresses<S: PoolStore>(
+    store: &S,
+    chain_id: u64,
+    factories: &[Address],
+    oracle_pools: &HashMap<Address, Address>,
+) -> Vec<Address> {
+    let univ3_pools = store.get_dex_pools(chain_id, "UNIV3").unwrap();
+    let univ2_pools = store.get_dex_pools(chain_id, "UNIV2").unwrap();
+    let solidly_pools = store.get_dex_pools(chain_id, "SOLIDLY").unwrap();
+    let algebra_pools = store.get_dex_pools(chain_id, "ALGEBRA").unwrap();
+    let curve_pools = store.get_dex_pools(chain_id, "CURVE").unwrap();
+    let mut balancer_pools = store.get_dex_pools(chain_id, "BALANCERV2").unwrap();
+    // swaps and joins/exits of Balancer pools are logged by the Vault
+    if !balancer_pools.is_empty() {
+        balancer_pools.push(vault_address());
+    }
+    let woofi_pools = store.get_dex_pools(chain_id, "WOOFIV2").unwrap();
+    let oracles = oracle_pools.keys().cloned().collect();
+    let all_pools = [
+        univ3_pools,
+        univ2_pools,
+        solidly_pools,
+        algebra_pools,
+        curve_pools,
+        balancer_pools,
+        woofi_pools,
+        oracles,
+        factories.to_vec(),
+    ];
+    all_pools.iter().flatten().cloned().collect()
 }
 
 pub async fn update_pool_states(
     threads: usize,
+    reorg_depth: u64,
+    from_block: Option<u64>,
     config_name: String,
+    arb: Option<ArbOptions>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let conf = config::get_config(config_name.clone());
-    let provider = get_ws_provider(conf.ws_rpc_url).await?;
+    let provider = get_ws_provider(conf.ws_rpc_url.clone()).await?;
     let chain_id = provider.get_chainid().await?.as_u64();
     let client = Arc::new(provider);
-    let swap_filter = get_filter(config_name.clone(), chain_id);
+    let store = Arc::new(RedisStore::open(&conf.redis_url)?);
+    let graph = config::connect_neo4j(&conf).await?;
+    let configured_factories = get_configured_factories(chain_id, &conf.univ2_factories);
+    let univ2_fees = get_univ2_fees(&configured_factories);
+    let factories: Vec<Address> = configured_factories
+        .iter()
+        .map(|factory| factory.address)
+        .collect();
+    // WooFi oracle posts are applied to the pools reading the oracle
+    let oracle_pools = Arc::new(get_oracle_pools(store.as_ref(), chain_id)?);
+    let addresses = get_filter_addresses(store.as_ref(), chain_id, &factories, &oracle_pools);
+    // resume after the last processed block unless overridden
+    let from_block = match from_block {
+        Some(from_block) => Some(from_block),
+        None => store
+            .get_last_block(chain_id)?
+            .map(|last_block| last_block + 1),
+    };
+    // changes of the recent blocks, used to roll back reorged blocks
+    let journal = Arc::new(Mutex::new(Journal::new(reorg_depth)));
+    // number of logs the workers have finished processing
+    let processed = Arc::new(AtomicUsize::new(0));
+    let unknown_events = Arc::new(UnknownEvents::default());
+    // pools updated by the workers, whose arbitrage cycles are simulated again
+    let arb_sender = match arb {
+        Some(options) => Some(arb::spawn_watcher(
+            options,
+            store.clone(),
+            chain_id,
+            conf.json_rpc_url.clone(),
+            &conf.redis_url,
+        )?),
+        None => None,
+    };
+
+    // Logs are sharded by pool address, each worker applies the logs of its pools in order
+    let mut senders = Vec::with_capacity(threads);
+    for _ in 0..threads.max(1) {
+        let (sender, mut receiver) = mpsc::channel::<Log>(WORKER_CHANNEL_CAPACITY);
+        senders.push(sender);
+        // each worker reads the node through its own handle of the shared batcher
+        let batcher = get_batcher(&conf.json_rpc_url)?;
+        let store = store.clone();
+        let journal = journal.clone();
+        let processed = processed.clone();
+        let unknown_events = unknown_events.clone();
+        let oracle_pools = oracle_pools.clone();
+        let arb_sender = arb_sender.clone();
+
+        tokio::spawn(async move {
+            while let Some(log) = receiver.recv().await {
+                let pool_address = get_log_pool(&log, &oracle_pools);
+                let block_number = log.block_number;
+                let changed = process_log(
+                    log,
+                    store.as_ref(),
+                    &journal,
+                    &unknown_events,
+                    &oracle_pools,
+                    &batcher,
+                    chain_id,
+                )
+                .await;
+                // only logs that changed the pool state move its arbitrage cycles
+                if let (Some(arb_sender), Some(block_number), true) =
+                    (&arb_sender, block_number, changed)
+                {
+                    if arb_sender
+                        .send((block_number.as_u64(), pool_address))
+                        .is_err()
+                    {
+                        warn!("arbitrage watcher stopped");
+                    }
+                }
+                processed.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+    }
 
-    // Create a channel to receive messages from the feed client
-    let (sender, receiver) = unbounded();
+    let feed = Feed {
+        batcher: MulticallBatcher::new(client.clone(), conf.multicall_config()),
+        client,
+        chain_id,
+        store: store.clone(),
+        journal: journal.clone(),
+        processed: processed.clone(),
+        graph,
+        chain_label: conf.chain_label,
+        factories: factories.into_iter().collect(),
+        univ2_fees,
+        filter: Filter::new().address(addresses.clone()),
+        addresses,
+        filter_changed: false,
+        backfilled_block: None,
+        resubscribed_after: None,
+        created_pools: Vec::new(),
+        senders,
+        oracle_pools,
+        sent: 0,
+        last_block: None,
+        last_log: None,
+    };
+    feed.run(from_block).await;
+    Ok(())
+}
+
+// all logs of a pool go to the same worker
+fn get_shard(pool_address: Address, shards: usize) -> usize {
+    (pool_address.to_low_u64_be() % shards as u64) as usize
+}
 
-    // Create a new thread that runs the background maintain loop
-    tokio::spawn(async move {
-        let mut stream = client.subscribe_logs(&swap_filter).await.unwrap();
-        while let Some(log) = stream.next().await {
-            sender.send(log).unwrap();
+// Vault logs belong to the pool of their pool id and oracle logs to the pool of the oracle
+fn get_log_pool(log: &Log, oracle_pools: &HashMap<Address, Address>) -> Address {
+    if log.address == vault_address() {
+        if let Some(pool_address) = get_vault_log_pool(log) {
+            return pool_address;
         }
+    }
+    if let Some(pool_address) = oracle_pools.get(&log.address) {
+        return *pool_address;
+    }
+    log.address
+}
+
+// logs returned by `eth_getLogs` in the order they were emitted
+fn sort_logs(logs: &mut [Log]) {
+    logs.sort_by_key(|log| (log.block_number, log.log_index));
+}
+
+// logs returned by `eth_getLogs` that were emitted after `last_log`, in order
+fn logs_after(mut logs: Vec<Log>, last_log: Option<(u64, U256)>) -> Vec<Log> {
+    sort_logs(&mut logs);
+    logs.retain(|log| {
+        let log_key = (
+            log.block_number.unwrap_or_default().as_u64(),
+            log.log_index.unwrap_or_default(),
+        );
+        last_log.is_none_or(|last_log| log_key > last_log)
     });
+    logs
+}
 
-    for _ in 0..threads - 1 {
-        let conf = config::get_config(config_name.clone());
-        let receiver_clone = receiver.clone();
+/// Forwards backfilled and subscribed logs to the workers and handles reorgs.
+///
+/// Before checking a new block against the journal and before rolling back,
+/// the feed waits until the workers have processed every log sent so far,
+/// and no new logs are sent until the rollback is done.
+/// The same wait marks a block as fully processed once the logs of the next block arrive.
+///
+/// Pools created by the factories are added by the feed itself,
+/// which then resubscribes with the extended filter
+/// and fetches the logs dropped with the previous subscription.
+struct Feed {
+    client: Arc<Provider<Ws>>,
+    // reads of the created pools
+    batcher: MulticallBatcher<Provider<Ws>>,
+    chain_id: u64,
+    store: Arc<RedisStore>,
+    journal: Arc<Mutex<Journal>>,
+    processed: Arc<AtomicUsize>,
+    graph: Option<Graph>,
+    chain_label: String,
+    factories: HashSet<Address>,
+    // fees of the pairs created by each UniswapV2 factory
+    univ2_fees: HashMap<Address, UniV2Fee>,
+    filter: Filter,
+    addresses: Vec<Address>,
+    // a pool was added to `addresses` since the last subscription
+    filter_changed: bool,
+    // last block applied by the backfill
+    backfilled_block: Option<u64>,
+    // logs at or before this point were already received by the previous subscription
+    resubscribed_after: Option<(u64, U256)>,
+    // (block number, address) of the pools added by the feed that are still journaled
+    created_pools: Vec<(u64, Address)>,
+    // one bounded channel per worker, full channels hold the feed back
+    senders: Vec<Sender<Log>>,
+    oracle_pools: Arc<HashMap<Address, Address>>,
+    sent: usize,
+    // (number, hash) of the block of the last sent log
+    last_block: Option<(u64, H256)>,
+    // (block number, log index) of the last handled subscribed log
+    last_log: Option<(u64, U256)>,
+}
 
-        tokio::spawn(async move {
-            loop {
-                let log = receiver_clone
-                    .recv()
-                    .expect("Failed to receive data from feed client");
+impl Feed {
+    async fn run(mut self, from_block: Option<u64>) {
+        let client = self.client.clone();
+        // subscribe before backfilling so that no block falls between the two
+        let mut stream = client.subscribe_logs(&self.filter).await.unwrap();
+        if let Some(from_block) = from_block {
+            match self.backfill(from_block).await {
+                Ok(to_block) => self.backfilled_block = Some(to_block),
+                Err(e) => warn!("failed to backfill from block {}: {:?}", from_block, e),
+            }
+        }
+        loop {
+            if self.filter_changed {
+                match client.subscribe_logs(&self.filter).await {
+                    Ok(new_stream) => {
+                        // the logs still buffered in the previous subscription are dropped with it
+                        stream = new_stream;
+                        self.filter_changed = false;
+                        info!("resubscribed to {} addresses", self.addresses.len());
+                        if let Err(e) = self.catch_up().await {
+                            warn!("failed to fetch the logs since resubscribing: {:?}", e);
+                        }
+                        self.resubscribed_after = self.last_log;
+                    }
+                    Err(e) => warn!("failed to resubscribe: {:?}", e),
+                }
+            }
+            let log = match stream.next().await {
+                Some(log) => log,
+                None => break,
+            };
+            let (block_number, block_hash) = match (log.block_number, log.block_hash) {
+                (Some(block_number), Some(block_hash)) => (block_number.as_u64(), block_hash),
+                _ => continue,
+            };
+            if log.removed == Some(true) {
+                // the block was orphaned, the logs of the canonical chain follow
+                self.rollback(block_number).await;
+                continue;
+            }
+            // already applied by the backfill
+            if matches!(self.backfilled_block, Some(to_block) if block_number <= to_block) {
+                continue;
+            }
+            let log_key = (block_number, log.log_index.unwrap_or_default());
+            if matches!(self.resubscribed_after, Some(last_log) if log_key <= last_log) {
+                continue;
+            }
+            self.receive(log, block_number, block_hash).await;
+        }
+    }
 
-                update_pool_state(log, &conf, chain_id).await;
+    // handle a new log, checking the block against the journal when it is the first log of it
+    async fn receive(&mut self, log: Log, block_number: u64, block_hash: H256) {
+        if self.last_block.map(|(_, hash)| hash) != Some(block_hash) {
+            // the workers journal the previous block, so they catch up before comparing with it
+            self.wait_until_processed().await;
+            if let Err(e) = self.check_new_block(block_number, block_hash).await {
+                warn!("failed to check block {}: {:?}", block_number, e);
             }
-        });
+            self.start_block(block_number, block_hash).await;
+        }
+        self.last_log = Some((block_number, log.log_index.unwrap_or_default()));
+        self.handle_log(log).await;
+    }
+
+    // apply the logs of the current filter from the block of the last handled log
+    // up to the current block, i.e. the rest of the block that created a pool
+    // and the logs emitted before the new subscription started
+    async fn catch_up(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let from_block = match (self.last_log, self.backfilled_block) {
+            (Some((block_number, _)), _) => block_number,
+            (None, Some(to_block)) => to_block + 1,
+            (None, None) => return Ok(()),
+        };
+        let to_block = self.client.get_block_number().await?.as_u64();
+        if from_block > to_block {
+            return Ok(());
+        }
+        let filter = self
+            .filter
+            .clone()
+            .from_block(from_block)
+            .to_block(to_block);
+        let logs = self.client.get_logs(&filter).await?;
+        for log in logs_after(logs, self.last_log) {
+            if let (Some(block_number), Some(block_hash)) = (log.block_number, log.block_hash) {
+                self.receive(log, block_number.as_u64(), block_hash).await;
+            }
+        }
+        Ok(())
+    }
+
+    // pool creations are handled by the feed, other logs go to the workers
+    async fn handle_log(&mut self, log: Log) {
+        if self.factories.contains(&log.address) {
+            self.add_created_pool(&log).await;
+            return;
+        }
+        self.send(log).await;
+    }
+
+    // add a pool created by a factory and extend the filter with it.
+    // the pool is fetched at the latest block, so its earlier logs are not needed.
+    // It is journaled with its block, and removed again if the block is rolled back
+    async fn add_created_pool(&mut self, log: &Log) {
+        let (block_number, block_hash) = match (log.block_number, log.block_hash) {
+            (Some(block_number), Some(block_hash)) => (block_number.as_u64(), block_hash),
+            _ => return,
+        };
+        let event = match decode_pool_created_event(log) {
+            Some(event) => event,
+            None => return,
+        };
+        if self.addresses.contains(&event.pool_address) {
+            return;
+        }
+        if let Ok(true) = pool_exists(self.store.as_ref(), self.chain_id, event.pool_address) {
+            return;
+        }
+        let recording_store = RecordingStore::new(self.store.as_ref());
+        match add_pool_from_created_event(
+            &recording_store,
+            self.chain_id,
+            &event,
+            &self.batcher,
+            self.graph.as_ref(),
+            &self.chain_label,
+        )
+        .await
+        {
+            Ok(_) => {
+                if let Some(fee) = self.univ2_fees.get(&log.address) {
+                    if let Err(e) =
+                        update_fee(&recording_store, self.chain_id, event.pool_address, *fee)
+                    {
+                        warn!("failed to set fee of {:?}: {:?}", event.pool_address, e);
+                    }
+                }
+                info!(
+                    "added pool {:?} ({})",
+                    event.pool_address,
+                    event.dex.as_str()
+                );
+                self.addresses.push(event.pool_address);
+                self.filter = Filter::new().address(self.addresses.clone());
+                self.filter_changed = true;
+                self.created_pools.push((block_number, event.pool_address));
+            }
+            Err(e) => warn!("failed to add pool {:?}: {:?}", event.pool_address, e),
+        }
+        let mut journal = self.journal.lock().unwrap();
+        journal.record(block_number, block_hash, recording_store.into_entries());
+        // pools older than the journal can no longer be rolled back
+        if let Some(oldest_block) = journal.oldest_block() {
+            self.created_pools
+                .retain(|(block_number, _)| *block_number >= oldest_block);
+        }
+    }
+
+    // apply the logs from `from_block` up to the current block in chunks,
+    // returns the last backfilled block
+    async fn backfill(&mut self, from_block: u64) -> Result<u64, Box<dyn std::error::Error>> {
+        let to_block = self.client.get_block_number().await?.as_u64();
+        info!("backfilling blocks {} to {}", from_block, to_block);
+        // pools created during the backfill are fetched at the latest block,
+        // so they are left out of the remaining chunks
+        let backfill_filter = self.filter.clone();
+        let mut chunk_start = from_block;
+        while chunk_start <= to_block {
+            let chunk_end = (chunk_start + BACKFILL_CHUNK_SIZE - 1).min(to_block);
+            let filter = backfill_filter
+                .clone()
+                .from_block(chunk_start)
+                .to_block(chunk_end);
+            let mut logs = self.client.get_logs(&filter).await?;
+            sort_logs(&mut logs);
+            for log in logs {
+                if let (Some(block_number), Some(block_hash)) = (log.block_number, log.block_hash) {
+                    if self.last_block.map(|(_, hash)| hash) != Some(block_hash) {
+                        self.start_block(block_number.as_u64(), block_hash).await;
+                    }
+                    self.handle_log(log).await;
+                }
+            }
+            info!("backfilled blocks {} to {}", chunk_start, chunk_end);
+            chunk_start = chunk_end + 1;
+        }
+        self.wait_until_processed().await;
+        self.set_last_block(to_block);
+        self.last_block = None;
+        Ok(to_block)
+    }
+
+    // the logs of the previous block have all been sent,
+    // so it is fully processed once the workers catch up
+    async fn start_block(&mut self, block_number: u64, block_hash: H256) {
+        if let Some((last_block_number, _)) = self.last_block {
+            self.wait_until_processed().await;
+            self.set_last_block(last_block_number);
+        }
+        self.last_block = Some((block_number, block_hash));
+    }
+
+    fn set_last_block(&self, block_number: u64) {
+        if let Err(e) = self.store.set_last_block(self.chain_id, block_number) {
+            warn!("failed to set last block {}: {:?}", block_number, e);
+        }
+    }
+
+    async fn send(&mut self, log: Log) {
+        let shard = get_shard(get_log_pool(&log, &self.oracle_pools), self.senders.len());
+        self.senders[shard].send(log).await.unwrap();
+        self.sent += 1;
     }
 
-    // main thread
-    let conf = config::get_config(config_name);
-    let receiver_clone = receiver.clone();
-    loop {
-        let log = receiver_clone
-            .recv()
-            .expect("Failed to receive data from feed client");
+    async fn wait_until_processed(&self) {
+        while self.processed.load(Ordering::SeqCst) < self.sent {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    async fn rollback(&mut self, from_block: u64) {
+        self.wait_until_processed().await;
+        self.last_block = None;
+        // the canonical logs of the rolled back blocks are not skipped as already applied
+        let before = from_block.saturating_sub(1);
+        self.backfilled_block = self.backfilled_block.map(|to_block| to_block.min(before));
+        for log_key in [&mut self.last_log, &mut self.resubscribed_after] {
+            if matches!(log_key, Some((block_number, _)) if *block_number >= from_block) {
+                *log_key = Some((before, U256::MAX));
+            }
+        }
+        match self.store.get_last_block(self.chain_id) {
+            Ok(Some(last_block)) if last_block >= from_block => {
+                self.set_last_block(from_block.saturating_sub(1))
+            }
+            Ok(_) => {}
+            Err(e) => warn!("failed to get last block: {:?}", e),
+        }
+        let mut journal = self.journal.lock().unwrap();
+        if matches!(journal.oldest_block(), Some(oldest_block) if from_block < oldest_block) {
+            warn!(
+                "reorg at block {} is deeper than the journal, state may diverge",
+                from_block
+            );
+        }
+        match journal.rollback(self.store.as_ref(), from_block) {
+            Ok(0) => {}
+            Ok(rolled_back) => info!("rolled back {} logs from block {}", rolled_back, from_block),
+            Err(e) => warn!("failed to roll back from block {}: {:?}", from_block, e),
+        }
+        drop(journal);
+        // the pools created in the rolled back blocks were removed from the store
+        let removed: Vec<Address> = self
+            .created_pools
+            .iter()
+            .filter(|(block_number, _)| *block_number >= from_block)
+            .map(|(_, pool_address)| *pool_address)
+            .collect();
+        if !removed.is_empty() {
+            self.created_pools
+                .retain(|(block_number, _)| *block_number < from_block);
+            self.addresses.retain(|address| !removed.contains(address));
+            self.filter = Filter::new().address(self.addresses.clone());
+            self.filter_changed = true;
+            info!(
+                "removed {} pools created in rolled back blocks",
+                removed.len()
+            );
+        }
+    }
+
+    // compare the parent hash of a new block with the journal,
+    // roll back the orphaned blocks and apply the canonical logs if they differ
+    async fn check_new_block(
+        &mut self,
+        block_number: u64,
+        block_hash: H256,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if self.journal.lock().unwrap().contains(block_hash) {
+            return Ok(());
+        }
+        let parent_hash = match self.client.get_block(block_hash).await? {
+            Some(block) => block.parent_hash,
+            None => return Ok(()),
+        };
+        if !self
+            .journal
+            .lock()
+            .unwrap()
+            .is_reorg(block_number, block_hash, parent_hash)
+        {
+            return Ok(());
+        }
+
+        // walk back the canonical chain until it meets the journal
+        let oldest_block = self.journal.lock().unwrap().oldest_block().unwrap_or(0);
+        let mut fork_block = block_number;
+        let mut canonical_hash = parent_hash;
+        let mut number = block_number.saturating_sub(1);
+        while number >= oldest_block && number > 0 {
+            let journaled_hash = self.journal.lock().unwrap().get_hash(number);
+            if journaled_hash == Some(canonical_hash) {
+                break;
+            }
+            if journaled_hash.is_some() {
+                fork_block = number;
+            }
+            canonical_hash = match self.client.get_block(canonical_hash).await? {
+                Some(block) => block.parent_hash,
+                None => break,
+            };
+            number -= 1;
+        }
+        warn!(
+            "reorg detected at block {}, rolling back from block {}",
+            block_number, fork_block
+        );
+        self.rollback(fork_block).await;
+
+        if fork_block < block_number {
+            let filter = self
+                .filter
+                .clone()
+                .from_block(fork_block)
+                .to_block(block_number - 1);
+            let mut logs = self.client.get_logs(&filter).await?;
+            sort_logs(&mut logs);
+            for log in logs {
+                self.handle_log(log).await;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Number of logs seen per unhandled event signature
+#[derive(Default)]
+struct UnknownEvents {
+    counts: Mutex<HashMap<H256, usize>>,
+}
 
-        update_pool_state(log, &conf, chain_id).await;
+impl UnknownEvents {
+    // warn on the first occurrence and then every power of ten
+    fn count(&self, event_sig: H256, pool_address: Address) {
+        let mut counts = self.counts.lock().unwrap();
+        let count = counts.entry(event_sig).or_insert(0);
+        *count += 1;
+        if 10usize.pow(count.ilog10()) == *count {
+            warn!(
+                "unknown event {:?} from {:?} seen {} times",
+                event_sig, pool_address, count
+            );
+        }
     }
 }
 
-async fn update_pool_state(log: Log, conf: &Config, chain_id: u64) {
-    let event_sig = log.topics[0];
-    let provider = get_provider(&conf.json_rpc_url).unwrap();
-    let middleware = Arc::new(provider);
+// apply a log and journal the changes it made,
+// returns whether the log changed the state of a pool
+async fn process_log<S: PoolStore>(
+    log: Log,
+    store: &S,
+    journal: &Mutex<Journal>,
+    unknown_events: &UnknownEvents,
+    oracle_pools: &HashMap<Address, Address>,
+    batcher: &MulticallBatcher<Provider<Http>>,
+    chain_id: u64,
+) -> bool {
+    let (block_number, block_hash) = match (log.block_number, log.block_hash) {
+        (Some(block_number), Some(block_hash)) => (block_number.as_u64(), block_hash),
+        _ => return false,
+    };
+    let recording_store = RecordingStore::new(store);
+    update_pool_state(
+        log,
+        &recording_store,
+        unknown_events,
+        oracle_pools,
+        batcher,
+        chain_id,
+    )
+    .await;
+    // events that leave the state unchanged and logs that failed to apply write nothing
+    let entries = recording_store.into_entries();
+    let changed = !entries.is_empty();
+    journal
+        .lock()
+        .unwrap()
+        .record(block_number, block_hash, entries);
+    changed
+}
+
+async fn update_pool_state<S: PoolStore>(
+    log: Log,
+    store: &S,
+    unknown_events: &UnknownEvents,
+    oracle_pools: &HashMap<Address, Address>,
+    batcher: &MulticallBatcher<Provider<Http>>,
+    chain_id: u64,
+) {
     match log.transaction_hash {
         Some(tx_hash) => {
             info!("tx_hash: {:?}", tx_hash);
@@ -84,30 +677,76 @@ async fn update_pool_state(log: Log, conf: &Config, chain_id: u64) {
         None => return,
     }
     let pool_address = log.address;
-    if event_sig == get_event_sig(UNIV3_SWAP_EVENT_SIG) {
-        update_with_swap_event(&conf.redis_url, chain_id, pool_address, &log.data);
-    } else if event_sig == get_event_sig(UNIV2_SYNC_EVENT_SIG) {
-        update_with_sync_event(&conf.redis_url, chain_id, pool_address, &log.data);
-    } else if event_sig == get_event_sig(UNIV3_MINT_EVENT_SIG) {
-        update_with_liquidity_event(
-            &conf.redis_url,
-            chain_id,
-            pool_address,
-            &log,
-            true,
-            middleware,
-        )
-        .await
-    } else if event_sig == get_event_sig(UNIV3_BURN_EVENT_SIG) {
-        update_with_liquidity_event(
-            &conf.redis_url,
-            chain_id,
-            pool_address,
-            &log,
-            false,
-            middleware,
-        )
-        .await
+    if let Some(pool_address) = oracle_pools.get(&log.address) {
+        update_with_oracle_log(store, chain_id, *pool_address, batcher).await;
+        return;
+    }
+    let event = match decode_pool_event(&log) {
+        Ok(event) => event,
+        Err(e) => {
+            warn!("failed to decode event from {:?}: {:?}", pool_address, e);
+            return;
+        }
+    };
+    match event {
+        PoolEvent::UniV3Swap(_) => update_with_swap_event(store, chain_id, pool_address, &log.data),
+        PoolEvent::UniV2Sync(_) => update_with_sync_event(store, chain_id, pool_address, &log.data),
+        PoolEvent::AlgebraFee(event) => {
+            update_with_fee_event(store, chain_id, pool_address, &event)
+        }
+        PoolEvent::AlgebraTickSpacing(event) => {
+            update_with_tick_spacing_event(store, chain_id, pool_address, &event)
+        }
+        PoolEvent::VelodromeSync(_) => {
+            update_with_velodrome_sync_event(store, chain_id, pool_address, &log.data)
+        }
+        PoolEvent::UniV2Fee(event) => {
+            univ2::update_with_fee_event(store, chain_id, pool_address, &event)
+        }
+        PoolEvent::UniV3Mint(_) => {
+            update_with_liquidity_event(store, chain_id, pool_address, &log, true, batcher).await
+        }
+        PoolEvent::UniV3Burn(_) => {
+            update_with_liquidity_event(store, chain_id, pool_address, &log, false, batcher).await
+        }
+        PoolEvent::UniV3Initialize(event) => {
+            update_with_initialize_event(store, chain_id, pool_address, &event)
+        }
+        PoolEvent::UniV3SetFeeProtocol(event) => {
+            update_with_set_fee_protocol_event(store, chain_id, pool_address, &event)
+        }
+        PoolEvent::CurveTokenExchange(event) => {
+            update_with_token_exchange_event(store, chain_id, pool_address, &event)
+        }
+        PoolEvent::CurveLiquidity(event) => {
+            curve::update_with_liquidity_event(store, chain_id, pool_address, &event)
+        }
+        PoolEvent::CurveTokenExchangeUnderlying(_) | PoolEvent::CurveRemoveLiquidityOne => {
+            update_from_node(store, chain_id, pool_address, batcher).await
+        }
+        PoolEvent::CurveRampA(event) => {
+            update_with_ramp_a_event(store, chain_id, pool_address, &event)
+        }
+        PoolEvent::BalancerSwap(event) => balancer::update_with_swap_event(store, chain_id, &event),
+        PoolEvent::BalancerPoolBalanceChanged(event) => {
+            update_with_pool_balance_changed_event(store, chain_id, &event)
+        }
+        PoolEvent::BalancerSwapFee(event) => {
+            update_with_swap_fee_event(store, chain_id, pool_address, &event)
+        }
+        PoolEvent::BalancerAmpUpdate(event) => {
+            update_with_amp_update_event(store, chain_id, pool_address, &event)
+        }
+        PoolEvent::WooFiSwap(event) => {
+            woofi::update_with_swap_event(store, chain_id, pool_address, &event)
+        }
+        PoolEvent::WooFiReserve(event) => {
+            update_with_reserve_event(store, chain_id, pool_address, &event)
+        }
+        // fees collected, flash loans and swaps reported alongside a Sync
+        // leave the pool state unchanged
+        PoolEvent::UniV3Collect(_) | PoolEvent::UniV3Flash(_) | PoolEvent::UniV2Swap(_) => {}
+        PoolEvent::Unknown(event_sig) => unknown_events.count(event_sig, pool_address),
     }
 }
 
@@ -118,3 +757,45 @@ pub async fn get_ws_provider(
     let ws_provider = Provider::<Ws>::connect(ws_rpc_url).await?;
     Ok(ws_provider)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(address: u64, block_number: u64, log_index: u64) -> Log {
+        Log {
+            address: Address::from_low_u64_be(address),
+            block_number: Some(block_number.into()),
+            log_index: Some(log_index.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_logs_after() {
+        // a factory creates pool 2 in block 10, which pool 1 syncs after and pool 2 initializes
+        let factory_log = log(100, 10, 3);
+        let logs = vec![
+            log(2, 10, 5),
+            log(1, 10, 4),
+            factory_log.clone(),
+            log(1, 10, 1),
+            log(1, 9, 7),
+            log(1, 11, 0),
+        ];
+        let last_log = Some((10, factory_log.log_index.unwrap()));
+        let logs: Vec<(u64, u64, u64)> = logs_after(logs, last_log)
+            .iter()
+            .map(|log| {
+                (
+                    log.address.to_low_u64_be(),
+                    log.block_number.unwrap().as_u64(),
+                    log.log_index.unwrap().as_u64(),
+                )
+            })
+            .collect();
+        assert_eq!(logs, vec![(1, 10, 4), (2, 10, 5), (1, 11, 0)]);
+
+        assert_eq!(logs_after(vec![log(1, 9, 7)], None).len(), 1);
+    }
+}
@@ -0,0 +1,14 @@
+use ethers::types::U256;
+
+/// Nearest f64 of a U256, from its 64 most significant bits
+pub fn u256_to_f64(value: U256) -> f64 {
+    let shift = value.bits().saturating_sub(64);
+    (value >> shift).as_u64() as f64 * 2f64.powi(shift as i32)
+}
+
+#[test]
+fn test_u256_to_f64() {
+    assert_eq!(u256_to_f64(U256::zero()), 0.0);
+    assert_eq!(u256_to_f64(U256::from(12345u64)), 12345.0);
+    assert_eq!(u256_to_f64(U256::exp10(30)), 1e30);
+}
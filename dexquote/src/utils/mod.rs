@@ -1,4 +1,8 @@
 mod address;
+mod math;
 mod sort;
+mod time;
 pub use address::*;
+pub use math::*;
 pub use sort::*;
+pub use time::*;
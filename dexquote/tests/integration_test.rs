@@ -3,7 +3,10 @@ use std::time::Instant;
 
 use cfmms::checkpoint;
 use dexquote::path;
-use dexquote::{constants::provider::get_provider, db::add_pool};
+use dexquote::{
+    constants::provider::get_provider,
+    db::{add_pool, RedisStore},
+};
 use ethers::types::{Address, U256};
 use neo4rs::Graph;
 
@@ -24,7 +27,7 @@ async fn test_query_possible_path() {
     );
     let provider = get_provider(&json_rpc_url).unwrap();
     let checkpoint_path = "fixtures/checkpoint.json";
-    let redis_client = redis::Client::open(REDIS_URL).unwrap();
+    let store = RedisStore::open(REDIS_URL).unwrap();
     let (_, pools) = checkpoint::sync_pools_from_checkpoint_with_throttle(
         &checkpoint_path,
         100000,
@@ -39,7 +42,7 @@ async fn test_query_possible_path() {
     let total_pool_num = pools.len();
     let mut err_count = 0;
     for pool in pools {
-        match add_pool(&redis_client, chain_id, pool, &graph, "Arb").await {
+        match add_pool(&store, chain_id, pool, Some(&graph), "Arb").await {
             Ok(_) => {}
             Err(e) => {
                 err_count += 1;
@@ -68,9 +71,15 @@ async fn test_query_possible_path() {
 
     assert_eq!(routes.len() > 0, true);
     let amount_in = U256::exp10(18);
-    let estimated_amount_out =
-        path::get_amount_out_from_path(REDIS_URL, chain_id, &json_rpc_url, amount_in, &routes[0])
-            .await
-            .unwrap();
+    let estimated_amount_out = path::get_amount_out_from_path(
+        &store,
+        chain_id,
+        &json_rpc_url,
+        amount_in,
+        &routes[0],
+        false,
+    )
+    .await
+    .unwrap();
     assert_eq!(estimated_amount_out > U256::zero(), true);
 }
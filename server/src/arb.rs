@@ -0,0 +1,70 @@
+use dexquote::{
+    arb::{find_opportunities, ArbConfig},
+    db::RedisStore,
+    path::PoolInfo,
+};
+use ethers::types::{Address, U256};
+use rocket::{
+    serde::{json::Json, Deserialize, Serialize},
+    State,
+};
+
+use crate::routing::{with_graph, Routing};
+
+const REDIS_URL: &str = "redis://:testtest@127.0.0.1:30073/";
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct ArbOpportunity {
+    pub cycle: Vec<PoolInfo>,
+    pub amount_in: String,
+    pub amount_out: String,
+    pub gas: u64,
+    pub gas_cost: String,
+    pub net_profit: String,
+}
+
+// cycles of 2 to `max_hops` pools through `token`, the most net profit first.
+// `gas_price` is in wei, 0.1 gwei by default
+#[get("/<token>?<max_hops>&<gas_price>&<offline>")]
+pub async fn get_opportunities(
+    token: String,
+    max_hops: Option<usize>,
+    gas_price: Option<String>,
+    offline: Option<bool>,
+    routing: &State<Routing>,
+) -> Json<Vec<ArbOpportunity>> {
+    let token = token.parse::<Address>().unwrap();
+    let chain_id = 42161;
+    let alchemy_api_key = std::env::var("ALCHEMY_API_KEY").expect("Could not get ALCHEMY_API_KEY");
+    let json_rpc_url = format!(
+        "https://arb-mainnet.g.alchemy.com/v2/{}",
+        alchemy_api_key.as_str()
+    );
+    let store = RedisStore::open(REDIS_URL).unwrap();
+    let max_hops = max_hops.unwrap_or(3).clamp(2, 4);
+    let cycles = with_graph(routing, &store, chain_id, |graph| {
+        graph.get_cycles(token, max_hops, 100)
+    });
+    let mut config = ArbConfig {
+        offline: offline.unwrap_or(false),
+        ..Default::default()
+    };
+    if let Some(gas_price) = gas_price {
+        config.gas_price = U256::from_dec_str(&gas_price).unwrap();
+    }
+    let opportunities = find_opportunities(&store, chain_id, &json_rpc_url, &cycles, &config).await;
+    Json(
+        opportunities
+            .into_iter()
+            .map(|opportunity| ArbOpportunity {
+                cycle: opportunity.cycle,
+                amount_in: opportunity.amount_in.to_string(),
+                amount_out: opportunity.amount_out.to_string(),
+                gas: opportunity.gas,
+                gas_cost: opportunity.gas_cost.to_string(),
+                net_profit: opportunity.net_profit.to_string(),
+            })
+            .collect(),
+    )
+}
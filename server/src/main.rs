@@ -10,8 +10,10 @@ extern crate rocket;
 #[cfg(test)]
 mod tests;
 
+mod arb;
 mod path;
 mod quote;
+mod routing;
 
 pub struct CORS;
 
@@ -46,6 +48,15 @@ fn rocket() -> _ {
     rocket::build()
         .mount("/healthcheck", routes![health_check])
         .mount("/path", routes![path::get_path])
-        .mount("/quote", routes![quote::quote_prices])
+        .mount("/arb", routes![arb::get_opportunities])
+        .mount(
+            "/quote",
+            routes![
+                quote::quote_prices,
+                quote::quote_prices_for_amount_out,
+                quote::quote_split_route
+            ],
+        )
+        .manage(routing::Routing::default())
         .attach(CORS)
 }
@@ -1,20 +1,19 @@
-use dexquote::path::{get_possible_paths, PoolInfo};
+use dexquote::{db::RedisStore, path::PoolInfo};
 use ethers::types::Address;
-use neo4rs::Graph;
-use rocket::serde::json::Json;
+use rocket::{serde::json::Json, State};
 
-const NEO4J_URI: &str = "bolt://localhost:7687";
-const NEO4J_PASSWORD: &str = "testtest";
+use crate::routing::{get_paths, Routing};
+
+const REDIS_URL: &str = "redis://:testtest@127.0.0.1:30073/";
 
 #[get("/<token_in>/<token_out>")]
-pub async fn get_path(token_in: String, token_out: String) -> Json<Vec<Vec<PoolInfo>>> {
-    let graph = Graph::new(NEO4J_URI, "neo4j", NEO4J_PASSWORD)
-        .await
-        .unwrap();
+pub async fn get_path(
+    token_in: String,
+    token_out: String,
+    routing: &State<Routing>,
+) -> Json<Vec<Vec<PoolInfo>>> {
     let token_in = token_in.parse::<Address>().unwrap();
     let token_out = token_out.parse::<Address>().unwrap();
-    match get_possible_paths(&graph, token_in, token_out, 1, 5, "Arb").await {
-        Some(paths) => Json(paths),
-        None => Json(Vec::new()),
-    }
+    let store = RedisStore::open(REDIS_URL).unwrap();
+    Json(get_paths(routing, &store, 42161, token_in, token_out, 1, 5))
 }
@@ -1,10 +1,20 @@
-use dexquote::path::{get_amount_out_from_path, get_possible_paths, PoolInfo};
+use dexquote::{
+    db::RedisStore,
+    path::{
+        get_amount_in_from_path, get_amount_out_from_path, get_quote_price_from_path,
+        get_split_amount_out, PoolInfo,
+    },
+    price::QuotePrice,
+};
 use ethers::types::{Address, U256};
-use neo4rs::Graph;
-use rocket::serde::{json::Json, Deserialize, Serialize};
+use rocket::{
+    serde::{json::Json, Deserialize, Serialize},
+    State,
+};
+use std::cmp::Reverse;
+
+use crate::routing::{with_graph, Routing};
 
-const NEO4J_URI: &str = "bolt://localhost:7687";
-const NEO4J_PASSWORD: &str = "testtest";
 const REDIS_URL: &str = "redis://:testtest@127.0.0.1:30073/";
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -12,49 +22,204 @@ const REDIS_URL: &str = "redis://:testtest@127.0.0.1:30073/";
 pub struct Route {
     pub path: Vec<PoolInfo>,
     pub estimated_amount_out: String,
+    // spot price, execution price and price impact, null if the decimals of a token are unknown
+    pub price: Option<QuotePrice>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct RouteAmountIn {
+    pub path: Vec<PoolInfo>,
+    pub estimated_amount_in: String,
+    pub price: Option<QuotePrice>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct SplitRoute {
+    pub path: Vec<PoolInfo>,
+    pub amount_in: String,
+    pub amount_out: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct SplitQuote {
+    pub amount_in: String,
+    pub amount_out: String,
+    pub routes: Vec<SplitRoute>,
 }
 
-#[get("/<token_in>/<token_out>/<amount_in>")]
+// paths that cannot be quoted (e.g. missing tick data in offline mode) are skipped
+#[get("/<token_in>/<token_out>/<amount_in>?<offline>")]
 pub async fn quote_prices(
     token_in: String,
     token_out: String,
     amount_in: String,
+    offline: Option<bool>,
+    routing: &State<Routing>,
 ) -> Json<Vec<Route>> {
-    let graph = Graph::new(NEO4J_URI, "neo4j", NEO4J_PASSWORD)
-        .await
-        .unwrap();
     let token_in = token_in.parse::<Address>().unwrap();
     let token_out = token_out.parse::<Address>().unwrap();
     let amount_in = U256::from_dec_str(&amount_in).unwrap();
-    let paths = match get_possible_paths(&graph, token_in, token_out, 2, 5, "Arb").await {
-        Some(paths) => paths,
-        None => return Json(Vec::new()),
-    };
     let chain_id = 42161;
     let alchemy_api_key = std::env::var("ALCHEMY_API_KEY").expect("Could not get ALCHEMY_API_KEY");
     let json_rpc_url = format!(
         "https://arb-mainnet.g.alchemy.com/v2/{}",
         alchemy_api_key.as_str()
     );
+    let store = RedisStore::open(REDIS_URL).unwrap();
+    // the paths with the best estimates from reserves and fees are simulated
+    let paths = with_graph(routing, &store, chain_id, |graph| {
+        graph.get_ranked_paths(token_in, token_out, amount_in, 2, 5)
+    });
     let mut routes = Vec::new();
-    for path in paths {
-        let estimated_amount_out = get_amount_out_from_path(
-            REDIS_URL,
+    for (path, _) in paths {
+        let estimated_amount_out = match get_amount_out_from_path(
+            &store,
             chain_id,
             &json_rpc_url,
             amount_in,
             path.as_slice(),
+            offline.unwrap_or(false),
         )
         .await
-        .unwrap();
-        let route = Route {
-            path,
-            estimated_amount_out: estimated_amount_out.to_string(),
+        {
+            Ok(estimated_amount_out) => estimated_amount_out,
+            Err(_) => continue,
         };
-        routes.push(route);
+        routes.push((path, estimated_amount_out));
     }
 
-    // sort by estimated_amount_out
-    routes.sort_by(|a, b| b.estimated_amount_out.cmp(&a.estimated_amount_out));
-    Json(routes)
+    // the most simulated output first, after fees and taxes
+    routes.sort_by_key(|(_, estimated_amount_out)| Reverse(*estimated_amount_out));
+    Json(
+        routes
+            .into_iter()
+            .map(|(path, estimated_amount_out)| Route {
+                price: get_quote_price_from_path(
+                    &store,
+                    chain_id,
+                    &path,
+                    amount_in,
+                    estimated_amount_out,
+                )
+                .ok(),
+                path,
+                estimated_amount_out: estimated_amount_out.to_string(),
+            })
+            .collect(),
+    )
+}
+
+// required input for an exact output, the cheapest route first
+#[get("/amount_out/<token_in>/<token_out>/<amount_out>?<offline>")]
+pub async fn quote_prices_for_amount_out(
+    token_in: String,
+    token_out: String,
+    amount_out: String,
+    offline: Option<bool>,
+    routing: &State<Routing>,
+) -> Json<Vec<RouteAmountIn>> {
+    let token_in = token_in.parse::<Address>().unwrap();
+    let token_out = token_out.parse::<Address>().unwrap();
+    let amount_out = U256::from_dec_str(&amount_out).unwrap();
+    let chain_id = 42161;
+    let alchemy_api_key = std::env::var("ALCHEMY_API_KEY").expect("Could not get ALCHEMY_API_KEY");
+    let json_rpc_url = format!(
+        "https://arb-mainnet.g.alchemy.com/v2/{}",
+        alchemy_api_key.as_str()
+    );
+    let store = RedisStore::open(REDIS_URL).unwrap();
+    let paths = with_graph(routing, &store, chain_id, |graph| {
+        graph.get_ranked_paths_for_amount_out(token_in, token_out, amount_out, 2, 5)
+    });
+    let mut routes = Vec::new();
+    for (path, _) in paths {
+        let estimated_amount_in = match get_amount_in_from_path(
+            &store,
+            chain_id,
+            &json_rpc_url,
+            amount_out,
+            path.as_slice(),
+            offline.unwrap_or(false),
+        )
+        .await
+        {
+            Ok(estimated_amount_in) => estimated_amount_in,
+            Err(_) => continue,
+        };
+        routes.push((path, estimated_amount_in));
+    }
+
+    routes.sort_by_key(|(_, estimated_amount_in)| *estimated_amount_in);
+    Json(
+        routes
+            .into_iter()
+            .map(|(path, estimated_amount_in)| RouteAmountIn {
+                price: get_quote_price_from_path(
+                    &store,
+                    chain_id,
+                    &path,
+                    estimated_amount_in,
+                    amount_out,
+                )
+                .ok(),
+                path,
+                estimated_amount_in: estimated_amount_in.to_string(),
+            })
+            .collect(),
+    )
+}
+
+// the input split across the best paths in `slices` equal parts, 20 by default
+#[get("/split/<token_in>/<token_out>/<amount_in>?<slices>&<offline>")]
+pub async fn quote_split_route(
+    token_in: String,
+    token_out: String,
+    amount_in: String,
+    slices: Option<usize>,
+    offline: Option<bool>,
+    routing: &State<Routing>,
+) -> Option<Json<SplitQuote>> {
+    let token_in = token_in.parse::<Address>().unwrap();
+    let token_out = token_out.parse::<Address>().unwrap();
+    let amount_in = U256::from_dec_str(&amount_in).unwrap();
+    let chain_id = 42161;
+    let alchemy_api_key = std::env::var("ALCHEMY_API_KEY").expect("Could not get ALCHEMY_API_KEY");
+    let json_rpc_url = format!(
+        "https://arb-mainnet.g.alchemy.com/v2/{}",
+        alchemy_api_key.as_str()
+    );
+    let store = RedisStore::open(REDIS_URL).unwrap();
+    let paths: Vec<Vec<PoolInfo>> = with_graph(routing, &store, chain_id, |graph| {
+        graph.get_ranked_paths(token_in, token_out, amount_in, 2, 5)
+    })
+    .into_iter()
+    .map(|(path, _)| path)
+    .collect();
+    let split = get_split_amount_out(
+        &store,
+        chain_id,
+        &json_rpc_url,
+        amount_in,
+        &paths,
+        slices.unwrap_or(20),
+        offline.unwrap_or(false),
+    )
+    .await
+    .ok()?;
+    Some(Json(SplitQuote {
+        amount_in: split.amount_in.to_string(),
+        amount_out: split.amount_out.to_string(),
+        routes: split
+            .routes
+            .into_iter()
+            .map(|route| SplitRoute {
+                path: route.path,
+                amount_in: route.amount_in.to_string(),
+                amount_out: route.amount_out.to_string(),
+            })
+            .collect(),
+    }))
 }
@@ -0,0 +1,66 @@
+use std::{
+    sync::{Mutex, RwLock, TryLockError},
+    time::{Duration, Instant},
+};
+
+use dexquote::{db::PoolStore, graph::RoutingGraph, path::PoolInfo};
+use ethers::types::Address;
+
+// the edges of the graph carry the reserves and liquidity used to rank paths,
+// so the graph is rebuilt from the store once it is older than this
+const REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Routing graph shared by the requests, built from the pool store on the first search
+#[derive(Default)]
+pub struct Routing {
+    graph: RwLock<RoutingGraph>,
+    // time of the last rebuild, held while rebuilding
+    refreshed_at: Mutex<Option<Instant>>,
+}
+
+// rebuild the graph from the store if it is stale, so that pools added or removed by
+// `import` and `sync` and their new states are picked up.
+// The new graph is built outside of the graph lock, searches are not held back meanwhile.
+fn refresh<S: PoolStore>(routing: &Routing, store: &S, chain_id: u64) {
+    let mut refreshed_at = match routing.refreshed_at.try_lock() {
+        Ok(refreshed_at) => refreshed_at,
+        // another request is rebuilding, the current graph is searched unless it is empty
+        Err(TryLockError::WouldBlock) if routing.graph.read().unwrap().pool_count() > 0 => return,
+        Err(_) => routing.refreshed_at.lock().unwrap(),
+    };
+    if refreshed_at.is_some_and(|refreshed_at| refreshed_at.elapsed() < REFRESH_INTERVAL) {
+        return;
+    }
+    match RoutingGraph::from_store(store, chain_id) {
+        Ok(graph) => {
+            *routing.graph.write().unwrap() = graph;
+            *refreshed_at = Some(Instant::now());
+        }
+        Err(e) => warn!("failed to rebuild the routing graph: {:?}", e),
+    }
+}
+
+pub fn with_graph<S: PoolStore, T>(
+    routing: &Routing,
+    store: &S,
+    chain_id: u64,
+    search: impl FnOnce(&RoutingGraph) -> T,
+) -> T {
+    refresh(routing, store, chain_id);
+    let graph = routing.graph.read().unwrap();
+    search(&graph)
+}
+
+pub fn get_paths<S: PoolStore>(
+    routing: &Routing,
+    store: &S,
+    chain_id: u64,
+    token_in: Address,
+    token_out: Address,
+    max_hops: usize,
+    limit: usize,
+) -> Vec<Vec<PoolInfo>> {
+    with_graph(routing, store, chain_id, |graph| {
+        graph.get_paths(token_in, token_out, max_hops, limit)
+    })
+}
@@ -2,7 +2,8 @@ use dexquote::path::PoolInfo;
 use rocket::http::Status;
 use rocket::local::blocking::Client;
 
-use crate::quote::Route;
+use crate::arb::ArbOpportunity;
+use crate::quote::{Route, RouteAmountIn};
 
 #[test]
 fn test_get_path() {
@@ -33,9 +34,58 @@ fn test_quote_prices() {
     println!("{:#?}", paths.unwrap());
 }
 
+#[test]
+fn test_quote_prices_offline() {
+    let token_in = "0x82aF49447D8a07e3bd95BD0d56f35241523fBab1";
+    let token_out = "0xFF970A61A04b1cA14834A43f5dE4533eBDDB5CC8";
+
+    let client = Client::tracked(super::rocket()).unwrap();
+    let amount_in = "1000000000000";
+    let uri = format!(
+        "/quote/{}/{}/{}?offline=true",
+        token_in, token_out, amount_in
+    );
+    let response = client.get(uri).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let paths = response.into_json::<Vec<Route>>();
+    assert!(paths.is_some());
+    println!("{:#?}", paths.unwrap());
+}
+
+#[test]
+fn test_quote_prices_for_amount_out() {
+    let token_in = "0x82aF49447D8a07e3bd95BD0d56f35241523fBab1";
+    let token_out = "0xFF970A61A04b1cA14834A43f5dE4533eBDDB5CC8";
+
+    let client = Client::tracked(super::rocket()).unwrap();
+    let amount_out = "1000000000";
+    let uri = format!(
+        "/quote/amount_out/{}/{}/{}",
+        token_in, token_out, amount_out
+    );
+    let response = client.get(uri).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let paths = response.into_json::<Vec<RouteAmountIn>>();
+    assert!(paths.is_some());
+    println!("{:#?}", paths.unwrap());
+}
+
 #[test]
 fn test_health_check() {
     let client = Client::tracked(super::rocket()).unwrap();
     let response = client.get("/healthcheck").dispatch();
     assert_eq!(response.into_string(), Some("OK".into()));
 }
+
+#[test]
+fn test_get_opportunities() {
+    let token = "0x82aF49447D8a07e3bd95BD0d56f35241523fBab1";
+
+    let client = Client::tracked(super::rocket()).unwrap();
+    let uri = format!("/arb/{}?max_hops=2&offline=true", token);
+    let response = client.get(uri).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let opportunities = response.into_json::<Vec<ArbOpportunity>>();
+    assert!(opportunities.is_some());
+    println!("{:#?}", opportunities.unwrap());
+}